@@ -0,0 +1,33 @@
+// Build script: record the git commit the binary was built from, for
+// `heic_convert --version --json`. Builds from a source archive have no commit.
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|hash| !hash.is_empty());
+    if let Some(hash) = hash {
+        println!("cargo:rustc-env=HEIC_CONVERT_GIT_HASH={}", hash);
+    }
+
+    // Rebuild when HEAD moves; watching a missing file would rerun the script every time
+    println!("cargo:rerun-if-changed=build.rs");
+    let head = Path::new(".git/HEAD");
+    if head.exists() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        if let Ok(content) = std::fs::read_to_string(head)
+            && let Some(reference) = content.trim().strip_prefix("ref: ")
+        {
+            for path in [format!(".git/{}", reference), String::from(".git/packed-refs")] {
+                if Path::new(&path).exists() {
+                    println!("cargo:rerun-if-changed={}", path);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,516 @@
+// Pixel adjustments applied during conversion (--denoise, --auto-levels,
+// --auto-white-balance, --curve, --lut, --dither)
+//
+// Adjustments work on 32-bit float RGB so that 16-bit sources keep their precision,
+// and the result is converted back to the pixel layout of the source, or with
+// --dither reduced to 8 bits per channel.
+use clap::ValueEnum;
+use image::{ColorType, DynamicImage, Rgba32FImage};
+use std::sync::{Arc, OnceLock};
+
+use crate::curve::Curve;
+use crate::explain;
+use crate::lut::Lut;
+
+// Percentiles that become black and white with --auto-levels, so a few stray
+// pixels (specular highlights, hot pixels) do not decide the stretch
+const LEVELS_CLIP: f64 = 0.005;
+
+// Gray-world gains are limited so that a picture of a red wall does not turn grey
+const MAX_GAIN: f32 = 2.0;
+
+// Threshold patterns for --dither
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Dither {
+    Ordered,   // 8x8 Bayer matrix: fast, a faint regular cross-hatch
+    BlueNoise, // 32x32 void-and-cluster map: no visible pattern
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Adjustments {
+    pub denoise: u8, // 0 (off) to 10
+    pub auto_levels: bool,
+    pub auto_white_balance: bool,
+    pub curves: Vec<Curve>,
+    pub lut: Option<Arc<Lut>>,
+    pub dither: Option<Dither>,
+}
+
+impl Adjustments {
+    pub fn is_empty(&self) -> bool {
+        !self.changes_pixels() && self.dither.is_none()
+    }
+
+    fn changes_pixels(&self) -> bool {
+        self.denoise > 0
+            || self.auto_levels
+            || self.auto_white_balance
+            || !self.curves.is_empty()
+            || self.lut.is_some()
+    }
+
+    // Apply the requested adjustments in a fixed order: noise reduction (before the
+    // levels stretch can amplify the noise), white balance, levels, then the look
+    // (curves and LUT) on the corrected image
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        if self.is_empty() {
+            return image;
+        }
+        let color = image.color();
+        // Dithering alone has nothing to do on an 8-bit image
+        if !self.changes_pixels() && !is_high_depth(color) {
+            explain::note("Dithering: skipped, the image already has 8 bits per channel");
+            return image;
+        }
+        let mut pixels = image.into_rgba32f();
+        if self.denoise > 0 {
+            pixels = denoise(&pixels, self.denoise);
+        }
+        if self.auto_white_balance {
+            gray_world(&mut pixels);
+        }
+        if self.auto_levels {
+            stretch_levels(&mut pixels);
+        }
+        if !self.curves.is_empty() {
+            apply_curves(&mut pixels, &self.curves);
+        }
+        if let Some(lut) = &self.lut {
+            apply_lut(&mut pixels, lut);
+        }
+        match self.dither {
+            Some(pattern) => dither(pixels, color, pattern),
+            None => restore(pixels, color),
+        }
+    }
+}
+
+fn is_high_depth(color: ColorType) -> bool {
+    color.bytes_per_pixel() / color.channel_count() > 1
+}
+
+fn luma(pixel: &[f32; 4]) -> f32 {
+    0.2126 * pixel[0] + 0.7152 * pixel[1] + 0.0722 * pixel[2]
+}
+
+// Gray-world white balance: scale each channel so that the average colour is neutral.
+// Nearly black or clipped pixels carry no colour information and are left out.
+fn gray_world(pixels: &mut Rgba32FImage) {
+    let mut sums = [0f64; 3];
+    let mut count = 0u64;
+    for pixel in pixels.pixels() {
+        let l = luma(&pixel.0);
+        if l > 0.02 && l < 0.98 {
+            for (sum, value) in sums.iter_mut().zip(pixel.0) {
+                *sum += value as f64;
+            }
+            count += 1;
+        }
+    }
+    if count == 0 || sums.iter().any(|&sum| sum <= 0.0) {
+        explain::note("White balance: skipped, the image has no usable mid-tones");
+        return;
+    }
+
+    let gray = (sums[0] + sums[1] + sums[2]) / 3.0;
+    let gains = sums.map(|sum| ((gray / sum) as f32).clamp(1.0 / MAX_GAIN, MAX_GAIN));
+    for pixel in pixels.pixels_mut() {
+        for (value, gain) in pixel.0.iter_mut().zip(gains) {
+            *value = (*value * gain).clamp(0.0, 1.0);
+        }
+    }
+    explain::note(format!(
+        "White balance (gray world): gains R {:.2}, G {:.2}, B {:.2}",
+        gains[0], gains[1], gains[2]
+    ));
+}
+
+// Histogram stretch: map the darkest and brightest luminance percentiles to black and
+// white. All channels get the same mapping, so colours keep their hue.
+fn stretch_levels(pixels: &mut Rgba32FImage) {
+    const BINS: usize = 1024;
+    let mut histogram = vec![0u64; BINS];
+    for pixel in pixels.pixels() {
+        let bin = (luma(&pixel.0).clamp(0.0, 1.0) * (BINS - 1) as f32) as usize;
+        histogram[bin] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    let percentile = |share: f64| {
+        let target = (total as f64 * share) as u64;
+        let mut seen = 0;
+        histogram
+            .iter()
+            .position(|&count| {
+                seen += count;
+                seen > target
+            })
+            .unwrap_or(BINS - 1) as f32
+            / (BINS - 1) as f32
+    };
+    let (low, high) = (percentile(LEVELS_CLIP), percentile(1.0 - LEVELS_CLIP));
+
+    // Flat images (a blank page, fog) would only get noisy
+    if high - low < 0.05 {
+        explain::note("Auto levels: skipped, the image has almost no tonal range");
+        return;
+    }
+    for pixel in pixels.pixels_mut() {
+        for value in pixel.0.iter_mut().take(3) {
+            *value = ((*value - low) / (high - low)).clamp(0.0, 1.0);
+        }
+    }
+    explain::note(format!(
+        "Auto levels: stretched {:.0}%-{:.0}% brightness to the full range",
+        low * 100.0,
+        high * 100.0
+    ));
+}
+
+fn apply_curves(pixels: &mut Rgba32FImage, curves: &[Curve]) {
+    for pixel in pixels.pixels_mut() {
+        for (index, value) in pixel.0.iter_mut().take(3).enumerate() {
+            for curve in curves.iter().filter(|curve| curve.affects(index)) {
+                *value = curve.eval(*value);
+            }
+        }
+    }
+    for curve in curves {
+        explain::note(format!("Curve applied: {}", curve));
+    }
+}
+
+fn apply_lut(pixels: &mut Rgba32FImage, lut: &Lut) {
+    for pixel in pixels.pixels_mut() {
+        let [r, g, b, _] = pixel.0;
+        let [r, g, b] = lut.apply([r, g, b]);
+        pixel.0[0] = r.clamp(0.0, 1.0);
+        pixel.0[1] = g.clamp(0.0, 1.0);
+        pixel.0[2] = b.clamp(0.0, 1.0);
+    }
+    let shape = if lut.three_d {
+        format!("{0}x{0}x{0}", lut.size)
+    } else {
+        format!("1D, {} entries", lut.size)
+    };
+    explain::note(format!("LUT applied: {} ({})", lut.title.as_deref().unwrap_or("untitled"), shape));
+}
+
+// Bilateral filter: every pixel becomes the average of its neighbours, weighted by
+// distance and by how similar their colour is, so grain is smoothed but edges stay.
+// The strength sets both the neighbourhood (radius 1-3) and the colour tolerance.
+fn denoise(pixels: &Rgba32FImage, strength: u8) -> Rgba32FImage {
+    let strength = strength.min(10) as f32;
+    let radius = 1 + (strength as i64 - 1) / 4;
+    let sigma_space = radius as f32;
+    let sigma_range = 0.01 + strength * 0.01;
+
+    // Spatial weights are the same for every pixel
+    let mut offsets = Vec::new();
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let distance = (dx * dx + dy * dy) as f32;
+            offsets.push((dx, dy, (-distance / (2.0 * sigma_space * sigma_space)).exp()));
+        }
+    }
+    let range_scale = -1.0 / (2.0 * sigma_range * sigma_range);
+
+    let (width, height) = (pixels.width() as i64, pixels.height() as i64);
+    let mut output = pixels.clone();
+    for y in 0..height {
+        for x in 0..width {
+            let center = pixels.get_pixel(x as u32, y as u32).0;
+            let mut sum = [0f32; 3];
+            let mut total = 0f32;
+            for &(dx, dy, spatial) in &offsets {
+                // Clamp at the borders
+                let nx = (x + dx).clamp(0, width - 1) as u32;
+                let ny = (y + dy).clamp(0, height - 1) as u32;
+                let neighbour = pixels.get_pixel(nx, ny).0;
+                let difference: f32 = (0..3).map(|c| (neighbour[c] - center[c]).powi(2)).sum();
+                let weight = spatial * (difference * range_scale).exp();
+                for (sum, value) in sum.iter_mut().zip(neighbour) {
+                    *sum += value * weight;
+                }
+                total += weight;
+            }
+            let pixel = output.get_pixel_mut(x as u32, y as u32);
+            for (value, sum) in pixel.0.iter_mut().zip(sum) {
+                *value = sum / total;
+            }
+        }
+    }
+    explain::note(format!(
+        "Noise reduction: bilateral filter, strength {} (radius {} px)",
+        strength, radius
+    ));
+    output
+}
+
+// Back to the pixel layout of the source (8 or 16 bit, with or without alpha)
+fn restore(pixels: Rgba32FImage, color: ColorType) -> DynamicImage {
+    let image = DynamicImage::ImageRgba32F(pixels);
+    match color {
+        ColorType::L8 => DynamicImage::ImageLuma8(image.to_luma8()),
+        ColorType::La8 => DynamicImage::ImageLumaA8(image.to_luma_alpha8()),
+        ColorType::Rgb8 => DynamicImage::ImageRgb8(image.to_rgb8()),
+        ColorType::L16 => DynamicImage::ImageLuma16(image.to_luma16()),
+        ColorType::La16 => DynamicImage::ImageLumaA16(image.to_luma_alpha16()),
+        ColorType::Rgb16 => DynamicImage::ImageRgb16(image.to_rgb16()),
+        ColorType::Rgba16 => DynamicImage::ImageRgba16(image.to_rgba16()),
+        ColorType::Rgb32F => DynamicImage::ImageRgb32F(image.to_rgb32f()),
+        ColorType::Rgba32F => image,
+        _ => DynamicImage::ImageRgba8(image.to_rgba8()),
+    }
+}
+
+// Reduce to 8 bits per channel, adding a threshold pattern before rounding so smooth
+// gradients (skies, walls) turn into fine grain instead of visible bands
+fn dither(pixels: Rgba32FImage, color: ColorType, pattern: Dither) -> DynamicImage {
+    let (size, thresholds): (usize, &[f32]) = match pattern {
+        Dither::Ordered => (8, bayer()),
+        Dither::BlueNoise => (BLUE_NOISE_SIZE, blue_noise()),
+    };
+    let mut output = image::RgbaImage::new(pixels.width(), pixels.height());
+    for (x, y, pixel) in pixels.enumerate_pixels() {
+        let offset = thresholds[(y as usize % size) * size + x as usize % size];
+        let target = output.get_pixel_mut(x, y);
+        for (channel, (value, out)) in pixel.0.iter().zip(target.0.iter_mut()).enumerate() {
+            // Alpha edges are rounded, not dithered
+            let offset = if channel == 3 { 0.0 } else { offset };
+            *out = (value * 255.0 + 0.5 + offset).floor().clamp(0.0, 255.0) as u8;
+        }
+    }
+    explain::note(format!(
+        "Dithered to 8 bits per channel ({})",
+        match pattern {
+            Dither::Ordered => "ordered, 8x8 Bayer matrix",
+            Dither::BlueNoise => "blue noise",
+        }
+    ));
+
+    let image = DynamicImage::ImageRgba8(output);
+    match color {
+        ColorType::L8 | ColorType::L16 => DynamicImage::ImageLuma8(image.to_luma8()),
+        ColorType::La8 | ColorType::La16 => DynamicImage::ImageLumaA8(image.to_luma_alpha8()),
+        ColorType::Rgb8 | ColorType::Rgb16 | ColorType::Rgb32F => DynamicImage::ImageRgb8(image.to_rgb8()),
+        _ => image,
+    }
+}
+
+// Thresholds in -0.5..0.5, row by row
+fn bayer() -> &'static [f32] {
+    static MAP: OnceLock<Vec<f32>> = OnceLock::new();
+    MAP.get_or_init(|| {
+        // Bit-interleaving construction of the 8x8 Bayer matrix
+        (0..64)
+            .map(|i| {
+                let (x, y) = (i % 8, i / 8);
+                let xor = x ^ y;
+                let mut rank = 0;
+                for bit in 0..3 {
+                    rank |= ((xor >> bit) & 1) << (5 - 2 * bit);
+                    rank |= ((y >> bit) & 1) << (4 - 2 * bit);
+                }
+                (rank as f32 + 0.5) / 64.0 - 0.5
+            })
+            .collect()
+    })
+}
+
+const BLUE_NOISE_SIZE: usize = 32;
+
+// Blue-noise thresholds from the void-and-cluster method (Ulichney 1993): pixels are
+// ranked by repeatedly filling the largest gap of the pattern so far. Computed once
+// per run; a 32x32 map takes a few milliseconds.
+fn blue_noise() -> &'static [f32] {
+    static MAP: OnceLock<Vec<f32>> = OnceLock::new();
+    MAP.get_or_init(|| {
+        const N: usize = BLUE_NOISE_SIZE;
+        const SIGMA: f32 = 1.5;
+
+        // Gaussian weight for every wrapped-around offset
+        let mut kernel = vec![0f32; N * N];
+        for dy in 0..N {
+            for dx in 0..N {
+                let (wx, wy) = (dx.min(N - dx) as f32, dy.min(N - dy) as f32);
+                kernel[dy * N + dx] = (-(wx * wx + wy * wy) / (2.0 * SIGMA * SIGMA)).exp();
+            }
+        }
+        let mut energy = vec![0f32; N * N];
+        let mut set = vec![false; N * N];
+        let toggle = |energy: &mut [f32], set: &mut [bool], at: usize, on: bool| {
+            set[at] = on;
+            let sign = if on { 1.0 } else { -1.0 };
+            let (ax, ay) = (at % N, at / N);
+            for (index, e) in energy.iter_mut().enumerate() {
+                let (dx, dy) = ((index % N + N - ax) % N, (index / N + N - ay) % N);
+                *e += sign * kernel[dy * N + dx];
+            }
+        };
+        let tightest = |energy: &[f32], set: &[bool]| {
+            (0..N * N).filter(|&i| set[i]).max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        };
+        let largest_void = |energy: &[f32], set: &[bool]| {
+            (0..N * N).filter(|&i| !set[i]).min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        };
+
+        // Start from a fixed pseudo-random tenth of the pixels and spread them evenly
+        let mut seed = 0x2545_F491u32;
+        let mut initial = 0;
+        while initial < N * N / 10 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let at = seed as usize % (N * N);
+            if !set[at] {
+                toggle(&mut energy, &mut set, at, true);
+                initial += 1;
+            }
+        }
+        while let (Some(cluster), Some(_)) = (tightest(&energy, &set), largest_void(&energy, &set)) {
+            toggle(&mut energy, &mut set, cluster, false);
+            let void = largest_void(&energy, &set).unwrap_or(cluster);
+            toggle(&mut energy, &mut set, void, true);
+            if void == cluster {
+                break;
+            }
+        }
+
+        let mut rank = vec![0usize; N * N];
+        // Ranks below the initial pattern: remove its tightest clusters one by one
+        let (mut phase_energy, mut phase_set) = (energy.clone(), set.clone());
+        for r in (0..initial).rev() {
+            if let Some(cluster) = tightest(&phase_energy, &phase_set) {
+                toggle(&mut phase_energy, &mut phase_set, cluster, false);
+                rank[cluster] = r;
+            }
+        }
+        // Ranks above: keep filling the largest void
+        for r in initial..N * N {
+            if let Some(void) = largest_void(&energy, &set) {
+                toggle(&mut energy, &mut set, void, true);
+                rank[void] = r;
+            }
+        }
+        rank.iter().map(|&r| (r as f32 + 0.5) / (N * N) as f32 - 0.5).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::parse_curve;
+    use image::{Rgb, RgbImage, Rgba};
+
+    // A row of pixels with the given colours
+    fn row(colors: &[[f32; 3]]) -> Rgba32FImage {
+        Rgba32FImage::from_fn(colors.len() as u32, 1, |x, _| {
+            let [r, g, b] = colors[x as usize];
+            Rgba([r, g, b, 1.0])
+        })
+    }
+
+    fn close(actual: f32, expected: f32) -> bool {
+        (actual - expected).abs() < 1e-4
+    }
+
+    #[test]
+    fn brightness_curve_lifts_the_mid_tones_only() {
+        let image = RgbImage::from_fn(3, 1, |x, _| Rgb([[0, 128, 255][x as usize]; 3]));
+        let adjustments = Adjustments { curves: vec![parse_curve("128:160").unwrap()], ..Default::default() };
+        let result = adjustments.apply(DynamicImage::ImageRgb8(image)).to_rgb8();
+        let values: Vec<u8> = result.pixels().map(|pixel| pixel[0]).collect();
+        assert_eq!(values, [0, 160, 255]);
+        assert!(result.pixels().all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2]));
+    }
+
+    #[test]
+    fn channel_curve_leaves_the_other_channels() {
+        let mut pixels = row(&[[0.5, 0.5, 0.5]]);
+        apply_curves(&mut pixels, &[parse_curve("b=128:64").unwrap()]);
+        let [r, g, b, _] = pixels.get_pixel(0, 0).0;
+        assert!(close(r, 0.5) && close(g, 0.5));
+        assert!((b - 64.0 / 255.0).abs() < 0.01, "blue is {}", b);
+    }
+
+    #[test]
+    fn levels_stretch_the_contrast_to_the_full_range() {
+        let grays: Vec<[f32; 3]> = (0..=100).map(|i| [0.25 + 0.005 * i as f32; 3]).collect();
+        let mut pixels = row(&grays);
+        stretch_levels(&mut pixels);
+        let first = pixels.get_pixel(0, 0).0;
+        let middle = pixels.get_pixel(50, 0).0;
+        let last = pixels.get_pixel(100, 0).0;
+        assert!(first[0] < 0.01 && last[0] > 0.99, "{:?} .. {:?}", first, last);
+        assert!((middle[0] - 0.5).abs() < 0.02, "middle is {:?}", middle);
+        assert!(close(middle[3], 1.0), "alpha is not stretched");
+    }
+
+    #[test]
+    fn flat_image_keeps_its_levels() {
+        let mut pixels = row(&[[0.40, 0.40, 0.40], [0.42, 0.42, 0.42]]);
+        let before = pixels.clone();
+        stretch_levels(&mut pixels);
+        assert_eq!(pixels, before);
+    }
+
+    #[test]
+    fn white_balance_neutralises_a_colour_cast() {
+        let mut pixels = row(&[[0.3, 0.4, 0.5], [0.6, 0.7, 0.8]]);
+        gray_world(&mut pixels);
+        let sums = (0..3).map(|c| pixels.pixels().map(|pixel| pixel.0[c]).sum::<f32>()).collect::<Vec<_>>();
+        assert!(close(sums[0], sums[1]) && close(sums[1], sums[2]), "channel sums {:?}", sums);
+    }
+
+    #[test]
+    fn white_balance_gains_are_limited() {
+        // The gray-world gains would be 0.42 for red and 3.3 for green and blue; a red
+        // wall stays red
+        let mut pixels = row(&[[0.8, 0.1, 0.1]]);
+        gray_world(&mut pixels);
+        let [r, g, b, _] = pixels.get_pixel(0, 0).0;
+        assert!(close(r, 0.8 / MAX_GAIN) && close(g, 0.1 * MAX_GAIN) && close(b, 0.1 * MAX_GAIN), "{} {} {}", r, g, b);
+        assert!(r > g);
+    }
+
+    #[test]
+    fn white_balance_skips_black_and_clipped_images() {
+        let mut pixels = row(&[[0.0, 0.0, 0.0], [1.0, 1.0, 1.0]]);
+        let before = pixels.clone();
+        gray_world(&mut pixels);
+        assert_eq!(pixels, before);
+    }
+
+    #[test]
+    fn denoise_smooths_grain_but_keeps_edges() {
+        let noisy: Vec<[f32; 3]> = (0..8).map(|x| [if x % 2 == 0 { 0.50 } else { 0.52 }; 3]).collect();
+        let smoothed = denoise(&row(&noisy), 10);
+        let spread = |image: &Rgba32FImage| {
+            let values: Vec<f32> = image.pixels().map(|pixel| pixel.0[0]).collect();
+            values.iter().cloned().fold(f32::MIN, f32::max) - values.iter().cloned().fold(f32::MAX, f32::min)
+        };
+        assert!(spread(&smoothed) < 0.01, "spread {}", spread(&smoothed));
+
+        let edge: Vec<[f32; 3]> = (0..8).map(|x| [if x < 4 { 0.0 } else { 1.0 }; 3]).collect();
+        let kept = denoise(&row(&edge), 10);
+        assert!(kept.get_pixel(3, 0).0[0] < 0.01 && kept.get_pixel(4, 0).0[0] > 0.99);
+    }
+
+    #[test]
+    fn dither_keeps_the_average_of_a_gradient() {
+        // 16-bit gray between two 8-bit steps
+        let value = 100.25 / 255.0;
+        let pixels = Rgba32FImage::from_pixel(8, 8, Rgba([value, value, value, 1.0]));
+        let dithered = dither(pixels, ColorType::Rgb16, Dither::Ordered).to_rgb8();
+        let average = dithered.pixels().map(|pixel| pixel[0] as f32).sum::<f32>() / 64.0;
+        assert!((average - 100.25).abs() < 0.1, "average {}", average);
+        assert!(dithered.pixels().all(|pixel| pixel[0] == 100 || pixel[0] == 101));
+    }
+
+    #[test]
+    fn eight_bit_image_is_not_dithered() {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(2, 2, Rgb([10, 20, 30])));
+        let adjustments = Adjustments { dither: Some(Dither::BlueNoise), ..Default::default() };
+        assert_eq!(adjustments.apply(image.clone()), image);
+    }
+}
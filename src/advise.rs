@@ -0,0 +1,130 @@
+// Format advice (--advise): which output suits an image, without converting it
+//
+// The image is decoded once and encoded in memory as PNG, as JPG at the default
+// quality and as --share-safe would write it, so the projected sizes are real
+// numbers rather than guesses. Content is classified from a sample of the pixels:
+// graphics (screenshots, diagrams) have few distinct colours and large flat areas.
+use anyhow::Result;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat};
+use std::collections::HashSet;
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::temp_workspace::TempWorkspace;
+use crate::{resize, units};
+
+// Pixels looked at for the content classification
+const SAMPLE_PIXELS: u64 = 250_000;
+
+// Below this many distinct colours in the sample, or above this share of pixels that
+// repeat their neighbour, an image counts as a graphic
+const GRAPHIC_COLOURS: usize = 1024;
+const GRAPHIC_FLAT: f64 = 0.6;
+
+pub struct Analysis {
+    pub graphic: bool,
+    pub colours: usize,
+    pub flat: f64,          // Share of sampled pixels equal to their right neighbour
+    pub transparency: bool, // Has an alpha channel that is actually used
+}
+
+pub fn analyse(image: &DynamicImage) -> Analysis {
+    let pixels = image.to_rgba8();
+    let total = pixels.width() as u64 * pixels.height() as u64;
+    let step = (total / SAMPLE_PIXELS).max(1) as usize;
+
+    let mut colours = HashSet::new();
+    let (mut flat, mut sampled) = (0u64, 0u64);
+    let raw = pixels.as_raw();
+    let width = pixels.width() as usize;
+    for index in (0..total as usize).step_by(step) {
+        let pixel = &raw[index * 4..index * 4 + 3];
+        colours.insert([pixel[0], pixel[1], pixel[2]]);
+        if (index + 1) % width != 0 {
+            flat += (pixel == &raw[index * 4 + 4..index * 4 + 7]) as u64;
+        }
+        sampled += 1;
+    }
+    let flat = flat as f64 / sampled.max(1) as f64;
+    let transparency = image.color().has_alpha() && pixels.pixels().any(|pixel| pixel.0[3] < 255);
+    Analysis {
+        graphic: colours.len() < GRAPHIC_COLOURS || flat > GRAPHIC_FLAT,
+        colours: colours.len(),
+        flat,
+        transparency,
+    }
+}
+
+// Encoded size in bytes, or None when the encoder fails
+fn png_size(image: &DynamicImage) -> Option<u64> {
+    let mut buffer = Cursor::new(Vec::new());
+    image.write_to(&mut buffer, ImageFormat::Png).ok()?;
+    Some(buffer.into_inner().len() as u64)
+}
+
+fn jpeg_size(image: &DynamicImage, quality: u8) -> Option<u64> {
+    let mut buffer = Vec::new();
+    // JPEG has no alpha channel
+    let rgb = DynamicImage::ImageRgb8(image.to_rgb8());
+    rgb.write_with_encoder(JpegEncoder::new_with_quality(&mut buffer, quality)).ok()?;
+    Some(buffer.len() as u64)
+}
+
+pub fn run(input: &Path, tmpdir: Option<&Path>) -> Result<()> {
+    let workspace = TempWorkspace::create(tmpdir)?;
+    let image = crate::decode_image(input, &workspace)?;
+    let analysis = analyse(&image);
+    let file_size = std::fs::metadata(input).map(|m| m.len()).unwrap_or(0);
+
+    say!("{}", input.display());
+    say!(
+        "  Content:      {} ({} colours in a sample, {:.0}% flat areas)",
+        if analysis.graphic { "graphic, like a screenshot or drawing" } else { "photographic" },
+        crate::locale::format_number(analysis.colours as f64, 0),
+        analysis.flat * 100.0
+    );
+    say!("  Transparency: {}", if analysis.transparency { "yes" } else { "none" });
+    say!(
+        "  Size:         {} x {} ({:.1} MP), {} now",
+        image.width(),
+        image.height(),
+        image.width() as f64 * image.height() as f64 / 1_000_000.0,
+        units::format_size(file_size)
+    );
+
+    say!("  Projected sizes:");
+    let show = |label: &str, size: Option<u64>| match size {
+        Some(size) => say!("    {:<26} {}", label, units::format_size(size)),
+        None => say!("    {:<26} cannot encode", label),
+    };
+    let png = png_size(&image);
+    let jpeg = jpeg_size(&image, crate::quality::JPEG_DEFAULT);
+    show("PNG (-f png)", png);
+    show("JPG (-f jpg)", jpeg);
+    let shared = resize::Resize {
+        width: Some(crate::SHARE_SAFE_EDGE),
+        height: Some(crate::SHARE_SAFE_EDGE),
+    }
+    .apply(image.clone());
+    show("JPG (-f jpg --share-safe)", jpeg_size(&shared, crate::SHARE_SAFE_QUALITY));
+
+    let ratio = match (png, jpeg) {
+        (Some(png), Some(jpeg)) if jpeg > 0 => png as f64 / jpeg as f64,
+        _ => 1.0,
+    };
+    let (format, reason) = if analysis.transparency {
+        ("png", "the image uses transparency, which JPG would fill in".to_string())
+    } else if ratio <= 1.0 {
+        ("png", "lossless, and here even smaller than JPG".to_string())
+    } else if analysis.graphic {
+        ("png", "sharp edges and flat colours stay crisp; JPG would add blotches around them".to_string())
+    } else {
+        ("jpg", format!("photos compress {:.0}x better as JPG with no visible loss", ratio))
+    };
+    say!("  Advice:       -f {}: {}", format, reason);
+    if format == "jpg" && image.width().max(image.height()) > crate::SHARE_SAFE_EDGE {
+        say!("                For e-mail or posting, add --share-safe");
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+// album.json manifests for static gallery generators (--album-manifest)
+//
+// Every converted image is added to (or updated in) the album.json of its output
+// directory, so gallery tools get titles, covers, sizes and dates without re-scanning
+// the images. A title edited by hand is kept; the cover is the oldest image.
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+use crate::{exif, file_type};
+
+pub const MANIFEST_NAME: &str = "album.json";
+
+// One update at a time: parallel conversions (--jobs) into the same directory would
+// each read the manifest, add their image and overwrite the others' additions
+static UPDATES: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Album {
+    pub title: String,
+    pub cover: Option<String>, // File name of the cover image
+    pub updated: String,       // RFC 3339
+    pub images: Vec<AlbumImage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AlbumImage {
+    pub file: String,   // Converted image, relative to the album directory
+    pub source: String, // Original file name
+    pub width: u32,
+    pub height: u32,
+    pub bytes: u64,
+    pub taken: Option<String>, // Capture date from EXIF (local time, ISO 8601)
+    pub camera: Option<String>,
+}
+
+// Add `output` (converted from `source`) to the album.json next to it
+pub fn update(output: &Path, source: &Path) -> Result<()> {
+    let _updating = UPDATES.lock().unwrap_or_else(PoisonError::into_inner);
+    let dir = output.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let path = dir.join(MANIFEST_NAME);
+
+    let mut album: Album = if path.exists() {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("cannot read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("{} is not a valid album manifest", path.display()))?
+    } else {
+        Album::default()
+    };
+
+    if album.title.is_empty() {
+        album.title = title_from_dir(dir);
+    }
+
+    let file = output.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let (width, height) = file_type::dimensions(output)
+        .with_context(|| format!("cannot read the size of {}", output.display()))?;
+    let tags = exif::read(source);
+    let camera = match (tags.make, tags.model) {
+        // Models usually repeat the make ("Apple" / "iPhone 15 Pro" vs "Canon" / "Canon EOS R6")
+        (Some(make), Some(model)) if !model.starts_with(&make) => Some(format!("{} {}", make, model)),
+        (make, model) => model.or(make),
+    };
+    let image = AlbumImage {
+        file: file.clone(),
+        source: source.file_name().unwrap_or_default().to_string_lossy().to_string(),
+        width,
+        height,
+        bytes: fs::metadata(output).map(|m| m.len()).unwrap_or(0),
+        taken: tags.date.map(|date| date.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        camera,
+    };
+
+    album.images.retain(|existing| existing.file != file);
+    album.images.push(image);
+    // Oldest first; images without a date go last
+    album
+        .images
+        .sort_by(|a, b| (a.taken.is_none(), &a.taken, &a.file).cmp(&(b.taken.is_none(), &b.taken, &b.file)));
+
+    album.cover = album.images.first().map(|image| image.file.clone());
+    album.updated = chrono::Local::now().to_rfc3339();
+
+    // Write a sibling file and rename it, so gallery tools never read half a manifest
+    let staging = dir.join(format!(".{}.tmp", MANIFEST_NAME));
+    fs::write(&staging, serde_json::to_string_pretty(&album)?)
+        .with_context(|| format!("cannot write {}", staging.display()))?;
+    fs::rename(&staging, &path).with_context(|| format!("cannot replace {}", path.display()))?;
+    Ok(())
+}
+
+// "2023_summer-trip" -> "2023 summer trip"
+fn title_from_dir(dir: &Path) -> String {
+    let name = dir
+        .canonicalize()
+        .ok()
+        .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().to_string()))
+        .unwrap_or_default();
+    name.replace(['_', '-'], " ").trim().to_string()
+}
@@ -0,0 +1,116 @@
+// HEIC files inside ZIP archives (-i "iCloud Photos.zip")
+//
+// iCloud's "Download originals" and most photo exports arrive as ZIP files. Such an
+// input is read entry by entry: each HEIC file is unpacked into a temporary workspace
+// on its own, converted and removed again, so the archive never has to be extracted as
+// a whole. The outputs go into a folder named after the archive next to it ("iCloud
+// Photos/"), or into --output-dir, keeping the folders of the archive unless --flatten
+// or --organize-by-date is given. Entries whose names would lead out of that folder
+// ("../", absolute paths) are skipped, and so are the AppleDouble files in __MACOSX/.
+use anyhow::{Context, Result, anyhow};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+
+use crate::error_codes::{CodeExt, ErrorCode, coded};
+use crate::temp_workspace::TempWorkspace;
+use crate::{Cli, events, inputs, rules, summary};
+
+pub fn is_archive(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+}
+
+// Convert the HEIC files in `archive`, counting their outcomes in `tally`
+pub fn run(
+    cli: &Cli,
+    rules: &[rules::Rule],
+    devices: &BTreeMap<String, String>,
+    archive: &Path,
+    tally: &mut summary::Tally,
+) -> Result<()> {
+    let unsupported = [
+        (cli.delete_original || cli.trash_original, "--delete-original and --trash-original (the archive is kept)"),
+        (cli.resume, "--resume"),
+        (cli.skip_duplicates || cli.link_duplicates, "--skip-duplicates"),
+        (cli.report.is_some(), "--report"),
+        (cli.archive_output.is_some(), "--archive-output"),
+    ];
+    if let Some((_, option)) = unsupported.iter().find(|(used, _)| *used) {
+        return Err(anyhow!("❌ {} cannot be used for the photos in an archive", option));
+    }
+
+    let file = fs::File::open(archive)
+        .with_context(|| format!("❌ Cannot read {}", archive.display()))
+        .code(ErrorCode::InputUnreadable)?;
+    let mut zip = zip::ZipArchive::new(io::BufReader::new(file))
+        .with_context(|| format!("❌ {} is not a readable ZIP archive", archive.display()))
+        .code(ErrorCode::InputUnreadable)?;
+
+    // The HEIC entries by index, with the path they have in the archive
+    let mut photos = Vec::new();
+    for index in 0..zip.len() {
+        let entry = zip.by_index_raw(index).with_context(|| format!("❌ Cannot read {}", archive.display()))?;
+        if entry.is_dir() || !inputs::is_heic_name(Path::new(entry.name())) {
+            continue;
+        }
+        match entry.enclosed_name() {
+            Some(name) if !name.components().any(|part| part == Component::Normal("__MACOSX".as_ref())) => {
+                photos.push((index, name))
+            }
+            Some(_) => {}
+            None => events::warn(format!("Skipped {} in {}: the name leads out of the folder", entry.name(), archive.display())),
+        }
+    }
+    if photos.is_empty() {
+        return Err(coded(ErrorCode::InputNotFound, anyhow!("❌ No .heic or .heif files in {}", archive.display())));
+    }
+    let output_dir = match &cli.output_dir {
+        Some(dir) => dir.clone(),
+        None => archive.with_extension(""),
+    };
+    say!("Found {} HEIC files in {}; converting them into {}", photos.len(), archive.display(), output_dir.display());
+    tally.files += photos.len();
+
+    let mut failed = 0;
+    for (number, (index, name)) in photos.iter().enumerate() {
+        say!("[{}/{}] {}", number + 1, photos.len(), name.display());
+        // Only this photo is unpacked; the workspace goes with it
+        let workspace = TempWorkspace::create(cli.tmpdir.as_deref())?;
+        let unpacked = workspace.staged_input_path(name);
+        let copied = fs::create_dir_all(unpacked.parent().unwrap_or(Path::new(".")))
+            .and_then(|_| fs::File::create(&unpacked))
+            .and_then(|mut file| {
+                let mut entry = zip.by_index(*index).map_err(io::Error::other)?;
+                io::copy(&mut entry, &mut file)
+            });
+        if let Err(e) = copied {
+            say_err!("❌ Cannot unpack {} from {}: {}", name.display(), archive.display(), e);
+            tally.fail(&archive.join(name), &anyhow!("Cannot unpack it: {}", e));
+            failed += 1;
+            continue;
+        }
+
+        let folder = name.parent().filter(|folder| !folder.as_os_str().is_empty() && !cli.flatten && !cli.organize_by_date);
+        let single = Cli {
+            input: Some(unpacked),
+            output_dir: Some(folder.map_or_else(|| output_dir.clone(), |folder| output_dir.join(folder))),
+            position: number,
+            ..cli.clone()
+        };
+        match crate::run_conversion(&single, rules, devices).or_else(crate::keep_existing) {
+            Ok(_) => tally.converted += 1,
+            Err(e) => {
+                say_err!("{:?}", e);
+                tally.fail(&archive.join(name), &e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow!("❌ {} of {} HEIC files in {} could not be converted", failed, photos.len(), archive.display()));
+    }
+    say!("✅ Converted {} HEIC files from {} into {}", photos.len(), archive.display(), output_dir.display());
+    Ok(())
+}
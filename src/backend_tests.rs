@@ -0,0 +1,350 @@
+// Tests of the ImageMagick and FFmpeg backends, and of cjxl for JPEG XL, against a
+// recording ProcessRunner: the command lines they build and the messages their failures
+// turn into, without the tools installed
+use std::cell::RefCell;
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+
+use crate::error_codes::{ErrorCode, code_of};
+use crate::process::{Finished, Invocation, ProcessRunner};
+use crate::{Backend, BitDepth, ConvertOptions, OutputFormat, adjust, convert_with_ffmpeg, convert_with_imagemagick, jxl, negotiate, resize, tiff_writer};
+
+// What the mocked process does
+type Answer = fn() -> io::Result<Finished>;
+
+// Records every invocation and answers with a fixed result
+struct MockRunner {
+    answer: Answer,
+    calls: RefCell<Vec<Invocation>>,
+}
+
+impl MockRunner {
+    fn succeeding() -> Self {
+        MockRunner { answer: || Ok(Finished { success: true, ..Default::default() }), calls: RefCell::default() }
+    }
+
+    fn failing(answer: Answer) -> Self {
+        MockRunner { answer, calls: RefCell::default() }
+    }
+
+    fn only_call(&self) -> Invocation {
+        let calls = self.calls.borrow();
+        assert_eq!(calls.len(), 1, "expected exactly one process");
+        calls[0].clone()
+    }
+}
+
+impl ProcessRunner for MockRunner {
+    fn run(&self, invocation: &Invocation) -> io::Result<Finished> {
+        self.calls.borrow_mut().push(invocation.clone());
+        (self.answer)()
+    }
+}
+
+fn stderr(text: &'static str) -> io::Result<Finished> {
+    Ok(Finished { success: false, stderr: text.as_bytes().to_vec() })
+}
+
+fn options() -> ConvertOptions {
+    ConvertOptions {
+        format: OutputFormat::Png,
+        backend: Backend::Auto,
+        deterministic: false,
+        strip_metadata: false,
+        quality: None,
+        lossless: false,
+        tiff_compression: tiff_writer::TiffCompression::None,
+        jxl_distance: None,
+        bit_depth: None,
+        auto_quality: false,
+        resize: None,
+        adjust: adjust::Adjustments::default(),
+        embed_thumbnail: false,
+        split_output: None,
+        bwlimit: None,
+        to_stdout: false,
+        requested: Vec::new(),
+        on_unsupported: negotiate::OnUnsupported::Warn,
+    }
+}
+
+fn args(invocation: &Invocation) -> Vec<&str> {
+    invocation.args.iter().map(|arg| arg.to_str().unwrap()).collect()
+}
+
+fn imagemagick(runner: &MockRunner, options: &ConvertOptions) -> anyhow::Result<()> {
+    convert_with_imagemagick(runner, Path::new("in.heic"), Path::new("out.png"), options)
+}
+
+fn ffmpeg(runner: &MockRunner, options: &ConvertOptions) -> anyhow::Result<()> {
+    convert_with_ffmpeg(runner, Path::new("in.heic"), Path::new("out.png"), options)
+}
+
+#[test]
+fn imagemagick_plain_conversion() {
+    let runner = MockRunner::succeeding();
+    imagemagick(&runner, &options()).unwrap();
+    let call = runner.only_call();
+    assert_eq!(call.program, "convert");
+    assert_eq!(args(&call), ["in.heic", "out.png"]);
+    assert!(call.env.is_empty());
+}
+
+#[test]
+fn imagemagick_options_go_between_input_and_output() {
+    let runner = MockRunner::succeeding();
+    let options = ConvertOptions {
+        quality: Some(80),
+        resize: Some(resize::parse_resize("1600x").unwrap()),
+        strip_metadata: true,
+        ..options()
+    };
+    imagemagick(&runner, &options).unwrap();
+    let call = runner.only_call();
+    let args = args(&call);
+    assert_eq!(args.first(), Some(&"in.heic"));
+    assert_eq!(args.last(), Some(&"out.png"));
+    assert!(args.windows(2).any(|pair| pair == ["-quality", "80"]));
+    assert!(args.windows(2).any(|pair| pair == ["+profile", "!icc,*"]));
+    assert!(args.windows(2).any(|pair| pair == ["-resize", "1600x>"]));
+}
+
+#[test]
+fn imagemagick_deterministic_strips_and_limits_threads() {
+    let runner = MockRunner::succeeding();
+    imagemagick(&runner, &ConvertOptions { deterministic: true, strip_metadata: true, ..options() }).unwrap();
+    let call = runner.only_call();
+    assert_eq!(args(&call), ["in.heic", "-strip", "-define", "png:exclude-chunks=date,time", "out.png"]);
+    assert_eq!(call.env, [("MAGICK_THREAD_LIMIT".to_string(), "1".to_string())]);
+}
+
+#[test]
+fn imagemagick_dither_asks_for_16_bits() {
+    let runner = MockRunner::succeeding();
+    let adjust = adjust::Adjustments { dither: Some(adjust::Dither::Ordered), ..Default::default() };
+    imagemagick(&runner, &ConvertOptions { adjust, ..options() }).unwrap();
+    assert!(args(&runner.only_call()).windows(2).any(|pair| pair == ["-depth", "16"]));
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_paths_are_passed_unchanged() {
+    use std::os::unix::ffi::OsStrExt;
+    let input = std::ffi::OsStr::from_bytes(b"caf\xe9.heic");
+    let runner = MockRunner::succeeding();
+    convert_with_imagemagick(&runner, Path::new(input), Path::new("out.png"), &options()).unwrap();
+    assert_eq!(runner.only_call().args[0], OsString::from(input));
+}
+
+#[test]
+fn imagemagick_without_heic_delegate() {
+    let runner = MockRunner::failing(|| stderr("convert: no decode delegate for this image format `HEIC'"));
+    let error = imagemagick(&runner, &options()).unwrap_err();
+    assert_eq!(code_of(&error), Some(ErrorCode::ImageMagickNoHeic));
+    assert!(error.to_string().contains("brew install libheif"));
+}
+
+#[test]
+fn imagemagick_other_failures_keep_the_tool_message() {
+    let runner = MockRunner::failing(|| stderr("convert: insufficient image data"));
+    let error = imagemagick(&runner, &options()).unwrap_err();
+    assert_eq!(code_of(&error), Some(ErrorCode::ToolFailed));
+    assert!(error.to_string().contains("insufficient image data"));
+}
+
+#[test]
+fn missing_program_is_tool_missing() {
+    let runner = MockRunner::failing(|| Err(io::Error::from(io::ErrorKind::NotFound)));
+    assert_eq!(code_of(&imagemagick(&runner, &options()).unwrap_err()), Some(ErrorCode::ToolMissing));
+    assert_eq!(code_of(&ffmpeg(&runner, &options()).unwrap_err()), Some(ErrorCode::ToolMissing));
+}
+
+#[test]
+fn ffmpeg_plain_conversion_overwrites() {
+    let runner = MockRunner::succeeding();
+    ffmpeg(&runner, &options()).unwrap();
+    let call = runner.only_call();
+    assert_eq!(call.program, "ffmpeg");
+    assert_eq!(args(&call), ["-i", "in.heic", "-y", "out.png"]);
+}
+
+#[test]
+fn ffmpeg_quality_maps_to_its_jpeg_scale() {
+    for (quality, scale) in [(100, "2"), (90, "4"), (50, "12"), (0, "22")] {
+        let runner = MockRunner::succeeding();
+        ffmpeg(&runner, &ConvertOptions { quality: Some(quality), ..options() }).unwrap();
+        let call = runner.only_call();
+        assert!(args(&call).windows(2).any(|pair| pair == ["-q:v", scale]), "quality {}", quality);
+    }
+}
+
+#[test]
+fn webp_quality_and_lossless_reach_both_tools() {
+    let lossy = ConvertOptions { format: OutputFormat::Webp, quality: Some(80), ..options() };
+    let lossless = ConvertOptions { format: OutputFormat::Webp, lossless: true, ..options() };
+    for (options, imagemagick_pair, ffmpeg_pair) in
+        [(lossy, ["-quality", "80"], ["-quality", "80"]), (lossless, ["-define", "webp:lossless=true"], ["-lossless", "1"])]
+    {
+        let runner = MockRunner::succeeding();
+        convert_with_imagemagick(&runner, Path::new("in.heic"), Path::new("out.webp"), &options).unwrap();
+        assert!(args(&runner.only_call()).windows(2).any(|pair| pair == imagemagick_pair));
+        let runner = MockRunner::succeeding();
+        convert_with_ffmpeg(&runner, Path::new("in.heic"), Path::new("out.webp"), &options).unwrap();
+        let call = runner.only_call();
+        assert!(args(&call).windows(2).any(|pair| pair == ffmpeg_pair), "{:?}", args(&call));
+        assert!(!args(&call).contains(&"-q:v"));
+    }
+}
+
+#[test]
+fn ffmpeg_avif_quality_is_a_crf() {
+    let runner = MockRunner::succeeding();
+    let options = ConvertOptions { format: OutputFormat::Avif, quality: Some(80), ..options() };
+    convert_with_ffmpeg(&runner, Path::new("in.heic"), Path::new("out.avif"), &options).unwrap();
+    assert_eq!(args(&runner.only_call()), ["-i", "in.heic", "-crf", "12", "-y", "out.avif"]);
+}
+
+#[test]
+fn tiff_compression_reaches_both_tools() {
+    let options = ConvertOptions {
+        format: OutputFormat::Tiff,
+        tiff_compression: tiff_writer::TiffCompression::Lzw,
+        ..options()
+    };
+    let runner = MockRunner::succeeding();
+    convert_with_imagemagick(&runner, Path::new("in.heic"), Path::new("out.tiff"), &options).unwrap();
+    assert_eq!(args(&runner.only_call()), ["in.heic", "-compress", "LZW", "out.tiff"]);
+    let runner = MockRunner::succeeding();
+    convert_with_ffmpeg(&runner, Path::new("in.heic"), Path::new("out.tiff"), &options).unwrap();
+    assert_eq!(args(&runner.only_call()), ["-i", "in.heic", "-compression_algo", "lzw", "-y", "out.tiff"]);
+}
+
+#[test]
+fn bmp_is_written_without_alpha_by_both_tools() {
+    let options = ConvertOptions { format: OutputFormat::Bmp, ..options() };
+    let runner = MockRunner::succeeding();
+    convert_with_imagemagick(&runner, Path::new("in.heic"), Path::new("out.bmp"), &options).unwrap();
+    assert_eq!(args(&runner.only_call()), ["in.heic", "BMP3:out.bmp"]);
+    let runner = MockRunner::succeeding();
+    convert_with_ffmpeg(&runner, Path::new("in.heic"), Path::new("out.bmp"), &options).unwrap();
+    assert_eq!(args(&runner.only_call()), ["-i", "in.heic", "-pix_fmt", "bgr24", "-y", "out.bmp"]);
+}
+
+#[test]
+fn imagemagick_writes_colour_ppm_for_pnm_names() {
+    let options = ConvertOptions { format: OutputFormat::Ppm, ..options() };
+    let runner = MockRunner::succeeding();
+    convert_with_imagemagick(&runner, Path::new("in.heic"), Path::new("out.pnm"), &options).unwrap();
+    assert_eq!(args(&runner.only_call()), ["in.heic", "PPM:out.pnm"]);
+}
+
+#[test]
+fn bit_depth_reaches_both_tools() {
+    let sixteen = ConvertOptions { bit_depth: Some(BitDepth::Sixteen), ..options() };
+    let runner = MockRunner::succeeding();
+    imagemagick(&runner, &sixteen).unwrap();
+    assert_eq!(args(&runner.only_call()), ["in.heic", "-depth", "16", "out.png"]);
+    let runner = MockRunner::succeeding();
+    ffmpeg(&runner, &sixteen).unwrap();
+    assert_eq!(args(&runner.only_call()), ["-i", "in.heic", "-pix_fmt", "rgb48be", "-y", "out.png"]);
+
+    // FFmpeg's TIFF encoder takes 16-bit samples little-endian only
+    let runner = MockRunner::succeeding();
+    let tiff = ConvertOptions { format: OutputFormat::Tiff, ..sixteen };
+    convert_with_ffmpeg(&runner, Path::new("in.heic"), Path::new("out.tiff"), &tiff).unwrap();
+    assert!(args(&runner.only_call()).windows(2).any(|pair| pair == ["-pix_fmt", "rgb48le"]));
+
+    let eight = ConvertOptions { bit_depth: Some(BitDepth::Eight), ..options() };
+    let runner = MockRunner::succeeding();
+    imagemagick(&runner, &eight).unwrap();
+    assert_eq!(args(&runner.only_call()), ["in.heic", "-depth", "8", "out.png"]);
+    let runner = MockRunner::succeeding();
+    ffmpeg(&runner, &eight).unwrap();
+    assert_eq!(args(&runner.only_call()), ["-i", "in.heic", "-pix_fmt", "rgb24", "-y", "out.png"]);
+}
+
+#[test]
+fn exr_is_decoded_at_16_bits() {
+    let exr = ConvertOptions { format: OutputFormat::Exr, ..options() };
+    let runner = MockRunner::succeeding();
+    imagemagick(&runner, &exr).unwrap();
+    assert_eq!(args(&runner.only_call()), ["in.heic", "-depth", "16", "out.png"]);
+    let runner = MockRunner::succeeding();
+    ffmpeg(&runner, &exr).unwrap();
+    assert_eq!(args(&runner.only_call()), ["-i", "in.heic", "-pix_fmt", "rgb48be", "-y", "out.png"]);
+}
+
+#[test]
+fn jxl_quality_or_distance_reaches_cjxl() {
+    let dir = std::env::temp_dir().join(format!("heic_convert-jxl-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let output = dir.join("out.jxl");
+    let png = output.with_extension("cjxl.png");
+    let img = image::DynamicImage::new_rgb8(4, 4);
+
+    let runner = MockRunner::succeeding();
+    jxl::save(&runner, &img, &output, Some(75), None).unwrap();
+    let call = runner.only_call();
+    assert_eq!(call.program, "cjxl");
+    assert_eq!(args(&call)[2..], ["-q", "75"]);
+    assert_eq!(call.args[..2], [png.clone().into_os_string(), output.clone().into_os_string()]);
+    assert!(!png.exists(), "the PNG handed to cjxl is removed");
+
+    let runner = MockRunner::succeeding();
+    jxl::save(&runner, &img, &output, None, Some(0.0)).unwrap();
+    assert_eq!(args(&runner.only_call())[2..], ["-d", "0"]);
+
+    let runner = MockRunner::failing(|| Err(io::Error::from(io::ErrorKind::NotFound)));
+    let error = jxl::save(&runner, &img, &output, None, Some(1.5)).unwrap_err();
+    assert_eq!(code_of(&error), Some(ErrorCode::ToolMissing));
+    assert!(format!("{:#}", error).contains("needs cjxl"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn ffmpeg_deterministic_is_bit_exact_on_one_thread() {
+    let runner = MockRunner::succeeding();
+    ffmpeg(&runner, &ConvertOptions { deterministic: true, ..options() }).unwrap();
+    assert_eq!(
+        args(&runner.only_call()),
+        [
+            "-threads", "1", "-i", "in.heic", "-fflags", "+bitexact", "-flags:v", "+bitexact", "-map_metadata", "-1",
+            "-y", "out.png"
+        ]
+    );
+}
+
+#[test]
+fn ffmpeg_share_safe_drops_metadata() {
+    let runner = MockRunner::succeeding();
+    ffmpeg(&runner, &ConvertOptions { strip_metadata: true, ..options() }).unwrap();
+    assert_eq!(args(&runner.only_call()), ["-i", "in.heic", "-map_metadata", "-1", "-y", "out.png"]);
+}
+
+#[test]
+fn ffmpeg_failures_are_classified() {
+    let cases: [(Answer, ErrorCode, &str); 3] = [
+        (|| stderr("in.heic: Invalid data found when processing input"), ErrorCode::ToolFailed, "corrupted"),
+        (|| stderr("out.png: Permission denied"), ErrorCode::SaveFailed, "out.png"),
+        (|| stderr("Conversion failed!"), ErrorCode::ToolFailed, "Conversion failed!"),
+    ];
+    for (answer, code, text) in cases {
+        let error = ffmpeg(&MockRunner::failing(answer), &options()).unwrap_err();
+        assert_eq!(code_of(&error), Some(code));
+        assert!(error.to_string().contains(text), "{}", error);
+    }
+}
+
+#[test]
+fn availability_needs_a_successful_version_call() {
+    let runner = MockRunner::succeeding();
+    assert!(crate::process::available(&runner, "convert"));
+    assert_eq!(args(&runner.only_call()), ["-version"]);
+
+    assert!(!crate::process::available(&MockRunner::failing(|| stderr("")), "convert"));
+    assert!(!crate::process::available(
+        &MockRunner::failing(|| Err(io::Error::from(io::ErrorKind::NotFound))),
+        "convert"
+    ));
+}
@@ -0,0 +1,66 @@
+// Canonical re-encoding of an image (`heic_convert canonical <img>`)
+//
+// Outputs written by different backends (image crate, ImageMagick, FFmpeg) differ in
+// metadata, chunk order, compression level and pixel layout even when the pixels are
+// the same. The canonical form is a metadata-free PNG with fixed encoder settings and
+// a normalized pixel layout, so two canonical files are byte-identical exactly when
+// the images look the same.
+use anyhow::{Context, Result};
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::{DynamicImage, ImageEncoder};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+// Write the canonical PNG of `input` to `output` (default: <stem>.canonical.png)
+pub fn write_canonical(input: &Path, output: Option<&Path>) -> Result<PathBuf> {
+    let image = image::open(input).with_context(|| {
+        format!(
+            "❌ Cannot decode image: {}\n\
+             `canonical` accepts any format the built-in decoder reads (PNG, JPEG, TIFF, WebP, ...).",
+            input.display()
+        )
+    })?;
+
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| {
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        input.with_file_name(format!("{}.canonical.png", stem))
+    });
+
+    let file = File::create(&output)
+        .with_context(|| format!("❌ Failed to create {}", output.display()))?;
+    let encoder = PngEncoder::new_with_quality(
+        BufWriter::new(file),
+        CompressionType::Default,
+        FilterType::Adaptive,
+    );
+
+    // 8-bit RGB, or RGBA only when the image actually uses transparency; 16-bit
+    // sources keep their precision
+    let (width, height) = (image.width(), image.height());
+    let sixteen_bit = image.color().bytes_per_pixel() / image.color().channel_count() > 1;
+    let opaque = !image.color().has_alpha() || image.to_rgba16().pixels().all(|p| p[3] == u16::MAX);
+
+    match (sixteen_bit, opaque) {
+        (false, true) => {
+            let pixels = image.to_rgb8();
+            encoder.write_image(&pixels, width, height, image::ExtendedColorType::Rgb8)
+        }
+        (false, false) => {
+            let pixels = image.to_rgba8();
+            encoder.write_image(&pixels, width, height, image::ExtendedColorType::Rgba8)
+        }
+        (true, true) => {
+            let pixels = DynamicImage::ImageRgb16(image.to_rgb16());
+            encoder.write_image(pixels.as_bytes(), width, height, image::ExtendedColorType::Rgb16)
+        }
+        (true, false) => {
+            let pixels = DynamicImage::ImageRgba16(image.to_rgba16());
+            encoder.write_image(pixels.as_bytes(), width, height, image::ExtendedColorType::Rgba16)
+        }
+    }
+    .with_context(|| format!("❌ Failed to write {}", output.display()))?;
+
+    say!("✅ Canonical image written to {}", output.display());
+    Ok(output)
+}
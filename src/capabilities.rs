@@ -0,0 +1,150 @@
+// `heic_convert --version --json`: what this build can do, for wrapper tools and installers
+//
+// Reports the version and commit, the cargo features compiled in, the formats the
+// built-in codecs read and write, and the external backends (ImageMagick, FFmpeg, cjxl)
+// found on this machine.
+use clap::ValueEnum;
+use image::ImageFormat;
+use serde::Serialize;
+#[cfg(feature = "external-tools")]
+use std::process::Command;
+
+use crate::OutputFormat;
+
+#[derive(Serialize)]
+pub struct Capabilities {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub git_hash: Option<&'static str>,
+    pub target: Target,
+    pub features: Vec<&'static str>,
+    pub output_formats: Vec<&'static str>,
+    pub codecs: Codecs,
+    pub backends: Vec<BackendInfo>,
+}
+
+#[derive(Serialize)]
+pub struct Target {
+    pub os: &'static str,
+    pub arch: &'static str,
+}
+
+// Formats of the built-in (image crate) codecs, by file extension
+#[derive(Serialize)]
+pub struct Codecs {
+    pub decode: Vec<&'static str>,
+    pub encode: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+pub struct BackendInfo {
+    pub name: &'static str,
+    pub available: bool,
+    pub version: Option<String>, // First line of the tool's version output
+}
+
+// Every cargo feature of Cargo.toml, and whether this build has it
+const FEATURES: [(&str, bool); 8] = [
+    ("webhook", cfg!(feature = "webhook")),
+    ("email", cfg!(feature = "email")),
+    ("upload", cfg!(feature = "upload")),
+    ("scripting", cfg!(feature = "scripting")),
+    ("self-update", cfg!(feature = "self-update")),
+    ("ios-backup", cfg!(feature = "ios-backup")),
+    ("external-tools", cfg!(feature = "external-tools")),
+    ("watch", cfg!(feature = "watch")),
+];
+
+pub fn detect() -> Capabilities {
+    let formats = |enabled: fn(&ImageFormat) -> bool| -> Vec<&'static str> {
+        ImageFormat::all()
+            .filter(enabled)
+            .filter_map(|format| format.extensions_str().first().copied())
+            .collect()
+    };
+
+    Capabilities {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: option_env!("HEIC_CONVERT_GIT_HASH"),
+        target: Target {
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+        },
+        features: FEATURES.iter().filter(|(_, on)| *on).map(|(name, _)| *name).collect(),
+        output_formats: output_formats(),
+        codecs: Codecs {
+            decode: formats(|format| format.reading_enabled()),
+            encode: formats(|format| format.writing_enabled()),
+        },
+        backends: vec![
+            BackendInfo {
+                name: "builtin",
+                available: true,
+                version: None,
+            },
+            external("imagemagick", "convert", "-version"),
+            external("ffmpeg", "ffmpeg", "-version"),
+            external("cjxl", "cjxl", "--version"),
+        ],
+    }
+}
+
+// The formats -f accepts, by extension (jpg and jpeg are one)
+fn output_formats() -> Vec<&'static str> {
+    let mut extensions: Vec<&'static str> = OutputFormat::value_variants().iter().map(OutputFormat::extension).collect();
+    extensions.dedup();
+    extensions
+}
+
+#[cfg(feature = "external-tools")]
+fn external(name: &'static str, program: &str, version_flag: &str) -> BackendInfo {
+    let output = Command::new(program).arg(version_flag).output().ok().filter(|out| out.status.success());
+    BackendInfo {
+        name,
+        available: output.is_some(),
+        version: output.and_then(|out| String::from_utf8_lossy(&out.stdout).lines().next().map(String::from)),
+    }
+}
+
+// Builds without external tools never use them
+#[cfg(not(feature = "external-tools"))]
+fn external(name: &'static str, _program: &str, _version_flag: &str) -> BackendInfo {
+    BackendInfo { name, available: false, version: None }
+}
+
+// The JSON document on stdout; nothing else is printed
+pub fn print_json() -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(&detect())?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_cargo_feature_is_reported() {
+        let manifest: toml::Table = include_str!("../Cargo.toml").parse().unwrap();
+        let mut declared: Vec<&str> = manifest["features"]
+            .as_table()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .filter(|name| *name != "default")
+            .collect();
+        let mut reported: Vec<&str> = FEATURES.iter().map(|(name, _)| *name).collect();
+        declared.sort_unstable();
+        reported.sort_unstable();
+        assert_eq!(reported, declared);
+    }
+
+    #[test]
+    fn every_output_format_is_reported_once() {
+        let formats = output_formats();
+        assert_eq!(formats.len(), OutputFormat::value_variants().len() - 1, "jpg and jpeg are one format");
+        for extension in ["png", "jpg", "webp", "avif", "tiff", "bmp", "jxl", "pdf", "exr", "ppm", "ico"] {
+            assert!(formats.contains(&extension), "{} is missing", extension);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+// User configuration file (config.toml)
+//
+// Looked up at --config <FILE> or, by default, at
+//   $XDG_CONFIG_HOME/heic_convert/config.toml  (usually ~/.config/heic_convert/config.toml)
+//   %APPDATA%\heic_convert\config.toml         (Windows)
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Everything that can be set in the configuration file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // Outgoing mail server used by --email-report
+    #[cfg(feature = "email")]
+    pub smtp: Option<SmtpConfig>,
+
+    // Per-file option overrides, see rules.rs
+    #[serde(rename = "rule")]
+    pub rules: Vec<RuleConfig>,
+
+    // Folder names for --by-device, by EXIF camera model: "iPhone 15 Pro" = "Anna"
+    pub devices: BTreeMap<String, String>,
+
+    // Used where the command line leaves an option out; written by `heic_convert init`
+    pub defaults: Defaults,
+}
+
+// [defaults] section; the format is checked when the configuration is applied
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Defaults {
+    pub format: Option<String>,
+    pub quality: Option<u8>,         // JPEG quality, 1-100
+    pub output_dir: Option<PathBuf>, // Generated output names go here instead of next to the input
+}
+
+// [[rule]] section: when = "width > 8000", set = { resize = "4000x" }
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    pub when: String,
+    #[serde(default)]
+    pub set: RuleSettings,
+}
+
+// Options a rule can set; the values are checked by rules::compile
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleSettings {
+    pub format: Option<String>,
+    pub backend: Option<String>,
+    pub resize: Option<String>,
+}
+
+// [smtp] section
+#[cfg(feature = "email")]
+#[derive(Debug, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // Name of an environment variable holding the password, to keep secrets out of the file
+    pub password_env: Option<String>,
+    pub from: String,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+}
+
+// How the SMTP connection is secured
+#[cfg(feature = "email")]
+#[derive(Debug, Default, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    #[default]
+    Starttls, // Port 587, upgrade to TLS
+    Tls,      // Port 465, implicit TLS
+    None,     // Plain text, only for local relays
+}
+
+// Default location of the configuration file
+pub fn default_path() -> Option<PathBuf> {
+    if cfg!(windows) {
+        return std::env::var_os("APPDATA")
+            .map(|dir| PathBuf::from(dir).join("heic_convert").join("config.toml"));
+    }
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("heic_convert").join("config.toml"))
+}
+
+// Load the configuration. An explicitly given file must exist; a missing default file
+// simply means "no configuration".
+pub fn load(explicit: Option<&Path>) -> Result<Config> {
+    let path = match explicit {
+        Some(path) => path.to_path_buf(),
+        None => match default_path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Config::default()),
+        },
+    };
+
+    let content = fs::read_to_string(&path).with_context(|| {
+        format!(
+            "❌ Cannot read configuration file: {}\n\
+             Please check that the file exists and is readable.",
+            path.display()
+        )
+    })?;
+
+    toml::from_str(&content).with_context(|| {
+        format!(
+            "❌ Invalid configuration file: {}\n\
+             Please check the TOML syntax and option names.",
+            path.display()
+        )
+    })
+}
@@ -0,0 +1,174 @@
+// CPU core selection on heterogeneous CPUs (--cores performance|efficiency|all)
+//
+// Linux: the process is pinned to the chosen core type with sched_setaffinity, which
+//        ImageMagick / FFmpeg child processes inherit.
+// macOS: Apple Silicon has no affinity API; the quality-of-service class decides whether
+//        work lands on P- or E-cores, so we set it for our own threads and launch the
+//        external tools through `taskpolicy` when efficiency cores are requested.
+use clap::ValueEnum;
+#[cfg(feature = "external-tools")]
+use std::process::Command;
+use std::sync::OnceLock;
+
+// Which kind of cores conversions may use
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum CoreSelection {
+    #[default]
+    All,         // Let the operating system decide
+    Performance, // Maximum speed (P-cores)
+    Efficiency,  // Battery friendly background conversion (E-cores)
+}
+
+static SELECTION: OnceLock<CoreSelection> = OnceLock::new();
+
+// Apply the selection to this process. Must run before worker threads are started
+// so that they inherit the affinity.
+pub fn apply(selection: CoreSelection) {
+    let _ = SELECTION.set(selection);
+    if selection == CoreSelection::All {
+        return;
+    }
+    apply_platform(selection);
+}
+
+// Build a Command for an external conversion tool that honours the core selection
+#[cfg(feature = "external-tools")]
+pub fn command(program: &str) -> Command {
+    if cfg!(target_os = "macos") && SELECTION.get() == Some(&CoreSelection::Efficiency) {
+        // Clamp the child to background QoS so it only runs on efficiency cores
+        let mut command = Command::new("taskpolicy");
+        command.arg("-c").arg("background").arg(program);
+        return command;
+    }
+    Command::new(program)
+}
+
+#[cfg(target_os = "linux")]
+fn apply_platform(selection: CoreSelection) {
+    let Some((performance, efficiency)) = linux_core_groups() else {
+        say!("ℹ️  All CPU cores are of the same type; --cores has no effect on this machine.");
+        return;
+    };
+    let cpus = match selection {
+        CoreSelection::Performance => performance,
+        _ => efficiency,
+    };
+
+    // SAFETY: cpu_set_t is plain data; we only pass a valid, initialised set to the kernel
+    let result = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for cpu in &cpus {
+            libc::CPU_SET(*cpu, &mut set);
+        }
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+
+    if result == 0 {
+        say!("Using {:?} cores: {}", selection, format_cpu_list(&cpus));
+    } else {
+        say_err!(
+            "⚠️  Could not restrict conversion to {:?} cores: {}",
+            selection,
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn apply_platform(selection: CoreSelection) {
+    let class = match selection {
+        CoreSelection::Performance => libc::qos_class_t::QOS_CLASS_USER_INITIATED,
+        _ => libc::qos_class_t::QOS_CLASS_BACKGROUND,
+    };
+    // SAFETY: plain FFI call that only changes the scheduling class of the current thread
+    let result = unsafe { libc::pthread_set_qos_class_self_np(class, 0) };
+    if result == 0 {
+        say!("Using {:?} cores (quality-of-service class adjusted)", selection);
+    } else {
+        say_err!("⚠️  Could not adjust the quality-of-service class for --cores");
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn apply_platform(_selection: CoreSelection) {
+    say_err!("⚠️  --cores is not supported on this platform yet; using all cores.");
+}
+
+// Split the online CPUs into (performance, efficiency) groups, or None on homogeneous CPUs.
+// Intel hybrid CPUs expose the groups directly; ARM big.LITTLE systems report a relative
+// capacity per core; otherwise the maximum clock frequency is used as a hint.
+#[cfg(target_os = "linux")]
+fn linux_core_groups() -> Option<(Vec<usize>, Vec<usize>)> {
+    use std::fs;
+
+    if let (Ok(core), Ok(atom)) = (
+        fs::read_to_string("/sys/devices/cpu_core/cpus"),
+        fs::read_to_string("/sys/devices/cpu_atom/cpus"),
+    ) {
+        let performance = parse_cpu_list(&core);
+        let efficiency = parse_cpu_list(&atom);
+        if !performance.is_empty() && !efficiency.is_empty() {
+            return Some((performance, efficiency));
+        }
+    }
+
+    let online = fs::read_to_string("/sys/devices/system/cpu/online")
+        .map(|list| parse_cpu_list(&list))
+        .unwrap_or_default();
+
+    for attribute in ["cpu_capacity", "cpufreq/cpuinfo_max_freq"] {
+        let ratings: Vec<(usize, u64)> = online
+            .iter()
+            .filter_map(|cpu| {
+                fs::read_to_string(format!("/sys/devices/system/cpu/cpu{}/{}", cpu, attribute))
+                    .ok()
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+                    .map(|rating| (*cpu, rating))
+            })
+            .collect();
+        if ratings.len() != online.len() || ratings.is_empty() {
+            continue;
+        }
+
+        let best = ratings.iter().map(|(_, rating)| *rating).max().unwrap_or(0);
+        let (performance, efficiency): (Vec<_>, Vec<_>) =
+            ratings.iter().partition(|(_, rating)| *rating == best);
+        if !efficiency.is_empty() {
+            return Some((
+                performance.into_iter().map(|(cpu, _)| cpu).collect(),
+                efficiency.into_iter().map(|(cpu, _)| cpu).collect(),
+            ));
+        }
+    }
+
+    None
+}
+
+// Parse kernel CPU lists such as "0-3,8,10-11"
+#[cfg(target_os = "linux")]
+fn parse_cpu_list(list: &str) -> Vec<usize> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                if let (Ok(start), Ok(end)) = (start.parse::<usize>(), end.parse::<usize>()) {
+                    cpus.extend(start..=end);
+                }
+            }
+            None => {
+                if let Ok(cpu) = part.parse() {
+                    cpus.push(cpu);
+                }
+            }
+        }
+    }
+    cpus
+}
+
+#[cfg(target_os = "linux")]
+fn format_cpu_list(cpus: &[usize]) -> String {
+    cpus.iter()
+        .map(|cpu| cpu.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
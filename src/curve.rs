@@ -0,0 +1,206 @@
+// Tone curves (--curve), applied to all colour channels or to one of them
+//
+// A curve is a list of input:output points on the 0-255 scale, e.g. "64:48,192:210"
+// for a gentle S-curve. 0:0 and 255:255 are added when the ends are not given, and
+// the points are joined smoothly without overshooting (monotone cubic interpolation).
+// A channel prefix limits the curve: "b=0:16,255:240" lifts the blue shadows only.
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    All,
+    Red,
+    Green,
+    Blue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    pub channel: Channel,
+    points: Vec<(f32, f32)>, // 0-1, sorted by input
+    slopes: Vec<f32>,
+}
+
+// Parse "[CHANNEL=]IN:OUT,IN:OUT,..."
+pub fn parse_curve(text: &str) -> Result<Curve, String> {
+    let usage = |detail: &str| {
+        format!(
+            "invalid curve '{}': {}; use [r|g|b=]IN:OUT,IN:OUT with values 0-255, e.g. 64:48,192:210",
+            text, detail
+        )
+    };
+    let (channel, list) = match text.trim().split_once('=') {
+        Some((name, list)) => {
+            let channel = match name.trim().to_lowercase().as_str() {
+                "rgb" => Channel::All,
+                "r" | "red" => Channel::Red,
+                "g" | "green" => Channel::Green,
+                "b" | "blue" => Channel::Blue,
+                _ => return Err(usage("unknown channel")),
+            };
+            (channel, list)
+        }
+        None => (Channel::All, text),
+    };
+
+    let mut points = Vec::new();
+    for pair in list.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+        let (input, output) = pair.split_once(':').ok_or_else(|| usage("expected IN:OUT"))?;
+        let value = |part: &str| part.trim().parse::<f32>().ok().filter(|v| (0.0..=255.0).contains(v));
+        match (value(input), value(output)) {
+            (Some(input), Some(output)) => points.push((input / 255.0, output / 255.0)),
+            _ => return Err(usage("values must be numbers from 0 to 255")),
+        }
+    }
+    if points.is_empty() {
+        return Err(usage("no points"));
+    }
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    if points.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+        return Err(usage("two points share the same input value"));
+    }
+    if points[0].0 > 0.0 {
+        points.insert(0, (0.0, 0.0));
+    }
+    if points[points.len() - 1].0 < 1.0 {
+        points.push((1.0, 1.0));
+    }
+
+    let slopes = monotone_slopes(&points);
+    Ok(Curve { channel, points, slopes })
+}
+
+// Fritsch-Carlson tangents: the curve never overshoots between two points
+fn monotone_slopes(points: &[(f32, f32)]) -> Vec<f32> {
+    let secants: Vec<f32> = points
+        .windows(2)
+        .map(|pair| (pair[1].1 - pair[0].1) / (pair[1].0 - pair[0].0))
+        .collect();
+    let mut slopes = vec![0f32; points.len()];
+    slopes[0] = secants[0];
+    slopes[points.len() - 1] = secants[secants.len() - 1];
+    for i in 1..points.len() - 1 {
+        // Flat at local extremes, the harmonic mean elsewhere
+        let (before, after) = (secants[i - 1], secants[i]);
+        slopes[i] = if before * after <= 0.0 {
+            0.0
+        } else {
+            2.0 / (1.0 / before + 1.0 / after)
+        };
+    }
+    slopes
+}
+
+impl Curve {
+    pub fn eval(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        let segment = self
+            .points
+            .windows(2)
+            .position(|pair| x <= pair[1].0)
+            .unwrap_or(self.points.len() - 2);
+        let ((x0, y0), (x1, y1)) = (self.points[segment], self.points[segment + 1]);
+        let h = x1 - x0;
+        let t = (x - x0) / h;
+        // Cubic Hermite basis
+        let (t2, t3) = (t * t, t * t * t);
+        let value = (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+            + (t3 - 2.0 * t2 + t) * h * self.slopes[segment]
+            + (-2.0 * t3 + 3.0 * t2) * y1
+            + (t3 - t2) * h * self.slopes[segment + 1];
+        value.clamp(0.0, 1.0)
+    }
+
+    // Whether the curve changes channel `index` (0 = red, 1 = green, 2 = blue)
+    pub fn affects(&self, index: usize) -> bool {
+        match self.channel {
+            Channel::All => true,
+            Channel::Red => index == 0,
+            Channel::Green => index == 1,
+            Channel::Blue => index == 2,
+        }
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let channel = match self.channel {
+            Channel::All => "rgb",
+            Channel::Red => "r",
+            Channel::Green => "g",
+            Channel::Blue => "b",
+        };
+        let points: Vec<String> = self
+            .points
+            .iter()
+            .map(|(input, output)| format!("{}:{}", (input * 255.0).round(), (output * 255.0).round()))
+            .collect();
+        write!(f, "{}={}", channel, points.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_are_parsed() {
+        let cases = [
+            ("64:48,192:210", "rgb=0:0,64:48,192:210,255:255"),
+            ("b=0:16,255:240", "b=0:16,255:240"),
+            ("Red = 128:140", "r=0:0,128:140,255:255"),
+            ("g=255:200, 0:30,", "g=0:30,255:200"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_curve(text).unwrap().to_string(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn mistakes_are_explained() {
+        let cases = [
+            ("", "no points"),
+            ("x=0:10", "unknown channel"),
+            ("64-48", "expected IN:OUT"),
+            ("64:300", "values must be numbers from 0 to 255"),
+            ("-1:0", "values must be numbers from 0 to 255"),
+            ("64:a", "values must be numbers from 0 to 255"),
+            ("64:48,64:60", "two points share the same input value"),
+        ];
+        for (text, message) in cases {
+            let error = parse_curve(text).unwrap_err();
+            assert!(error.contains(message), "{:?}: {}", text, error);
+        }
+    }
+
+    #[test]
+    fn curve_passes_through_its_points() {
+        let curve = parse_curve("64:48,192:210").unwrap();
+        for (input, output) in [(0.0, 0.0), (64.0, 48.0), (192.0, 210.0), (255.0, 255.0)] {
+            assert!((curve.eval(input / 255.0) * 255.0 - output).abs() < 1e-3, "{}", input);
+        }
+        // Inputs outside 0-1 are clamped
+        assert_eq!(curve.eval(-0.5), 0.0);
+        assert_eq!(curve.eval(1.5), 1.0);
+    }
+
+    #[test]
+    fn interpolation_never_overshoots() {
+        // A steep step between flat parts would overshoot with plain cubic splines
+        let curve = parse_curve("0:0,100:10,120:245,255:255").unwrap();
+        let values: Vec<f32> = (0..=255).map(|x| curve.eval(x as f32 / 255.0)).collect();
+        assert!(values.windows(2).all(|pair| pair[1] >= pair[0] - 1e-6), "the curve is not monotone");
+        assert!(values.iter().all(|v| (0.0..=1.0).contains(v)));
+
+        // Flat at a local peak instead of rising above it
+        let peak = parse_curve("0:0,128:200,255:0").unwrap();
+        assert!((0..=255).all(|x| peak.eval(x as f32 / 255.0) <= 200.0 / 255.0 + 1e-6));
+    }
+
+    #[test]
+    fn channel_limits_the_curve() {
+        let blue = parse_curve("b=0:16").unwrap();
+        assert_eq!((0..3).map(|i| blue.affects(i)).collect::<Vec<_>>(), [false, false, true]);
+        assert!((0..3).all(|i| parse_curve("rgb=0:16").unwrap().affects(i)));
+    }
+}
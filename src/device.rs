@@ -0,0 +1,39 @@
+// Sort outputs into one folder per camera (--by-device)
+//
+// Photos of several phones collected in one place are told apart by their EXIF camera
+// model. The folder is named after the model ("iPhone 15 Pro") unless the [devices]
+// section of the configuration file gives it a friendlier name:
+//
+//   [devices]
+//   "iPhone 15 Pro" = "Anna"
+//   "iPhone 12 mini" = "Ben"
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::exif;
+
+// Folder for images without a camera model (screenshots, edited exports)
+pub const UNKNOWN: &str = "Unknown device";
+
+// Folder name for `input`. Model names are matched case-insensitively.
+pub fn folder(input: &Path, names: &BTreeMap<String, String>) -> String {
+    let tags = exif::read(input);
+    let Some(model) = tags.model.or(tags.make) else {
+        return UNKNOWN.to_string();
+    };
+    let name = names
+        .iter()
+        .find(|(configured, _)| configured.trim().eq_ignore_ascii_case(&model))
+        .map_or(model.as_str(), |(_, name)| name.as_str());
+    sanitize(name)
+}
+
+// Keep the name usable as a directory on every platform
+fn sanitize(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    if cleaned.is_empty() { UNKNOWN.to_string() } else { cleaned.to_string() }
+}
@@ -0,0 +1,79 @@
+// Inputs with the same content (--skip-duplicates, --link-duplicates)
+//
+// Phone exports and merged backups often hold byte-identical copies of a photo under
+// other names ("IMG_0001 copy.HEIC", "IMG_0001(1).HEIC"). Before a multi-file run starts,
+// inputs of the same size are compared by a BLAKE3 hash of their content; the first of
+// each group is converted and the others are its copies, which are left alone or, with
+// --link-duplicates, get a hard link to its output. Files that cannot be read are kept
+// as inputs, so that their conversion reports the problem.
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// The inputs to convert, in their order, and each copy with the input it is a copy of
+pub fn find(inputs: &[PathBuf]) -> (Vec<PathBuf>, Vec<(PathBuf, PathBuf)>) {
+    // Only files that share their size with another one are read
+    let mut sizes: HashMap<u64, usize> = HashMap::new();
+    for input in inputs {
+        if let Ok(metadata) = fs::metadata(input) {
+            *sizes.entry(metadata.len()).or_default() += 1;
+        }
+    }
+
+    let mut first_with: HashMap<(u64, blake3::Hash), &PathBuf> = HashMap::new();
+    let mut unique = Vec::new();
+    let mut copies = Vec::new();
+    for input in inputs {
+        let Ok(size) = fs::metadata(input).map(|metadata| metadata.len()) else {
+            unique.push(input.clone());
+            continue;
+        };
+        if sizes.get(&size).is_none_or(|count| *count < 2) {
+            unique.push(input.clone());
+            continue;
+        }
+        match hash(input) {
+            Ok(hash) => match first_with.get(&(size, hash)) {
+                Some(original) => copies.push((input.clone(), (*original).clone())),
+                None => {
+                    first_with.insert((size, hash), input);
+                    unique.push(input.clone());
+                }
+            },
+            Err(_) => unique.push(input.clone()),
+        }
+    }
+    (unique, copies)
+}
+
+fn hash(path: &Path) -> io::Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(fs::File::open(path)?)?;
+    Ok(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_are_found_by_content() {
+        let dir = std::env::temp_dir().join(format!("heic_convert-duplicates-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let files = [("a.heic", "photo"), ("b.heic", "other"), ("c.heic", "photo"), ("d.heic", "longer photo")];
+        let inputs: Vec<PathBuf> = files
+            .iter()
+            .map(|(name, content)| {
+                fs::write(dir.join(name), content).unwrap();
+                dir.join(name)
+            })
+            .collect();
+        let missing = dir.join("missing.heic");
+
+        let (unique, copies) = find(&[inputs.clone(), vec![missing.clone()]].concat());
+        assert_eq!(unique, vec![inputs[0].clone(), inputs[1].clone(), inputs[3].clone(), missing]);
+        assert_eq!(copies, vec![(inputs[2].clone(), inputs[0].clone())]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,192 @@
+// Battery / thermal friendly mode (--eco)
+//
+// When the machine runs on battery or reports thermal pressure, the conversion is moved
+// to efficiency cores, given a lower scheduling priority and the external tools are
+// limited to a single thread. Under thermal pressure we also pause before starting so
+// the machine gets a chance to cool down.
+#[cfg(all(any(target_os = "macos", windows), feature = "external-tools"))]
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+// Longest time we wait for thermal pressure to go away before converting anyway
+const MAX_COOLDOWN: Duration = Duration::from_secs(300);
+const COOLDOWN_POLL: Duration = Duration::from_secs(15);
+
+// What the power / thermal probes found
+#[derive(Debug, Default, Clone, Copy)]
+struct PowerState {
+    on_battery: bool,
+    thermal_pressure: bool,
+}
+
+// Whether eco restrictions are in effect for this run
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+// Check power and thermal state and throttle this process if needed.
+// Returns true when eco restrictions were switched on.
+pub fn engage() -> bool {
+    let state = PowerState {
+        on_battery: on_battery(),
+        thermal_pressure: thermal_pressure(),
+    };
+    if !state.on_battery && !state.thermal_pressure {
+        say!("🔌 --eco: on mains power and not running hot, converting at full speed.");
+        return false;
+    }
+
+    let reason = match (state.on_battery, state.thermal_pressure) {
+        (true, true) => "on battery and running hot",
+        (true, false) => "on battery",
+        _ => "running hot",
+    };
+    say!("🍃 --eco: {}, converting slowly on efficiency cores.", reason);
+
+    ACTIVE.store(true, Ordering::Relaxed);
+    lower_priority();
+
+    if state.thermal_pressure {
+        wait_for_cooldown();
+    }
+    true
+}
+
+// Ask the scheduler to prefer other work; child processes inherit the nice value
+#[cfg(unix)]
+fn lower_priority() {
+    // SAFETY: plain FFI call that only changes the priority of this process
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 10) } != 0 {
+        say_err!("⚠️  --eco: could not lower the process priority");
+    }
+}
+
+#[cfg(not(unix))]
+fn lower_priority() {}
+
+// Pause until thermal pressure is gone, giving up after MAX_COOLDOWN
+fn wait_for_cooldown() {
+    say!("⏳ Waiting for the machine to cool down before converting...");
+    let started = Instant::now();
+    while started.elapsed() < MAX_COOLDOWN {
+        thread::sleep(COOLDOWN_POLL);
+        if !thermal_pressure() {
+            say!("▶️  Temperature back to normal, starting.");
+            return;
+        }
+    }
+    say!("⚠️  Still running hot after {} minutes; converting anyway.", MAX_COOLDOWN.as_secs() / 60);
+}
+
+// Run a probe command and return its standard output, if it could be run
+#[cfg(all(any(target_os = "macos", windows), feature = "external-tools"))]
+fn probe(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Without external tools the state is unknown, and treated as mains power and cool
+#[cfg(all(any(target_os = "macos", windows), not(feature = "external-tools")))]
+fn probe(_program: &str, _args: &[&str]) -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "macos")]
+fn on_battery() -> bool {
+    probe("pmset", &["-g", "batt"]).is_some_and(|out| out.contains("'Battery Power'"))
+}
+
+// pmset reports a CPU speed limit below 100 when the system is thermally throttled
+#[cfg(target_os = "macos")]
+fn thermal_pressure() -> bool {
+    probe("pmset", &["-g", "therm"]).is_some_and(|out| {
+        out.lines()
+            .filter_map(|line| line.trim().strip_prefix("CPU_Speed_Limit"))
+            .filter_map(|rest| rest.trim_start_matches([' ', '=']).trim().parse::<u32>().ok())
+            .any(|limit| limit < 100)
+    })
+}
+
+// Win32_Battery.BatteryStatus 1 means "discharging"
+#[cfg(windows)]
+fn on_battery() -> bool {
+    probe(
+        "powershell",
+        &["-NoProfile", "-Command", "(Get-CimInstance Win32_Battery).BatteryStatus"],
+    )
+    .is_some_and(|out| out.lines().any(|line| line.trim() == "1"))
+}
+
+// Any thermal zone at or above its passive (throttling) trip point
+#[cfg(windows)]
+fn thermal_pressure() -> bool {
+    probe(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            "Get-CimInstance -Namespace root/wmi MSAcpi_ThermalZoneTemperature | \
+             Where-Object { $_.PassiveTripPoint -gt 0 -and $_.CurrentTemperature -ge $_.PassiveTripPoint } | \
+             Measure-Object | Select-Object -ExpandProperty Count",
+        ],
+    )
+    .and_then(|out| out.trim().parse::<u32>().ok())
+    .is_some_and(|count| count > 0)
+}
+
+#[cfg(target_os = "linux")]
+fn on_battery() -> bool {
+    let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let read = |name: &str| {
+            std::fs::read_to_string(entry.path().join(name))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        read("type") == "Battery" && read("status") == "Discharging"
+    })
+}
+
+// Any thermal zone at or above one of its passive (throttling) trip points
+#[cfg(target_os = "linux")]
+fn thermal_pressure() -> bool {
+    let Ok(entries) = std::fs::read_dir("/sys/class/thermal") else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let zone = entry.path();
+        let read = |name: String| {
+            std::fs::read_to_string(zone.join(name))
+                .ok()
+                .map(|value| value.trim().to_string())
+        };
+        let Some(temp) = read(String::from("temp")).and_then(|t| t.parse::<i64>().ok()) else {
+            return false;
+        };
+        (0..16).any(|trip| {
+            read(format!("trip_point_{}_type", trip)).as_deref() == Some("passive")
+                && read(format!("trip_point_{}_temp", trip))
+                    .and_then(|t| t.parse::<i64>().ok())
+                    .is_some_and(|limit| limit > 0 && temp >= limit)
+        })
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn on_battery() -> bool {
+    false
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn thermal_pressure() -> bool {
+    false
+}
@@ -0,0 +1,137 @@
+// End-of-run e-mail report (--email-report, feature "email")
+use anyhow::{Context, Result, anyhow};
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+
+use crate::config::{SmtpConfig, SmtpSecurity};
+use crate::summary::RunSummary;
+use crate::{locale, units};
+
+// Send the run summary (and the failure details, if any) to `recipient`
+pub fn send_report(recipient: &str, summary: &RunSummary, smtp: &SmtpConfig) -> Result<()> {
+    let subject = format!(
+        "heic_convert {} on {}",
+        if summary.error.is_none() { "succeeded" } else { "FAILED" },
+        summary.host.as_deref().unwrap_or("unknown host")
+    );
+
+    let body = body(summary);
+
+    let message = Message::builder()
+        .from(smtp.from.parse().with_context(|| {
+            format!("❌ Invalid sender address in [smtp] from: {}", smtp.from)
+        })?)
+        .to(recipient
+            .parse()
+            .with_context(|| format!("❌ Invalid --email-report address: {}", recipient))?)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(body)?;
+
+    let mut builder = match smtp.security {
+        SmtpSecurity::Starttls => SmtpTransport::starttls_relay(&smtp.host)?,
+        SmtpSecurity::Tls => SmtpTransport::relay(&smtp.host)?,
+        SmtpSecurity::None => SmtpTransport::builder_dangerous(&smtp.host),
+    };
+    if let Some(port) = smtp.port {
+        builder = builder.port(port);
+    }
+
+    let password = match &smtp.password_env {
+        Some(variable) => Some(std::env::var(variable).map_err(|_| {
+            anyhow!("❌ [smtp] password_env names {}, but it is not set", variable)
+        })?),
+        None => smtp.password.clone(),
+    };
+    if let Some(username) = &smtp.username {
+        builder = builder.credentials(Credentials::new(
+            username.clone(),
+            password.unwrap_or_default(),
+        ));
+    }
+
+    builder.build().send(&message).map_err(|e| {
+        anyhow!(
+            "❌ Failed to send e-mail report via {}: {}\n\
+             Please check the [smtp] settings in your configuration file.",
+            smtp.host,
+            e
+        )
+    })?;
+
+    say!("E-mail report sent to {}", recipient);
+    Ok(())
+}
+
+// The text of the report: the headline, the facts of the run and every failure
+fn body(summary: &RunSummary) -> String {
+    let mut body = String::new();
+    body.push_str(&summary.headline());
+    body.push_str("\n\n");
+    body.push_str(&format!("Status:   {}\n", summary.status));
+    body.push_str(&format!("Input:    {}\n", summary.input.as_deref().unwrap_or("-")));
+    body.push_str(&format!("Output:   {}\n", summary.output.as_deref().unwrap_or("-")));
+    body.push_str(&format!("Format:   {}\n", summary.format));
+    let size = |bytes: Option<u64>| bytes.map(units::format_size).unwrap_or_else(|| String::from("-"));
+    body.push_str(&format!("Sizes:    {} -> {}\n", size(summary.input_bytes), size(summary.output_bytes)));
+    if summary.input.is_none() {
+        body.push_str(&format!(
+            "Files:    {} converted, {} skipped, {} failed of {}\n",
+            summary.converted,
+            summary.skipped,
+            summary.failures.len(),
+            summary.files
+        ));
+    }
+    body.push_str(&format!("Duration: {}\n", units::format_duration(summary.duration())));
+    if let Ok(finished) = chrono::DateTime::parse_from_rfc3339(&summary.finished_at) {
+        let finished = finished.with_timezone(&chrono::Local);
+        body.push_str(&format!("Finished: {}\n", locale::format_date_time(&finished)));
+    }
+    body.push_str(&format!("Version:  {} {}\n", summary.tool, summary.version));
+    if let Some(error) = &summary.error {
+        body.push_str("\nFailures:\n");
+        match &summary.input {
+            // A single conversion keeps its whole error message
+            Some(input) => {
+                body.push_str(&format!("  {}\n", input));
+                for line in error.lines() {
+                    body.push_str(&format!("    {}\n", line));
+                }
+            }
+            None => {
+                for failure in &summary.failures {
+                    body.push_str(&format!("  {}\n    {}\n", failure.input, failure.error));
+                }
+            }
+        }
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::summary::{Failure, Tally};
+    use clap::Parser;
+
+    #[test]
+    fn batch_report_lists_every_failure() {
+        let cli = crate::Cli::parse_from(["heic_convert", "-f", "jpg"]);
+        let tally = Tally {
+            files: 3,
+            converted: 1,
+            skipped: 0,
+            failures: vec![
+                Failure { input: String::from("a/IMG_0001.HEIC"), error: String::from("no 'meta' box") },
+                Failure { input: String::from("b/IMG_0002.HEIC"), error: String::from("Permission denied") },
+            ],
+        };
+        let summary = RunSummary::from_tally(&cli, tally, std::time::Duration::from_secs(3), Vec::new());
+        let body = body(&summary);
+        assert!(body.contains("2 of 3 images could not be converted"), "{}", body);
+        assert!(body.contains("Files:    1 converted, 0 skipped, 2 failed of 3"), "{}", body);
+        assert!(body.contains("Failures:\n  a/IMG_0001.HEIC\n    no 'meta' box\n  b/IMG_0002.HEIC\n    Permission denied\n"), "{}", body);
+    }
+}
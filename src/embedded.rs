@@ -0,0 +1,343 @@
+// Rescue HEIC images embedded in other files (--extract-embedded FILE)
+//
+// Photos end up inside containers that image tools cannot open: attachments of saved
+// e-mails (.eml, .emlx, .mbox), data: URIs in HTML exports of Apple Notes, and files
+// or image streams in PDFs. The container is searched for HEIC payloads in three
+// layers, without understanding its format completely:
+//   - base64 blocks (MIME attachments, data: URIs) are decoded,
+//   - Flate-compressed PDF streams are inflated,
+//   - the raw bytes and every decoded block are scanned for HEIF files, whose extent
+//     follows from their top-level boxes.
+// Each payload is written as a .heic file into FILE_embedded/ next to the container.
+use anyhow::{Context, Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::GeneralPurpose;
+use base64::engine::{DecodePaddingMode, GeneralPurposeConfig};
+use flate2::read::ZlibDecoder;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::file_type::{self, DetectedType};
+
+// Standard alphabet; the padding is stripped before decoding
+const STANDARD: GeneralPurpose = GeneralPurpose::new(
+    &base64::alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::RequireNone),
+);
+
+// Inflated PDF streams larger than this are not kept (a guard against zip bombs)
+const MAX_INFLATED: u64 = 512 * 1024 * 1024;
+
+// A found image with the name its container gave it, if any
+struct Payload {
+    name: Option<String>,
+    bytes: Vec<u8>,
+}
+
+// Extract the HEIC payloads of `container` and return the written files
+pub fn extract(container: &Path) -> Result<Vec<PathBuf>> {
+    let data = fs::read(container).with_context(|| format!("❌ Cannot read {}", container.display()))?;
+
+    let mut payloads = Vec::new();
+    for (name, block) in base64_blocks(&data) {
+        payloads.extend(carve(&block).into_iter().map(|bytes| Payload { name: name.clone(), bytes }));
+    }
+    for stream in pdf_streams(&data) {
+        payloads.extend(carve(&stream).into_iter().map(|bytes| Payload { name: None, bytes }));
+    }
+    payloads.extend(carve(&data).into_iter().map(|bytes| Payload { name: None, bytes }));
+
+    // The same image can be found in several layers
+    let mut seen = HashSet::new();
+    payloads.retain(|payload| seen.insert(payload.bytes.clone()));
+    if payloads.is_empty() {
+        return Err(anyhow!(
+            "❌ No HEIC images found in {}\n\
+             Searched base64 attachments and data: URIs, compressed PDF streams and the raw bytes.",
+            container.display()
+        ));
+    }
+
+    let stem = container.file_stem().unwrap_or_default().to_string_lossy();
+    let dir = container.with_file_name(format!("{}_embedded", stem));
+    fs::create_dir_all(&dir).with_context(|| format!("❌ Cannot create {}", dir.display()))?;
+
+    let mut written = Vec::new();
+    let mut names = HashSet::new();
+    for (index, payload) in payloads.iter().enumerate() {
+        let name = payload
+            .name
+            .as_deref()
+            .map(|name| format!("{}.heic", file_type::base_stem(Path::new(name))))
+            .filter(|name| names.insert(name.clone()))
+            .unwrap_or_else(|| format!("{}_{:02}.heic", stem, index + 1));
+        let path = dir.join(name);
+        fs::write(&path, &payload.bytes).with_context(|| format!("❌ Cannot write {}", path.display()))?;
+        say!("Extracted {} ({} bytes)", path.display(), payload.bytes.len());
+        written.push(path);
+    }
+    Ok(written)
+}
+
+// Decoded base64 blocks: data: URIs, and MIME bodies (consecutive lines of nothing but
+// base64), each with the attachment file name from the MIME headers in front of it
+fn base64_blocks(data: &[u8]) -> Vec<(Option<String>, Vec<u8>)> {
+    let text = String::from_utf8_lossy(data);
+    let is_base64 = |c: char| c.is_ascii_alphanumeric() || "+/=".contains(c);
+    let decode = |block: &str| {
+        let compact: String = block.chars().filter(|c| !c.is_whitespace()).collect();
+        STANDARD.decode(compact.trim_end_matches('=')).ok()
+    };
+
+    let mut blocks = Vec::new();
+    for (at, _) in text.match_indices(";base64,") {
+        let body = &text[at + ";base64,".len()..];
+        let end = body.find(|c: char| !(is_base64(c) || c.is_whitespace())).unwrap_or(body.len());
+        blocks.extend(decode(&body[..end]).map(|bytes| (None, bytes)));
+    }
+
+    let mut block_start = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end();
+        let body_line = content.len() >= 16 && content.chars().all(is_base64);
+        match (body_line, block_start) {
+            (true, None) => block_start = Some(offset),
+            (false, Some(start)) => {
+                let name = attachment_name(&text[..start]);
+                blocks.extend(decode(&text[start..offset]).map(|bytes| (name, bytes)));
+                block_start = None;
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+    if let Some(start) = block_start {
+        blocks.extend(decode(&text[start..]).map(|bytes| (attachment_name(&text[..start]), bytes)));
+    }
+    blocks
+}
+
+// filename="IMG_0001.HEIC" or name=IMG_0001.HEIC in the MIME headers just before a body
+fn attachment_name(before: &str) -> Option<String> {
+    let mut from = before.len().saturating_sub(1024);
+    while !before.is_char_boundary(from) {
+        from += 1;
+    }
+    // Only the headers of this part, not those of the previous one
+    let headers = before[from..].rsplit("\n--").next().unwrap_or_default();
+    ["filename=", "name="].iter().find_map(|key| {
+        let value = &headers[headers.to_ascii_lowercase().rfind(key)? + key.len()..];
+        let value = value.trim_start_matches('"');
+        let end = value.find(|c: char| c == '"' || c == ';' || c.is_whitespace()).unwrap_or(value.len());
+        Some(value[..end].to_string()).filter(|name| !name.is_empty())
+    })
+}
+
+// The inflated contents of every Flate-compressed PDF stream
+fn pdf_streams(data: &[u8]) -> Vec<Vec<u8>> {
+    if !data.starts_with(b"%PDF") {
+        return Vec::new();
+    }
+    let mut streams = Vec::new();
+    let mut at = 0;
+    while let Some(found) = find(&data[at..], b"stream") {
+        let keyword = at + found;
+        at = keyword + b"stream".len();
+        // "endstream" contains "stream" too
+        if keyword >= 3 && &data[keyword - 3..keyword] == b"end" {
+            continue;
+        }
+        let dictionary = &data[keyword.saturating_sub(512)..keyword];
+        if find(dictionary, b"FlateDecode").is_none() {
+            continue;
+        }
+        let mut start = at;
+        for expected in [b'\r', b'\n'] {
+            if data.get(start) == Some(&expected) {
+                start += 1;
+            }
+        }
+        let Some(length) = find(&data[start..], b"endstream") else {
+            break;
+        };
+        // A damaged stream still yields the part before the damage
+        let mut inflated = Vec::new();
+        let _ = ZlibDecoder::new(&data[start..start + length]).take(MAX_INFLATED).read_to_end(&mut inflated);
+        if !inflated.is_empty() {
+            streams.push(inflated);
+        }
+        at = start + length;
+    }
+    streams
+}
+
+// HEIF files inside `data`, found by their 'ftyp' box and measured by walking the
+// top-level boxes that follow it
+fn carve(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut found = Vec::new();
+    if data.len() < 8 {
+        return found;
+    }
+    let mut at = 4;
+    while let Some(position) = find(&data[at..], b"ftyp") {
+        let start = at + position - 4;
+        at += position + 4;
+        if file_type::detect_bytes(&data[start..]) != Some(DetectedType::Heic) {
+            continue;
+        }
+        if let Some(end) = heif_end(data, start) {
+            found.push(data[start..end].to_vec());
+            at = end.max(at);
+        }
+    }
+    found
+}
+
+// End of the HEIF file starting at `start`, or None when it is incomplete. A file
+// needs at least a 'meta' box (the image description) to be of any use.
+fn heif_end(data: &[u8], start: usize) -> Option<usize> {
+    let mut at = start;
+    let mut has_meta = false;
+    while at + 8 <= data.len() {
+        let size = u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]) as u64;
+        let kind = &data[at + 4..at + 8];
+        if !kind.iter().all(|c| c.is_ascii_alphanumeric() || *c == b' ') {
+            break;
+        }
+        let size = match size {
+            0 => (data.len() - at) as u64, // Extends to the end of the data
+            1 => u64::from_be_bytes(data.get(at + 8..at + 16)?.try_into().ok()?),
+            size => size,
+        };
+        if size < 8 || size > (data.len() - at) as u64 {
+            break;
+        }
+        has_meta |= kind == b"meta";
+        at += size as usize;
+    }
+    (has_meta && at > start).then_some(at)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::ZlibEncoder;
+    use std::io::Write;
+
+    // The smallest HEIF carve() accepts: an 'ftyp' box and an empty 'meta' box
+    const HEIF: &[u8] = b"\0\0\0\x18ftypheic\0\0\0\0mif1heic\0\0\0\x08meta";
+
+    fn mime(body: &str) -> String {
+        format!(
+            "--boundary\r\nContent-Type: image/heic; name=\"IMG_0001.HEIC\"\r\n\
+             Content-Transfer-Encoding: base64\r\n\r\n{}\r\n--boundary--\r\n",
+            body
+        )
+    }
+
+    fn pdf(stream: &[u8]) -> Vec<u8> {
+        let mut data = b"%PDF-1.7\n1 0 obj << /Filter /FlateDecode >>\nstream\n".to_vec();
+        data.extend_from_slice(stream);
+        data
+    }
+
+    fn deflate(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn whole_heif_is_carved_and_a_cut_one_is_not() {
+        let mut data = b"junk before".to_vec();
+        data.extend_from_slice(HEIF);
+        assert_eq!(carve(&data), vec![HEIF.to_vec()]);
+        for cut in [4, 12, HEIF.len() - 1] {
+            assert!(carve(&HEIF[..cut]).is_empty(), "cut at {}", cut);
+        }
+    }
+
+    #[test]
+    fn box_sizes_past_the_data_are_not_followed() {
+        let mut huge = HEIF.to_vec();
+        huge.extend_from_slice(b"\0\0\0\x01mdat\xff\xff\xff\xff\xff\xff\xff\xff");
+        assert_eq!(carve(&huge), vec![HEIF.to_vec()]);
+        let mut small = HEIF.to_vec();
+        small.extend_from_slice(b"\0\0\0\x04mdat");
+        assert_eq!(carve(&small), vec![HEIF.to_vec()]);
+    }
+
+    #[test]
+    fn mime_attachment_is_decoded_with_its_name() {
+        let body = STANDARD.encode(HEIF);
+        let blocks = base64_blocks(mime(&body).as_bytes());
+        assert_eq!(blocks, vec![(Some(String::from("IMG_0001.HEIC")), HEIF.to_vec())]);
+    }
+
+    #[test]
+    fn truncated_or_malformed_base64_decodes_to_nothing() {
+        let body = STANDARD.encode(HEIF);
+        // One character past a whole quantum cannot be decoded
+        let cut = &body[..body.len() / 4 * 4 - 3];
+        assert!(base64_blocks(mime(cut).as_bytes()).is_empty());
+        // A message that ends in the middle of the body, without a closing boundary
+        let unfinished = mime(&body);
+        let unfinished = &unfinished[..unfinished.find(&body).unwrap() + 21];
+        assert!(base64_blocks(unfinished.as_bytes()).iter().all(|(_, bytes)| carve(bytes).is_empty()));
+        for uri in [&b"<img src=\"data:image/heic;base64,"[..], b"<img src=\"data:image/heic;base64,====\">"] {
+            assert!(base64_blocks(uri).iter().all(|(_, bytes)| bytes.is_empty()));
+        }
+        assert!(base64_blocks(&[0xff, 0xfe, b'\n', 0x80]).is_empty());
+    }
+
+    #[test]
+    fn flate_stream_is_inflated() {
+        let mut data = pdf(&deflate(HEIF));
+        data.extend_from_slice(b"\nendstream\nendobj\n%%EOF\n");
+        assert_eq!(pdf_streams(&data), vec![HEIF.to_vec()]);
+    }
+
+    #[test]
+    fn damaged_pdf_streams_give_no_image() {
+        let compressed = deflate(HEIF);
+        // No endstream: the file stops inside the stream
+        assert!(pdf_streams(&pdf(&compressed)).is_empty());
+        // Not zlib data at all
+        assert!(pdf_streams(&pdf(b"not compressed\nendstream")).is_empty());
+        // Cut in half: whatever inflates is not a whole HEIF
+        let mut cut = pdf(&compressed[..compressed.len() / 2]);
+        cut.extend_from_slice(b"\nendstream");
+        assert!(pdf_streams(&cut).iter().all(|stream| carve(stream).is_empty()));
+        // Keywords at the very start and end of the data
+        assert!(pdf_streams(b"%PDFstream").is_empty());
+        assert!(pdf_streams(b"%PDF /FlateDecode stream").is_empty());
+        assert!(pdf_streams(b"%PDF /FlateDecode stream\r\n").is_empty());
+    }
+
+    #[test]
+    fn malformed_container_is_an_error() {
+        let dir = std::env::temp_dir().join(format!("heic_convert-embedded-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let body = STANDARD.encode(HEIF);
+        let containers: [(&str, Vec<u8>); 3] = [
+            ("cut.eml", mime(&body[..body.len() - 9]).into_bytes()),
+            ("cut.pdf", pdf(&deflate(HEIF)[..10])),
+            ("cut.heic", HEIF[..30].to_vec()),
+        ];
+        for (name, data) in containers {
+            let path = dir.join(name);
+            fs::write(&path, data).unwrap();
+            let error = extract(&path).unwrap_err();
+            assert!(error.to_string().contains("No HEIC images found"), "{}: {}", name, error);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
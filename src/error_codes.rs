@@ -0,0 +1,380 @@
+// Stable error codes and the knowledge base behind `heic_convert explain <CODE>`.
+//
+// Failures are tagged with a code where they happen (`coded` / `.code(...)`), so the
+// remediation text is picked by code instead of by matching message strings.
+use anyhow::Result;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    InputMissing,
+    InputNotFound,
+    InputNotAFile,
+    InputEmpty,
+    InputUnreadable,
+    OutputDirCreate,
+    OutputDirReadOnly,
+    NoBackend,
+    ImageMagickNoHeic,
+    ToolMissing,
+    ToolFailed,
+    SaveFailed,
+    TempDir,
+    ReadRetriesExhausted,
+    VolumeTooSmall,
+    UploadFailed,
+    ImportFailed,
+    Config,
+    BuiltinCannotRead,
+    PersistFailed,
+    NameExpr,
+    Lut,
+    InputNotSettled,
+    IosBackup,
+    UnsupportedFeature,
+    OutputExists,
+}
+
+// What the user reads for one code
+pub struct ErrorInfo {
+    pub code: ErrorCode,
+    pub id: &'static str,
+    pub title: &'static str,
+    pub causes: &'static [&'static str],
+    pub fixes: &'static [&'static str],
+}
+
+pub const CATALOG: &[ErrorInfo] = &[
+    ErrorInfo {
+        code: ErrorCode::InputMissing,
+        id: "E001",
+        title: "No input file given",
+        causes: &["The -i/--input option was left out"],
+        fixes: &["Run heic_convert -i photo.heic", "See heic_convert help formats for examples"],
+    },
+    ErrorInfo {
+        code: ErrorCode::InputNotFound,
+        id: "E002",
+        title: "Input file does not exist",
+        causes: &["Typo in the path", "The file was moved or deleted", "A relative path was given from another directory"],
+        fixes: &["Check the path with ls", "Use an absolute path", "Quote paths that contain spaces"],
+    },
+    ErrorInfo {
+        code: ErrorCode::InputNotAFile,
+        id: "E003",
+        title: "Input is not a file",
+        causes: &["A directory was given as input"],
+        fixes: &["Pass a single image file", "Convert a folder with a shell loop (heic_convert help batch)"],
+    },
+    ErrorInfo {
+        code: ErrorCode::InputEmpty,
+        id: "E004",
+        title: "Input file is empty",
+        causes: &["An interrupted copy or download", "A cloud placeholder that was never downloaded"],
+        fixes: &[
+            "Copy the file from its source again",
+            "Download the original in iCloud Photos / Finder first",
+            "If it is still syncing, wait for it with --wait-for-sync 5m",
+        ],
+    },
+    ErrorInfo {
+        code: ErrorCode::InputUnreadable,
+        id: "E005",
+        title: "Cannot access the input file",
+        causes: &["Missing read permission", "The storage device was disconnected"],
+        fixes: &["Check the file permissions (ls -l)", "Reconnect the device; for flaky media use --retry-io 3"],
+    },
+    ErrorInfo {
+        code: ErrorCode::OutputDirCreate,
+        id: "E006",
+        title: "Cannot create the output directory",
+        causes: &["No write permission in the parent directory", "Invalid characters or a path that is too long", "Disk full"],
+        fixes: &["Choose another -o location", "Create the directory yourself and check its permissions", "Free up disk space"],
+    },
+    ErrorInfo {
+        code: ErrorCode::OutputDirReadOnly,
+        id: "E007",
+        title: "No write permission to the output directory",
+        causes: &["The directory is read-only", "The volume is mounted read-only"],
+        fixes: &["Choose another -o location", "Fix the permissions (chmod u+w <dir>)"],
+    },
+    ErrorInfo {
+        code: ErrorCode::NoBackend,
+        id: "E008",
+        title: "HEIC format support is not available",
+        causes: &["The built-in decoder cannot read HEIC and neither ImageMagick nor FFmpeg is installed"],
+        fixes: &[
+            "Install ImageMagick: brew install imagemagick",
+            "Install FFmpeg: brew install ffmpeg",
+            "macOS: open the file in Preview and use Export as PNG/JPEG",
+        ],
+    },
+    ErrorInfo {
+        code: ErrorCode::ImageMagickNoHeic,
+        id: "E009",
+        title: "ImageMagick was built without HEIC support",
+        causes: &["ImageMagick has no libheif delegate (\"no decode delegate\")"],
+        fixes: &["brew install libheif && brew reinstall imagemagick", "Check with: convert -list format | grep HEIC", "Or use --backend ffmpeg"],
+    },
+    ErrorInfo {
+        code: ErrorCode::ToolMissing,
+        id: "E010",
+        title: "Conversion tool not found",
+        causes: &["--backend names a tool that is not installed or not on PATH"],
+        fixes: &["Install the tool (brew install imagemagick / ffmpeg)", "Use --backend auto to pick whatever is installed"],
+    },
+    ErrorInfo {
+        code: ErrorCode::ToolFailed,
+        id: "E011",
+        title: "The external conversion tool failed",
+        causes: &["The file is damaged or uses a HEIC feature the tool does not support"],
+        fixes: &["Look at the tool's message printed above", "Try the other backend (--backend imagemagick / ffmpeg)", "Run with --explain to see what was attempted"],
+    },
+    ErrorInfo {
+        code: ErrorCode::SaveFailed,
+        id: "E012",
+        title: "Failed to save the output image",
+        causes: &["Disk full", "No write permission in the temporary directory"],
+        fixes: &["Free up disk space", "Point --tmpdir at a writable directory"],
+    },
+    ErrorInfo {
+        code: ErrorCode::TempDir,
+        id: "E013",
+        title: "Cannot create the temporary directory",
+        causes: &["The --tmpdir location does not exist or is not writable", "Disk full"],
+        fixes: &["Create the directory or choose another --tmpdir", "Run heic_convert clean-temp to remove leftovers"],
+    },
+    ErrorInfo {
+        code: ErrorCode::ReadRetriesExhausted,
+        id: "E014",
+        title: "Reading the input kept failing",
+        causes: &["The storage device is failing", "The network share dropped out repeatedly"],
+        fixes: &["Retry more often: --retry-io 10 --io-backoff", "Copy the file with a recovery tool (ddrescue) and convert the copy"],
+    },
+    ErrorInfo {
+        code: ErrorCode::VolumeTooSmall,
+        id: "E015",
+        title: "Output is larger than the --split-output volume size",
+        causes: &["A single image does not fit into one volume"],
+        fixes: &["Choose a larger --split-output size", "Convert to JPG (-f jpg) for smaller files"],
+    },
+    ErrorInfo {
+        code: ErrorCode::UploadFailed,
+        id: "E016",
+        title: "Upload to the remote destination failed",
+        causes: &["Wrong --upload URL or credentials", "The server is unreachable"],
+        fixes: &["Check the URL and the password environment variable", "Test the connection with sftp or curl"],
+    },
+    ErrorInfo {
+        code: ErrorCode::ImportFailed,
+        id: "E017",
+        title: "Import into the photo server failed",
+        causes: &["Wrong --api-url or API key", "The server is not running"],
+        fixes: &["Create a new API key / app password", "Open the --api-url in a browser to check the server"],
+    },
+    ErrorInfo {
+        code: ErrorCode::Config,
+        id: "E018",
+        title: "Configuration problem",
+        causes: &[
+            "The configuration file is missing, unreadable or has invalid TOML",
+            "A required section such as [smtp] is missing",
+            "A [[rule]] condition or setting is invalid",
+        ],
+        fixes: &["Check the file given with --config (default ~/.config/heic_convert/config.toml)", "Compare it with the example in the README"],
+    },
+    ErrorInfo {
+        code: ErrorCode::BuiltinCannotRead,
+        id: "E019",
+        title: "The built-in decoder cannot read this file",
+        causes: &["--backend builtin was chosen for a HEIC file"],
+        fixes: &["Use --backend auto, imagemagick or ffmpeg"],
+    },
+    ErrorInfo {
+        code: ErrorCode::PersistFailed,
+        id: "E020",
+        title: "Cannot move the result into place",
+        causes: &["The output location became unwritable during the run", "Disk full on the output volume"],
+        fixes: &["Check free space and permissions at the output location", "Write somewhere else with -o"],
+    },
+    ErrorInfo {
+        code: ErrorCode::NameExpr,
+        id: "E021",
+        title: "The --name-expr expression or --name-template failed",
+        causes: &[
+            "A syntax error in the expression",
+            "A tag the file does not have, e.g. exif.date on a screenshot",
+            "The result is empty, absolute or contains \"..\"",
+            "A --name-template that only holds {date}, for a file without a date",
+        ],
+        fixes: &[
+            "Quote the expression in single quotes so the shell keeps the double quotes",
+            "Fall back for missing tags: if exif.date != () { exif.date.format(\"%Y\") } else { file.modified.format(\"%Y\") }",
+            "See heic_convert help batch for examples",
+        ],
+    },
+    ErrorInfo {
+        code: ErrorCode::Lut,
+        id: "E022",
+        title: "Cannot load the --lut file",
+        causes: &[
+            "The file does not exist or is not readable",
+            "The file is not in the .cube format (e.g. a .3dl or .look file)",
+            "The number of entries does not match LUT_3D_SIZE",
+        ],
+        fixes: &[
+            "Check the path given with --lut",
+            "Export the look as a .cube file from your editing software",
+        ],
+    },
+    ErrorInfo {
+        code: ErrorCode::InputNotSettled,
+        id: "E023",
+        title: "The input is still being synced or written",
+        causes: &[
+            "A sync client (iCloud Drive, Dropbox, OneDrive) has not finished downloading the file",
+            "Another program is still copying the file",
+            "The name is a temporary download name such as .part or .crdownload",
+        ],
+        fixes: &[
+            "Allow more time, e.g. --wait-for-sync 10m",
+            "For iCloud Drive, choose Download Now in Finder or run: brctl download <file>",
+            "Convert the file under its final name once the transfer has finished",
+        ],
+    },
+    ErrorInfo {
+        code: ErrorCode::IosBackup,
+        id: "E024",
+        title: "Cannot read the iPhone backup",
+        causes: &[
+            "The folder is not a single backup (it has no Manifest.db)",
+            "The backup is encrypted",
+            "The backup was made by iOS 9 or older",
+        ],
+        fixes: &[
+            "Pass the folder of one device, e.g. ~/Library/Application Support/MobileSync/Backup/<device id>",
+            "Make an unencrypted backup: Finder / iTunes → your iPhone → uncheck \"Encrypt local backup\"",
+        ],
+    },
+    ErrorInfo {
+        code: ErrorCode::UnsupportedFeature,
+        id: "E025",
+        title: "The backend cannot do everything that was asked for",
+        causes: &[
+            "--on-unsupported fail was given and the chosen backend cannot keep the EXIF metadata or colour profile",
+            "No installed backend can honour all requested options",
+        ],
+        fixes: &[
+            "Install ImageMagick, which keeps metadata and colour profiles",
+            "Use --on-unsupported switch to move to a backend that can, or warn to convert anyway",
+            "Drop the option the backend cannot honour (see the message for the list)",
+        ],
+    },
+    ErrorInfo {
+        code: ErrorCode::OutputExists,
+        id: "E026",
+        title: "The output file already exists",
+        causes: &[
+            "--no-clobber was given and an earlier run (or another input with the same name) wrote the output",
+        ],
+        fixes: &[
+            "Use --skip-existing to leave inputs alone that were converted before",
+            "Use --rename-on-conflict to write the new image under a numbered name",
+            "Remove the old output, or choose another name with -o or --output-dir",
+        ],
+    },
+];
+
+impl ErrorCode {
+    pub fn info(self) -> &'static ErrorInfo {
+        CATALOG
+            .iter()
+            .find(|info| info.code == self)
+            .expect("every error code has a catalog entry")
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.info().id)
+    }
+}
+
+// An error tagged with its code. It prints the original message followed by a pointer
+// to the knowledge base.
+pub struct Coded {
+    pub code: ErrorCode,
+    error: anyhow::Error,
+}
+
+impl fmt::Display for Coded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#}\n\nsee: heic_convert explain {}", self.error, self.code)
+    }
+}
+
+impl fmt::Debug for Coded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Coded {}
+
+pub fn coded(code: ErrorCode, error: anyhow::Error) -> anyhow::Error {
+    // Keep the first code when an already tagged error is tagged again
+    if code_of(&error).is_some() {
+        return error;
+    }
+    anyhow::Error::new(Coded { code, error })
+}
+
+// Tag the error of a Result with a code
+pub trait CodeExt<T> {
+    fn code(self, code: ErrorCode) -> Result<T>;
+}
+
+impl<T> CodeExt<T> for Result<T> {
+    fn code(self, code: ErrorCode) -> Result<T> {
+        self.map_err(|e| coded(code, e))
+    }
+}
+
+pub fn code_of(error: &anyhow::Error) -> Option<ErrorCode> {
+    error.downcast_ref::<Coded>().map(|coded| coded.code)
+}
+
+// `heic_convert explain` with or without a code
+pub fn explain(id: Option<&str>) -> Result<()> {
+    let Some(id) = id else {
+        say!("Error codes (heic_convert explain <CODE>):");
+        for info in CATALOG {
+            say!("  {}  {}", info.id, info.title);
+        }
+        return Ok(());
+    };
+
+    let info = CATALOG
+        .iter()
+        .find(|info| info.id.eq_ignore_ascii_case(id.trim()))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "❌ Unknown error code: {}\n\
+                 Run heic_convert explain to list all codes.",
+                id
+            )
+        })?;
+
+    say!("{}: {}", info.id, info.title);
+    say!();
+    say!("Possible causes:");
+    for cause in info.causes {
+        say!("  • {}", cause);
+    }
+    say!();
+    say!("How to fix it:");
+    for fix in info.fixes {
+        say!("  • {}", fix);
+    }
+    Ok(())
+}
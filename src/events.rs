@@ -0,0 +1,65 @@
+// Conversion events and the features that react to them
+//
+// The conversion announces what happens with `publish`: files found, each conversion
+// starting, the backend picked for it and its finish (or skip), the end of the run and
+// warnings that do not stop it. Features that report on a run (statistics, the JSON
+// summary line, the --report manifest, webhooks, e-mail reports) subscribe to the events
+// instead of being called from every place that knows about them, so a new kind of
+// output only needs a new subscriber. Subscribers run on the publishing thread, in the
+// order they subscribed.
+use std::cell::Cell;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::summary::RunSummary;
+
+pub enum Event<'a> {
+    FileDiscovered(&'a Path),
+    ConversionStarted(&'a Path),
+    BackendChosen { input: &'a Path, backend: &'a str },
+    ConversionFinished { input: &'a Path, output: Option<&'a Path>, error: Option<&'a anyhow::Error>, elapsed: Duration },
+    ConversionSkipped { input: &'a Path, output: &'a Path }, // --skip-existing found the output already there
+    DuplicateFound { input: &'a Path, original: &'a Path }, // --skip-duplicates, after the copy was handled
+    RunFinished(&'a RunSummary), // After the last conversion event of the run
+    Warning(&'a str),
+}
+
+pub trait Subscriber: Send {
+    fn notify(&mut self, event: &Event);
+}
+
+impl<F: FnMut(&Event) + Send> Subscriber for F {
+    fn notify(&mut self, event: &Event) {
+        self(event)
+    }
+}
+
+static SUBSCRIBERS: Mutex<Vec<Box<dyn Subscriber>>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static PUBLISHING: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn subscribe(subscriber: impl Subscriber + 'static) {
+    SUBSCRIBERS.lock().unwrap_or_else(PoisonError::into_inner).push(Box::new(subscriber));
+}
+
+pub fn publish(event: Event) {
+    // Events raised by a subscriber (its own warnings) are not delivered again; the
+    // subscriber list is locked while it runs
+    if PUBLISHING.get() {
+        return;
+    }
+    PUBLISHING.set(true);
+    for subscriber in SUBSCRIBERS.lock().unwrap_or_else(PoisonError::into_inner).iter_mut() {
+        subscriber.notify(&event);
+    }
+    PUBLISHING.set(false);
+}
+
+// Print a warning and let the subscribers know about it
+pub fn warn(message: impl AsRef<str>) {
+    say_err!("⚠️  {}", message.as_ref());
+    publish(Event::Warning(message.as_ref()));
+}
@@ -0,0 +1,48 @@
+// EXIF tags of a source image: capture date and camera.
+//
+// Works for HEIC/HEIF, JPEG, PNG, TIFF and WebP containers. Missing or damaged
+// metadata is not an error; the fields are simply left empty.
+use chrono::NaiveDateTime;
+use ::exif::{In, Tag, Value};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+#[derive(Debug, Default, Clone)]
+pub struct ExifInfo {
+    pub date: Option<NaiveDateTime>, // DateTimeOriginal, falling back to DateTime
+    pub make: Option<String>,
+    pub model: Option<String>,
+}
+
+pub fn read(path: &Path) -> ExifInfo {
+    let Ok(file) = File::open(path) else {
+        return ExifInfo::default();
+    };
+    let Ok(exif) = ::exif::Reader::new().read_from_container(&mut BufReader::new(file)) else {
+        return ExifInfo::default();
+    };
+
+    let text = |tag: Tag| {
+        exif.get_field(tag, In::PRIMARY).and_then(|field| match &field.value {
+            Value::Ascii(values) => values
+                .first()
+                .map(|bytes| String::from_utf8_lossy(bytes).trim().to_string())
+                .filter(|text| !text.is_empty()),
+            _ => None,
+        })
+    };
+
+    ExifInfo {
+        date: text(Tag::DateTimeOriginal)
+            .or_else(|| text(Tag::DateTime))
+            .and_then(|date| NaiveDateTime::parse_from_str(&date, "%Y:%m:%d %H:%M:%S").ok()),
+        make: text(Tag::Make),
+        model: text(Tag::Model),
+    }
+}
+
+// Whether `path` carries EXIF metadata at all
+pub fn present(path: &Path) -> bool {
+    File::open(path).is_ok_and(|file| ::exif::Reader::new().read_from_container(&mut BufReader::new(file)).is_ok())
+}
@@ -0,0 +1,34 @@
+// Decision log for --explain
+//
+// Conversion code calls `explain::note` wherever it makes a choice (backend, metadata,
+// transforms, skipped work). The notes are numbered so the order in which steps were
+// applied is visible, and are only printed when --explain is given.
+use colored::Colorize;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STEP: AtomicUsize = AtomicUsize::new(0);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// Start the decision log for one input file
+pub fn begin(input: &std::path::Path) {
+    if enabled() {
+        STEP.store(0, Ordering::Relaxed);
+        say!("{} {}", "🔎 Explain:".bold(), input.display());
+    }
+}
+
+// Record one decision
+pub fn note(message: impl AsRef<str>) {
+    if enabled() {
+        let step = STEP.fetch_add(1, Ordering::Relaxed) + 1;
+        say!("   {:>2}. {}", step, message.as_ref());
+    }
+}
@@ -0,0 +1,96 @@
+// Rename files whose extension does not match their content (e.g. JPEGs saved as .heic)
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::file_type::{self, IMAGE_EXTENSIONS};
+
+// Scan `dir` and rename mismatched image files in place without re-encoding them.
+// Stacked extensions (IMG_0001.HEIC.heic) are collapsed to one. Running it twice is
+// harmless: files that already have a single matching extension are left alone.
+// With `lowercase` (--normalize-names) the new extensions are always lowercase.
+pub fn fix_extensions(dir: &Path, lowercase: bool) -> Result<()> {
+    if !dir.is_dir() {
+        return Err(anyhow!(
+            "❌ Not a directory: {}\n\
+             --fix-extensions expects a directory to scan.",
+            dir.display()
+        ));
+    }
+
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("❌ Cannot read directory: {}", dir.display()))?;
+
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+
+    let mut renamed = 0;
+    let mut unchanged = 0;
+    let mut skipped = 0;
+
+    for path in paths {
+        let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+            continue;
+        };
+        if !IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()) {
+            continue;
+        }
+
+        let Some(detected) = file_type::detect(&path) else {
+            say!("⚠️  Unrecognised content, leaving as is: {}", path.display());
+            skipped += 1;
+            continue;
+        };
+
+        let stem = file_type::base_stem(&path);
+        let stacked = path.file_stem().is_some_and(|s| s.to_string_lossy() != stem);
+        let matches = detected.matches_extension(extension);
+        if matches && !stacked {
+            unchanged += 1;
+            continue;
+        }
+
+        // A matching extension is kept as spelled; otherwise keep the caller's
+        // capitalisation style: IMG_0001.HEIC becomes IMG_0001.JPG
+        let new_extension = if matches {
+            extension.to_string()
+        } else if extension.chars().all(|c| c.is_ascii_uppercase()) {
+            detected.extension().to_uppercase()
+        } else {
+            detected.extension().to_string()
+        };
+        let new_extension = if lowercase { new_extension.to_lowercase() } else { new_extension };
+        let target = path.with_file_name(format!("{}.{}", stem, new_extension));
+
+        if target.exists() {
+            say!(
+                "⚠️  Cannot rename {} -> {}: target already exists",
+                path.display(),
+                target.display()
+            );
+            skipped += 1;
+            continue;
+        }
+
+        fs::rename(&path, &target).with_context(|| {
+            format!(
+                "❌ Failed to rename {} to {}\n\
+                 Please check directory permissions.",
+                path.display(),
+                target.display()
+            )
+        })?;
+        say!("Renamed {} -> {}", path.display(), target.display());
+        renamed += 1;
+    }
+
+    say!(
+        "✅ Extension check finished: {} renamed, {} already correct, {} skipped",
+        renamed, unchanged, skipped
+    );
+    Ok(())
+}
@@ -0,0 +1,196 @@
+// `extract` subcommand: pull the photos off a mounted phone or memory card
+//
+// The source (e.g. /Volumes/iPhone/DCIM) is only read: every HEIC below it is
+// converted into <to>/<year>/<year-month-day>/ by its EXIF capture date (undated/
+// without one), and <to>/extract-manifest.json lists each source with its checksum
+// next to the output it became, so the copy can be checked before the phone is wiped.
+// Other files (JPEG, videos) are left alone.
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::file_type::{self, DetectedType};
+use crate::{Cli, events, exif, rules, summary, units};
+
+pub const MANIFEST_NAME: &str = "extract-manifest.json";
+
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub from: String,
+    pub to: String,
+    pub finished: String, // RFC 3339
+    pub files: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    pub source: String, // Relative to the source directory
+    pub source_bytes: u64,
+    pub source_sha256: String,
+    pub output: Option<String>, // Relative to the target directory; None when the conversion failed
+    pub output_bytes: Option<u64>,
+    pub output_sha256: Option<String>,
+    pub verified: bool, // The output exists and reads back as an image
+    pub error: Option<String>,
+}
+
+pub fn run(
+    cli: &Cli,
+    rules: &[rules::Rule],
+    devices: &BTreeMap<String, String>,
+    from: &Path,
+    to: &Path,
+    tally: &mut summary::Tally,
+) -> Result<()> {
+    if !from.is_dir() {
+        return Err(anyhow!(
+            "❌ Not a directory: {}\n\
+             --from expects the mounted device folder, e.g. /Volumes/iPhone/DCIM",
+            from.display()
+        ));
+    }
+    if cli.delete_original || cli.trash_original {
+        return Err(anyhow!("❌ extract never changes the device; copy the photos off first, then delete them there"));
+    }
+    if cli.report.is_some() {
+        return Err(anyhow!("❌ extract lists every photo in {} in the target already; leave out --report", MANIFEST_NAME));
+    }
+    if cli.archive_output.is_some() {
+        return Err(anyhow!("❌ extract sorts the photos into date folders in the target; leave out --archive-output"));
+    }
+    // Nothing may be written to the device, not even by accident
+    let source = from.canonicalize().with_context(|| format!("❌ Cannot read {}", from.display()))?;
+    if resolved(to).starts_with(&source) {
+        return Err(anyhow!(
+            "❌ The target {} lies inside the source {}\n\
+             extract never writes to the device; choose a --to outside of it.",
+            to.display(),
+            from.display()
+        ));
+    }
+    fs::create_dir_all(to).with_context(|| format!("❌ Cannot create the target directory {}", to.display()))?;
+
+    let files = file_type::files_below(from).with_context(|| format!("❌ Cannot scan {}", from.display()))?;
+    let (photos, others): (Vec<PathBuf>, Vec<PathBuf>) =
+        files.into_iter().partition(|path| file_type::detect(path) == Some(DetectedType::Heic));
+    say!(
+        "🔎 Found {} HEIC photos in {} ({} other files left alone)",
+        photos.len(),
+        from.display(),
+        others.len()
+    );
+
+    for photo in &photos {
+        events::publish(events::Event::FileDiscovered(photo));
+    }
+    tally.files = photos.len();
+
+    let relative = |path: &Path, base: &Path| path.strip_prefix(base).unwrap_or(path).display().to_string();
+    let mut entries = Vec::new();
+    for photo in &photos {
+        let source_bytes = fs::metadata(photo).map_or(0, |m| m.len());
+        let source_sha256 = sha256(photo).with_context(|| format!("❌ Cannot read {}", photo.display()))?;
+        let single = Cli {
+            input: Some(photo.clone()),
+            output: None,
+            output_dir: Some(to.join(date_folder(photo))),
+            ..cli.clone()
+        };
+        let mut entry = Entry {
+            source: relative(photo, from),
+            source_bytes,
+            source_sha256,
+            output: None,
+            output_bytes: None,
+            output_sha256: None,
+            verified: false,
+            error: None,
+        };
+        match crate::run_conversion(&single, rules, devices).or_else(crate::keep_existing) {
+            Ok(output) => {
+                entry.output = Some(relative(&output, to));
+                entry.output_bytes = fs::metadata(&output).ok().map(|m| m.len());
+                entry.output_sha256 = sha256(&output).ok();
+                entry.verified = image::image_dimensions(&output).is_ok();
+                if entry.verified {
+                    tally.converted += 1;
+                } else {
+                    events::warn(format!("{} does not read back as an image", output.display()));
+                    tally.fail(photo, &anyhow!("{} does not read back as an image", output.display()));
+                }
+            }
+            Err(e) => {
+                say_err!("{:?}", e);
+                entry.error = Some(format!("{:#}", e).lines().next().unwrap_or_default().to_string());
+                tally.fail(photo, &e);
+            }
+        }
+        entries.push(entry);
+    }
+
+    let manifest = Manifest {
+        from: from.display().to_string(),
+        to: to.display().to_string(),
+        finished: chrono::Local::now().to_rfc3339(),
+        files: entries,
+    };
+    let manifest_path = to.join(MANIFEST_NAME);
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("❌ Cannot write {}", manifest_path.display()))?;
+
+    let verified = manifest.files.iter().filter(|entry| entry.verified).count();
+    if verified < photos.len() {
+        return Err(anyhow!(
+            "❌ {} of {} photos were not extracted; see {} before deleting anything from the device",
+            photos.len() - verified,
+            photos.len(),
+            manifest_path.display()
+        ));
+    }
+    let bytes: u64 = manifest.files.iter().filter_map(|entry| entry.output_bytes).sum();
+    say!();
+    say!(
+        "✅ Extracted all {} photos into {} ({}); manifest: {}",
+        photos.len(),
+        to.display(),
+        units::format_size(bytes),
+        manifest_path.display()
+    );
+    Ok(())
+}
+
+// `path` with symbolic links and .. resolved, also when its last components do not exist yet
+fn resolved(path: &Path) -> PathBuf {
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        if let Ok(real) = existing.canonicalize() {
+            return missing.iter().rev().fold(real, |path, name| path.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+// 2024/2024-05-17 from the EXIF capture date, undated without one
+fn date_folder(photo: &Path) -> PathBuf {
+    match exif::read(photo).date {
+        Some(date) => Path::new(&date.format("%Y").to_string()).join(date.format("%Y-%m-%d").to_string()),
+        None => PathBuf::from("undated"),
+    }
+}
+
+fn sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
@@ -0,0 +1,296 @@
+// Identify image files by their content (magic bytes) instead of trusting the extension
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+// Image container types we can recognise from the first bytes of a file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DetectedType {
+    Heic,
+    Avif,
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    Tiff,
+    Bmp,
+    Jxl,
+    Pdf,
+    Exr,
+    Pnm,
+    Ico,
+}
+
+impl DetectedType {
+    // Canonical lowercase extension for the detected type
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DetectedType::Heic => "heic",
+            DetectedType::Avif => "avif",
+            DetectedType::Jpeg => "jpg",
+            DetectedType::Png => "png",
+            DetectedType::Gif => "gif",
+            DetectedType::Webp => "webp",
+            DetectedType::Tiff => "tiff",
+            DetectedType::Bmp => "bmp",
+            DetectedType::Jxl => "jxl",
+            DetectedType::Pdf => "pdf",
+            DetectedType::Exr => "exr",
+            DetectedType::Pnm => "ppm",
+            DetectedType::Ico => "ico",
+        }
+    }
+
+    // Whether `extension` is an accepted spelling for this type (jpg/jpeg, heic/heif, ...)
+    pub fn matches_extension(&self, extension: &str) -> bool {
+        let extension = extension.to_lowercase();
+        let accepted: &[&str] = match self {
+            DetectedType::Heic => &["heic", "heif", "hif"],
+            DetectedType::Avif => &["avif"],
+            DetectedType::Jpeg => &["jpg", "jpeg", "jpe"],
+            DetectedType::Png => &["png"],
+            DetectedType::Gif => &["gif"],
+            DetectedType::Webp => &["webp"],
+            DetectedType::Tiff => &["tif", "tiff"],
+            DetectedType::Bmp => &["bmp", "dib"],
+            DetectedType::Jxl => &["jxl"],
+            DetectedType::Pdf => &["pdf"],
+            DetectedType::Exr => &["exr"],
+            DetectedType::Pnm => &["ppm", "pnm", "pgm", "pbm", "pam"],
+            DetectedType::Ico => &["ico"],
+        };
+        accepted.contains(&extension.as_str())
+    }
+}
+
+// Extensions of files we are willing to inspect and rename
+pub const IMAGE_EXTENSIONS: &[&str] = &[
+    "heic", "heif", "hif", "avif", "jpg", "jpeg", "jpe", "png", "gif", "webp", "tif", "tiff", "bmp", "dib", "jxl", "exr",
+    "ppm", "pnm", "ico",
+];
+
+// File name without its image extensions, in any capitalisation and stacked ones too:
+// IMG_0001.HEIC, IMG_0001.Heic and IMG_0001.HEIC.heic (left by some export and sync
+// tools) all give IMG_0001. Other dots stay: trip.v2.heic gives trip.v2.
+pub fn base_stem(path: &Path) -> String {
+    let mut stem = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    while let Some((rest, extension)) = stem.rsplit_once('.') {
+        if rest.is_empty() || !IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()) {
+            break;
+        }
+        stem.truncate(rest.len());
+    }
+    stem
+}
+
+// `path` with its extension in lowercase (--normalize-names)
+pub fn lowercase_extension(path: &Path) -> PathBuf {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => path.with_extension(extension.to_lowercase()),
+        None => path.to_path_buf(),
+    }
+}
+
+// Every file below `dir`, sorted; hidden entries (.Trashes, ._ AppleDouble files) are
+// skipped. Links to folders are not followed, since one pointing upwards would make the
+// scan go round in circles; links to files are listed.
+pub fn files_below(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    fn scan(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')) {
+                continue;
+            }
+            if entry.file_type()?.is_symlink() && path.is_dir() {
+                continue;
+            }
+            if path.is_dir() {
+                scan(&path, files)?;
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+    let mut files = Vec::new();
+    scan(dir, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+// Read the start of a file and work out what it really contains
+pub fn detect(path: &Path) -> Option<DetectedType> {
+    let mut header = [0u8; 64];
+    let mut file = File::open(path).ok()?;
+    let mut read = 0;
+    while read < header.len() {
+        match file.read(&mut header[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(_) => return None,
+        }
+    }
+    detect_bytes(&header[..read])
+}
+
+// Identify a file type from its leading bytes
+pub fn detect_bytes(bytes: &[u8]) -> Option<DetectedType> {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(DetectedType::Jpeg);
+    }
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(DetectedType::Png);
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return Some(DetectedType::Gif);
+    }
+    if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return Some(DetectedType::Webp);
+    }
+    if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+        return Some(DetectedType::Tiff);
+    }
+    // "BM" alone is too common a start for text; the size of the header that follows
+    // is one of the few BMP versions
+    if bytes.len() >= 18 && bytes.starts_with(b"BM") {
+        let header_size = u32::from_le_bytes([bytes[14], bytes[15], bytes[16], bytes[17]]);
+        if [12, 40, 52, 56, 108, 124].contains(&header_size) {
+            return Some(DetectedType::Bmp);
+        }
+    }
+    // A bare JPEG XL codestream, or one in the ISO-BMFF container
+    if bytes.starts_with(&[0xFF, 0x0A]) || bytes.starts_with(b"\0\0\0\x0cJXL \r\n\x87\n") {
+        return Some(DetectedType::Jxl);
+    }
+    if bytes.starts_with(b"%PDF-") {
+        return Some(DetectedType::Pdf);
+    }
+    if bytes.starts_with(&[0x76, 0x2f, 0x31, 0x01]) {
+        return Some(DetectedType::Exr);
+    }
+    // Netpbm: P1 to P7 and the whitespace that ends the magic number
+    if bytes.len() >= 3 && bytes[0] == b'P' && (b'1'..=b'7').contains(&bytes[1]) && bytes[2].is_ascii_whitespace() {
+        return Some(DetectedType::Pnm);
+    }
+    // An icon directory: reserved zero, type 1 and at least one image
+    if bytes.len() >= 6 && bytes.starts_with(&[0, 0, 1, 0]) && bytes[4..6] != [0, 0] {
+        return Some(DetectedType::Ico);
+    }
+    if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+        return detect_ftyp(bytes);
+    }
+    None
+}
+
+// HEIF-family files start with an ISO-BMFF 'ftyp' box listing brands
+fn detect_ftyp(bytes: &[u8]) -> Option<DetectedType> {
+    let box_size = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    let end = box_size.min(bytes.len());
+
+    // Major brand followed by minor version and the compatible brand list
+    let mut brands = vec![&bytes[8..12]];
+    let mut offset = 16;
+    while offset + 4 <= end {
+        brands.push(&bytes[offset..offset + 4]);
+        offset += 4;
+    }
+
+    let has = |names: &[&[u8]]| brands.iter().any(|brand| names.contains(brand));
+    if has(&[b"avif", b"avis"]) {
+        Some(DetectedType::Avif)
+    } else if has(&[b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"mif1", b"msf1"]) {
+        Some(DetectedType::Heic)
+    } else {
+        None
+    }
+}
+
+// Pixel size of an image without decoding it. HEIF and JPEG XL files are not readable
+// by the image crate, so their size comes from the 'ispe' (image spatial extent)
+// properties and the JPEG XL size header; a PDF gives the size of its first image.
+pub fn dimensions(path: &Path) -> Option<(u32, u32)> {
+    match detect(path)? {
+        DetectedType::Heic | DetectedType::Avif => heif_dimensions(path),
+        DetectedType::Jxl => jxl_dimensions(path),
+        DetectedType::Pdf => pdf_dimensions(path),
+        _ => image::image_dimensions(path).ok(),
+    }
+}
+
+// The largest 'ispe' in the metadata is the full image; smaller ones belong to grid
+// tiles and thumbnails. The metadata sits at the start of the file, before the pixels.
+fn heif_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let mut header = Vec::new();
+    File::open(path).ok()?.take(1024 * 1024).read_to_end(&mut header).ok()?;
+
+    let be32 = |at: usize| u32::from_be_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]]);
+    header
+        .windows(4)
+        .enumerate()
+        // size, 'ispe', version/flags, width, height
+        .filter(|&(at, name)| name == b"ispe" && at >= 4 && at + 16 <= header.len())
+        .map(|(at, _)| (be32(at + 8), be32(at + 12)))
+        .max_by_key(|&(width, height)| width as u64 * height as u64)
+}
+
+// The size header follows the codestream signature FF 0A, which starts the file or the
+// first 'jxlc' / 'jxlp' box of the container (after the part index of 'jxlp')
+fn jxl_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let mut header = Vec::new();
+    File::open(path).ok()?.take(64 * 1024).read_to_end(&mut header).ok()?;
+    let start = if header.starts_with(&[0xFF, 0x0A]) {
+        0
+    } else {
+        header.windows(4).enumerate().find_map(|(at, name)| match name {
+            b"jxlc" => Some(at + 4),
+            b"jxlp" => Some(at + 8),
+            _ => None,
+        })?
+    };
+    let codestream = header.get(start..)?.strip_prefix(&[0xFF, 0x0A])?;
+
+    // Fields are packed least significant bit first
+    let mut position = 0;
+    let mut bits = |count: usize| -> Option<u32> {
+        let mut value = 0;
+        for bit in 0..count {
+            let byte = codestream.get((position + bit) / 8)?;
+            value |= ((byte >> ((position + bit) % 8)) as u32 & 1) << bit;
+        }
+        position += count;
+        Some(value)
+    };
+    let small = bits(1)? == 1;
+    let size = |bits: &mut dyn FnMut(usize) -> Option<u32>| -> Option<u32> {
+        if small {
+            return Some((bits(5)? + 1) * 8);
+        }
+        let width = [9, 13, 18, 30][bits(2)? as usize];
+        Some(bits(width)? + 1)
+    };
+    let height = size(&mut bits)?;
+    let ratio = bits(3)?;
+    let width = match ratio {
+        0 => size(&mut bits)?,
+        _ => {
+            let (numerator, denominator) = [(1, 1), (12, 10), (4, 3), (3, 2), (16, 9), (5, 4), (2, 1)][ratio as usize - 1];
+            (height as u64 * numerator / denominator) as u32
+        }
+    };
+    Some((width, height))
+}
+
+// The /Width and /Height of the first image dictionary; the PDFs written by pdf.rs put
+// it before the image data
+fn pdf_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let mut header = Vec::new();
+    File::open(path).ok()?.take(64 * 1024).read_to_end(&mut header).ok()?;
+    let number_after = |key: &[u8]| -> Option<u32> {
+        let at = header.windows(key.len()).position(|window| window == key)? + key.len();
+        let digits: Vec<u8> =
+            header[at..].iter().skip_while(|b| b.is_ascii_whitespace()).take_while(|b| b.is_ascii_digit()).copied().collect();
+        std::str::from_utf8(&digits).ok()?.parse().ok()
+    };
+    Some((number_after(b"/Width")?, number_after(b"/Height")?))
+}
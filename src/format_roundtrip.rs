@@ -0,0 +1,151 @@
+// Property tests: random small images are saved in every output format, read back and
+// compared with the original. Lossless formats must return the exact pixels, lossy ones
+// the exact size and, for flat images, colours close to the original. Formats this build
+// cannot decode (AVIF, PDF) are checked for the size their container records. JPEG XL is
+// encoded by cjxl, which the tests do not expect to be installed, and ICO files hold
+// the image at fixed icon sizes instead.
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, LumaA, Rgb, Rgba};
+use proptest::prelude::*;
+use std::path::PathBuf;
+
+use crate::tiff_writer::TiffCompression;
+use crate::{OutputFormat, save_image};
+
+// What a format promises about the pixels it gets back
+enum Fidelity {
+    Exact,
+    EightBit, // The exact pixels at 8 bits per channel, maybe read back as RGB or RGBA
+    Opaque,   // The exact colours at 8 bits per channel, without the alpha
+    Lossy { flat_tolerance: u8 }, // Largest channel error for a single-colour image
+    WriteOnly,                    // No decoder in this build
+    External,                     // Encoded by another program
+    Icon,                         // Square images of fixed sizes, tested in ico.rs
+}
+
+// Every format must be listed here, so a new one cannot skip the round trip
+fn fidelity(format: &OutputFormat) -> Fidelity {
+    match format {
+        OutputFormat::Png => Fidelity::Exact,
+        OutputFormat::Jpg | OutputFormat::Jpeg => Fidelity::Lossy { flat_tolerance: 6 },
+        OutputFormat::Webp => Fidelity::EightBit,
+        OutputFormat::Avif => Fidelity::WriteOnly,
+        OutputFormat::Tiff => Fidelity::EightBit,
+        OutputFormat::Bmp => Fidelity::Opaque,
+        OutputFormat::Jxl => Fidelity::External,
+        OutputFormat::Pdf => Fidelity::WriteOnly,
+        OutputFormat::Exr => Fidelity::EightBit,
+        OutputFormat::Ppm => Fidelity::Opaque,
+        OutputFormat::Ico => Fidelity::Icon,
+    }
+}
+
+// Images in the colour types the decoders hand to save_image
+fn any_image() -> impl Strategy<Value = DynamicImage> {
+    (1u32..48, 1u32..48, 0..5usize, any::<u64>()).prop_map(|(width, height, kind, seed)| {
+        let mut state = seed | 1;
+        let mut next = move || {
+            // xorshift: cheap noise, reproducible from the seed proptest reports
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        };
+        match kind {
+            0 => DynamicImage::ImageRgb8(ImageBuffer::from_fn(width, height, |_, _| Rgb([next(), next(), next()]))),
+            1 => DynamicImage::ImageRgba8(ImageBuffer::from_fn(width, height, |_, _| {
+                Rgba([next(), next(), next(), next()])
+            })),
+            2 => DynamicImage::ImageLuma8(ImageBuffer::from_fn(width, height, |_, _| Luma([next()]))),
+            3 => DynamicImage::ImageLumaA8(ImageBuffer::from_fn(width, height, |_, _| LumaA([next(), next()]))),
+            _ => DynamicImage::ImageRgb16(ImageBuffer::from_fn(width, height, |_, _| {
+                Rgb([next() as u16 * 257, next() as u16 * 257, next() as u16 * 257])
+            })),
+        }
+    })
+}
+
+fn scratch(format: &OutputFormat) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("heic_convert-roundtrip-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let name = format!("{:?}-{:?}.{}", std::thread::current().id(), format, format.extension());
+    dir.join(name.replace(['(', ')'], ""))
+}
+
+fn round_trip(img: &DynamicImage, format: &OutputFormat, quality: Option<u8>) -> DynamicImage {
+    let path = scratch(format);
+    save_image(img, &path, format, quality, TiffCompression::Lzw, None).unwrap_or_else(|e| panic!("saving as {:?} failed: {:#}", format, e));
+    let decoded = image::open(&path).unwrap_or_else(|e| panic!("reading back {:?} failed: {}", format, e));
+    let _ = std::fs::remove_file(&path);
+    decoded
+}
+
+// The size recorded in a file the image crate cannot read back
+fn written_dimensions(img: &DynamicImage, format: &OutputFormat, quality: Option<u8>) -> Option<(u32, u32)> {
+    let path = scratch(format);
+    save_image(img, &path, format, quality, TiffCompression::Lzw, None).unwrap_or_else(|e| panic!("saving as {:?} failed: {:#}", format, e));
+    let dimensions = crate::file_type::dimensions(&path);
+    let _ = std::fs::remove_file(&path);
+    dimensions
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(48))]
+
+    #[test]
+    fn every_format_keeps_the_dimensions(img in any_image(), quality in proptest::option::of(1u8..=100)) {
+        for format in OutputFormat::value_variants() {
+            let dimensions = match fidelity(format) {
+                Fidelity::External | Fidelity::Icon => continue,
+                Fidelity::WriteOnly => written_dimensions(&img, format, quality),
+                _ => Some(round_trip(&img, format, quality).dimensions()),
+            };
+            prop_assert_eq!(dimensions, Some(img.dimensions()), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn lossless_formats_keep_every_pixel(img in any_image()) {
+        for format in OutputFormat::value_variants() {
+            match fidelity(format) {
+                Fidelity::Exact => {
+                    let decoded = round_trip(&img, format, None);
+                    prop_assert_eq!(decoded.color(), img.color(), "{:?}", format);
+                    prop_assert!(decoded.as_bytes() == img.as_bytes(), "{:?} changed the pixels", format);
+                }
+                Fidelity::EightBit => {
+                    let decoded = round_trip(&img, format, None);
+                    prop_assert!(decoded.to_rgba8() == img.to_rgba8(), "{:?} changed the pixels", format);
+                }
+                Fidelity::Opaque => {
+                    let decoded = round_trip(&img, format, None);
+                    prop_assert!(decoded.to_rgb8() == img.to_rgb8(), "{:?} changed the pixels", format);
+                }
+                Fidelity::Lossy { .. } | Fidelity::WriteOnly | Fidelity::External | Fidelity::Icon => {}
+            }
+        }
+    }
+
+    #[test]
+    fn lossy_formats_keep_flat_colours(
+        width in 1u32..48,
+        height in 1u32..48,
+        colour in any::<[u8; 3]>(),
+        quality in 50u8..=100,
+    ) {
+        let img = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(width, height, Rgb(colour)));
+        for format in OutputFormat::value_variants() {
+            if let Fidelity::Lossy { flat_tolerance } = fidelity(format) {
+                let decoded = round_trip(&img, format, Some(quality)).to_rgb8();
+                for pixel in decoded.pixels() {
+                    for (got, expected) in pixel.0.iter().zip(colour) {
+                        prop_assert!(
+                            got.abs_diff(expected) <= flat_tolerance,
+                            "{:?} at quality {}: {:?} came back as {:?}", format, quality, colour, pixel.0
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+// Gamut warnings for wide-colour sources (--gamut-check srgb)
+//
+// iPhones record in Display P3, which holds more saturated reds and greens than sRGB.
+// Pixels outside sRGB are clipped (or shown washed out) once the image is viewed as
+// sRGB, so this reports how much of each image is affected. The source colour space
+// comes from the HEIF 'colr' box or the embedded ICC profile.
+use clap::ValueEnum;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::file_type::{self, DetectedType};
+
+// Output gamuts that can be checked against
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Target {
+    Srgb,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorSpace {
+    Srgb,
+    DisplayP3,
+    Other, // Tagged, but with primaries this check does not know
+}
+
+impl ColorSpace {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorSpace::Srgb => "sRGB",
+            ColorSpace::DisplayP3 => "Display P3",
+            ColorSpace::Other => "an unsupported colour space",
+        }
+    }
+}
+
+// Share of out-of-gamut pixels from which an image is flagged
+pub const NOTICEABLE: f64 = 1.0;
+
+// Linear Display P3 to linear sRGB (both D65)
+const P3_TO_SRGB: [[f32; 3]; 3] = [
+    [1.224_94, -0.224_94, 0.0],
+    [-0.042_057, 1.042_057, 0.0],
+    [-0.019_638, -0.078_636, 1.098_274],
+];
+
+// Rounding in the source encoding puts neutral pixels a hair outside the gamut
+const TOLERANCE: f32 = 0.002;
+
+// The colour space the pixels of `path` are encoded in. Untagged images are sRGB by
+// convention; None means the file could not be read.
+pub fn source_space(path: &Path) -> Option<ColorSpace> {
+    match file_type::detect(path)? {
+        DetectedType::Heic | DetectedType::Avif => heif_space(path),
+        _ => {
+            let mut decoder = image::ImageReader::open(path).ok()?.with_guessed_format().ok()?.into_decoder().ok()?;
+            let icc = image::ImageDecoder::icc_profile(&mut decoder).ok()?;
+            Some(icc.map_or(ColorSpace::Srgb, |icc| icc_space(&icc)))
+        }
+    }
+}
+
+// Percentage of pixels outside sRGB when the image is encoded in `space`
+pub fn out_of_gamut(image: &image::DynamicImage, space: ColorSpace) -> f64 {
+    if space != ColorSpace::DisplayP3 {
+        return 0.0;
+    }
+    let pixels = image.to_rgb32f();
+    let outside = pixels
+        .pixels()
+        .filter(|pixel| {
+            // P3 uses the sRGB transfer curve
+            let linear = pixel.0.map(|v| if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) });
+            P3_TO_SRGB.iter().any(|row| {
+                let value: f32 = row.iter().zip(linear).map(|(m, v)| m * v).sum();
+                !(-TOLERANCE..=1.0 + TOLERANCE).contains(&value)
+            })
+        })
+        .count();
+    outside as f64 * 100.0 / (pixels.width() as u64 * pixels.height() as u64).max(1) as f64
+}
+
+// HEIF files describe colour in a 'colr' box: either CICP code points ('nclx') or an
+// ICC profile ('prof' / 'rICC'). The metadata sits at the start of the file.
+fn heif_space(path: &Path) -> Option<ColorSpace> {
+    let mut header = Vec::new();
+    File::open(path).ok()?.take(1024 * 1024).read_to_end(&mut header).ok()?;
+
+    let be32 = |at: usize| u32::from_be_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]]) as usize;
+    for at in (4..header.len().saturating_sub(8)).filter(|&at| &header[at..at + 4] == b"colr") {
+        let size = be32(at - 4);
+        let body = at + 8..(at - 4 + size).min(header.len()).max(at + 8);
+        match &header[at + 4..at + 8] {
+            // colour_primaries: 1 = BT.709 / sRGB, 12 = Display P3
+            b"nclx" if body.len() >= 2 => {
+                return Some(match u16::from_be_bytes([header[body.start], header[body.start + 1]]) {
+                    1 | 2 => ColorSpace::Srgb, // 2 = unspecified
+                    12 => ColorSpace::DisplayP3,
+                    _ => ColorSpace::Other,
+                });
+            }
+            b"prof" | b"rICC" => return Some(icc_space(&header[body])),
+            _ => {}
+        }
+    }
+    Some(ColorSpace::Srgb)
+}
+
+// Tell the profiles apart by their red colorant (rXYZ, D50-adapted): X is about 0.436
+// for sRGB and 0.515 for Display P3
+fn icc_space(icc: &[u8]) -> ColorSpace {
+    let be32 = |at: usize| icc.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+    let Some(count) = be32(128) else {
+        return ColorSpace::Other;
+    };
+    let red_x = (0..(count as usize).min(icc.len() / 12)).find_map(|index| {
+        let entry = 132 + index * 12;
+        if icc.get(entry..entry + 4)? != b"rXYZ" {
+            return None;
+        }
+        // 'XYZ ', 4 reserved bytes, then X as s15Fixed16
+        let offset = be32(entry + 4)? as usize;
+        Some(be32(offset + 8)? as i32 as f64 / 65536.0)
+    });
+    match red_x {
+        Some(x) if (x - 0.436).abs() < 0.02 => ColorSpace::Srgb,
+        Some(x) if (x - 0.515).abs() < 0.02 => ColorSpace::DisplayP3,
+        _ => ColorSpace::Other,
+    }
+}
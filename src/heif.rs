@@ -0,0 +1,483 @@
+// Structure of HEIF/HEIC containers, for integrity checks and repairs (`validate`)
+//
+// A HEIC file is a sequence of ISO-BMFF boxes: 'ftyp' names the brands, 'meta' holds
+// the item metadata and 'mdat' the coded image data. Inside 'meta', 'pitm' names the
+// primary item, 'iloc' says where the data of every item lies in the file and 'iref'
+// links a grid image to its tiles ('dimg'). Only these boxes are read; the pixels are
+// not decoded. Bounds are checked everywhere, so damaged files give problems, not panics.
+use std::fmt;
+
+// Brands of HEIC still images and image sequences
+const HEIC_BRANDS: &[&[u8; 4]] = &[b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"mif1", b"msf1"];
+
+// A top-level box: where it starts and how long its header says it is
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoxHeader {
+    pub offset: u64,
+    pub kind: [u8; 4],
+    pub header_len: u64,
+    pub size: u64, // Including the header; a size of 0 ("up to the end") is resolved
+}
+
+impl BoxHeader {
+    pub fn end(&self) -> u64 {
+        self.offset + self.size
+    }
+
+    pub fn name(&self) -> String {
+        String::from_utf8_lossy(&self.kind).into_owned()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Extent {
+    pub offset: u64, // Absolute, base offset included
+    pub length: u64, // 0 means "up to the end of the file"
+}
+
+// Where the data of one item is; only construction method 0 (file offsets) points into the file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemLocation {
+    pub item: u32,
+    pub in_file: bool,
+    pub extents: Vec<Extent>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Layout {
+    pub len: u64,
+    pub heic: bool, // 'ftyp' comes first and lists a HEIC brand
+    pub boxes: Vec<BoxHeader>,
+    pub bad_box_at: Option<u64>, // A box whose size is smaller than its own header; the walk stops there
+    pub has_meta: bool,
+    pub primary: Option<u32>,
+    pub has_iloc: bool,
+    pub locations: Vec<ItemLocation>,
+    pub tiles: Vec<(u32, Vec<u32>)>, // 'dimg' references: derived (grid) item -> its inputs
+}
+
+impl Layout {
+    // The primary item and, for a grid image, its tiles
+    pub fn primary_items(&self) -> Vec<u32> {
+        let Some(primary) = self.primary else {
+            return Vec::new();
+        };
+        let mut items = vec![primary];
+        for (from, to) in &self.tiles {
+            if *from == primary {
+                items.extend(to);
+            }
+        }
+        items
+    }
+
+    // Whether all data of `item` lies inside the file
+    pub fn item_intact(&self, item: u32) -> bool {
+        self.locations.iter().find(|location| location.item == item).is_some_and(|location| {
+            !location.in_file
+                || location.extents.iter().all(|extent| extent.offset.saturating_add(extent.length) <= self.len)
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    NotHeic,
+    BadBoxSize { offset: u64 },
+    BoxPastEnd { kind: String, missing: u64 },
+    Missing(&'static str),
+    PrimaryDataMissing,
+    ItemDataMissing { items: usize },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::NotHeic => write!(f, "not a HEIC file (no 'ftyp' box with a HEIC brand)"),
+            Problem::BadBoxSize { offset } => write!(f, "box at byte {} has an impossible size", offset),
+            Problem::BoxPastEnd { kind, missing } => {
+                write!(f, "truncated: the '{}' box needs {} more bytes than the file has", kind, missing)
+            }
+            Problem::Missing(kind) => write!(f, "no '{}' box", kind),
+            Problem::PrimaryDataMissing => write!(f, "the data of the main image is missing"),
+            Problem::ItemDataMissing { items } => {
+                write!(f, "{} secondary item(s) (thumbnails, depth maps) point past the end of the file", items)
+            }
+        }
+    }
+}
+
+pub fn parse(bytes: &[u8]) -> Layout {
+    let mut layout = Layout { len: bytes.len() as u64, ..Default::default() };
+    let mut offset = 0u64;
+    while offset < layout.len {
+        let Some(header) = box_header(bytes, offset, layout.len) else {
+            layout.bad_box_at = Some(offset);
+            break;
+        };
+        offset = header.end();
+        layout.boxes.push(header);
+    }
+
+    if let Some(ftyp) = layout.boxes.first().filter(|header| &header.kind == b"ftyp") {
+        let brands = slice(bytes, ftyp.offset + ftyp.header_len, ftyp.end());
+        // Major brand, minor version, then the compatible brands
+        layout.heic = brands
+            .chunks_exact(4)
+            .enumerate()
+            .any(|(index, brand)| index != 1 && HEIC_BRANDS.iter().any(|heic| heic.as_slice() == brand));
+    }
+
+    if let Some(meta) = layout.boxes.iter().find(|header| &header.kind == b"meta").cloned() {
+        layout.has_meta = true;
+        // 'meta' is a full box: version and flags come before the children
+        let content = slice(bytes, meta.offset + meta.header_len + 4, meta.end());
+        let mut at = 0u64;
+        while let Some(child) = box_header(content, at, content.len() as u64) {
+            let body = slice(content, at + child.header_len, child.end());
+            match &child.kind {
+                b"pitm" => layout.primary = read_pitm(body),
+                b"iloc" => {
+                    layout.has_iloc = true;
+                    layout.locations = read_iloc(body).unwrap_or_default();
+                }
+                b"iref" => layout.tiles = read_iref(body),
+                _ => {}
+            }
+            at = child.end();
+        }
+    }
+    layout
+}
+
+pub fn check(layout: &Layout) -> Vec<Problem> {
+    if !layout.heic {
+        return vec![Problem::NotHeic];
+    }
+    let mut problems = Vec::new();
+    if let Some(offset) = layout.bad_box_at {
+        problems.push(Problem::BadBoxSize { offset });
+    }
+    if let Some(last) = layout.boxes.last().filter(|header| header.end() > layout.len) {
+        problems.push(Problem::BoxPastEnd { kind: last.name(), missing: last.end() - layout.len });
+    }
+    // Without 'meta' its children are missing too; one problem is enough
+    let required = [(layout.has_meta, "meta"), (layout.primary.is_some(), "pitm"), (layout.has_iloc, "iloc")];
+    if let Some((_, kind)) = required.iter().find(|(present, _)| !present) {
+        problems.push(Problem::Missing(kind));
+    }
+    if !problems.iter().any(|problem| matches!(problem, Problem::Missing(_))) {
+        let primary = layout.primary_items();
+        if !primary.iter().all(|item| layout.item_intact(*item)) {
+            problems.push(Problem::PrimaryDataMissing);
+        }
+        let broken = layout
+            .locations
+            .iter()
+            .filter(|location| !primary.contains(&location.item) && !layout.item_intact(location.item))
+            .count();
+        if broken > 0 {
+            problems.push(Problem::ItemDataMissing { items: broken });
+        }
+    }
+    problems
+}
+
+// A corrected copy of a damaged file, or None when it cannot be repaired. The 'mdat' box
+// holding the main image is made to end where the data really ends: at the end of a
+// truncated file, or where junk appended by a bad transfer begins. Item offsets are
+// absolute, so boxes after it may be swallowed without moving any data; 'meta' must come
+// first for that. The main image must be complete in the bytes that are there.
+pub fn repair(bytes: &[u8], layout: &Layout) -> Option<Vec<u8>> {
+    let problems = check(layout);
+    let fatal = |problem: &Problem| {
+        matches!(problem, Problem::NotHeic | Problem::Missing(_) | Problem::PrimaryDataMissing)
+    };
+    if problems.is_empty() || problems.iter().any(fatal) {
+        return None;
+    }
+    let extents: Vec<&Extent> = layout
+        .primary_items()
+        .into_iter()
+        .filter_map(|item| layout.locations.iter().find(|location| location.item == item))
+        .filter(|location| location.in_file)
+        .flat_map(|location| &location.extents)
+        .collect();
+    let data_start = extents.iter().map(|extent| extent.offset).min()?;
+    let data_end = extents.iter().map(|extent| extent.offset + extent.length).max()?;
+
+    let meta = layout.boxes.iter().find(|header| &header.kind == b"meta")?;
+    let mdat = layout
+        .boxes
+        .iter()
+        .find(|header| &header.kind == b"mdat" && (header.offset..header.end()).contains(&data_start))?;
+    if meta.offset > mdat.offset {
+        return None;
+    }
+    let end = match layout.bad_box_at {
+        Some(junk) if junk >= data_end => junk,
+        _ => layout.len,
+    };
+    let size = end - mdat.offset;
+
+    let mut repaired = bytes[..mdat.offset as usize].to_vec();
+    if mdat.header_len == 16 {
+        repaired.extend(1u32.to_be_bytes());
+        repaired.extend(b"mdat");
+        repaired.extend(size.to_be_bytes());
+    } else {
+        // A longer header would move the data the item offsets point at
+        repaired.extend(u32::try_from(size).ok()?.to_be_bytes());
+        repaired.extend(b"mdat");
+    }
+    repaired.extend(&bytes[(mdat.offset + mdat.header_len) as usize..end as usize]);
+    Some(repaired)
+}
+
+// The header of the box at `offset`; None when it is cut off or its size is impossible.
+// A box may run past `len` (truncated file); check() reports that.
+fn box_header(bytes: &[u8], offset: u64, len: u64) -> Option<BoxHeader> {
+    let mut reader = Reader::at(bytes, offset);
+    let size = reader.uint(4)?;
+    let kind: [u8; 4] = reader.take(4)?.try_into().ok()?;
+    let (header_len, size) = match size {
+        0 => (8, len - offset),
+        1 => (16, reader.uint(8)?),
+        size => (8, size),
+    };
+    (size >= header_len).then_some(BoxHeader { offset, kind, header_len, size })
+}
+
+fn read_pitm(body: &[u8]) -> Option<u32> {
+    let mut reader = Reader::at(body, 0);
+    let version = reader.uint(1)?;
+    reader.take(3)?;
+    Some(reader.uint(if version == 0 { 2 } else { 4 })? as u32)
+}
+
+fn read_iloc(body: &[u8]) -> Option<Vec<ItemLocation>> {
+    let mut reader = Reader::at(body, 0);
+    let version = reader.uint(1)?;
+    reader.take(3)?;
+    let sizes = reader.uint(1)?;
+    let (offset_size, length_size) = ((sizes >> 4) as usize, (sizes & 0xF) as usize);
+    let sizes = reader.uint(1)?;
+    let base_offset_size = (sizes >> 4) as usize;
+    let index_size = if version >= 1 { (sizes & 0xF) as usize } else { 0 };
+    let item_count = reader.uint(if version < 2 { 2 } else { 4 })?;
+
+    let mut locations = Vec::new();
+    for _ in 0..item_count {
+        let item = reader.uint(if version < 2 { 2 } else { 4 })? as u32;
+        let construction_method = if version >= 1 { reader.uint(2)? & 0xF } else { 0 };
+        reader.uint(2)?; // data_reference_index
+        let base_offset = reader.uint(base_offset_size)?;
+        let extent_count = reader.uint(2)?;
+        let mut extents = Vec::new();
+        for _ in 0..extent_count {
+            reader.uint(index_size)?;
+            let offset = base_offset.saturating_add(reader.uint(offset_size)?);
+            let length = reader.uint(length_size)?;
+            extents.push(Extent { offset, length });
+        }
+        locations.push(ItemLocation { item, in_file: construction_method == 0, extents });
+    }
+    Some(locations)
+}
+
+fn read_iref(body: &[u8]) -> Vec<(u32, Vec<u32>)> {
+    let mut references = Vec::new();
+    let Some(version) = body.first() else {
+        return references;
+    };
+    let id_size = if *version == 0 { 2 } else { 4 };
+    let content = slice(body, 4, body.len() as u64);
+    let mut at = 0u64;
+    while let Some(child) = box_header(content, at, content.len() as u64) {
+        let mut reader = Reader::at(content, at + child.header_len);
+        if &child.kind == b"dimg"
+            && let Some(from) = reader.uint(id_size)
+            && let Some(count) = reader.uint(2)
+        {
+            let to: Vec<u32> = (0..count).map_while(|_| reader.uint(id_size)).map(|id| id as u32).collect();
+            references.push((from as u32, to));
+        }
+        at = child.end();
+    }
+    references
+}
+
+// bytes[start..end], clamped to what is there
+fn slice(bytes: &[u8], start: u64, end: u64) -> &[u8] {
+    let end = (end.min(bytes.len() as u64)) as usize;
+    let start = (start as usize).min(end);
+    &bytes[start..end]
+}
+
+// Big-endian reads that stop at the end of the data
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn at(bytes: &'a [u8], offset: u64) -> Self {
+        Reader { bytes, at: offset.min(bytes.len() as u64) as usize }
+    }
+
+    fn take(&mut self, count: usize) -> Option<&'a [u8]> {
+        let taken = self.bytes.get(self.at..self.at.checked_add(count)?)?;
+        self.at += count;
+        Some(taken)
+    }
+
+    // An unsigned integer of 0, 1, 2, 4 or 8 bytes
+    fn uint(&mut self, size: usize) -> Option<u64> {
+        Some(self.take(size)?.iter().fold(0u64, |value, byte| (value << 8) | *byte as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boxed(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut bytes = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend(kind);
+        bytes.extend(body);
+        bytes
+    }
+
+    fn full_box(kind: &[u8; 4], version: u8, body: &[u8]) -> Vec<u8> {
+        let mut content = vec![version, 0, 0, 0];
+        content.extend(body);
+        boxed(kind, &content)
+    }
+
+    // ftyp, meta (pitm = 1, iloc for items 1 and 2), mdat with `data` bytes; item 1 is
+    // the first half of the data, item 2 (a thumbnail) the second half
+    fn heic(data: usize) -> Vec<u8> {
+        let ftyp = boxed(b"ftyp", b"heic\0\0\0\0mif1heic");
+        let pitm = full_box(b"pitm", 0, &1u16.to_be_bytes());
+        let meta_len = |iloc_len: usize| 8 + 4 + pitm.len() + iloc_len;
+        // iloc version 0: offset and length 4 bytes, no base offset
+        let iloc_body_len = 2 + 2 + 2 * (2 + 2 + 2 + 4 + 4);
+        let iloc_len = 12 + iloc_body_len;
+        let mdat_data = (ftyp.len() + meta_len(iloc_len) + 8) as u32;
+        let half = (data / 2) as u32;
+        let mut iloc = vec![0x44, 0x00];
+        iloc.extend(2u16.to_be_bytes());
+        for (item, offset) in [(1u16, mdat_data), (2, mdat_data + half)] {
+            iloc.extend(item.to_be_bytes());
+            iloc.extend(0u16.to_be_bytes());
+            iloc.extend(1u16.to_be_bytes());
+            iloc.extend(offset.to_be_bytes());
+            iloc.extend(half.to_be_bytes());
+        }
+        let mut meta_body = vec![0, 0, 0, 0];
+        meta_body.extend(pitm);
+        meta_body.extend(full_box(b"iloc", 0, &iloc));
+        let mut bytes = ftyp;
+        bytes.extend(boxed(b"meta", &meta_body));
+        bytes.extend(boxed(b"mdat", &vec![0xAB; data]));
+        bytes
+    }
+
+    #[test]
+    fn intact_file_has_no_problems() {
+        let layout = parse(&heic(100));
+        assert_eq!(check(&layout), Vec::new());
+        assert_eq!(layout.primary, Some(1));
+        assert_eq!(layout.locations.len(), 2);
+        assert_eq!(layout.boxes.iter().map(BoxHeader::name).collect::<Vec<_>>(), ["ftyp", "meta", "mdat"]);
+    }
+
+    #[test]
+    fn truncated_thumbnail_keeps_the_primary_image() {
+        let mut bytes = heic(100);
+        bytes.truncate(bytes.len() - 10);
+        assert_eq!(
+            check(&parse(&bytes)),
+            [Problem::BoxPastEnd { kind: String::from("mdat"), missing: 10 }, Problem::ItemDataMissing { items: 1 }]
+        );
+    }
+
+    #[test]
+    fn truncated_primary_image_is_reported() {
+        let mut bytes = heic(100);
+        bytes.truncate(bytes.len() - 60);
+        let problems = check(&parse(&bytes));
+        assert!(problems.contains(&Problem::PrimaryDataMissing), "{:?}", problems);
+    }
+
+    #[test]
+    fn other_files_are_not_heic() {
+        assert_eq!(check(&parse(b"\x89PNG\r\n\x1a\n")), [Problem::NotHeic]);
+        assert_eq!(check(&parse(b"")), [Problem::NotHeic]);
+    }
+
+    #[test]
+    fn impossible_box_size_stops_the_walk() {
+        let mut bytes = heic(100);
+        let ftyp_len = 24;
+        bytes[ftyp_len..ftyp_len + 4].copy_from_slice(&3u32.to_be_bytes());
+        let problems = check(&parse(&bytes));
+        assert!(problems.contains(&Problem::BadBoxSize { offset: ftyp_len as u64 }), "{:?}", problems);
+        assert!(problems.contains(&Problem::Missing("meta")), "{:?}", problems);
+    }
+
+    #[test]
+    fn truncated_mdat_is_closed_at_the_end_of_the_file() {
+        let mut bytes = heic(100);
+        bytes.truncate(bytes.len() - 10);
+        let repaired = repair(&bytes, &parse(&bytes)).expect("the main image is complete");
+        assert_eq!(repaired.len(), bytes.len());
+        // Only the cut-off thumbnail is left
+        assert_eq!(check(&parse(&repaired)), [Problem::ItemDataMissing { items: 1 }]);
+    }
+
+    #[test]
+    fn junk_after_the_data_is_dropped() {
+        let intact = heic(100);
+        let mut bytes = intact.clone();
+        bytes.extend([0u8; 5]);
+        assert_eq!(check(&parse(&bytes)), [Problem::BadBoxSize { offset: intact.len() as u64 }]);
+        assert_eq!(repair(&bytes, &parse(&bytes)), Some(intact));
+    }
+
+    #[test]
+    fn short_mdat_is_grown_over_the_data() {
+        let intact = heic(100);
+        let mut bytes = intact.clone();
+        let mdat = intact.len() - 108;
+        bytes[mdat..mdat + 4].copy_from_slice(&20u32.to_be_bytes());
+        assert!(!check(&parse(&bytes)).is_empty());
+        assert_eq!(repair(&bytes, &parse(&bytes)), Some(intact));
+    }
+
+    #[test]
+    fn lost_main_image_or_intact_file_is_not_repaired() {
+        let mut bytes = heic(100);
+        assert_eq!(repair(&bytes, &parse(&bytes)), None);
+        bytes.truncate(bytes.len() - 60);
+        assert_eq!(repair(&bytes, &parse(&bytes)), None);
+    }
+
+    #[test]
+    fn grid_tiles_count_as_the_primary_image() {
+        let layout = Layout {
+            len: 100,
+            heic: true,
+            has_meta: true,
+            has_iloc: true,
+            primary: Some(1),
+            locations: vec![
+                ItemLocation { item: 1, in_file: false, extents: vec![] },
+                ItemLocation { item: 2, in_file: true, extents: vec![Extent { offset: 50, length: 80 }] },
+            ],
+            tiles: vec![(1, vec![2])],
+            ..Default::default()
+        };
+        assert_eq!(check(&layout), [Problem::PrimaryDataMissing]);
+    }
+}
@@ -0,0 +1,431 @@
+// Detailed help topics (`heic_convert help <topic>`) and the man page (`heic_convert man`).
+//
+// The guidance lives here as data so the terminal help and the man page are always
+// generated from the same text. Options are not repeated in the topics: the man page
+// takes them from the clap definitions, and `--help` shows them on the terminal.
+use clap::CommandFactory;
+
+use crate::Cli;
+
+pub struct Topic {
+    pub name: &'static str,
+    pub summary: &'static str,
+    pub sections: &'static [Section],
+}
+
+pub struct Section {
+    pub heading: &'static str,
+    pub entries: &'static [Entry],
+}
+
+pub enum Entry {
+    Text(&'static str),                  // A paragraph
+    Example(&'static str, &'static str), // What it does, the command
+    Item(&'static str, &'static str),    // A term and its explanation
+}
+
+use Entry::{Example, Item, Text};
+
+pub const TOPICS: &[Topic] = &[
+    Topic {
+        name: "formats",
+        summary: "Output formats, file names and file types",
+        sections: &[
+            Section {
+                heading: "OUTPUT FORMATS",
+                entries: &[
+                    Item("png", "Lossless, keeps transparency (default)"),
+                    Item("jpg, jpeg", "Smaller files, no transparency"),
+                    Example("Convert to JPG", "heic_convert -i photo.heic -f jpg"),
+                    Example("Smaller JPEGs", "heic_convert -i photo.heic -f jpg --quality 80"),
+                    Item("webp", "Smaller than JPEG at the same look, keeps transparency; lossless with --lossless"),
+                    Example("For web publishing", "heic_convert -i photo.heic -f webp --quality 75"),
+                    Item("avif", "The AV1 sibling of HEIC: about half the size of JPEG, read by current browsers"),
+                    Example("Archive a library as AVIF", "heic_convert -i ~/Pictures/Export -r -f avif"),
+                    Item("tiff", "For print and scan pipelines; --tiff-compression none (default), lzw or deflate"),
+                    Example("LZW-compressed TIFF for a print shop", "heic_convert -i photo.heic -f tiff --tiff-compression lzw"),
+                    Item("bmp", "Uncompressed 24-bit bitmap for older Windows tools; no transparency"),
+                    Example("BMP for a label printer", "heic_convert -i label.heic -f bmp"),
+                    Item("jxl", "JPEG XL, encoded by cjxl; --quality, --jxl-distance 0-25 or --lossless"),
+                    Example("Archive as JPEG XL", "heic_convert -i ~/Pictures/Export -r -f jxl --jxl-distance 1"),
+                    Item("pdf", "A page per image (every image of a multi-image HEIC with ImageMagick), stored as JPEG"),
+                    Example("File receipts as PDF", "heic_convert -i ~/Receipts -f pdf --output-dir ~/Expenses"),
+                    Item("exr", "OpenEXR in linear float light for compositing; PQ and HLG highlights stay above 1.0"),
+                    Example("Plates for Nuke or Blender", "heic_convert -i ~/Pictures/Plates -f exr --output-dir ~/Shots"),
+                    Item("ppm", "Binary PPM for netpbm tool chains and scripts; 16-bit samples for deep images"),
+                    Example("Pipe into netpbm", "heic_convert -i photo.heic -f ppm -o - | pnmscale 0.5 | pnmtojpeg > small.jpg"),
+                    Item("ico", "Windows icon / favicon with 16, 32, 64 and 256 pixel sizes, centred on a transparent square"),
+                    Example("Favicon from a logo photo", "heic_convert -i logo.heic -o favicon.ico"),
+                    Text("PNG, TIFF, JPEG XL and PPM keep the bit depth the decoder delivers; --bit-depth 16 keeps all of a 10/12-bit HEIC for editing, --bit-depth 8 always reduces."),
+                    Example("16-bit PNG for editing", "heic_convert -i hdr.heic --bit-depth 16"),
+                    Text("Without -o the output is written next to the input with the new extension: photo.heic becomes photo.png. --output-dir puts it in another folder instead."),
+                    Example("Choose the output file", "heic_convert -i IMG_1234.heic -o my_photo.png"),
+                    Text("JPEG is written at quality 85 unless --quality says otherwise; --quality auto picks 75 to 92 from how busy each image is."),
+                    Example("Let the image decide", "heic_convert -i photo.heic -f jpg --quality auto"),
+                    Text("`init` asks for a default format, quality and output folder and stores them under [defaults] in the configuration file; the command line still wins."),
+                    Example("First-run setup", "heic_convert init"),
+                    Example("Add an EXIF thumbnail for fast grid views in photo browsers", "heic_convert -i photo.heic -f jpg --embed-thumbnail"),
+                    Example("Shrink to at most 4000 pixels wide (never enlarges)", "heic_convert -i panorama.heic --resize 4000x"),
+                ],
+            },
+            Section {
+                heading: "CORRECTIONS",
+                entries: &[
+                    Text("--denoise 1-10 smooths sensor noise with an edge-preserving filter before encoding; night shots look cleaner and JPEGs get smaller. Strong settings soften fine texture."),
+                    Example("Clean up a high-ISO night shot", "heic_convert -i night.heic -f jpg --denoise 5"),
+                    Text("--auto-levels stretches the darkest and brightest 0.5% of the pixels to black and white; --auto-white-balance scales the colour channels until the average mid-tone is grey. All corrections run after resizing, before encoding; noise reduction comes first."),
+                    Example("Fix a flat, orange indoor shot", "heic_convert -i IMG_0042.heic -f jpg --auto-levels --auto-white-balance"),
+                    Text("--curve takes IN:OUT points from 0 to 255 (r=, g= or b= for one channel) and --lut applies a .cube colour lookup table; both run after the automatic corrections, curves first."),
+                    Example("Bake a look into a whole conversion", "heic_convert -i IMG_0042.heic -f jpg --curve 64:48,192:210 --lut portra.cube"),
+                    Text("--dither adds a fine threshold pattern when 10- or 16-bit images are reduced to 8 bits, so skies and gradients do not band. blue-noise (default) has no visible structure; ordered uses an 8x8 Bayer matrix."),
+                    Example("Convert an HDR shot without banding", "heic_convert -i sunset.heic -f jpg --dither"),
+                ],
+            },
+            Section {
+                heading: "FILE TYPES",
+                entries: &[
+                    Text("Photos copied off an iPhone sometimes carry the wrong extension. --fix-extensions looks at the content of every image in a directory and renames the ones that disagree, without re-encoding."),
+                    Example("Fix JPEGs saved as .heic (and vice versa)", "heic_convert --fix-extensions ~/Pictures/iPhone"),
+                    Text("Extensions are matched in any case, and stacked ones such as IMG_0001.HEIC.heic count as one (the output is IMG_0001.png). --normalize-names lowercases output extensions."),
+                    Example("Collapse stacked extensions and lowercase them", "heic_convert --fix-extensions ~/Pictures/iPhone --normalize-names"),
+                    Example("Rescue the HEIC attachments of a saved e-mail (also PDFs and Notes HTML exports)", "heic_convert --extract-embedded \"Holiday photos.eml\" -f jpg"),
+                    Example("Import from a mounted phone into date folders, with a verification manifest", "heic_convert extract --from /Volumes/iPhone/DCIM --to ~/Pictures/Converted -f jpg"),
+                    Example("Recover the camera roll from an unencrypted iPhone backup (feature \"ios-backup\")", "heic_convert --ios-backup ~/Library/Application\\ Support/MobileSync/Backup/<device id> -f jpg"),
+                ],
+            },
+            Section {
+                heading: "COMPARING OUTPUTS",
+                entries: &[
+                    Text("`canonical` re-encodes an image as a metadata-free PNG with fixed settings, so outputs of different backends can be compared byte-wise. --deterministic makes repeated conversions byte-identical."),
+                    Example("Normalize an output", "heic_convert canonical photo.png -o photo.canonical.png"),
+                ],
+            },
+        ],
+    },
+    Topic {
+        name: "metadata",
+        summary: "What happens to EXIF, ICC profiles and timestamps",
+        sections: &[
+            Section {
+                heading: "BY BACKEND",
+                entries: &[
+                    Item("builtin", "Writes pixels only: EXIF, XMP and ICC profiles are dropped"),
+                    Item("imagemagick", "Carries EXIF and ICC over where the output format supports them"),
+                    Item("ffmpeg", "Copies container metadata only; EXIF and ICC are dropped"),
+                    Text("--explain shows which backend handled a file and what was carried over."),
+                ],
+            },
+            Section {
+                heading: "SHARING",
+                entries: &[
+                    Text("--share-safe removes EXIF, XMP and the GPS position (the colour profile is kept), shrinks to fit 2048x2048 and sets JPEG quality 80."),
+                    Example("Prepare a photo for e-mail", "heic_convert -i IMG_0042.heic -f jpg --share-safe"),
+                ],
+            },
+            Section {
+                heading: "REPRODUCIBLE OUTPUT",
+                entries: &[
+                    Text("--deterministic strips metadata and encoder timestamps and runs external tools single-threaded, so the same input always gives the same bytes."),
+                    Example("Byte-identical output for content-addressed storage", "heic_convert -i photo.heic --deterministic"),
+                ],
+            },
+            Section {
+                heading: "COLOUR SPACES",
+                entries: &[
+                    Text("iPhones record in Display P3. Where the ICC profile is dropped, or a viewer ignores it, the image is shown as sRGB and saturated colours change. --gamut-check srgb reports the share of pixels outside sRGB and flags images from 1%."),
+                    Example("Check a sunset shot before sharing", "heic_convert -i sunset.heic -f jpg --gamut-check srgb"),
+                ],
+            },
+        ],
+    },
+    Topic {
+        name: "backends",
+        summary: "How images are decoded and how to tune the work",
+        sections: &[
+            Section {
+                heading: "BACKENDS",
+                entries: &[
+                    Text("With --backend auto (default) the built-in decoder is tried first, then ImageMagick, then FFmpeg. Any other value uses that backend only."),
+                    Item("builtin", "Rust image crate; fastest, but cannot read HEIC on most systems"),
+                    Item("imagemagick", "`convert` with libheif support"),
+                    Item("ffmpeg", "FFmpeg with HEIF demuxing"),
+                    Example("Force ImageMagick", "heic_convert -i photo.heic --backend imagemagick"),
+                    Text("Builtin and FFmpeg cannot keep EXIF metadata or a wide-gamut colour profile. --on-unsupported warn (default) converts anyway with a warning, switch moves to a backend that can, fail stops with E025."),
+                    Example("Never lose metadata silently", "heic_convert -i photo.heic --on-unsupported fail"),
+                ],
+            },
+            Section {
+                heading: "SYSTEM REQUIREMENTS",
+                entries: &[
+                    Item("macOS", "brew install libheif imagemagick"),
+                    Item("Linux", "apt-get install libheif-dev imagemagick"),
+                    Item("Windows", "Install ImageMagick or FFmpeg and add it to PATH"),
+                ],
+            },
+            Section {
+                heading: "CPU, BATTERY AND STORAGE",
+                entries: &[
+                    Example("Keep the fast cores free on a laptop", "heic_convert -i photo.heic --cores efficiency"),
+                    Example("Slow down only on battery or when the machine runs hot", "heic_convert -i photo.heic --eco"),
+                    Example("Retry read errors on a failing SD card", "heic_convert -i /Volumes/SDCARD/DCIM/IMG_0001.heic --retry-io 5 --io-backoff"),
+                    Example("Wait up to 5 minutes for a file that is still syncing", "heic_convert -i ~/Dropbox/IMG_0001.heic --wait-for-sync 5m"),
+                    Text("Intermediate files go to a temporary directory (--tmpdir) and are moved into place when complete; `clean-temp` removes leftovers of killed runs."),
+                ],
+            },
+        ],
+    },
+    Topic {
+        name: "batch",
+        summary: "Converting many files and tidying up afterwards",
+        sections: &[
+            Section {
+                heading: "MANY FILES",
+                entries: &[
+                    Example("Convert every HEIC file in a folder", "heic_convert -i ~/Pictures/Export -f jpg"),
+                    Example("Include the subfolders, e.g. exports nested by year and month", "heic_convert -i ~/Pictures/Export -f jpg --recursive"),
+                    Example("Leave out thumbnail folders and edited copies", "heic_convert -i ~/Pictures/Export -r -f jpg --exclude \"*/Thumbnails/*\" --exclude \"IMG_E*.heic\""),
+                    Example("Convert into a separate tree with the same subfolders (--flatten: all in one folder)", "heic_convert -i ~/Pictures/Export -r -f jpg --output-dir ~/Pictures/Converted"),
+                    Example("Files matching a pattern, expanded by heic_convert (also on Windows)", "heic_convert -i \"IMG_2*.heic\" -f jpg"),
+                    Example("Several files in one run (-i can be repeated too)", "heic_convert -f jpg IMG_0001.heic IMG_0002.heic"),
+                    Example("Hand a converted set over as one ZIP file", "heic_convert -i ~/Pictures/Wedding -r -f jpg --archive-output wedding.zip"),
+                    Example("The photos in a ZIP archive (iCloud download), read one at a time", "heic_convert \"iCloud Photos.zip\" -f jpg"),
+                    Example("Files found by another program (one per line, or NUL-separated with -print0)", "find . -name '*.heic' -print0 | heic_convert --files-from - -f jpg"),
+                    Example("Convert every HEIC that arrives in Downloads (AirDrop) until Ctrl-C", "heic_convert -f jpg watch ~/Downloads --to ~/Pictures/Converted"),
+                    Example("Run an interrupted batch again; files already converted are skipped", "heic_convert -i ~/Pictures/Export -f jpg --skip-existing"),
+                    Example("Convert byte-identical copies only once (--link-duplicates: hard-link their outputs)", "heic_convert -i ~/Pictures/Export -r -f jpg --skip-duplicates"),
+                    Example("Go on after Ctrl-C, a crash or a power loss with the files the run had not finished", "heic_convert -i ~/Pictures/Export -f jpg --resume"),
+                    Example("Move each original to the trash once its output is verified (--delete-original: delete it)", "heic_convert -i ~/Pictures/Export -f jpg --trash-original"),
+                    Example("List every input with its output, backend, duration and outcome (.json for JSON)", "heic_convert -i ~/Pictures/Export -f jpg --report migration.csv"),
+                    Example("Keep existing outputs and number the new ones (photo_1.jpg, ...)", "heic_convert -i ~/Pictures/Export -f jpg --rename-on-conflict"),
+                    Text("An existing output is overwritten with a warning; --overwrite does it silently, --no-clobber fails the input (E026) instead."),
+                    Example("Convert four files at a time (--jobs 0: one per CPU core)", "heic_convert -i ~/Pictures/Export -f jpg --jobs 4"),
+                    Example("Spread outputs over DVD-sized folders (part_001, part_002, ...)", "heic_convert -i ~/Pictures/Export --split-output 4.7GB"),
+                    Example("Wait for the night before converting", "heic_convert -i big_panorama.heic --schedule 02:00-06:00"),
+                    Example("Stop starting new files after 3.5 hours (the rest goes to heic_convert-remaining.txt)", "heic_convert --ios-backup ~/Backups/<device id> --time-budget 3h30m"),
+                    Example("Convert the small files first to get many results early", "heic_convert --ios-backup ~/Backups/<device id> --order smallest-first"),
+                    Example("Name outputs by capture date and position", "heic_convert -i ~/Pictures/Export --name-template \"{date}_{counter:4}_{stem}.{ext}\""),
+                    Example("Name outputs by capture date (feature \"scripting\")", "heic_convert -i IMG_0042.heic --name-expr 'exif.date.format(\"%Y-%m-%d\") + \"_\" + file.stem'"),
+                    Example("Import into a library of year and month folders by capture date", "heic_convert -i ~/Pictures/Export -r --output-dir ~/Pictures/Library --organize-by-date"),
+                    Example("One folder per family phone ([devices] in the config names them)", "heic_convert -i ~/Pictures/Export --by-device"),
+                    Example("Keep album.json up to date for a static gallery", "heic_convert -i ~/Pictures/Export -f jpg --album-manifest"),
+                    Text("[[rule]] sections in the configuration file change format, backend or resize per file: when = \"width > 8000\" with set = { resize = \"4000x\" }. Conditions use width, height, megapixels, aspect, size, ext, name, make and model joined with and / or."),
+                    Text("--name-expr sees exif.date, exif.make, exif.model and file.stem, file.name, file.ext, file.size, file.modified; missing tags are (). A \"/\" in the result creates a sub-directory next to the input."),
+                ],
+            },
+            Section {
+                heading: "PIPES AND SCRIPTS",
+                entries: &[
+                    Text("-o - writes the image to stdout and --json prints a one-line JSON summary there; in both modes every message, including the banner, goes to stderr."),
+                    Example("Convert straight into another program", "heic_convert -i photo.heic -f jpg -o - | ssh server 'cat > photo.jpg'"),
+                    Example("Read the result size in a script", "heic_convert -i photo.heic --json 2>/dev/null | jq .output_bytes"),
+                    Example("List version, features, codecs and installed backends as JSON", "heic_convert --version --json"),
+                ],
+            },
+            Section {
+                heading: "AFTER A MIGRATION",
+                entries: &[
+                    Text("--orphan-report lists Live Photo .mov clips and .aae edit files whose HEIC original is gone but whose converted output exists; --orphan-review-dir moves them aside for review."),
+                    Example("Find leftover companions", "heic_convert --orphan-report ~/Pictures/Converted --orphan-review-dir ~/Review"),
+                    Example("Remove temporary files of crashed runs", "heic_convert clean-temp"),
+                ],
+            },
+            Section {
+                heading: "INVENTORY",
+                entries: &[
+                    Text("`info` shows type, size, dimensions, capture date and camera; --stats adds a luminance histogram, clipping percentages and average brightness, and lists the shots that look under- or over-exposed."),
+                    Example("Find badly exposed shots", "heic_convert info ~/Pictures/2023/*.heic --stats"),
+                    Text("--advise recommends PNG or JPG for an image, from its content, transparency and the sizes it would have in each format, without converting."),
+                    Example("Decide before a large run", "heic_convert -i IMG_0042.heic --advise"),
+                    Text("`validate` checks that HEIC files are intact (truncated files, missing image data) without writing anything; --decode also decodes each file in full, --repair writes a corrected copy when only the container is damaged."),
+                    Example("Check a photo library before deleting the originals", "heic_convert validate ~/Pictures --decode"),
+                    Example("Write repaired copies of files whose main image is intact", "heic_convert validate ~/Pictures/Imported --repair"),
+                ],
+            },
+            Section {
+                heading: "USAGE STATISTICS",
+                entries: &[
+                    Text("`stats --enable` starts counting converted files, bytes saved and time spent in ~/.local/share/heic_convert/stats.json. Nothing is recorded until then and nothing is ever sent anywhere."),
+                    Example("Show the lifetime totals", "heic_convert stats"),
+                    Example("Stop counting and delete the file", "heic_convert stats --disable"),
+                ],
+            },
+        ],
+    },
+    Topic {
+        name: "server",
+        summary: "Network destinations, notifications and unattended runs",
+        sections: &[
+            Section {
+                heading: "DESTINATIONS",
+                entries: &[
+                    Example("Upload to SFTP (feature \"upload\")", "heic_convert -i photo.heic --upload sftp://me@nas/photos"),
+                    Example("Import into an Immich album named after the input folder", "heic_convert -i Holidays/IMG_1.heic --import-to immich --api-url http://nas:2283"),
+                    Example("Write to a NAS share without saturating the network", "heic_convert -i photo.heic -o /Volumes/NAS/photo.png --bwlimit 10MB/s"),
+                ],
+            },
+            Section {
+                heading: "NOTIFICATIONS",
+                entries: &[
+                    Example("POST a JSON summary when the run ends", "heic_convert -i photo.heic --webhook https://ntfy.sh/my-topic"),
+                    Example("E-mail the summary (feature \"email\", [smtp] in the config file)", "heic_convert -i photo.heic --email-report me@example.com"),
+                    Text("The configuration file is read from --config or ~/.config/heic_convert/config.toml."),
+                ],
+            },
+        ],
+    },
+    Topic {
+        name: "troubleshooting",
+        summary: "Common errors and how to fix them",
+        sections: &[
+            Section {
+                heading: "COMMON ERRORS",
+                entries: &[
+                    Item("HEIC format support is not available", "No backend can read the file. Install ImageMagick (brew install imagemagick) or FFmpeg (brew install ffmpeg)."),
+                    Item("Input file does not exist", "Check the path, that the file was not moved, and that you can read it."),
+                    Item("Failed to save image", "Check write permission and free space in the output directory."),
+                    Item("No write permission to output directory", "Choose another -o location or fix the directory permissions."),
+                    Item("heic_convert crashed unexpectedly", "A bug. Attach the diagnostic report named in the message (it holds no image data) to a new issue."),
+                    Text("Every failure ends with a line such as `see: heic_convert explain E008`; that command lists the likely causes and fixes for the code, and `heic_convert explain` lists all codes."),
+                ],
+            },
+            Section {
+                heading: "FINDING OUT MORE",
+                entries: &[
+                    Text("--explain prints why each backend, metadata and transform decision was made. --ascii-only gives plain output that is easier to read with a screen reader or paste into a bug report."),
+                    Text("Run without a terminal (piped, cron, CI) the output is plain automatically and a conversion ends with its JSON summary line; HEIC_CONVERT_HEADLESS=1 or =0 forces either behaviour."),
+                    Text("Builds with the \"self-update\" feature can install fixes with `heic_convert self-update`; the download is installed only if its release signature checks out, and --check just reports whether a newer version exists."),
+                    Item("Alternatives", "convert input.heic output.png; ffmpeg -i input.heic output.png; the macOS Preview app (Export as PNG/JPEG)"),
+                ],
+            },
+        ],
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Topic> {
+    TOPICS.iter().find(|topic| topic.name.eq_ignore_ascii_case(name))
+}
+
+// `heic_convert help` without a topic: short usage and the list of topics
+pub fn print_index() {
+    say!("HEIC to PNG/JPG Converter - Help");
+    say!();
+    say!("USAGE:");
+    say!("  heic_convert -i input.heic                    # Convert to PNG (default)");
+    say!("  heic_convert -i input.heic -f jpg             # Convert to JPG");
+    say!("  heic_convert -i input.heic -o output.png      # Specify output file");
+    say!();
+    say!("TOPICS (heic_convert help <topic>):");
+    for topic in TOPICS {
+        say!("  {:<18} {}", topic.name, topic.summary);
+    }
+    say!();
+    say!("All options: heic_convert --help    Man page: heic_convert man");
+}
+
+pub fn print_topic(topic: &Topic) {
+    let title = format!("{} - {}", topic.name, topic.summary);
+    say!("{}", title);
+    say!("{}", "=".repeat(title.chars().count()));
+    for section in topic.sections {
+        say!();
+        say!("{}:", section.heading);
+        for entry in section.entries {
+            match entry {
+                Text(text) => say!("  {}", text),
+                Example(what, command) => {
+                    say!("  # {}:", what);
+                    say!("  {}", command);
+                }
+                Item(term, text) if term.chars().count() <= 20 => say!("  {:<20} {}", term, text),
+                Item(term, text) => {
+                    say!("  {}", term);
+                    say!("  {:<20} {}", "", text);
+                }
+            }
+        }
+    }
+}
+
+// Every topic, one after another (--bighelp)
+pub fn print_all() {
+    print_index();
+    for topic in TOPICS {
+        say!();
+        print_topic(topic);
+    }
+}
+
+// Escape text for roff
+fn roff(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+// The man page in roff format, built from the clap definitions and the help topics
+pub fn render_man() -> String {
+    let command = Cli::command();
+    let mut page = String::new();
+    page.push_str(&format!(
+        ".TH HEIC_CONVERT 1 \"\" \"heic_convert {}\" \"User Commands\"\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    page.push_str(".SH NAME\nheic_convert \\- ");
+    page.push_str(&roff(&command.get_about().map(|a| a.to_string()).unwrap_or_default()));
+    page.push_str("\n.SH SYNOPSIS\n\\fBheic_convert\\fR \\-i \\fIINPUT\\fR [\\fIOPTIONS\\fR]\n");
+    page.push_str(".br\n\\fBheic_convert\\fR \\fICOMMAND\\fR [\\fIARGS\\fR]\n");
+
+    page.push_str(".SH OPTIONS\n");
+    for arg in command.get_arguments().filter(|arg| !arg.is_hide_set()) {
+        let mut flags = Vec::new();
+        if let Some(short) = arg.get_short() {
+            flags.push(format!("\\fB\\-{}\\fR", short));
+        }
+        if let Some(long) = arg.get_long() {
+            flags.push(format!("\\fB\\-\\-{}\\fR", roff(long)));
+        }
+        if flags.is_empty() {
+            continue;
+        }
+        let value = arg
+            .get_value_names()
+            .and_then(|names| names.first())
+            .filter(|_| arg.get_action().takes_values())
+            .map(|name| format!(" \\fI{}\\fR", name))
+            .unwrap_or_default();
+        page.push_str(&format!(".TP\n{}{}\n", flags.join(", "), value));
+        page.push_str(&roff(&arg.get_help().map(|h| h.to_string()).unwrap_or_default()));
+        page.push('\n');
+    }
+
+    page.push_str(".SH COMMANDS\n");
+    for sub in command.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        page.push_str(&format!(".TP\n\\fB{}\\fR\n", roff(sub.get_name())));
+        page.push_str(&roff(&sub.get_about().map(|a| a.to_string()).unwrap_or_default()));
+        page.push('\n');
+    }
+
+    for topic in TOPICS {
+        page.push_str(&format!(".SH {}\n", roff(&topic.summary.to_uppercase())));
+        for section in topic.sections {
+            page.push_str(&format!(".SS {}\n", roff(section.heading)));
+            for entry in section.entries {
+                match entry {
+                    Text(text) => page.push_str(&format!(".PP\n{}\n", roff(text))),
+                    Example(what, command) => page.push_str(&format!(
+                        ".PP\n{}:\n.RS\n.nf\n{}\n.fi\n.RE\n",
+                        roff(what),
+                        roff(command)
+                    )),
+                    Item(term, text) => {
+                        page.push_str(&format!(".TP\n\\fB{}\\fR\n{}\n", roff(term), roff(text)))
+                    }
+                }
+            }
+        }
+    }
+    page
+}
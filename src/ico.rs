@@ -0,0 +1,69 @@
+// Windows icons with several sizes in one file (-f ico)
+//
+// Favicons and application icons are picked from an ICO file by size, so one file
+// carries the image at 16, 32, 64 and 256 pixels. Icons are square: an image of another
+// shape is scaled to fit and centred on a transparent square, so a logo photo is never
+// cut off. Sizes above the longer edge of the image are left out rather than blown up,
+// but the smallest size is always there. Every size is stored as a PNG, which Windows
+// Vista and later and every browser read.
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ExtendedColorType, RgbaImage};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+const SIZES: [u32; 4] = [16, 32, 64, 256];
+
+pub fn save(img: &DynamicImage, path: &Path) -> image::ImageResult<()> {
+    let icons = icons(img);
+    let frames = icons
+        .iter()
+        .map(|icon| IcoFrame::as_png(icon.as_raw(), icon.width(), icon.height(), ExtendedColorType::Rgba8))
+        .collect::<image::ImageResult<Vec<_>>>()?;
+    IcoEncoder::new(BufWriter::new(File::create(path)?)).encode_images(&frames)
+}
+
+// The square images the icon holds, smallest first
+fn icons(img: &DynamicImage) -> Vec<RgbaImage> {
+    let longest = img.width().max(img.height());
+    SIZES
+        .iter()
+        .enumerate()
+        .filter(|&(n, &size)| n == 0 || size <= longest)
+        .map(|(_, &size)| {
+            let scaled = img.resize(size, size, FilterType::Lanczos3).to_rgba8();
+            let mut icon = RgbaImage::new(size, size);
+            let (x, y) = ((size - scaled.width()) / 2, (size - scaled.height()) / 2);
+            imageops::overlay(&mut icon, &scaled, x.into(), y.into());
+            icon
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn a_wide_image_is_centred_in_every_size() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(400, 200, Rgba([255, 0, 0, 255])));
+        let icons = icons(&img);
+        assert_eq!(icons.iter().map(|icon| icon.width()).collect::<Vec<_>>(), SIZES);
+        for icon in &icons {
+            let size = icon.width();
+            assert_eq!(icon.height(), size);
+            assert_eq!(icon.get_pixel(size / 2, size / 2).0[3], 255);
+            assert_eq!(icon.get_pixel(size / 2, 0).0[3], 0, "{}x{} has no transparent margin", size, size);
+        }
+    }
+
+    #[test]
+    fn small_images_are_not_blown_up() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::new(40, 40));
+        assert_eq!(icons(&img).iter().map(|icon| icon.width()).collect::<Vec<_>>(), [16, 32]);
+        let tiny = DynamicImage::ImageRgb8(image::RgbImage::new(8, 8));
+        assert_eq!(icons(&tiny).iter().map(|icon| icon.width()).collect::<Vec<_>>(), [16]);
+    }
+}
@@ -0,0 +1,156 @@
+// `heic_convert info <IMAGES>...`: what a file is, and with --stats how it is exposed
+//
+// The exposure statistics use the luminance (Rec. 709) of every pixel: a 16-bin
+// histogram, the share of clipped shadows / highlights and the average brightness.
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::temp_workspace::TempWorkspace;
+use crate::{exif, file_type, ui, units};
+
+const BINS: usize = 16;
+
+// Share of clipped pixels from which a shot is flagged
+const CLIPPING_LIMIT: f64 = 5.0;
+
+pub struct Exposure {
+    pub histogram: [u64; BINS],
+    pub average: f64,    // 0-255
+    pub shadows: f64,    // % of pixels at luminance 0-2
+    pub highlights: f64, // % of pixels at luminance 253-255
+}
+
+impl Exposure {
+    pub fn of(image: &image::DynamicImage) -> Exposure {
+        let pixels = image.to_rgb8();
+        let mut histogram = [0u64; BINS];
+        let (mut sum, mut shadows, mut highlights) = (0u64, 0u64, 0u64);
+        for pixel in pixels.pixels() {
+            let [r, g, b] = pixel.0.map(|c| c as f64);
+            let luma = (0.2126 * r + 0.7152 * g + 0.0722 * b).round() as usize;
+            histogram[luma * BINS / 256] += 1;
+            sum += luma as u64;
+            if luma <= 2 {
+                shadows += 1;
+            } else if luma >= 253 {
+                highlights += 1;
+            }
+        }
+        let count = (pixels.width() as u64 * pixels.height() as u64).max(1) as f64;
+        Exposure {
+            histogram,
+            average: sum as f64 / count,
+            shadows: shadows as f64 * 100.0 / count,
+            highlights: highlights as f64 * 100.0 / count,
+        }
+    }
+
+    // Rough verdict for finding shots worth a second look
+    pub fn verdict(&self) -> &'static str {
+        if self.highlights >= CLIPPING_LIMIT || self.average > 190.0 {
+            "over-exposed"
+        } else if self.shadows >= CLIPPING_LIMIT || self.average < 60.0 {
+            "under-exposed"
+        } else {
+            "ok"
+        }
+    }
+
+    // One character per bin, scaled to the fullest bin
+    fn sparkline(&self) -> String {
+        let levels: Vec<char> = if ui::ascii_only() {
+            "0123456789".chars().collect()
+        } else {
+            "▁▂▃▄▅▆▇█".chars().collect()
+        };
+        let peak = self.histogram.iter().copied().max().unwrap_or(0).max(1);
+        self.histogram
+            .iter()
+            .map(|&count| levels[(count * (levels.len() as u64 - 1) / peak) as usize])
+            .collect()
+    }
+}
+
+pub fn run(images: &[PathBuf], stats: bool, tmpdir: Option<&Path>) -> Result<()> {
+    let mut flagged = Vec::new();
+    let mut analysed = 0;
+    for (index, path) in images.iter().enumerate() {
+        if index > 0 {
+            say!();
+        }
+        say!("{}", path.display());
+        let Ok(metadata) = std::fs::metadata(path) else {
+            say!("  ❌ Cannot read this file");
+            continue;
+        };
+
+        let kind = file_type::detect(path).map_or("unknown", |kind| kind.extension());
+        say!("  Type:        {}", kind.to_uppercase());
+        say!("  File size:   {}", units::format_size(metadata.len()));
+        if let Some((width, height)) = file_type::dimensions(path) {
+            say!(
+                "  Dimensions:  {} x {} ({:.1} MP)",
+                width,
+                height,
+                width as f64 * height as f64 / 1_000_000.0
+            );
+        }
+        let tags = exif::read(path);
+        if let Some(date) = tags.date {
+            say!("  Taken:       {}", date.format("%Y-%m-%d %H:%M:%S"));
+        }
+        if let Some(camera) = [tags.make, tags.model].into_iter().flatten().reduce(|make, model| {
+            if model.starts_with(&make) { model } else { format!("{} {}", make, model) }
+        }) {
+            say!("  Camera:      {}", camera);
+        }
+
+        if !stats {
+            continue;
+        }
+        // HEIC files are decoded with the conversion backends into scratch space
+        let workspace = TempWorkspace::create(tmpdir)?;
+        match crate::decode_image(path, &workspace) {
+            Ok(image) => {
+                let exposure = Exposure::of(&image);
+                analysed += 1;
+                say!(
+                    "  Brightness:  {:.0} of 255 ({:.0}%)",
+                    exposure.average,
+                    exposure.average * 100.0 / 255.0
+                );
+                say!(
+                    "  Clipped:     {:.1}% shadows, {:.1}% highlights",
+                    exposure.shadows,
+                    exposure.highlights
+                );
+                say!("  Histogram:   {}  (dark → bright)", exposure.sparkline());
+                say!("  Exposure:    {}", exposure.verdict());
+                if exposure.verdict() != "ok" {
+                    flagged.push((path, exposure.verdict()));
+                }
+            }
+            Err(e) => {
+                // Only the headline here; the details are one `explain` away
+                let headline = format!("{:#}", e).lines().next().unwrap_or_default().to_string();
+                match crate::error_codes::code_of(&e) {
+                    Some(code) => say!("  ❌ Cannot decode for statistics: {} (see: heic_convert explain {})", headline, code),
+                    None => say!("  ❌ Cannot decode for statistics: {}", headline),
+                }
+            }
+        }
+    }
+
+    if stats && analysed > 1 {
+        say!();
+        if flagged.is_empty() {
+            say!("✅ No under- or over-exposed images among {}", analysed);
+        } else {
+            say!("⚠️  {} of {} images may need attention:", flagged.len(), analysed);
+            for (path, verdict) in flagged {
+                say!("  • {} ({})", path.display(), verdict);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+// `init` subcommand: first-run setup that writes the configuration file
+//
+// Shows the platform and the installed backends, then asks for the default output
+// format, the JPEG quality and where converted images should go, and writes the
+// answers to the [defaults] section of the configuration file. Answers are read line
+// by line from stdin, so they can be piped in as well; an empty answer or the end of
+// the input takes the suggested value.
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config;
+
+// Suggested JPEG quality: visually lossless for photos at about a third of the PNG size
+const SUGGESTED_QUALITY: u8 = 90;
+
+pub fn run(explicit: Option<&Path>, force: bool) -> Result<()> {
+    let path = explicit
+        .map(Path::to_path_buf)
+        .or_else(config::default_path)
+        .ok_or_else(|| anyhow!("❌ Cannot find the configuration directory: HOME is not set\nChoose a file with --config <FILE>."))?;
+    if path.exists() && !force {
+        return Err(anyhow!(
+            "❌ A configuration file already exists: {}\n\
+             Edit it directly, or start over with: heic_convert init --force",
+            path.display()
+        ));
+    }
+
+    say!("🔎 Setting up heic_convert on {} ({})", platform(), std::env::consts::ARCH);
+    report_backends();
+    say!();
+
+    let mut stdin = io::stdin().lock();
+    let format = ask(&mut stdin, "Default output format, png (lossless) or jpg (smaller)", "png", |answer| {
+        match answer.to_lowercase().as_str() {
+            "png" => Some("png"),
+            "jpg" | "jpeg" => Some("jpg"),
+            _ => None,
+        }
+    })?;
+    let quality = match format {
+        "jpg" => Some(ask(&mut stdin, "JPEG quality, 1-100", &SUGGESTED_QUALITY.to_string(), |answer| {
+            answer.parse::<u8>().ok().filter(|quality| (1..=100).contains(quality))
+        })?),
+        _ => None,
+    };
+    let output_dir = ask(&mut stdin, "Folder for converted images (empty: next to the originals)", "", |answer| {
+        Some(expand_home(answer))
+    })?;
+
+    if path.exists() {
+        let backup = path.with_extension("toml.bak");
+        fs::rename(&path, &backup).with_context(|| format!("❌ Cannot keep the old configuration as {}", backup.display()))?;
+        say!("Kept the old configuration as {}", backup.display());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("❌ Cannot create {}", dir.display()))?;
+    }
+    fs::write(&path, render(format, quality, output_dir.as_deref()))
+        .with_context(|| format!("❌ Cannot write the configuration file: {}", path.display()))?;
+
+    say!();
+    say!("✅ Wrote {}", path.display());
+    say!("   Convert a photo with these settings: heic_convert -i photo.heic");
+    say!("   Options on the command line still win, e.g. -f png for a single file.");
+    Ok(())
+}
+
+fn platform() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "macOS",
+        "linux" => "Linux",
+        "windows" => "Windows",
+        other => other,
+    }
+}
+
+// HEIC needs an external tool; say which are installed and how to get one
+fn report_backends() {
+    if !cfg!(feature = "external-tools") {
+        say!("   • Built-in decoder only: this build cannot start ImageMagick or FFmpeg");
+        return;
+    }
+    let imagemagick = crate::check_imagemagick_available();
+    let ffmpeg = crate::check_ffmpeg_available();
+    let state = |installed: bool| if installed { "installed" } else { "not installed" };
+    say!("   • ImageMagick: {}", state(imagemagick));
+    say!("   • FFmpeg: {}", state(ffmpeg));
+    if imagemagick || ffmpeg {
+        return;
+    }
+    let install = match std::env::consts::OS {
+        "macos" => "brew install imagemagick",
+        "windows" => "winget install ImageMagick.ImageMagick",
+        _ => "sudo apt install imagemagick libheif1 (or your distribution's equivalent)",
+    };
+    say!("⚠️  Neither can be found, and the built-in decoder cannot read HEIC. Install ImageMagick with: {}", install);
+}
+
+// Ask until `parse` accepts the answer; an empty line or the end of the input takes `suggested`
+fn ask<T>(input: &mut impl BufRead, question: &str, suggested: &str, parse: impl Fn(&str) -> Option<T>) -> Result<T> {
+    loop {
+        print!("{} [{}]: ", question, suggested);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        let answer = match input.read_line(&mut line)? {
+            0 => {
+                say!();
+                suggested
+            }
+            _ => match line.trim() {
+                "" => suggested,
+                answer => answer,
+            },
+        };
+        match parse(answer) {
+            Some(value) => return Ok(value),
+            None => say!("⚠️  \"{}\" is not a valid answer, please try again", answer),
+        }
+    }
+}
+
+// ~/Pictures -> /home/me/Pictures; nothing for an empty answer
+fn expand_home(answer: &str) -> Option<PathBuf> {
+    let home = || std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).map(PathBuf::from);
+    match answer {
+        "" => None,
+        "~" => home(),
+        _ => match (answer.strip_prefix("~/"), home()) {
+            (Some(rest), Some(home)) => Some(home.join(rest)),
+            _ => Some(PathBuf::from(answer)),
+        },
+    }
+}
+
+// The configuration file, with a pointer to the other settings
+fn render(format: &str, quality: Option<u8>, output_dir: Option<&Path>) -> String {
+    let mut text = String::from(
+        "# Written by `heic_convert init`. Options given on the command line take precedence.\n\
+         # [[rule]], [devices] and [smtp] sections can be added here as well, see the README.\n\
+         \n\
+         [defaults]\n",
+    );
+    text.push_str(&format!("format = \"{}\"\n", format));
+    if let Some(quality) = quality {
+        text.push_str(&format!("quality = {}\n", quality));
+    }
+    if let Some(dir) = output_dir {
+        // A TOML string, with backslashes and quotes in the path escaped
+        text.push_str(&format!("output_dir = {}\n", toml::Value::String(dir.display().to_string())));
+    }
+    text
+}
@@ -0,0 +1,189 @@
+// What a run converts: the HEIC files behind the -i arguments, the trailing ones and
+// those listed in the --files-from file
+//
+// A file is converted as given. A folder stands for the HEIC files directly inside it,
+// or with --recursive in it and all its subfolders, picked by their extension (.heic,
+// .heif, .hif in any capitalisation); hidden files and folders, such as the
+// ._IMG_0001.HEIC AppleDouble companions left on shared drives, are skipped. A
+// wildcard pattern ("IMG_2*.heic", "2023/**/*.heic") is expanded here rather than by
+// the shell, which on Windows passes it on as it is; matches that are not HEIC files
+// are skipped with a warning. Files matching an --exclude pattern are left out of all
+// of them.
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::error_codes::{CodeExt, ErrorCode, coded};
+use crate::events;
+use crate::file_type::{self, DetectedType};
+
+// The inputs listed in `list` (- for standard input), as written by `find` (one per line)
+// or `find -print0` (NUL-separated, for names with line breaks in them)
+pub fn read_list(list: &Path) -> Result<Vec<PathBuf>> {
+    let mut bytes = Vec::new();
+    let read = if list == Path::new("-") {
+        io::stdin().read_to_end(&mut bytes)
+    } else {
+        fs::File::open(list).and_then(|mut file| file.read_to_end(&mut bytes))
+    };
+    read.with_context(|| format!("❌ Cannot read the file list {}", list.display())).code(ErrorCode::InputUnreadable)?;
+
+    let entries: Vec<&[u8]> = if bytes.contains(&0) {
+        bytes.split(|byte| *byte == 0).collect()
+    } else {
+        bytes.split(|byte| *byte == b'\n').map(|line| line.strip_suffix(b"\r").unwrap_or(line)).collect()
+    };
+    let paths: Vec<PathBuf> = entries.into_iter().filter(|entry| !entry.is_empty()).map(path_from_bytes).collect();
+    if paths.is_empty() {
+        return Err(coded(ErrorCode::InputNotFound, anyhow!("❌ No files listed in {}", list.display())));
+    }
+    Ok(paths)
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+// The files of a batch run over all inputs given, each expanded as by `batch` and every
+// file taken once. None when a single file is given (or nothing at all).
+pub fn collect(inputs: &[PathBuf], recursive: bool) -> Result<Option<Vec<PathBuf>>> {
+    match inputs {
+        [] => Ok(None),
+        [input] => batch(input, recursive),
+        _ => {
+            let mut seen = HashSet::new();
+            let mut files = Vec::new();
+            for input in inputs {
+                let expanded = batch(input, recursive)?.unwrap_or_else(|| vec![input.clone()]);
+                files.extend(expanded.into_iter().filter(|file| seen.insert(file.clone())));
+            }
+            Ok(Some(files))
+        }
+    }
+}
+
+// The files of a batch run: the HEIC files in a folder or matching a pattern. None
+// when `input` is a single file.
+pub fn batch(input: &Path, recursive: bool) -> Result<Option<Vec<PathBuf>>> {
+    if input.is_dir() {
+        let files = heic_files(input, recursive)
+            .with_context(|| format!("❌ Cannot read the folder {}", input.display()))
+            .code(ErrorCode::InputUnreadable)?;
+        if files.is_empty() {
+            return Err(coded(ErrorCode::InputNotFound, anyhow!("❌ No .heic or .heif files in {}", input.display())));
+        }
+        return Ok(Some(files));
+    }
+    let pattern = input.to_string_lossy();
+    if input.exists() || !is_pattern(&pattern) {
+        return Ok(None);
+    }
+
+    // Case is ignored, as by the shells of Windows and macOS; '*' does not match a leading dot
+    let options = glob::MatchOptions { case_sensitive: false, require_literal_separator: true, require_literal_leading_dot: true };
+    let matches = glob::glob_with(&pattern, options)
+        .map_err(|e| anyhow!("❌ Invalid pattern {}: {}", pattern, e))
+        .code(ErrorCode::InputNotFound)?;
+    let (files, others): (Vec<PathBuf>, Vec<PathBuf>) =
+        matches.filter_map(Result::ok).filter(|path| path.is_file()).partition(|path| is_heic_name(path));
+    for other in &others {
+        events::warn(format!("Skipped {}: not a HEIC file", other.display()));
+    }
+    if files.is_empty() {
+        return Err(coded(ErrorCode::InputNotFound, anyhow!("❌ No HEIC files match {}", pattern)));
+    }
+    Ok(Some(files))
+}
+
+// An --exclude pattern, matched like the -i patterns but with '*' crossing folders
+pub fn parse_exclude(pattern: &str) -> Result<glob::Pattern, String> {
+    glob::Pattern::new(pattern).map_err(|e| format!("invalid pattern: {}", e))
+}
+
+// The files that match none of the --exclude patterns. A pattern with a slash in it is
+// matched against the whole path and the path below the folder or pattern the file
+// was found in ("*/Thumbnails/*", "2023/**"); one without against each part of the
+// path below it, so that "Thumbnails" leaves out a folder and "IMG_E*.heic" the edits.
+pub fn exclude(files: Vec<PathBuf>, patterns: &[glob::Pattern], inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    if patterns.is_empty() {
+        return Ok(files);
+    }
+    let roots: Vec<PathBuf> = inputs.iter().filter_map(|input| root(input)).collect();
+    let options = glob::MatchOptions { case_sensitive: false, require_literal_separator: false, require_literal_leading_dot: false };
+    let excluded = |file: &PathBuf| {
+        let below = roots
+            .iter()
+            .filter_map(|root| file.strip_prefix(root).ok())
+            .min_by_key(|below| below.components().count())
+            .or_else(|| file.file_name().map(Path::new))
+            .unwrap_or(file);
+        patterns.iter().any(|pattern| {
+            if pattern.as_str().contains(['/', '\\']) {
+                pattern.matches_path_with(file, options) || pattern.matches_path_with(below, options)
+            } else {
+                below.components().any(|part| pattern.matches_with(&part.as_os_str().to_string_lossy(), options))
+            }
+        })
+    };
+    let total = files.len();
+    let kept: Vec<PathBuf> = files.into_iter().filter(|file| !excluded(file)).collect();
+    if kept.is_empty() {
+        return Err(coded(ErrorCode::InputNotFound, anyhow!("❌ All {} HEIC files found match an --exclude pattern", total)));
+    }
+    if kept.len() < total {
+        say!("Left out {} of {} HEIC files matching --exclude", total - kept.len(), total);
+    }
+    Ok(kept)
+}
+
+// The folder a batch input stands for, below which its files keep their place in a
+// mirrored --output-dir: a folder itself, or the part of a pattern before the first
+// wildcard. None for a single file.
+pub fn root(input: &Path) -> Option<PathBuf> {
+    if input.is_dir() {
+        return Some(input.to_path_buf());
+    }
+    if input.exists() || !is_pattern(&input.to_string_lossy()) {
+        return None;
+    }
+    let literal = input.components().take_while(|part| !is_pattern(&part.as_os_str().to_string_lossy()));
+    Some(literal.collect())
+}
+
+// Whether -i holds wildcards: *, ? or [...]
+fn is_pattern(input: &str) -> bool {
+    input.contains(['*', '?', '['])
+}
+
+// The HEIC files in `dir`, sorted by path
+pub fn heic_files(dir: &Path, recursive: bool) -> io::Result<Vec<PathBuf>> {
+    if recursive {
+        let mut files = file_type::files_below(dir)?;
+        files.retain(|path| is_heic_name(path));
+        return Ok(files);
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && is_heic_name(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+pub fn is_heic_name(path: &Path) -> bool {
+    let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    !hidden && extension.is_some_and(|extension| DetectedType::Heic.matches_extension(extension))
+}
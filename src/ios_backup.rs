@@ -0,0 +1,154 @@
+// Photos from an iTunes / Finder backup of an iPhone (--ios-backup DIR, feature "ios-backup")
+//
+// A backup stores every file under the SHA-1 of its domain and path, in folders named
+// after the first two hex digits (3f/3f2a...). Manifest.db, an SQLite database, maps
+// these names back: the Files table holds fileID, domain and relativePath, e.g.
+// CameraRollDomain, Media/DCIM/100APPLE/IMG_0001.HEIC. Encrypted backups encrypt
+// Manifest.db as well and cannot be read without the backup password.
+use anyhow::{Context, Result, anyhow};
+use rusqlite::{Connection, OpenFlags};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// One photo in the backup
+#[derive(Debug)]
+pub struct BackupPhoto {
+    pub source: PathBuf,   // Hashed file inside the backup
+    pub relative: PathBuf, // DCIM/100APPLE/IMG_0001.HEIC
+}
+
+// The HEIC photos of the camera roll, in album order
+pub fn photos(dir: &Path) -> Result<Vec<BackupPhoto>> {
+    let manifest = dir.join("Manifest.db");
+    if !manifest.is_file() {
+        return Err(anyhow!(
+            "❌ No Manifest.db in {}\n\
+             --ios-backup expects one backup folder, e.g.\n\
+             ~/Library/Application Support/MobileSync/Backup/<device id> (macOS) or\n\
+             %APPDATA%\\Apple Computer\\MobileSync\\Backup\\<device id> (Windows).\n\
+             Backups of iOS 9 and older (Manifest.mbdb) are not supported.",
+            dir.display()
+        ));
+    }
+
+    // An encrypted Manifest.db does not even look like a database to SQLite
+    let unreadable = |error: rusqlite::Error| {
+        if error.sqlite_error_code() == Some(rusqlite::ErrorCode::NotADatabase) {
+            anyhow!(
+                "❌ The backup in {} is encrypted.\n\
+                 Encrypted backups can only be read with the backup password. Make an unencrypted\n\
+                 backup instead: Finder / iTunes → your iPhone → uncheck \"Encrypt local backup\".",
+                dir.display()
+            )
+        } else {
+            anyhow!(error).context(format!("❌ Cannot read {}", manifest.display()))
+        }
+    };
+    let connection = Connection::open_with_flags(&manifest, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("❌ Cannot open {}", manifest.display()))?;
+    let mut statement = connection
+        .prepare(
+            "SELECT fileID, relativePath FROM Files \
+             WHERE domain = 'CameraRollDomain' AND flags = 1 AND relativePath LIKE 'Media/DCIM/%' \
+             AND (lower(relativePath) LIKE '%.heic' OR lower(relativePath) LIKE '%.heif') \
+             ORDER BY relativePath",
+        )
+        .map_err(unreadable)?;
+    let rows = statement
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(unreadable)?;
+
+    let mut photos = Vec::new();
+    for row in rows {
+        let (file_id, relative_path) = row?;
+        let Some(relative) = safe_relative(&relative_path) else {
+            say_err!("⚠️  Skipping a backup entry with an unusual path: {}", relative_path);
+            continue;
+        };
+        // The SHA-1 as 40 hex digits; anything else must not become part of a path
+        if file_id.len() != 40 || !file_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            say_err!("⚠️  Skipping {}: \"{}\" is not a backup file name", relative_path, file_id);
+            continue;
+        }
+        let source = dir.join(&file_id[..2]).join(&file_id);
+        if !source.is_file() {
+            say_err!("⚠️  {} is listed in Manifest.db but missing from the backup", relative_path);
+            continue;
+        }
+        photos.push(BackupPhoto { source, relative });
+    }
+    Ok(photos)
+}
+
+// Media/DCIM/100APPLE/IMG_0001.HEIC -> DCIM/100APPLE/IMG_0001.HEIC; None for paths
+// that could leave the output directory
+fn safe_relative(relative_path: &str) -> Option<PathBuf> {
+    let path = Path::new(relative_path).strip_prefix("Media").ok()?;
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then(|| path.to_path_buf())
+}
+
+// Copy the photo out of the backup under its real name below `output_dir`, where it is
+// then converted. A copy from an earlier run is reused.
+pub fn restore(photo: &BackupPhoto, output_dir: &Path) -> Result<PathBuf> {
+    let target = output_dir.join(&photo.relative);
+    let size = |path: &Path| fs::metadata(path).map(|m| m.len()).ok();
+    if size(&target).is_some() && size(&target) == size(&photo.source) {
+        return Ok(target);
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("❌ Cannot create {}", parent.display()))?;
+    }
+    fs::copy(&photo.source, &target)
+        .with_context(|| format!("❌ Cannot copy {} to {}", photo.source.display(), target.display()))?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backup(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("heic_convert-ios-backup-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let connection = Connection::open(dir.join("Manifest.db")).unwrap();
+        connection
+            .execute_batch("CREATE TABLE Files (fileID TEXT, domain TEXT, relativePath TEXT, flags INTEGER)")
+            .unwrap();
+        for (file_id, relative_path) in files {
+            connection
+                .execute(
+                    "INSERT INTO Files VALUES (?1, 'CameraRollDomain', ?2, 1)",
+                    [file_id, relative_path],
+                )
+                .unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn only_hashed_file_names_are_looked_up() {
+        let id = "3f2a9e0d5c4b1a6f7e8d9c0b1a2f3e4d5c6b7a89";
+        let dir = backup("ids", &[(id, "Media/DCIM/100APPLE/IMG_0001.HEIC"), ("../../etc", "Media/DCIM/100APPLE/IMG_0002.HEIC")]);
+        fs::create_dir_all(dir.join("3f")).unwrap();
+        fs::write(dir.join("3f").join(id), b"photo").unwrap();
+        let photos = photos(&dir).unwrap();
+        assert_eq!(photos.len(), 1);
+        assert_eq!(photos[0].relative, Path::new("DCIM/100APPLE/IMG_0001.HEIC"));
+    }
+
+    #[test]
+    fn only_unreadable_databases_count_as_encrypted() {
+        let dir = backup("encrypted", &[]);
+        fs::write(dir.join("Manifest.db"), [0x5Au8; 4096]).unwrap();
+        assert!(photos(&dir).unwrap_err().to_string().contains("is encrypted"));
+
+        // A Manifest.db of another layout is reported as it is
+        let dir = backup("layout", &[]);
+        Connection::open(dir.join("Manifest.db")).unwrap().execute_batch("DROP TABLE Files").unwrap();
+        let error = format!("{:#}", photos(&dir).unwrap_err());
+        assert!(error.contains("Cannot read") && error.contains("no such table"), "{}", error);
+    }
+}
@@ -0,0 +1,66 @@
+// JPEG XL output through cjxl, the reference encoder of libjxl (-f jxl)
+//
+// There is no JPEG XL encoder among the crates we build on, so the image is written as
+// a lossless PNG next to the output and handed to cjxl, which has to be installed
+// (brew install jpeg-xl, apt install libjxl-tools). Every backend ends here: ImageMagick
+// and FFmpeg decode to a PNG first. The size is set by the quality (cjxl -q, 90 by
+// default, the same as distance 1) or by the Butteraugli distance (-d): 0 is lossless,
+// 1 visually lossless, and higher values give smaller files.
+use anyhow::{Context, Result, anyhow};
+use image::DynamicImage;
+use std::fs;
+use std::path::Path;
+
+use crate::error_codes::{CodeExt, ErrorCode, coded};
+use crate::process::{Invocation, ProcessRunner};
+
+// cjxl refuses distances above 25
+const MAX_DISTANCE: f32 = 25.0;
+
+pub fn parse_distance(text: &str) -> Result<f32, String> {
+    text.trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|distance| (0.0..=MAX_DISTANCE).contains(distance))
+        .ok_or_else(|| format!("invalid distance '{}'; use 0 (lossless) to {}", text, MAX_DISTANCE))
+}
+
+// Encode `img` as JPEG XL at `output`: at `distance` when given, otherwise at `quality`
+pub fn save(
+    runner: &dyn ProcessRunner,
+    img: &DynamicImage,
+    output: &Path,
+    quality: Option<u8>,
+    distance: Option<f32>,
+) -> Result<()> {
+    let png = output.with_extension("cjxl.png");
+    img.save_with_format(&png, image::ImageFormat::Png)
+        .with_context(|| format!("❌ Cannot write the image for cjxl to {}", png.display()))?;
+    let finished = runner.run(&invocation(&png, output, quality, distance));
+    let _ = fs::remove_file(&png);
+    let finished = finished
+        .context(
+            "❌ JPEG XL output needs cjxl, which is not installed or not found in PATH.\n\
+             Install it with: brew install jpeg-xl (macOS) or apt install libjxl-tools (Linux)",
+        )
+        .code(ErrorCode::ToolMissing)?;
+    if !finished.success {
+        let message = anyhow!("❌ cjxl failed to encode {}: {}", output.display(), finished.stderr_text().trim());
+        return Err(coded(ErrorCode::ToolFailed, message));
+    }
+    Ok(())
+}
+
+// The cjxl command line; one thread under --eco
+fn invocation(png: &Path, output: &Path, quality: Option<u8>, distance: Option<f32>) -> Invocation {
+    let mut command = Invocation::new("cjxl");
+    command.arg(png).arg(output);
+    match distance {
+        Some(distance) => command.arg("-d").arg(distance.to_string()),
+        None => command.arg("-q").arg(quality.unwrap_or(crate::quality::JXL_DEFAULT).to_string()),
+    };
+    if crate::eco::active() {
+        command.arg("--num_threads").arg("1");
+    }
+    command
+}
@@ -0,0 +1,118 @@
+// Linear-light pixels for OpenEXR output (-f exr)
+//
+// Compositing tools expect EXR to hold light linearly: 1.0 is diffuse white, and HDR
+// highlights go above it. Decoded pixels are encoded with a transfer curve, which is
+// undone here. The curve comes from the CICP code points of the HEIF 'colr' box: PQ
+// (SMPTE ST 2084) and HLG captures keep their highlights, with PQ's 203 nits and HLG's
+// 75% signal (the reference white of BT.2408) at 1.0; everything else uses the sRGB
+// curve, which Display P3 shares. The primaries stay those of the source. iPhone HDR
+// photos store an SDR image plus a gain map, and only the SDR image is converted.
+use image::{DynamicImage, Rgb32FImage, Rgba32FImage};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::file_type::{self, DetectedType};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transfer {
+    Srgb,
+    Pq,
+    Hlg,
+}
+
+// Reference white of PQ content in nits, and the range PQ encodes
+const PQ_WHITE: f32 = 203.0;
+const PQ_PEAK: f32 = 10_000.0;
+
+// The transfer curve of the pixels in `path`; sRGB unless a HEIF or AVIF file says otherwise
+pub fn transfer(path: &Path) -> Transfer {
+    match file_type::detect(path) {
+        Some(DetectedType::Heic | DetectedType::Avif) => heif_transfer(path).unwrap_or(Transfer::Srgb),
+        _ => Transfer::Srgb,
+    }
+}
+
+// `img` as 32-bit float light, with the alpha channel kept as it is
+pub fn linearize(img: &DynamicImage, transfer: Transfer) -> DynamicImage {
+    let decode = |v: f32| match transfer {
+        Transfer::Srgb => srgb_to_linear(v),
+        Transfer::Pq => pq_to_linear(v),
+        Transfer::Hlg => hlg_to_linear(v) / hlg_to_linear(0.75),
+    };
+    if img.color().has_alpha() {
+        let mut pixels: Rgba32FImage = img.to_rgba32f();
+        for pixel in pixels.pixels_mut() {
+            for channel in &mut pixel.0[..3] {
+                *channel = decode(*channel);
+            }
+        }
+        DynamicImage::ImageRgba32F(pixels)
+    } else {
+        let mut pixels: Rgb32FImage = img.to_rgb32f();
+        for pixel in pixels.pixels_mut() {
+            pixel.0 = pixel.0.map(decode);
+        }
+        DynamicImage::ImageRgb32F(pixels)
+    }
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+}
+
+// The PQ EOTF, scaled so that reference white is 1.0
+fn pq_to_linear(v: f32) -> f32 {
+    const M1: f32 = 2610.0 / 16384.0;
+    const M2: f32 = 2523.0 / 4096.0 * 128.0;
+    const C1: f32 = 3424.0 / 4096.0;
+    const C2: f32 = 2413.0 / 4096.0 * 32.0;
+    const C3: f32 = 2392.0 / 4096.0 * 32.0;
+    let power = v.max(0.0).powf(1.0 / M2);
+    ((power - C1).max(0.0) / (C2 - C3 * power)).powf(1.0 / M1) * PQ_PEAK / PQ_WHITE
+}
+
+// The inverse HLG OETF: scene light from 0 to 1
+fn hlg_to_linear(v: f32) -> f32 {
+    const A: f32 = 0.178_832_77;
+    const B: f32 = 0.284_668_92;
+    const C: f32 = 0.559_910_7;
+    let v = v.max(0.0);
+    if v <= 0.5 { v * v / 3.0 } else { (((v - C) / A).exp() + B) / 12.0 }
+}
+
+// transfer_characteristics of an 'nclx' colour box: 16 is PQ, 18 is HLG
+fn heif_transfer(path: &Path) -> Option<Transfer> {
+    let mut header = Vec::new();
+    File::open(path).ok()?.take(1024 * 1024).read_to_end(&mut header).ok()?;
+    let at = header.windows(8).position(|window| window == b"colrnclx")?;
+    let code = u16::from_be_bytes([*header.get(at + 10)?, *header.get(at + 11)?]);
+    Some(match code {
+        16 => Transfer::Pq,
+        18 => Transfer::Hlg,
+        _ => Transfer::Srgb,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_white_is_one() {
+        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+        // 203 nits is code value 0.58 in PQ
+        assert!((pq_to_linear(0.580_7) - 1.0).abs() < 0.01);
+        let hlg = DynamicImage::ImageRgb32F(Rgb32FImage::from_pixel(1, 1, image::Rgb([0.75; 3])));
+        assert!((linearize(&hlg, Transfer::Hlg).to_rgb32f().get_pixel(0, 0).0[0] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn highlights_go_above_one_and_alpha_is_kept() {
+        assert!(pq_to_linear(0.75) > 4.0);
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba([128, 128, 128, 128])));
+        let pixel = linearize(&img, Transfer::Srgb).to_rgba32f().get_pixel(0, 0).0;
+        assert!((pixel[0] - 0.2158).abs() < 0.001, "{:?}", pixel);
+        assert!((pixel[3] - 128.0 / 255.0).abs() < 1e-6);
+    }
+}
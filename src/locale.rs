@@ -0,0 +1,69 @@
+// Locale-aware formatting of numbers and dates for human-readable output.
+// JSON output always carries the raw values; only text meant for people is localized.
+//
+// The locale is taken from LC_ALL, LC_NUMERIC / LC_TIME or LANG (e.g. "de_DE.UTF-8").
+use std::sync::OnceLock;
+
+// Language and region of the user's locale, e.g. ("de", "DE")
+struct Locale {
+    language: String,
+    region: String,
+}
+
+fn locale_for(category: &str) -> Locale {
+    let value = ["LC_ALL", category, "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+
+    // Strip ".UTF-8" and "@euro" style suffixes
+    let name = value.split(['.', '@']).next().unwrap_or("");
+    let (language, region) = name.split_once('_').unwrap_or((name, ""));
+    Locale {
+        language: language.to_lowercase(),
+        region: region.to_uppercase(),
+    }
+}
+
+// Languages that write 1.234,5 instead of 1,234.5
+const DECIMAL_COMMA: &[&str] = &[
+    "de", "fr", "es", "it", "pt", "nl", "ru", "pl", "cs", "sk", "sv", "da", "nb", "nn", "no",
+    "fi", "tr", "el", "hu", "ro", "uk", "bg", "hr", "sl", "sr", "lt", "lv", "et", "id", "vi",
+];
+
+fn decimal_comma() -> bool {
+    static COMMA: OnceLock<bool> = OnceLock::new();
+    *COMMA.get_or_init(|| {
+        let locale = locale_for("LC_NUMERIC");
+        // Swiss German uses a decimal point
+        DECIMAL_COMMA.contains(&locale.language.as_str()) && locale.region != "CH"
+    })
+}
+
+// Format a number with a fixed number of decimals and the locale's decimal separator
+pub fn format_number(value: f64, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value);
+    if decimal_comma() {
+        text.replace('.', ",")
+    } else {
+        text
+    }
+}
+
+// Date and time in the order the user's locale expects
+#[cfg(feature = "email")]
+pub fn format_date_time(time: &chrono::DateTime<chrono::Local>) -> String {
+    let locale = locale_for("LC_TIME");
+    let pattern = match (locale.language.as_str(), locale.region.as_str()) {
+        ("en", "US") => "%m/%d/%Y %I:%M %p",
+        ("en", _) | ("fr", _) | ("es", _) | ("it", _) | ("pt", _) | ("el", _) => "%d/%m/%Y %H:%M",
+        ("nl", _) => "%d-%m-%Y %H:%M",
+        ("ja", _) | ("zh", _) => "%Y/%m/%d %H:%M",
+        ("ko", _) => "%Y. %m. %d. %H:%M",
+        ("de", _) | ("ru", _) | ("pl", _) | ("cs", _) | ("sk", _) | ("fi", _) | ("tr", _)
+        | ("uk", _) | ("nb", _) | ("no", _) | ("da", _) | ("ro", _) | ("hr", _) => "%d.%m.%Y %H:%M",
+        _ => "%Y-%m-%d %H:%M",
+    };
+    time.format(pattern).to_string()
+}
@@ -0,0 +1,227 @@
+// Colour lookup tables in the .cube format (--lut)
+//
+// .cube files (Adobe / Resolve) are plain text: optional TITLE, LUT_3D_SIZE or
+// LUT_1D_SIZE, optional DOMAIN_MIN / DOMAIN_MAX, then one "r g b" line per entry with
+// red changing fastest. 3D tables are sampled with trilinear interpolation.
+use anyhow::{Context, Result, anyhow, bail};
+use std::fs;
+use std::path::Path;
+
+// 1D tables of 1024 or 4096 entries are common; a 3D table holds size³ entries
+const MAX_1D_SIZE: usize = 65_536;
+const MAX_3D_SIZE: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct Lut {
+    pub title: Option<String>,
+    pub size: usize,
+    pub three_d: bool,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+    table: Vec<[f32; 3]>,
+}
+
+pub fn load(path: &Path) -> Result<Lut> {
+    let text = fs::read_to_string(path).with_context(|| format!("❌ Cannot read the LUT {}", path.display()))?;
+    parse(&text).with_context(|| format!("❌ {} is not a valid .cube LUT", path.display()))
+}
+
+pub fn parse(text: &str) -> Result<Lut> {
+    let mut title = None;
+    let mut size = None;
+    let mut three_d = true;
+    let mut domain_min = [0.0; 3];
+    let mut domain_max = [1.0; 3];
+    let mut table = Vec::new();
+
+    let triple = |words: &[&str], line: usize| -> Result<[f32; 3]> {
+        match words {
+            [r, g, b] => {
+                let number = |w: &str| w.parse::<f32>().map_err(|_| anyhow!("line {}: \"{}\" is not a number", line, w));
+                Ok([number(r)?, number(g)?, number(b)?])
+            }
+            _ => bail!("line {}: expected three numbers", line),
+        }
+    };
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[0] {
+            "TITLE" => title = Some(line["TITLE".len()..].trim().trim_matches('"').to_string()),
+            "LUT_3D_SIZE" | "LUT_1D_SIZE" => {
+                three_d = words[0] == "LUT_3D_SIZE";
+                let largest = if three_d { MAX_3D_SIZE } else { MAX_1D_SIZE };
+                let value = words
+                    .get(1)
+                    .and_then(|w| w.parse::<usize>().ok())
+                    .filter(|&n| (2..=largest).contains(&n))
+                    .ok_or_else(|| anyhow!("line {}: the size must be between 2 and {}", number, largest))?;
+                size = Some(value);
+            }
+            "DOMAIN_MIN" => domain_min = triple(&words[1..], number)?,
+            "DOMAIN_MAX" => domain_max = triple(&words[1..], number)?,
+            // Keywords of other tools (e.g. LUT_3D_INPUT_RANGE) are skipped
+            word if word.starts_with(|c: char| c.is_ascii_alphabetic()) => {}
+            _ => table.push(triple(&words, number)?),
+        }
+    }
+
+    let size = size.ok_or_else(|| anyhow!("LUT_3D_SIZE or LUT_1D_SIZE is missing"))?;
+    let expected = if three_d { size * size * size } else { size };
+    if table.len() != expected {
+        bail!("expected {} entries for size {}, found {}", expected, size, table.len());
+    }
+    if (0..3).any(|c| domain_max[c] <= domain_min[c]) {
+        bail!("DOMAIN_MAX must be above DOMAIN_MIN");
+    }
+    Ok(Lut {
+        title,
+        size,
+        three_d,
+        domain_min,
+        domain_max,
+        table,
+    })
+}
+
+impl Lut {
+    pub fn apply(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let last = (self.size - 1) as f32;
+        // Position of each channel in table steps
+        let position: [f32; 3] = std::array::from_fn(|c| {
+            ((rgb[c] - self.domain_min[c]) / (self.domain_max[c] - self.domain_min[c])).clamp(0.0, 1.0) * last
+        });
+
+        if !self.three_d {
+            return std::array::from_fn(|c| {
+                let (low, fraction) = split(position[c], self.size);
+                let (a, b) = (self.table[low][c], self.table[(low + 1).min(self.size - 1)][c]);
+                a + (b - a) * fraction
+            });
+        }
+
+        let (r, fr) = split(position[0], self.size);
+        let (g, fg) = split(position[1], self.size);
+        let (b, fb) = split(position[2], self.size);
+        let next = |i: usize| (i + 1).min(self.size - 1);
+        let at = |r: usize, g: usize, b: usize| self.table[r + g * self.size + b * self.size * self.size];
+        let mix = |a: [f32; 3], b: [f32; 3], t: f32| -> [f32; 3] { std::array::from_fn(|c| a[c] + (b[c] - a[c]) * t) };
+
+        let c00 = mix(at(r, g, b), at(next(r), g, b), fr);
+        let c10 = mix(at(r, next(g), b), at(next(r), next(g), b), fr);
+        let c01 = mix(at(r, g, next(b)), at(next(r), g, next(b)), fr);
+        let c11 = mix(at(r, next(g), next(b)), at(next(r), next(g), next(b)), fr);
+        mix(mix(c00, c10, fg), mix(c01, c11, fg), fb)
+    }
+}
+
+// Table index below a position and the fraction towards the next one
+fn split(position: f32, size: usize) -> (usize, f32) {
+    let low = (position.floor() as usize).min(size - 1);
+    (low, position - low as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+        (0..3).all(|c| (a[c] - b[c]).abs() < 1e-5)
+    }
+
+    // An identity 3D table with red changing fastest
+    fn identity_cube(size: usize) -> String {
+        let mut text = format!("TITLE \"Identity\"\nLUT_3D_SIZE {}\n", size);
+        let step = |i: usize| i as f32 / (size - 1) as f32;
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    text.push_str(&format!("{} {} {}\n", step(r), step(g), step(b)));
+                }
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn sizes_are_limited_per_kind() {
+        let one_d = |size: usize| {
+            let mut text = format!("LUT_1D_SIZE {}\n", size);
+            text.push_str(&"0.5 0.5 0.5\n".repeat(size));
+            parse(&text)
+        };
+        for size in [2, 1024, 4096, 65_536] {
+            assert!(one_d(size).is_ok(), "1D size {}", size);
+        }
+        assert!(one_d(65_537).unwrap_err().to_string().contains("between 2 and 65536"));
+        assert!(one_d(1).is_err());
+        assert!(parse("LUT_3D_SIZE 257\n").unwrap_err().to_string().contains("between 2 and 256"));
+        assert!(parse("LUT_3D_SIZE 1\n").is_err());
+    }
+
+    #[test]
+    fn mistakes_are_explained() {
+        let cases = [
+            ("0 0 0\n1 1 1\n", "LUT_3D_SIZE or LUT_1D_SIZE is missing"),
+            ("LUT_1D_SIZE 2\n0 0 0\n", "expected 2 entries for size 2, found 1"),
+            ("LUT_3D_SIZE 2\n0 0 0\n", "expected 8 entries for size 2, found 1"),
+            ("LUT_1D_SIZE 2\n0 0\n1 1 1\n", "line 2: expected three numbers"),
+            ("LUT_1D_SIZE 2\n0 0 x\n1 1 1\n", "line 2: \"x\" is not a number"),
+            ("LUT_1D_SIZE big\n", "line 1: the size must be"),
+            ("LUT_1D_SIZE 2\nDOMAIN_MAX 0 1 1\n0 0 0\n1 1 1\n", "DOMAIN_MAX must be above DOMAIN_MIN"),
+        ];
+        for (text, message) in cases {
+            let error = parse(text).unwrap_err();
+            assert!(error.to_string().contains(message), "{:?}: {}", text, error);
+        }
+    }
+
+    #[test]
+    fn header_lines_are_read() {
+        let lut = parse("# Resolve\nTITLE \"Warm\"\nLUT_3D_INPUT_RANGE 0 1\n\nLUT_1D_SIZE 2\n0 0 0\n1 1 1\n").unwrap();
+        assert_eq!(lut.title.as_deref(), Some("Warm"));
+        assert_eq!((lut.size, lut.three_d), (2, false));
+        assert!(parse(&identity_cube(3)).unwrap().three_d);
+    }
+
+    #[test]
+    fn one_d_tables_interpolate_each_channel() {
+        let lut = parse("LUT_1D_SIZE 3\n0 1 0\n0.5 0.5 0.25\n1 0 1\n").unwrap();
+        assert!(close(lut.apply([0.0, 0.0, 0.0]), [0.0, 1.0, 0.0]));
+        assert!(close(lut.apply([0.25, 0.5, 0.75]), [0.25, 0.5, 0.625]));
+        assert!(close(lut.apply([1.0, 1.0, 1.0]), [1.0, 0.0, 1.0]));
+        // Values outside the domain are clamped to its ends
+        assert!(close(lut.apply([-1.0, 2.0, 0.5]), [0.0, 0.0, 0.25]));
+    }
+
+    #[test]
+    fn three_d_tables_interpolate_trilinearly() {
+        let lut = parse(&identity_cube(5)).unwrap();
+        for rgb in [[0.0, 0.0, 0.0], [0.1, 0.6, 0.95], [1.0, 0.3, 0.0], [0.5, 0.5, 0.5]] {
+            assert!(close(lut.apply(rgb), rgb), "{:?} -> {:?}", rgb, lut.apply(rgb));
+        }
+
+        // Swap red and blue: red changes fastest, so the first entries vary in blue
+        let mut text = String::from("LUT_3D_SIZE 2\n");
+        for b in 0..2 {
+            for g in 0..2 {
+                for r in 0..2 {
+                    text.push_str(&format!("{} {} {}\n", b, g, r));
+                }
+            }
+        }
+        let swap = parse(&text).unwrap();
+        assert!(close(swap.apply([0.2, 0.5, 0.9]), [0.9, 0.5, 0.2]));
+    }
+
+    #[test]
+    fn domain_scales_the_input() {
+        let lut = parse("DOMAIN_MIN 0 0 0\nDOMAIN_MAX 2 2 2\nLUT_1D_SIZE 2\n0 0 0\n1 1 1\n").unwrap();
+        assert!(close(lut.apply([1.0, 0.5, 2.0]), [0.5, 0.25, 1.0]));
+    }
+}
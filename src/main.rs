@@ -1,14 +1,124 @@
 // External crate imports for error handling, CLI parsing, image processing, and system interaction
 use anyhow::{Context, Result, anyhow};     // Error handling with context
-use clap::{Parser, ValueEnum};              // Command-line argument parsing
-use image::{DynamicImage, ImageFormat};     // Image processing library
+use clap::{Parser, Subcommand, ValueEnum};  // Command-line argument parsing
+use image::{ColorType, DynamicImage, ImageFormat}; // Image processing library
 use std::fs;                                // File system operations
 use std::path::{Path, PathBuf};             // Path handling utilities
-use std::process::Command;                  // External command execution
+use std::sync::atomic::{AtomicUsize, Ordering}; // Handing out files to parallel workers (--jobs)
+use std::time::Instant;                     // Timing of conversion runs
+use summary::RunSummary;                    // Machine-readable summary of a conversion run
 
 // use colored::Colorize;
 
+// Console output that honours --ascii-only (see ui.rs) and is kept for crash reports;
+// defined before the modules so that every module can use them. `say!` moves to stderr
+// when stdout carries data (-o -, --json).
+macro_rules! say {
+    () => {
+        if $crate::ui::stdout_is_data() { eprintln!() } else { println!() }
+    };
+    ($($arg:tt)*) => {{
+        let line = format!($($arg)*);
+        $crate::crash_report::remember(&line);
+        if $crate::ui::stdout_is_data() {
+            eprintln!("{}", $crate::ui::plain(&line))
+        } else {
+            println!("{}", $crate::ui::plain(&line))
+        }
+    }};
+}
+macro_rules! say_err {
+    () => { eprintln!() };
+    ($($arg:tt)*) => {{
+        let line = format!($($arg)*);
+        $crate::crash_report::remember(&line);
+        eprintln!("{}", $crate::ui::plain(&line))
+    }};
+}
+
+mod adjust; // Noise reduction, automatic levels and white balance
+mod advise; // Format advice without converting (--advise)
+mod album; // album.json manifests for static gallery generators
+mod archive; // HEIC files inside ZIP archives, converted one entry at a time
+#[cfg(test)]
+mod backend_tests; // ImageMagick / FFmpeg command lines and error mapping
+mod canonical; // Normalized re-encoding for comparing outputs byte-wise
+mod capabilities; // `--version --json` capability report for tooling
+mod config; // User configuration file
+mod cores; // Performance / efficiency core selection
+mod crash_report; // Diagnostic bundle when the program panics
+mod curve; // Tone curves (--curve)
+mod device; // One output folder per camera model (--by-device)
+mod duplicates; // Copies among the inputs of a run, found by content hash
+mod eco; // Slow down on battery or under thermal pressure
+mod embedded; // Rescue HEIC images from e-mails, PDFs and HTML exports
+mod error_codes; // Stable error codes and the `explain` knowledge base
+#[cfg(feature = "email")]
+mod email; // E-mail report at the end of a run
+mod events; // Conversion events for reporting features
+mod exif; // Capture date and camera from EXIF tags
+mod explain; // --explain decision log
+mod extract; // `extract` subcommand: convert photos off a mounted device into date folders
+mod extension_fix; // Rename files whose extension does not match their content
+mod file_type; // Detect image types from magic bytes
+#[cfg(test)]
+mod format_roundtrip; // Property tests: every output format read back after saving
+mod gamut; // Out-of-gamut warnings for Display P3 sources
+mod heif; // HEIF box structure for integrity checks
+mod help; // Help topics and man page, generated from shared data
+mod ico; // Windows icons with several sizes
+mod info; // `info` subcommand: file facts and exposure statistics
+mod inputs; // The HEIC files behind the -i argument (a file, a folder or a pattern)
+mod init; // `init` subcommand: first-run setup wizard
+#[cfg(feature = "ios-backup")]
+mod ios_backup; // Camera roll photos from iTunes / Finder backups
+mod jxl; // JPEG XL output through cjxl
+mod linear; // Linear-light pixels for OpenEXR output
+mod locale; // Locale-aware number and date formatting
+mod lut; // .cube colour lookup tables (--lut)
+#[cfg(feature = "scripting")]
+mod name_expr; // Output names computed by a Rhai expression
+mod name_template; // Output names from a template such as "{date}_{stem}.{ext}"
+mod negotiate; // Which requested options the chosen backend can honour
+mod originals; // Delete or trash the source once its output is verified
+mod orphans; // Report Live Photo / edit sidecar files left behind after a migration
+mod pack; // --archive-output: the outputs of a run packed into a ZIP or tar.gz file
+mod paths; // Output path resolution
+mod pdf; // PDF output, one page per image
+mod ppm; // Binary PPM output for netpbm tool chains
+mod process; // External tool invocations behind a mockable runner
+#[cfg(feature = "upload")]
+mod photo_server; // Import converted images into Immich / PhotoPrism
+mod quality; // Default quality per format and --quality auto
+mod report; // --report manifest of every conversion, as JSON or CSV
+mod resize; // Shrink images to a maximum size (--resize, rules)
+mod resume; // Progress of multi-file runs for --resume
+mod retry_io; // Resumable reads from unreliable storage
+mod rules; // Per-file option overrides from the configuration file
+mod schedule; // Allowed time windows for conversions
+#[cfg(feature = "self-update")]
+mod self_update; // Verified in-place updates from the release feed
+mod stats; // Opt-in local lifetime usage statistics
+mod summary; // Machine-readable summary of a conversion run
+mod sync_guard; // Wait for inputs that are still being synced or written
+mod temp_workspace; // Scratch directories for intermediate files with guaranteed cleanup
+mod thumbnail; // EXIF thumbnails in converted JPEGs
+mod throttle; // Bandwidth-limited writes for network destinations
+mod tiff_writer; // TIFF output with LZW or Deflate compression
 mod toml_extract; // Extract and print the version information according to the toml file
+mod ui; // Decorative or plain ASCII console output
+mod units; // Parse human-readable sizes such as 4.7GB and durations such as 5m
+mod validate; // `validate` subcommand: find corrupt or truncated HEIC files
+#[cfg(feature = "upload")]
+mod upload; // SFTP / WebDAV destinations for converted images
+#[cfg(feature = "webhook")]
+mod webhook; // POST the run summary to a webhook when a run finishes
+mod volumes; // Distribute outputs over size-limited directories
+#[cfg(feature = "watch")]
+mod watch; // `watch` subcommand: convert HEIC files as they arrive in a folder
+
+use error_codes::{CodeExt, ErrorCode, coded};
+use temp_workspace::TempWorkspace;
 
 // Enum to represent supported output image formats
 #[derive(Clone, Debug, ValueEnum)]
@@ -16,6 +126,15 @@ enum OutputFormat {
     Png,    // PNG format
     Jpg,    // JPEG format (alternative naming)
     Jpeg,   // JPEG format (standard naming)
+    Webp,   // WebP, lossy unless --lossless
+    Avif,   // AVIF, the AV1 still image format
+    Tiff,   // TIFF for print and scan pipelines, optionally compressed
+    Bmp,    // Uncompressed Windows bitmap for legacy tools
+    Jxl,    // JPEG XL, encoded by cjxl
+    Pdf,    // PDF with a page for every image in the file
+    Exr,    // OpenEXR with linear-light float pixels, for HDR
+    Ppm,    // Binary PPM (netpbm) for pipelines and scripts
+    Ico,    // Windows icon holding 16, 32, 64 and 256 pixel sizes
 }
 
 impl OutputFormat {
@@ -24,6 +143,15 @@ impl OutputFormat {
         match self {
             OutputFormat::Png => ImageFormat::Png,
             OutputFormat::Jpg | OutputFormat::Jpeg => ImageFormat::Jpeg,
+            OutputFormat::Webp => ImageFormat::WebP,
+            OutputFormat::Avif => ImageFormat::Avif,
+            OutputFormat::Tiff => ImageFormat::Tiff,
+            OutputFormat::Bmp => ImageFormat::Bmp,
+            OutputFormat::Jxl => ImageFormat::Png, // What cjxl encodes from, see jxl.rs
+            OutputFormat::Pdf => ImageFormat::Jpeg, // The pages, see pdf.rs
+            OutputFormat::Exr => ImageFormat::OpenExr,
+            OutputFormat::Ppm => ImageFormat::Pnm,
+            OutputFormat::Ico => ImageFormat::Ico,
         }
     }
 
@@ -32,224 +160,917 @@ impl OutputFormat {
         match self {
             OutputFormat::Png => "png",
             OutputFormat::Jpg | OutputFormat::Jpeg => "jpg",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Avif => "avif",
+            OutputFormat::Tiff => "tiff",
+            OutputFormat::Bmp => "bmp",
+            OutputFormat::Jxl => "jxl",
+            OutputFormat::Pdf => "pdf",
+            OutputFormat::Exr => "exr",
+            OutputFormat::Ppm => "ppm",
+            OutputFormat::Ico => "ico",
+        }
+    }
+
+    // The name of the format, as the {format} placeholder of --name-template writes it
+    fn name(&self) -> &str {
+        match self {
+            OutputFormat::Png => "PNG",
+            OutputFormat::Jpg | OutputFormat::Jpeg => "JPEG",
+            OutputFormat::Webp => "WEBP",
+            OutputFormat::Avif => "AVIF",
+            OutputFormat::Tiff => "TIFF",
+            OutputFormat::Bmp => "BMP",
+            OutputFormat::Jxl => "JXL",
+            OutputFormat::Pdf => "PDF",
+            OutputFormat::Exr => "EXR",
+            OutputFormat::Ppm => "PPM",
+            OutputFormat::Ico => "ICO",
+        }
+    }
+
+    // The format an output name asks for by its extension (photo.JPEG, out.png)
+    fn from_extension(path: &Path) -> Option<OutputFormat> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "png" => Some(OutputFormat::Png),
+            "jpg" | "jpeg" | "jpe" => Some(OutputFormat::Jpg),
+            "webp" => Some(OutputFormat::Webp),
+            "avif" => Some(OutputFormat::Avif),
+            "tif" | "tiff" => Some(OutputFormat::Tiff),
+            "bmp" | "dib" => Some(OutputFormat::Bmp),
+            "jxl" => Some(OutputFormat::Jxl),
+            "pdf" => Some(OutputFormat::Pdf),
+            "exr" => Some(OutputFormat::Exr),
+            "ppm" | "pnm" => Some(OutputFormat::Ppm),
+            "ico" => Some(OutputFormat::Ico),
+            _ => None,
+        }
+    }
+}
+
+// Bits per channel of the output (--bit-depth)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum BitDepth {
+    #[value(name = "8")]
+    Eight,
+    #[value(name = "16")]
+    Sixteen,
+}
+
+impl BitDepth {
+    // `img` with this many bits per channel; grey stays grey and alpha is kept
+    fn apply(self, img: DynamicImage) -> DynamicImage {
+        let sixteen = matches!(
+            img.color(),
+            ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 | ColorType::Rgb32F | ColorType::Rgba32F
+        );
+        match (self, img.color()) {
+            (BitDepth::Eight, _) if !sixteen => img,
+            (BitDepth::Eight, ColorType::L16) => DynamicImage::ImageLuma8(img.to_luma8()),
+            (BitDepth::Eight, ColorType::La16) => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+            (BitDepth::Eight, color) if color.has_alpha() => DynamicImage::ImageRgba8(img.to_rgba8()),
+            (BitDepth::Eight, _) => DynamicImage::ImageRgb8(img.to_rgb8()),
+            (BitDepth::Sixteen, ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16) => img,
+            (BitDepth::Sixteen, color) => {
+                if !sixteen {
+                    explain::note("The image has 8 bits per channel; --bit-depth 16 only makes the file larger");
+                }
+                match color {
+                    ColorType::L8 => DynamicImage::ImageLuma16(img.to_luma16()),
+                    ColorType::La8 => DynamicImage::ImageLumaA16(img.to_luma_alpha16()),
+                    color if color.has_alpha() => DynamicImage::ImageRgba16(img.to_rgba16()),
+                    _ => DynamicImage::ImageRgb16(img.to_rgb16()),
+                }
+            }
         }
     }
 }
 
+// Conversion backends, tried in this order when --backend is auto
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Backend {
+    Auto,        // First backend that can handle the file
+    Builtin,     // Rust image crate
+    Imagemagick, // ImageMagick `convert`
+    Ffmpeg,      // FFmpeg
+}
+
+// Order in which a multi-file run works through its inputs (--order)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Order {
+    SmallestFirst, // Many quick results early
+    LargestFirst,  // Heavy panoramas while someone is still watching
+}
+
+// --share-safe: longest edge and JPEG quality for images that are mailed or posted
+const SHARE_SAFE_EDGE: u32 = 2048;
+const SHARE_SAFE_QUALITY: u8 = 80;
+
+// Settings that shape how a single image is converted and written
+struct ConvertOptions {
+    format: OutputFormat,
+    backend: Backend,
+    deterministic: bool,
+    strip_metadata: bool, // EXIF, XMP and GPS; the ICC profile is kept
+    quality: Option<u8>,  // JPEG / lossy WebP / AVIF quality, None for the encoder default
+    lossless: bool,       // --lossless: WebP or JPEG XL without loss; `quality` is None then
+    tiff_compression: tiff_writer::TiffCompression,
+    jxl_distance: Option<f32>, // JPEG XL distance (0 with --lossless); `quality` is None then
+    bit_depth: Option<BitDepth>, // PNG / TIFF / JPEG XL bits per channel; None keeps the decoder's
+    auto_quality: bool,   // --quality auto: tune `quality` to the pixels where we encode them
+    resize: Option<resize::Resize>,
+    adjust: adjust::Adjustments,
+    embed_thumbnail: bool,
+    split_output: Option<u64>,
+    bwlimit: Option<u64>,
+    to_stdout: bool, // -o -: write the image bytes to stdout instead of a file
+    requested: Vec<negotiate::Feature>, // Options that need backend support
+    on_unsupported: negotiate::OnUnsupported,
+}
+
 // Command-line interface structure using clap derive macros
-#[derive(Parser)]
+#[derive(Parser, Clone)]
 #[command(name = "heic_convert")]
-#[command(about = "Convert HEIC images to PNG or JPG format")]
+#[command(about = "Convert HEIC images to PNG, JPG, WebP, AVIF, TIFF, BMP, JPEG XL, PDF, OpenEXR, PPM or ICO format")]
 #[command(version)]
+#[command(disable_help_subcommand = true)]
 struct Cli {
-    /// Input HEIC file path - the source file to convert
-    #[arg(short, long)]
+    /// Input HEIC file path - the source file to convert, a folder to convert every HEIC file in it, or a quoted pattern such as "IMG_2*.heic" (repeat -i for several)
+    #[arg(short = 'i', long = "input", value_name = "INPUT")]
+    inputs: Vec<PathBuf>,
+
+    /// More input files, folders or patterns, as with -i: heic_convert IMG_0001.heic IMG_0002.heic
+    #[arg(value_name = "FILES")]
+    files: Vec<PathBuf>,
+
+    // The input of a single conversion: the only one given, or each file of a batch in turn
+    #[arg(skip)]
     input: Option<PathBuf>,
 
-    /// Output file path - where to save the converted image (auto-generated if not specified)
+    // Folders and pattern prefixes of a batch, whose subfolders are mirrored in --output-dir
+    #[arg(skip)]
+    roots: Vec<PathBuf>,
+
+    // Place of the input in a batch, from 0, for {counter} in --name-template
+    #[arg(skip)]
+    position: usize,
+
+    // Output of the input that this one is a copy of (--link-duplicates)
+    #[arg(skip)]
+    duplicate_of: Option<PathBuf>,
+
+    /// Read more inputs from a file, one per line or NUL-separated (find -print0); - reads standard input
+    #[arg(long, value_name = "PATH")]
+    files_from: Option<PathBuf>,
+
+    /// With a folder for -i, also convert the HEIC files in all its subfolders
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// Leave out files whose name or folder matches GLOB, e.g. IMG_E*.heic or "*/Thumbnails/*"; repeat for several
+    #[arg(long, value_name = "GLOB", value_parser = inputs::parse_exclude)]
+    exclude: Vec<glob::Pattern>,
+
+    /// Output file path - where to save the converted image (auto-generated if not specified; - for stdout; a folder when converting several images)
     #[arg(short, long)]
     output: Option<PathBuf>,
 
-    /// Output format - PNG (default), JPG, or JPEG
-    #[arg(short, long, value_enum, default_value = "png")]
-    format: OutputFormat,
+    /// Print a JSON summary of the run on stdout; all other messages go to stderr (with --version: capabilities)
+    #[arg(long)]
+    json: bool,
+
+    /// Output format - PNG (default), JPG, JPEG, WebP, AVIF, TIFF, BMP, JXL (JPEG XL, needs cjxl), PDF, EXR (linear light), PPM or ICO (16-256 px icon); taken from the extension of --output when omitted
+    #[arg(short, long, global = true, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// JPEG / WebP / AVIF / JPEG XL / PDF quality from 1 (smallest) to 100 (best), or auto to tune it to each image [default: 85, WebP and AVIF 80, JPEG XL 90]
+    #[arg(long, global = true, value_name = "1-100|auto", value_parser = quality::parse_quality)]
+    quality: Option<quality::Quality>,
+
+    /// Write WebP or JPEG XL without loss, like PNG but smaller; without it both are lossy like JPEG
+    #[arg(long, global = true, conflicts_with = "quality")]
+    lossless: bool,
+
+    /// JPEG XL Butteraugli distance instead of --quality: 0 lossless, 1 visually lossless, up to 25
+    #[arg(long, global = true, value_name = "0-25", value_parser = jxl::parse_distance, conflicts_with_all = ["quality", "lossless"])]
+    jxl_distance: Option<f32>,
+
+    /// Compression of TIFF output: none, lzw or deflate (all lossless)
+    #[arg(long, global = true, value_enum, value_name = "METHOD", default_value_t)]
+    tiff_compression: tiff_writer::TiffCompression,
+
+    /// Bits per channel of PNG, TIFF, JPEG XL and PPM output; 16 keeps the precision of 10/12-bit HEIC for later editing [default: as decoded]
+    #[arg(long, global = true, value_enum, value_name = "8|16")]
+    bit_depth: Option<BitDepth>,
+
+    /// Write generated output names into this directory instead of next to the input, recreating the subfolders of a folder input
+    #[arg(long, value_name = "DIR", conflicts_with = "output")]
+    output_dir: Option<PathBuf>,
+
+    /// Put every output of a folder or pattern straight into --output-dir instead of recreating its subfolders
+    #[arg(long)]
+    flatten: bool,
+
+    /// Shrink to fit WIDTHx, xHEIGHT or WIDTHxHEIGHT, keeping the aspect ratio (never enlarges)
+    #[arg(long, value_name = "GEOMETRY", value_parser = resize::parse_resize)]
+    resize: Option<resize::Resize>,
+
+    /// Reduce noise before encoding, from 1 (light) to 10 (strong); helps high-ISO night shots and shrinks JPEGs
+    #[arg(long, value_name = "0-10", default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=10))]
+    denoise: u8,
+
+    /// Stretch the tonal range so the darkest pixels become black and the brightest white
+    #[arg(long)]
+    auto_levels: bool,
+
+    /// Neutralize colour casts by balancing the average colour to grey (gray world)
+    #[arg(long)]
+    auto_white_balance: bool,
+
+    /// Tone curve as IN:OUT points (0-255), optionally for one channel: 64:48,192:210 or b=0:16; repeatable
+    #[arg(long, value_name = "POINTS", value_parser = curve::parse_curve)]
+    curve: Vec<curve::Curve>,
+
+    /// Apply a colour look from a .cube LUT file (1D or 3D)
+    #[arg(long, value_name = "FILE")]
+    lut: Option<PathBuf>,
+
+    /// Dither when reducing 10/16-bit images to 8 bits, to prevent banding in skies and gradients
+    #[arg(long, value_enum, value_name = "PATTERN", num_args = 0..=1, default_missing_value = "blue-noise")]
+    dither: Option<adjust::Dither>,
+
+    /// Report the share of pixels a Display P3 source has outside the given gamut
+    #[arg(long, value_enum, value_name = "GAMUT")]
+    gamut_check: Option<gamut::Target>,
+
+    /// Analyze the input and recommend an output format with projected sizes, without converting
+    #[arg(long)]
+    advise: bool,
+
+    /// Ready to e-mail or post: remove metadata and location, fit within 2048 pixels, JPEG quality 80
+    #[arg(long)]
+    share_safe: bool,
+
+    /// Embed an EXIF thumbnail in JPEG outputs for fast grid views in photo browsers
+    #[arg(long)]
+    embed_thumbnail: bool,
+
+    /// Compute the output name with a Rhai expression, e.g. 'exif.date.format("%Y-%m-%d") + "_" + file.stem'
+    #[cfg(feature = "scripting")]
+    #[arg(long, value_name = "EXPR", conflicts_with_all = ["output", "name_template"])]
+    name_expr: Option<String>,
+
+    /// Name generated outputs from {stem}, {date}, {counter}, {ext} and {format}, e.g. "{date}_{stem}.{ext}"
+    #[arg(long, value_name = "TEMPLATE", conflicts_with = "output", value_parser = name_template::parse_template)]
+    name_template: Option<name_template::Template>,
+
+    /// Put the output in a subfolder named after the camera model (names from [devices] in the config)
+    #[arg(long, conflicts_with = "output")]
+    by_device: bool,
+
+    /// Put the output in YYYY/MM subfolders by the EXIF capture date (undated/ without one)
+    #[arg(long, conflicts_with = "output")]
+    organize_by_date: bool,
+
+    /// Directory for intermediate files (partial outputs, external-tool scratch)
+    #[arg(long, global = true)]
+    tmpdir: Option<PathBuf>,
+
+    /// Rename files in a directory whose extension does not match their content (no re-encoding)
+    #[arg(long, value_name = "DIR")]
+    fix_extensions: Option<PathBuf>,
+
+    /// Find HEIC images inside an e-mail (.eml, .mbox), PDF or HTML export, save them to FILE_embedded/ and convert them
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["inputs", "files", "files_from"])]
+    extract_embedded: Option<PathBuf>,
+
+    /// Convert the camera roll HEICs of an unencrypted iPhone backup folder into ./<folder>_photos (feature "ios-backup")
+    #[cfg(feature = "ios-backup")]
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["inputs", "files", "files_from", "extract_embedded"])]
+    ios_backup: Option<PathBuf>,
+
+    /// Lowercase output extensions (-o IMG_0001.PNG writes IMG_0001.png; also for --fix-extensions)
+    #[arg(long)]
+    normalize_names: bool,
+
+    /// Upload the converted image to sftp://user@host/path or webdav(s)://user@host/path
+    #[cfg(feature = "upload")]
+    #[arg(long, value_name = "URL", value_parser = upload::parse_target)]
+    upload: Option<upload::UploadTarget>,
 
-    /// Show detailed help with usage examples
+    /// Import the converted image into a self-hosted photo server (album = source folder name)
+    #[cfg(feature = "upload")]
+    #[arg(long, value_enum, value_name = "SERVER", requires = "api_url")]
+    import_to: Option<photo_server::PhotoServer>,
+
+    /// Base URL of the photo server, e.g. http://nas.local:2283
+    #[cfg(feature = "upload")]
+    #[arg(long, value_name = "URL")]
+    api_url: Option<String>,
+
+    /// API key (Immich) or app password (PhotoPrism); defaults to $HEIC_CONVERT_API_KEY
+    #[cfg(feature = "upload")]
+    #[arg(long, value_name = "KEY")]
+    api_key: Option<String>,
+
+    /// POST a JSON run summary to this URL when the run completes or fails (Discord, Slack, ntfy, ...)
+    #[cfg(feature = "webhook")]
+    #[arg(long, value_name = "URL")]
+    webhook: Option<String>,
+
+    /// E-mail the run summary and failure list to this address (SMTP settings from the config file)
+    #[cfg(feature = "email")]
+    #[arg(long, value_name = "ADDRESS")]
+    email_report: Option<String>,
+
+    /// Configuration file [default: ~/.config/heic_convert/config.toml]
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Only convert inside these daily windows, e.g. "02:00-06:00" or "12:00-13:00,22:00-07:00"; waits until a window opens
+    #[arg(long, value_name = "WINDOWS", value_parser = schedule::parse_schedule)]
+    schedule: Option<schedule::Schedule>,
+
+    /// Stop starting new files after this long, e.g. 30m; the unfinished files are listed in heic_convert-remaining.txt
+    #[arg(long, value_name = "DURATION", value_parser = units::parse_duration)]
+    time_budget: Option<std::time::Duration>,
+
+    /// Leave inputs alone whose output already exists, so an interrupted batch can be run again
+    #[arg(long)]
+    skip_existing: bool,
+
+    /// Convert only the first of inputs with the same content; the copies are left alone
+    #[arg(long)]
+    skip_duplicates: bool,
+
+    /// Like --skip-duplicates, but give each copy a hard link to the output of the first
+    #[arg(long)]
+    link_duplicates: bool,
+
+    /// Go on with an interrupted multi-file run (the same command), leaving out the files it finished
+    #[arg(long)]
+    resume: bool,
+
+    /// Delete the input once its output has been written and reads back as an image
+    #[arg(long, conflicts_with = "trash_original")]
+    delete_original: bool,
+
+    /// Like --delete-original, but move the input to the system trash
+    #[arg(long)]
+    trash_original: bool,
+
+    /// Replace an existing output without a warning
+    #[arg(long, conflicts_with_all = ["skip_existing", "no_clobber", "rename_on_conflict"])]
+    overwrite: bool,
+
+    /// Never replace an existing output; its input fails instead (E026)
+    #[arg(long, conflicts_with_all = ["skip_existing", "rename_on_conflict"])]
+    no_clobber: bool,
+
+    /// Keep an existing output and write the new one as name_1.png, name_2.png, ...
+    #[arg(long, conflicts_with = "skip_existing")]
+    rename_on_conflict: bool,
+
+    /// Convert up to N files at the same time in a multi-file run; 0 uses one per CPU core
+    #[arg(short, long, value_name = "N", default_value_t = 1)]
+    jobs: usize,
+
+    /// Order of the files in a multi-file run by file size: smallest-first or largest-first [default: as found]
+    #[arg(long, value_enum, value_name = "ORDER")]
+    order: Option<Order>,
+
+    /// Write a manifest of the run (input, output, backend, duration, size, outcome) to FILE: CSV for a .csv name, else JSON
+    #[arg(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
+    /// Pack the outputs of a multi-file run into one .zip, .tar.gz or .tgz FILE instead of loose files (keeps the originals)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["output", "output_dir", "resume", "delete_original", "trash_original"])]
+    archive_output: Option<PathBuf>,
+
+    /// Report .mov/.aae companions whose HEIC original is gone but whose converted output exists
+    #[arg(long, value_name = "DIR")]
+    orphan_report: Option<PathBuf>,
+
+    /// Move the orphaned companions found by --orphan-report into this folder for review
+    #[arg(long, value_name = "DIR", requires = "orphan_report")]
+    orphan_review_dir: Option<PathBuf>,
+
+    /// Add the converted image to album.json in the output directory (title, cover, images with size and date)
+    #[arg(long)]
+    album_manifest: bool,
+
+    /// Distribute outputs over numbered directories (part_001, part_002, ...) of at most this size, e.g. 4.7GB
+    #[arg(long, value_name = "SIZE", value_parser = units::parse_size)]
+    split_output: Option<u64>,
+
+    /// Limit write throughput to the output location, e.g. 10MB/s (useful for network mounts)
+    #[arg(long, value_name = "RATE", value_parser = throttle::parse_rate)]
+    bwlimit: Option<u64>,
+
+    /// Which CPU cores to convert on: performance (fastest), efficiency (battery friendly) or all
+    #[arg(long, value_enum, value_name = "CORES", default_value_t = cores::CoreSelection::All)]
+    cores: cores::CoreSelection,
+
+    /// On battery or when the machine runs hot: use efficiency cores, lower priority and single-threaded tools
+    #[arg(long)]
+    eco: bool,
+
+    /// Copy the input to the temp directory first, retrying failed reads up to N times and resuming where they stopped
+    #[arg(long, value_name = "N")]
+    retry_io: Option<u32>,
+
+    /// Wait exponentially longer between --retry-io attempts (0.5s, 1s, 2s, ... up to 30s)
+    #[arg(long, requires = "retry_io")]
+    io_backoff: bool,
+
+    /// Wait up to DURATION (e.g. 5m) for an input that is empty, still growing or only in iCloud
+    #[arg(long, value_name = "DURATION", value_parser = units::parse_duration)]
+    wait_for_sync: Option<std::time::Duration>,
+
+    /// Conversion backend to use; auto tries builtin, then ImageMagick, then FFmpeg
+    #[arg(long, value_enum, default_value_t = Backend::Auto)]
+    backend: Backend,
+
+    /// When the backend cannot keep metadata, the colour profile or another requested option
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = negotiate::OnUnsupported::Warn)]
+    on_unsupported: negotiate::OnUnsupported,
+
+    /// Byte-identical output for identical input: strip metadata and timestamps, single-threaded tools
+    #[arg(long)]
+    deterministic: bool,
+
+    /// Show sizes in SI (kB, MB) or binary (KiB, MiB) units; JSON output always uses bytes
+    #[arg(long, global = true, value_enum, default_value_t = units::UnitSystem::Si)]
+    units: units::UnitSystem,
+
+    /// Plain ASCII output without emoji, colours or the banner (screen-reader friendly)
+    #[arg(long, global = true)]
+    ascii_only: bool,
+
+    /// Print why each backend, metadata and transform decision was made
+    #[arg(long)]
+    explain: bool,
+
+    /// Show all help topics with usage examples (same as `help` for every topic)
     #[arg(long)]
     bighelp: bool,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
 }
 
-// Display comprehensive help information with detailed usage examples
-fn print_bighelp() {
-    println!("HEIC to PNG/JPG Converter - Detailed Help");
-    println!("=========================================");
-    println!();
-    println!(
-        "This tool converts HEIC (High Efficiency Image Container) files to PNG or JPG format."
-    );
-    println!();
-    println!("BASIC USAGE:");
-    println!("  heic_convert -i input.heic                    # Convert to PNG (default)");
-    println!("  heic_convert -i input.heic -f jpg             # Convert to JPG");
-    println!("  heic_convert -i input.heic -o output.png      # Specify output file");
-    println!();
-    println!("EXAMPLES:");
-    println!("  # Convert a single HEIC file to PNG:");
-    println!("  heic_convert -i photo.heic");
-    println!("  # Output: photo.png");
-    println!();
-    println!("  # Convert to JPG format:");
-    println!("  heic_convert -i photo.heic -f jpg");
-    println!("  # Output: photo.jpg");
-    println!();
-    println!("  # Specify custom output filename:");
-    println!("  heic_convert -i IMG_1234.heic -o my_photo.png");
-    println!("  # Output: my_photo.png");
-    println!();
-    println!("  # Convert with different output directory:");
-    println!("  heic_convert -i /path/to/photo.heic -o /output/dir/converted.jpg -f jpg");
-    println!("  # Output: /output/dir/converted.jpg");
-    println!();
-    println!("  # Convert multiple files (use in shell script):");
-    println!("  for file in *.heic; do heic_convert -i \"$file\" -f png; done");
-    println!("  # Converts all HEIC files in current directory to PNG");
-    println!();
-    println!("OPTIONS:");
-    println!("  -i, --input <FILE>     Input HEIC file path");
-    println!("  -o, --output <FILE>    Output file path (optional)");
-    println!("  -f, --format <FORMAT>  Output format: png, jpg, jpeg [default: png]");
-    println!("  --bighelp              Show this detailed help");
-    println!("  -h, --help             Show basic help");
-    println!("  -V, --version          Show version");
-    println!();
-    println!("NOTES:");
-    println!(
-        "  - If no output file is specified, the tool will generate one based on the input filename"
-    );
-    println!("  - Supported output formats: PNG, JPG/JPEG");
-    println!("  - The tool preserves image quality during conversion");
-    println!("  - Requires libheif system library to be installed (brew install libheif)");
-    println!();
-    println!("SYSTEM REQUIREMENTS:");
-    println!("  - macOS: Install libheif via Homebrew: brew install libheif");
-    println!("  - Linux: Install libheif via package manager: apt-get install libheif-dev");
-    println!("  - Windows: Install libheif development libraries");
-    println!();
-    println!("ALTERNATIVE METHODS:");
-    println!("  If this tool doesn't work, you can also use:");
-    println!("  - ImageMagick: convert input.heic output.png");
-    println!("  - FFmpeg: ffmpeg -i input.heic output.png");
-    println!("  - Online converters: convertio.co, cloudconvert.com");
-}
-
-// Generate an output file path based on input filename and desired format
-// This function creates a new filename with the appropriate extension in the same directory
-fn generate_output_path(input: &Path, format: &OutputFormat) -> PathBuf {
-    let stem = input.file_stem().unwrap_or_default();      // Get filename without extension
-    let parent = input.parent().unwrap_or(Path::new(".")); // Get parent directory, default to current
-    // Combine parent directory, filename stem, and new extension
-    parent.join(format!("{}.{}", stem.to_string_lossy(), format.extension()))
-}
-
-// Check if ImageMagick is available on the system by running 'convert -version'
-fn check_imagemagick_available() -> bool {
-    match Command::new("convert")
-        .arg("-version")
-        .output() 
-    {
-        Ok(output) => output.status.success(),
-        Err(_) => false,  // Command failed to execute (likely not installed)
+impl Commands {
+    // Subcommands that convert like a normal run, started with the conversions
+    fn converts(&self) -> bool {
+        match self {
+            Commands::Extract { .. } => true,
+            #[cfg(feature = "watch")]
+            Commands::Watch { .. } => true,
+            _ => false,
+        }
     }
 }
 
-// Check if FFmpeg is available on the system by running 'ffmpeg -version'
-fn check_ffmpeg_available() -> bool {
-    match Command::new("ffmpeg")
-        .arg("-version")
-        .output()
-    {
-        Ok(output) => output.status.success(),
-        Err(_) => false,  // Command failed to execute (likely not installed)
+impl Cli {
+    // Every input given: the -i arguments followed by the trailing ones
+    fn arguments(&self) -> Vec<PathBuf> {
+        self.inputs.iter().chain(&self.files).cloned().collect()
+    }
+
+    // -f, the format from [defaults] in the configuration file, or PNG
+    fn output_format(&self) -> OutputFormat {
+        self.format.clone().unwrap_or(OutputFormat::Png)
     }
 }
 
+// Subcommands that run instead of a conversion
+#[derive(Subcommand, Clone)]
+enum Commands {
+    /// Show detailed help on a topic: formats, metadata, backends, batch, server, troubleshooting
+    Help {
+        /// Topic to show; lists the topics when omitted
+        topic: Option<String>,
+    },
+
+    /// Describe an error code (E001, E002, ...): likely causes and how to fix it; lists all codes when omitted
+    Explain {
+        /// Error code from a failure message, e.g. E008
+        code: Option<String>,
+    },
+
+    /// Print the man page (roff) to standard output, e.g. heic_convert man > heic_convert.1
+    Man,
+
+    /// Remove temporary files left behind by crashed or interrupted runs
+    CleanTemp,
+
+    /// Import from a phone or memory card: convert every HEIC below --from into date folders under --to, with a verification manifest; --from is never written to
+    Extract {
+        /// Mounted device folder to read, e.g. /Volumes/iPhone/DCIM
+        #[arg(long, value_name = "DIR")]
+        from: PathBuf,
+
+        /// Where the year/date folders and extract-manifest.json are created
+        #[arg(long, value_name = "DIR")]
+        to: PathBuf,
+    },
+
+    /// First-run setup: check the installed backends, ask for default format, quality and output folder, write the config file
+    Init {
+        /// Replace an existing configuration file (the old one is kept as config.toml.bak)
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Download and install the latest release after verifying its signature
+    #[cfg(feature = "self-update")]
+    SelfUpdate {
+        /// Only report whether a newer version exists
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Convert every HEIC file that arrives in a folder (a Downloads or AirDrop folder) until Ctrl-C
+    #[cfg(feature = "watch")]
+    Watch {
+        /// Folder to watch
+        dir: PathBuf,
+
+        /// Where the converted images go [default: next to the originals]
+        #[arg(long, value_name = "DIR")]
+        to: Option<PathBuf>,
+
+        /// Also watch the subfolders, recreating them in --to
+        #[arg(short, long)]
+        recursive: bool,
+    },
+
+    /// Show type, size, dimensions, capture date and camera of images
+    Info {
+        /// Images to inspect
+        #[arg(required = true)]
+        images: Vec<PathBuf>,
+
+        /// Also compute a luminance histogram, clipping percentages and average brightness
+        #[arg(long)]
+        stats: bool,
+    },
+
+    /// Check that HEIC files are intact (truncated files, missing image data) without converting anything
+    Validate {
+        /// HEIC file, or folder to check recursively
+        path: PathBuf,
+
+        /// Also decode every file in full with the conversion backends (slower)
+        #[arg(long)]
+        decode: bool,
+
+        /// Write a corrected copy (<name>.repaired.heic) of files whose main image is intact
+        #[arg(long)]
+        repair: bool,
+    },
+
+    /// Show lifetime usage statistics (opt-in, stored only on this computer)
+    Stats {
+        /// Start recording statistics in ~/.local/share/heic_convert/stats.json
+        #[arg(long, conflicts_with = "disable")]
+        enable: bool,
+
+        /// Stop recording and delete the statistics file
+        #[arg(long)]
+        disable: bool,
+    },
+
+    /// Re-encode an image as a normalized, metadata-free PNG so outputs can be compared byte-wise
+    Canonical {
+        /// Image to normalize (PNG, JPEG, ...)
+        image: PathBuf,
+
+        /// Where to write the canonical PNG [default: <name>.canonical.png]
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+// Check if ImageMagick is available on the system by running 'convert -version'; the
+// answer is kept for the rest of the run
+fn check_imagemagick_available() -> bool {
+    static AVAILABLE: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *AVAILABLE.get_or_init(|| process::available(&process::SystemRunner, "convert"))
+}
+
+// Check if FFmpeg is available on the system by running 'ffmpeg -version', once per run
+fn check_ffmpeg_available() -> bool {
+    static AVAILABLE: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *AVAILABLE.get_or_init(|| process::available(&process::SystemRunner, "ffmpeg"))
+}
+
 // Convert HEIC file using ImageMagick's 'convert' command
-fn convert_with_imagemagick(input_path: &Path, output_path: &Path) -> Result<()> {
-    println!(
-        "Using ImageMagick to convert {} to {}",
-        input_path.display(),
-        output_path.display()
-    );
+fn convert_with_imagemagick(
+    runner: &dyn process::ProcessRunner,
+    input_path: &Path,
+    output_path: &Path,
+    options: &ConvertOptions,
+) -> Result<()> {
+    say!("Using ImageMagick to convert {}", input_path.display());
+    if options.deterministic {
+        explain::note("Metadata: stripped, including PNG date/time chunks (--deterministic)");
+    } else if options.strip_metadata {
+        explain::note("Metadata: EXIF, XMP and GPS removed, ICC profile kept (--share-safe)");
+    } else {
+        explain::note("Metadata: carried over by ImageMagick where the output format supports it (EXIF, ICC)");
+    }
 
-    // Execute ImageMagick convert command with input and output paths
-    let output = Command::new("convert")
-        .arg(input_path.to_str().unwrap())
-        .arg(output_path.to_str().unwrap())
-        .output()
-        .context("Failed to execute ImageMagick convert command. Make sure ImageMagick is installed: 'brew install imagemagick'")?;
+    let finished = runner
+        .run(&imagemagick_invocation(input_path, output_path, options))
+        .context("Failed to execute ImageMagick convert command. Make sure ImageMagick is installed: 'brew install imagemagick'")
+        .code(ErrorCode::ToolMissing)?;
 
     // Check if the conversion was successful
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        
-        // Provide specific error messages for common ImageMagick issues
-        if stderr.contains("no decode delegate") || stderr.contains("HEIC") {
-            return Err(anyhow!(
-                "ImageMagick HEIC support is not available.\n\
-                 Install HEIC support with: brew install imagemagick --with-heif\n\
-                 Or try: brew install libheif && brew reinstall imagemagick\n\
-                 Original error: {}", stderr
-            ));
-        } else if stderr.contains("command not found") || stderr.contains("No such file") {
-            return Err(anyhow!(
-                "ImageMagick is not installed or not found in PATH.\n\
-                 Install it with: brew install imagemagick\n\
-                 Original error: {}", stderr
-            ));
-        } else {
-            return Err(anyhow!("ImageMagick conversion failed: {}", stderr));
-        }
+    if !finished.success {
+        return Err(imagemagick_error(&finished.stderr_text()));
     }
-
-    println!("Successfully converted to {}", output_path.display());
     Ok(())
 }
 
+// The ImageMagick command line: input, processing options, output
+fn imagemagick_invocation(input_path: &Path, output_path: &Path, options: &ConvertOptions) -> process::Invocation {
+    let mut command = process::Invocation::new("convert");
+    if eco::active() || options.deterministic {
+        command.env("MAGICK_THREAD_LIMIT", "1"); // Keep ImageMagick on a single thread
+    }
+    command.arg(input_path);
+    if let Some(resize) = options.resize {
+        command.arg("-resize").arg(resize.imagemagick_geometry());
+    }
+    if options.sixteen_bits() {
+        command.arg("-depth").arg("16");
+    } else if options.bit_depth == Some(BitDepth::Eight) {
+        command.arg("-depth").arg("8");
+    }
+    if options.deterministic {
+        // Drop metadata and the PNG date/time chunks that change on every run
+        command
+            .arg("-strip")
+            .arg("-define")
+            .arg("png:exclude-chunks=date,time");
+    } else if options.strip_metadata {
+        // Every profile except the colour profile, so P3 images keep their colours
+        command.arg("+profile").arg("!icc,*");
+    }
+    if let Some(quality) = options.quality {
+        command.arg("-quality").arg(quality.to_string());
+    }
+    if options.lossless && matches!(options.format, OutputFormat::Webp) {
+        command.arg("-define").arg("webp:lossless=true");
+    }
+    if matches!(options.format, OutputFormat::Tiff) {
+        command.arg("-compress").arg(options.tiff_compression.imagemagick());
+    }
+    if matches!(OutputFormat::from_extension(output_path), Some(OutputFormat::Bmp)) {
+        // The Windows 3 layout without alpha, which every BMP reader accepts; the
+        // default BMP4 header with colour masks trips up older tools. Not for the PNG
+        // written ahead of pixel adjustments.
+        let mut bmp3 = std::ffi::OsString::from("BMP3:");
+        bmp3.push(output_path);
+        command.arg(bmp3);
+    } else if matches!(OutputFormat::from_extension(output_path), Some(OutputFormat::Ppm)) {
+        // Colour PPM also for grey images and .pnm names, where ImageMagick would pick
+        // PGM or PAM by the pixels
+        let mut ppm = std::ffi::OsString::from("PPM:");
+        ppm.push(output_path);
+        command.arg(ppm);
+    } else {
+        command.arg(output_path);
+    }
+    command
+}
+
+// Provide specific error messages for common ImageMagick issues
+fn imagemagick_error(stderr: &str) -> anyhow::Error {
+    if stderr.contains("no decode delegate") || stderr.contains("HEIC") {
+        coded(ErrorCode::ImageMagickNoHeic, anyhow!(
+            "ImageMagick HEIC support is not available.\n\
+             Install HEIC support with: brew install imagemagick --with-heif\n\
+             Or try: brew install libheif && brew reinstall imagemagick\n\
+             Original error: {}", stderr
+        ))
+    } else if stderr.contains("command not found") || stderr.contains("No such file") {
+        coded(ErrorCode::ToolMissing, anyhow!(
+            "ImageMagick is not installed or not found in PATH.\n\
+             Install it with: brew install imagemagick\n\
+             Original error: {}", stderr
+        ))
+    } else {
+        coded(ErrorCode::ToolFailed, anyhow!("ImageMagick conversion failed: {}", stderr))
+    }
+}
+
 // Convert HEIC file using FFmpeg
-fn convert_with_ffmpeg(input_path: &Path, output_path: &Path) -> Result<()> {
-    println!(
-        "Using FFmpeg to convert {} to {}",
-        input_path.display(),
-        output_path.display()
-    );
+fn convert_with_ffmpeg(
+    runner: &dyn process::ProcessRunner,
+    input_path: &Path,
+    output_path: &Path,
+    options: &ConvertOptions,
+) -> Result<()> {
+    say!("Using FFmpeg to convert {}", input_path.display());
+    if options.deterministic {
+        explain::note("Metadata: stripped, bit-exact encoder flags (--deterministic)");
+    } else if options.strip_metadata {
+        explain::note("Metadata: all removed, including GPS (--share-safe)");
+    } else {
+        explain::note("Metadata: EXIF and ICC dropped (FFmpeg only copies container metadata)");
+    }
 
-    // Execute FFmpeg command with input file, overwrite flag, and output file
-    let output = Command::new("ffmpeg")
-        .arg("-i")                              // Input flag
-        .arg(input_path.to_str().unwrap())
-        .arg("-y")                              // Overwrite output file without asking
-        .arg(output_path.to_str().unwrap())
-        .output()
-        .context("Failed to execute FFmpeg command. Make sure FFmpeg is installed: 'brew install ffmpeg'")?;
+    let finished = runner
+        .run(&ffmpeg_invocation(input_path, output_path, options))
+        .context("Failed to execute FFmpeg command. Make sure FFmpeg is installed: 'brew install ffmpeg'")
+        .code(ErrorCode::ToolMissing)?;
 
     // Check if the conversion was successful
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        
-        // Provide specific error messages for common FFmpeg issues
-        if stderr.contains("No such file or directory") && stderr.contains("ffmpeg") {
-            return Err(anyhow!(
-                "FFmpeg is not installed or not found in PATH.\n\
-                 Install it with: brew install ffmpeg\n\
-                 Original error: {}", stderr
-            ));
-        } else if stderr.contains("Invalid data found") || stderr.contains("could not find codec") {
-            return Err(anyhow!(
-                "FFmpeg cannot decode this HEIC file. The file may be corrupted or use an unsupported HEIC variant.\n\
-                 Try installing FFmpeg with additional codec support: brew install ffmpeg --with-libheif\n\
-                 Original error: {}", stderr
-            ));
-        } else if stderr.contains("Permission denied") {
-            return Err(anyhow!(
-                "Permission denied when trying to write output file: {}\n\
-                 Check file permissions and disk space.\n\
-                 Original error: {}", 
-                output_path.display(), stderr
-            ));
-        } else {
-            return Err(anyhow!("FFmpeg conversion failed: {}", stderr));
+    if !finished.success {
+        return Err(ffmpeg_error(&finished.stderr_text(), output_path));
+    }
+    Ok(())
+}
+
+// The FFmpeg command line: input file, processing options, overwrite flag and output file
+fn ffmpeg_invocation(input_path: &Path, output_path: &Path, options: &ConvertOptions) -> process::Invocation {
+    let mut command = process::Invocation::new("ffmpeg");
+    if eco::active() || options.deterministic {
+        command.arg("-threads").arg("1"); // Keep FFmpeg on a single thread
+    }
+    command
+        .arg("-i")                              // Input flag
+        .arg(input_path);
+    if let Some(resize) = options.resize {
+        command.arg("-vf").arg(resize.ffmpeg_filter());
+    }
+    // The file FFmpeg writes: the output, or the PNG written ahead of pixel adjustments
+    let writes = OutputFormat::from_extension(output_path).unwrap_or(options.format.clone());
+    let sixteen = options.sixteen_bits();
+    match writes {
+        // 24-bit BMP; FFmpeg keeps the alpha of the input as 32-bit BGRA otherwise
+        OutputFormat::Bmp => {
+            command.arg("-pix_fmt").arg("bgr24");
+        }
+        OutputFormat::Tiff if sixteen => {
+            command.arg("-pix_fmt").arg("rgb48le");
+        }
+        _ if sixteen => {
+            command.arg("-pix_fmt").arg("rgb48be");
         }
+        _ if options.bit_depth == Some(BitDepth::Eight) => {
+            command.arg("-pix_fmt").arg("rgb24");
+        }
+        _ => {}
+    }
+    if options.deterministic {
+        // No encoder version strings or copied metadata in the output
+        command
+            .arg("-fflags").arg("+bitexact")
+            .arg("-flags:v").arg("+bitexact")
+            .arg("-map_metadata").arg("-1");
+    } else if options.strip_metadata {
+        command.arg("-map_metadata").arg("-1");
+    }
+    match (&options.format, options.quality) {
+        // libwebp takes the quality as it is
+        (OutputFormat::Webp, _) if options.lossless => {
+            command.arg("-lossless").arg("1");
+        }
+        (OutputFormat::Webp, Some(quality)) => {
+            command.arg("-quality").arg(quality.to_string());
+        }
+        // The AV1 encoder's constant rate factor runs from 0 (best) to 63
+        (OutputFormat::Avif, Some(quality)) => {
+            command.arg("-crf").arg(((100 - quality.min(100) as u32) * 63 / 100).to_string());
+        }
+        // FFmpeg's JPEG scale runs from 2 (best) to 31
+        (_, Some(quality)) => {
+            command.arg("-q:v").arg(((100 - quality.min(100) as u32) / 5 + 2).min(31).to_string());
+        }
+        (OutputFormat::Tiff, _) => {
+            command.arg("-compression_algo").arg(options.tiff_compression.ffmpeg());
+        }
+        (_, None) => {}
     }
+    command
+        .arg("-y")                              // Overwrite output file without asking
+        .arg(output_path);
+    command
+}
 
-    println!("Successfully converted to {}", output_path.display());
-    Ok(())
+// Provide specific error messages for common FFmpeg issues
+fn ffmpeg_error(stderr: &str, output_path: &Path) -> anyhow::Error {
+    if stderr.contains("No such file or directory") && stderr.contains("ffmpeg") {
+        coded(ErrorCode::ToolMissing, anyhow!(
+            "FFmpeg is not installed or not found in PATH.\n\
+             Install it with: brew install ffmpeg\n\
+             Original error: {}", stderr
+        ))
+    } else if stderr.contains("Invalid data found") || stderr.contains("could not find codec") {
+        coded(ErrorCode::ToolFailed, anyhow!(
+            "FFmpeg cannot decode this HEIC file. The file may be corrupted or use an unsupported HEIC variant.\n\
+             Try installing FFmpeg with additional codec support: brew install ffmpeg --with-libheif\n\
+             Original error: {}", stderr
+        ))
+    } else if stderr.contains("Permission denied") {
+        coded(ErrorCode::SaveFailed, anyhow!(
+            "Permission denied when trying to write output file: {}\n\
+             Check file permissions and disk space.\n\
+             Original error: {}",
+            output_path.display(), stderr
+        ))
+    } else {
+        coded(ErrorCode::ToolFailed, anyhow!("FFmpeg conversion failed: {}", stderr))
+    }
 }
 
-// Main conversion function that orchestrates the HEIC to image conversion process
+// Main conversion function that orchestrates the HEIC to image conversion process.
+// The output is first written into the scratch workspace and only moved to
+// `output_path` once the backend has finished, so failed runs never leave half-written files.
+// Returns the path the image was finally written to.
 fn convert_heic_to_image(
     input_path: &Path,
     output_path: &Path,
-    format: &OutputFormat,
+    workspace: &TempWorkspace,
+    options: &ConvertOptions,
+) -> Result<PathBuf> {
+    // "-" names no file, and external tools pick the format from the extension
+    let partial_path = if options.to_stdout {
+        workspace.partial_path(Path::new(&format!("stdout.{}", options.format.extension())))
+    } else {
+        workspace.partial_path(output_path)
+    };
+    run_conversion_backends(input_path, &partial_path, output_path, options)?;
+    explain::note(format!(
+        "Encoded as {} into the temporary directory",
+        options.format.extension().to_uppercase()
+    ));
+
+    // PNG has no EXIF thumbnail slot, so --embed-thumbnail only applies to JPEG outputs
+    if options.embed_thumbnail {
+        match options.format {
+            OutputFormat::Jpg | OutputFormat::Jpeg => match thumbnail::embed(&partial_path) {
+                Ok(true) => explain::note("EXIF thumbnail embedded (--embed-thumbnail)"),
+                Ok(false) => explain::note("EXIF thumbnail kept: the output already has one"),
+                Err(e) => events::warn(format!("Could not embed a thumbnail: {}", e)),
+            },
+            OutputFormat::Png | OutputFormat::Webp | OutputFormat::Avif | OutputFormat::Tiff | OutputFormat::Bmp | OutputFormat::Jxl | OutputFormat::Pdf | OutputFormat::Exr | OutputFormat::Ppm | OutputFormat::Ico => {
+                events::warn("--embed-thumbnail only applies to JPEG output; skipped")
+            }
+        }
+    }
+
+    // -o -: the image is the only thing written to stdout
+    if options.to_stdout {
+        let mut file = fs::File::open(&partial_path)?;
+        let mut stdout = std::io::stdout().lock();
+        std::io::copy(&mut file, &mut stdout)
+            .and_then(|_| std::io::Write::flush(&mut stdout))
+            .context("❌ Cannot write the image to stdout")
+            .code(ErrorCode::SaveFailed)?;
+        explain::note("Written to stdout (-o -)");
+        say!("Successfully converted to stdout");
+        return Ok(PathBuf::from("-"));
+    }
+
+    // With --split-output the file goes into a size-limited volume directory next to the output
+    let placing = options.split_output.map(|_| volumes::placing());
+    let final_path = match options.split_output {
+        Some(limit) => {
+            let size = fs::metadata(&partial_path).map(|m| m.len()).unwrap_or(0);
+            let base = output_path.parent().unwrap_or(Path::new("."));
+            let volume = volumes::select_volume(base, size, limit).code(ErrorCode::VolumeTooSmall)?;
+            fs::create_dir_all(&volume).with_context(|| {
+                format!("❌ Failed to create output volume: {}", volume.display())
+            })?;
+            explain::note(format!(
+                "Placed in {} to keep each folder under the --split-output limit",
+                volume.display()
+            ));
+            volume.join(output_path.file_name().unwrap_or_default())
+        }
+        None => output_path.to_path_buf(),
+    };
+
+    match options.bwlimit {
+        Some(rate) => explain::note(format!("Copied into place at {} (--bwlimit)", units::format_rate(rate))),
+        None => explain::note("Moved into place from the temporary directory"),
+    }
+    temp_workspace::persist(&partial_path, &final_path, options.bwlimit).code(ErrorCode::PersistFailed)?;
+    drop(placing);
+    say!("Successfully converted to {}", final_path.display());
+    Ok(final_path)
+}
+
+// Try each conversion strategy in turn, writing the result to `partial_path`
+fn run_conversion_backends(
+    input_path: &Path,
+    partial_path: &Path,
+    output_path: &Path,
+    options: &ConvertOptions,
 ) -> Result<()> {
     // Validate that the input file has a HEIC/HEIF extension
     let extension = input_path
@@ -260,66 +1081,344 @@ fn convert_heic_to_image(
 
     // Warn if extension doesn't look like HEIC, but continue anyway
     if !["heic", "heif"].contains(&extension.as_str()) {
-        println!("⚠️  Warning: File extension '{}' is not typical for HEIC files.", extension);
-        println!("    Expected: .heic or .heif");
-        println!("    Attempting conversion anyway...");
-        println!();
+        say!("⚠️  Warning: File extension '{}' is not typical for HEIC files.", extension);
+        say!("    Expected: .heic or .heif");
+        say!("    Attempting conversion anyway...");
+        say!();
+    }
+
+    if let Some(resize) = options.resize {
+        explain::note(format!("Resized to fit {} (images that already fit are left alone)", resize));
+    }
+
+    // An explicitly chosen backend is used on its own, without falling back
+    if options.backend != Backend::Auto {
+        explain::note(format!("Backend: {:?}, chosen with --backend (no fallback)", options.backend));
+        let backend = negotiate::negotiate(options.backend, &options.requested, options.on_unsupported)?;
+        return run_backend(backend, None, input_path, partial_path, output_path, options);
     }
 
     // Strategy 1: Try to use the Rust image crate's built-in support first (fastest)
-    match image::open(input_path) {
+    let img_error = match image::open(input_path) {
         Ok(img) => {
-            println!(
-                "Converting {} to {}",
-                input_path.display(),
-                output_path.display()
-            );
-            save_image(&img, output_path, format)?;
-            return Ok(());
+            explain::note("Backend: built-in decoder, the first choice, can read this file");
+            let backend = negotiate::negotiate(Backend::Builtin, &options.requested, options.on_unsupported)?;
+            let decoded = (backend == Backend::Builtin).then_some(img);
+            return run_backend(backend, decoded, input_path, partial_path, output_path, options);
+        }
+        Err(img_error) => img_error,
+    };
+    // Image crate doesn't support HEIC, fall back to external tools
+    say!("Rust image crate cannot handle this file, trying external tools...");
+    say!("Image crate error: {}", img_error);
+    explain::note(format!("Built-in decoder skipped: {}", img_error));
+
+    // Strategy 2: Try ImageMagick (most common and reliable)
+    if check_imagemagick_available() {
+        explain::note("Backend: ImageMagick, the first installed external tool");
+        let backend = negotiate::negotiate(Backend::Imagemagick, &options.requested, options.on_unsupported)?;
+        return run_backend(backend, None, input_path, partial_path, output_path, options);
+    }
+
+    // Strategy 3: Try FFmpeg (alternative option)
+    explain::note("ImageMagick skipped: not installed");
+    if check_ffmpeg_available() {
+        explain::note("Backend: FFmpeg, the only installed external tool");
+        let backend = negotiate::negotiate(Backend::Ffmpeg, &options.requested, options.on_unsupported)?;
+        return run_backend(backend, None, input_path, partial_path, output_path, options);
+    }
+
+    // No conversion methods available - provide helpful error message
+    Err(coded(ErrorCode::NoBackend, anyhow!(
+        "HEIC format support is not available.\n\
+         \n\
+         To enable HEIC conversion, install one of these tools:\n\
+         \n\
+         1. ImageMagick:\n\
+            brew install imagemagick\n\
+         \n\
+         2. FFmpeg:\n\
+            brew install ffmpeg\n\
+         \n\
+         3. System libheif library:\n\
+            brew install libheif\n\
+         \n\
+         Alternative solutions:\n\
+         - Use online converters like convertio.co or cloudconvert.com\n\
+         - Use the macOS Preview app: Open HEIC → Export as PNG/JPEG\n\
+         - Use Photos app: Export as JPEG"
+    )))
+}
+
+// Convert with one backend; `decoded` is the input already read by the built-in decoder
+fn run_backend(
+    backend: Backend,
+    decoded: Option<DynamicImage>,
+    input_path: &Path,
+    partial_path: &Path,
+    output_path: &Path,
+    options: &ConvertOptions,
+) -> Result<()> {
+    let name = format!("{:?}", backend).to_lowercase();
+    events::publish(events::Event::BackendChosen { input: input_path, backend: &name });
+    match backend {
+        Backend::Builtin => {
+            let img = match decoded {
+                Some(img) => img,
+                None => image::open(input_path)
+                    .with_context(|| {
+                        format!(
+                            "❌ The built-in decoder cannot read {}\n\
+                             Try --backend imagemagick or --backend ffmpeg instead.",
+                            input_path.display()
+                        )
+                    })
+                    .code(ErrorCode::BuiltinCannotRead)?,
+            };
+            say!("Converting {} to {}", input_path.display(), output_path.display());
+            explain::note("Metadata: EXIF, XMP and ICC dropped (the built-in encoder writes pixels only)");
+            let img = linear_light(process(img, options), input_path, options);
+            save_image(&img, partial_path, &options.format, options.quality_for(&img), options.tiff_compression, options.jxl_distance)
+        }
+        Backend::Imagemagick => convert_with_tool(convert_with_imagemagick, input_path, partial_path, options),
+        Backend::Ffmpeg => convert_with_tool(convert_with_ffmpeg, input_path, partial_path, options),
+        Backend::Auto => unreachable!("auto is resolved to a backend before converting"),
+    }
+}
+
+// Decode any supported image for analysis; formats the built-in decoder cannot read
+// (HEIC) are converted to a lossless PNG in `workspace` first
+fn decode_image(input_path: &Path, workspace: &TempWorkspace) -> Result<DynamicImage> {
+    if let Ok(img) = image::open(input_path) {
+        return Ok(img);
+    }
+    let decoded = workspace.partial_path(Path::new("decoded.png"));
+    let options = ConvertOptions {
+        format: OutputFormat::Png,
+        backend: Backend::Auto,
+        deterministic: false,
+        strip_metadata: false,
+        quality: None,
+        lossless: false,
+        tiff_compression: tiff_writer::TiffCompression::None,
+        jxl_distance: None,
+        bit_depth: None,
+        auto_quality: false,
+        resize: None,
+        adjust: adjust::Adjustments::default(),
+        embed_thumbnail: false,
+        split_output: None,
+        bwlimit: None,
+        to_stdout: false,
+        requested: Vec::new(),
+        on_unsupported: negotiate::OnUnsupported::Warn,
+    };
+    run_conversion_backends(input_path, &decoded, &decoded, &options)?;
+    image::open(&decoded).with_context(|| format!("❌ Cannot read the decoded image of {}", input_path.display()))
+}
+
+// -o -: the converted image goes to stdout
+fn writes_to_stdout(cli: &Cli) -> bool {
+    cli.output.as_deref().is_some_and(paths::is_stdout)
+}
+
+// --gamut-check: how much of the source lies outside sRGB
+fn report_gamut(input_path: &Path, workspace: &TempWorkspace) {
+    let space = match gamut::source_space(input_path) {
+        Some(gamut::ColorSpace::DisplayP3) => gamut::ColorSpace::DisplayP3,
+        Some(space) => {
+            say!("🎨 Gamut: the source is {}; nothing to check", space.name());
+            return;
+        }
+        None => {
+            events::warn("Gamut check skipped: cannot read the colour information");
+            return;
+        }
+    };
+    let image = match decode_image(input_path, workspace) {
+        Ok(image) => image,
+        Err(e) => {
+            events::warn(format!("Gamut check skipped: {}", format!("{:#}", e).lines().next().unwrap_or_default()));
+            return;
+        }
+    };
+    let outside = gamut::out_of_gamut(&image, space);
+    explain::note(format!("Gamut check: {:.1}% of the pixels lie outside sRGB", outside));
+    if outside >= gamut::NOTICEABLE {
+        say!(
+            "🎨 Gamut: {:.1}% of the pixels are outside sRGB ({} source); saturated colours will look noticeably different",
+            outside,
+            space.name()
+        );
+    } else {
+        say!("🎨 Gamut: {:.1}% of the pixels are outside sRGB ({} source); no visible change expected", outside, space.name());
+    }
+}
+
+// Apply --resize and the pixel adjustments to an image decoded by the built-in backend
+fn process(img: DynamicImage, options: &ConvertOptions) -> DynamicImage {
+    let img = match options.resize {
+        Some(resize) => resize.apply(img),
+        None => img,
+    };
+    let img = options.adjust.apply(img);
+    match options.bit_depth {
+        Some(depth) => depth.apply(img),
+        None => img,
+    }
+}
+
+// EXR holds linear light: undo the transfer curve the source of `input_path` was encoded with
+fn linear_light(img: DynamicImage, input_path: &Path, options: &ConvertOptions) -> DynamicImage {
+    if !matches!(options.format, OutputFormat::Exr) {
+        return img;
+    }
+    let transfer = linear::transfer(input_path);
+    explain::note(format!("EXR: pixels made linear from the {:?} transfer curve", transfer));
+    linear::linearize(&img, transfer)
+}
+
+// Run an external tool. With pixel adjustments the tool writes a lossless PNG first
+// (16 bits per channel for --dither), which is adjusted and then encoded once in the
+// requested format. JPEG XL always takes that way, since cjxl encodes it, and so do
+// PDF, whose pages are put together here from every image the tool decoded, EXR,
+// whose pixels are made linear here (at 16 bits per channel), and ICO, whose sizes the
+// tools would not all write.
+fn convert_with_tool(
+    tool: fn(&dyn process::ProcessRunner, &Path, &Path, &ConvertOptions) -> Result<()>,
+    input_path: &Path,
+    partial_path: &Path,
+    options: &ConvertOptions,
+) -> Result<()> {
+    if options.adjust.is_empty() && !matches!(options.format, OutputFormat::Jxl | OutputFormat::Pdf | OutputFormat::Exr | OutputFormat::Ico) {
+        if options.auto_quality {
+            explain::note(format!(
+                "Quality: {} (the tool encodes directly, so --quality auto falls back to the default)",
+                options.quality.unwrap_or_default()
+            ));
         }
-        Err(img_error) => {
-            // Image crate doesn't support HEIC, fall back to external tools
-            println!("Rust image crate cannot handle this file, trying external tools...");
-            println!("Image crate error: {}", img_error);
+        return tool(&process::SystemRunner, input_path, partial_path, options);
+    }
+    let intermediate = partial_path.with_extension("tool.png");
+    tool(&process::SystemRunner, input_path, &intermediate, options)?;
+    let mut pages = Vec::new();
+    for frame in tool_frames(&intermediate) {
+        let img = image::open(&frame)
+            .with_context(|| format!("❌ Cannot read the tool output {}", frame.display()))
+            .code(ErrorCode::ToolFailed);
+        let _ = fs::remove_file(&frame);
+        pages.push(options.adjust.apply(img?));
+    }
+    if !options.adjust.is_empty() {
+        explain::note("Adjusted after the tool ran; metadata written by the tool is dropped");
+    }
+    if let OutputFormat::Pdf = options.format {
+        if pages.len() > 1 {
+            explain::note(format!("PDF: {} pages, one for each image in the file", pages.len()));
         }
+        let quality = options.quality_for(&pages[0]).unwrap_or(quality::JPEG_DEFAULT);
+        return pdf::save(&pages, partial_path, quality)
+            .with_context(|| format!("Failed to save the PDF to: {}", partial_path.display()))
+            .code(ErrorCode::SaveFailed);
     }
+    let img = &linear_light(pages.swap_remove(0), input_path, options);
+    save_image(img, partial_path, &options.format, options.quality_for(img), options.tiff_compression, options.jxl_distance)
+}
 
-    // Strategy 2: Try ImageMagick (most common and reliable)
-    if check_imagemagick_available() {
-        return convert_with_imagemagick(input_path, output_path);
+// The PNG files a tool wrote to `intermediate`. ImageMagick decodes every image of a
+// HEIC that holds several and numbers the files from 0 (photo.tool-0.png); without
+// any file the intermediate itself is returned, for the error message.
+fn tool_frames(intermediate: &Path) -> Vec<PathBuf> {
+    if intermediate.exists() {
+        return vec![intermediate.to_path_buf()];
     }
+    let stem = intermediate.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let frames: Vec<PathBuf> = (0..)
+        .map(|n| intermediate.with_file_name(format!("{}-{}.png", stem, n)))
+        .take_while(|frame| frame.exists())
+        .collect();
+    if frames.is_empty() { vec![intermediate.to_path_buf()] } else { frames }
+}
 
-    // Strategy 3: Try FFmpeg (alternative option)
-    if check_ffmpeg_available() {
-        return convert_with_ffmpeg(input_path, output_path);
+impl ConvertOptions {
+    // Whether a tool decodes at 16 bits per channel: for --bit-depth 16, for full
+    // precision when --dither reduces, and for the float pixels of EXR
+    fn sixteen_bits(&self) -> bool {
+        self.adjust.dither.is_some() || self.bit_depth == Some(BitDepth::Sixteen) || matches!(self.format, OutputFormat::Exr)
     }
 
-    // No conversion methods available - provide helpful error message
-    Err(anyhow!(
-        "HEIC format support is not available.\n\
-         \n\
-         To enable HEIC conversion, install one of these tools:\n\
-         \n\
-         1. ImageMagick:\n\
-            brew install imagemagick\n\
-         \n\
-         2. FFmpeg:\n\
-            brew install ffmpeg\n\
-         \n\
-         3. System libheif library:\n\
-            brew install libheif\n\
-         \n\
-         Alternative solutions:\n\
-         - Use online converters like convertio.co or cloudconvert.com\n\
-         - Use the macOS Preview app: Open HEIC → Export as PNG/JPEG\n\
-         - Use Photos app: Export as JPEG"
-    ))
+    // The quality to encode `img` with; --quality auto measures the image
+    fn quality_for(&self, img: &DynamicImage) -> Option<u8> {
+        if !self.auto_quality {
+            return self.quality;
+        }
+        let busyness = quality::busyness(img);
+        let tuned = quality::auto(busyness);
+        explain::note(format!("Quality: {} (--quality auto, busyness {:.1})", tuned, busyness));
+        Some(tuned)
+    }
 }
 
+// rav1e speed for AVIF, 1 (smallest files) to 10 (fastest): within a few percent of the
+// size of its default 4 in a fraction of the time
+const AVIF_SPEED: u8 = 6;
+
 // Save a DynamicImage to disk in the specified format
-fn save_image(img: &DynamicImage, output_path: &Path, format: &OutputFormat) -> Result<()> {
-    // Save the image using the specified format and provide detailed error context
-    img.save_with_format(output_path, format.to_image_format())
+fn save_image(
+    img: &DynamicImage,
+    output_path: &Path,
+    format: &OutputFormat,
+    quality: Option<u8>,
+    tiff_compression: tiff_writer::TiffCompression,
+    jxl_distance: Option<f32>,
+) -> Result<()> {
+    // JPEG has no alpha channel and only 8 bits per channel: keep grey images grey,
+    // drop the alpha and reduce the depth of everything else. The JPEG pages of a PDF
+    // are made the same way, and so is BMP, since older readers do not expect an alpha
+    // channel. WebP and AVIF keep the alpha but have 8 bits per channel as well. EXR
+    // holds 32-bit float pixels, which linear_light has made already for conversions.
+    // PPM drops the alpha and keeps the depth itself, see ppm.rs; the icon sizes of
+    // ICO are made in ico.rs.
+    let flattened = match (format, img.color()) {
+        (OutputFormat::Exr, ColorType::Rgb32F | ColorType::Rgba32F) => None,
+        (OutputFormat::Exr, _) if img.color().has_alpha() => Some(DynamicImage::ImageRgba32F(img.to_rgba32f())),
+        (OutputFormat::Exr, _) => Some(DynamicImage::ImageRgb32F(img.to_rgb32f())),
+        (OutputFormat::Png | OutputFormat::Tiff | OutputFormat::Jxl | OutputFormat::Ppm | OutputFormat::Ico, _)
+        | (_, ColorType::L8 | ColorType::Rgb8) => None,
+        (OutputFormat::Webp | OutputFormat::Avif, ColorType::La8 | ColorType::Rgba8) => None,
+        (OutputFormat::Webp | OutputFormat::Avif, _) if img.color().has_alpha() => {
+            Some(DynamicImage::ImageRgba8(img.to_rgba8()))
+        }
+        (_, ColorType::La8 | ColorType::L16 | ColorType::La16) => Some(DynamicImage::ImageLuma8(img.to_luma8())),
+        _ => Some(DynamicImage::ImageRgb8(img.to_rgb8())),
+    };
+    let img = flattened.as_ref().unwrap_or(img);
+    if let OutputFormat::Jxl = format {
+        return jxl::save(&process::SystemRunner, img, output_path, quality, jxl_distance);
+    }
+
+    // Save the image using the specified format and provide detailed error context;
+    // the built-in WebP encoder is lossless only
+    let result = match (format, quality) {
+        (OutputFormat::Jpg | OutputFormat::Jpeg, Some(quality)) => fs::File::create(output_path)
+            .map_err(image::ImageError::IoError)
+            .and_then(|file| {
+                img.write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(std::io::BufWriter::new(file), quality))
+            }),
+        // One encoder thread under --eco
+        (OutputFormat::Avif, quality) => fs::File::create(output_path).map_err(image::ImageError::IoError).and_then(|file| {
+            let quality = quality.unwrap_or(quality::AVIF_DEFAULT);
+            let encoder = image::codecs::avif::AvifEncoder::new_with_speed_quality(std::io::BufWriter::new(file), AVIF_SPEED, quality)
+                .with_num_threads(eco::active().then_some(1));
+            img.write_with_encoder(encoder)
+        }),
+        (OutputFormat::Tiff, _) => tiff_writer::save(img, output_path, tiff_compression),
+        (OutputFormat::Ppm, _) => ppm::save(img, output_path),
+        (OutputFormat::Ico, _) => ico::save(img, output_path),
+        (OutputFormat::Pdf, quality) => pdf::save(std::slice::from_ref(img), output_path, quality.unwrap_or(quality::JPEG_DEFAULT)),
+        _ => img.save_with_format(output_path, format.to_image_format()),
+    };
+    result
         .with_context(|| {
             format!(
                 "Failed to save image to: {}\n\
@@ -330,27 +1429,32 @@ fn save_image(img: &DynamicImage, output_path: &Path, format: &OutputFormat) ->
                  - Output directory doesn't exist", 
                 output_path.display()
             )
-        })?;
+        })
+        .code(ErrorCode::SaveFailed)?;
 
-    println!("Successfully converted to {}", output_path.display());
     Ok(())
 }
 
 // Check system requirements and provide early feedback about available conversion methods
 fn check_system_requirements() -> Result<()> {
+    if !cfg!(feature = "external-tools") {
+        say!("ℹ️  This build converts with the built-in decoder only (no external tool support).");
+        return Ok(());
+    }
+
     let imagemagick_available = check_imagemagick_available();
     let ffmpeg_available = check_ffmpeg_available();
     
     // If no external tools are available, warn the user early
     if !imagemagick_available && !ffmpeg_available {
-        println!("⚠️  Warning: No HEIC conversion tools detected!");
-        println!();
-        println!("The Rust image crate has limited HEIC support. For best results, install:");
-        println!("  • ImageMagick: brew install imagemagick");
-        println!("  • FFmpeg: brew install ffmpeg");
-        println!();
-        println!("Attempting conversion anyway...");
-        println!();
+        say!("⚠️  Warning: No HEIC conversion tools detected!");
+        say!();
+        say!("The Rust image crate has limited HEIC support. For best results, install:");
+        say!("  • ImageMagick: brew install imagemagick");
+        say!("  • FFmpeg: brew install ffmpeg");
+        say!();
+        say!("Attempting conversion anyway...");
+        say!();
     } else {
         let mut available_tools = Vec::new();
         if imagemagick_available {
@@ -359,7 +1463,7 @@ fn check_system_requirements() -> Result<()> {
         if ffmpeg_available {
             available_tools.push("FFmpeg");
         }
-        println!("✅ Conversion tools available: {}", available_tools.join(", "));
+        say!("✅ Conversion tools available: {}", available_tools.join(", "));
     }
     
     Ok(())
@@ -367,6 +1471,12 @@ fn check_system_requirements() -> Result<()> {
 
 // Display an ASCII art banner for the application
 fn show_banner() {
+    if ui::ascii_only() {
+        say!("HEIC Convert");
+        say!();
+        return;
+    }
+
     // ASCII art banner displaying "HEIC CONVERT"
     let banner = String::from(
         "\n
@@ -395,24 +1505,731 @@ fn show_banner() {
 
 // Main application entry point
 fn main() -> Result<()> {
+    crash_report::install();
+    ui::init_from_args();
+    let result = run();
+
+    // Print the error ourselves so --ascii-only also covers the final message
+    if ui::ascii_only()
+        && let Err(e) = &result
+    {
+        let message = format!("{:?}", e);
+        say_err!("Error: {}", message.trim_start_matches("❌ "));
+        summary::print_line();
+        std::process::exit(1);
+    }
+    summary::print_line();
+    result
+}
+
+fn run() -> Result<()> {
+    // Parse command-line arguments; --help, --version and usage errors still get the banner
+    let mut cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        // --version --json: a machine-readable capability report instead of the version line
+        Err(e)
+            if e.kind() == clap::error::ErrorKind::DisplayVersion
+                && std::env::args_os().any(|arg| arg == "--json") =>
+        {
+            return capabilities::print_json();
+        }
+        Err(e) => {
+            toml_extract::main();
+            show_banner();
+            e.exit();
+        }
+    };
+    units::set_unit_system(cli.units);
+    if let Some(list) = &cli.files_from {
+        let listed = inputs::read_list(list)?;
+        cli.files.extend(listed);
+    }
+    if let [input] = cli.arguments().as_slice() {
+        cli.input = Some(input.clone());
+    }
+
+    // The man page goes to stdout, so it must not be mixed with the banner
+    if let Some(Commands::Man) = &cli.command {
+        print!("{}", help::render_man());
+        return Ok(());
+    }
+
     // Initialize the application by displaying version information and banner
     toml_extract::main();  // Display version information from Cargo.toml
     show_banner();         // Display ASCII art banner
 
-    // Parse command-line arguments
-    let cli = Cli::parse();
-
     // If user requested detailed help, show it and exit
     if cli.bighelp {
-        print_bighelp();
+        help::print_all();
         return Ok(());
     }
 
-    // Check system requirements and available conversion tools
+    // `extract` converts like a normal run, so it is started with the conversions below
+    let extract = match &cli.command {
+        Some(Commands::Extract { from, to }) => Some((from.clone(), to.clone())),
+        _ => None,
+    };
+
+    // Run a subcommand instead of a conversion when one was given
+    if let Some(command) = cli.command.as_ref().filter(|command| !command.converts()) {
+        return match command {
+            Commands::Help { topic: None } => {
+                help::print_index();
+                Ok(())
+            }
+            Commands::Help { topic: Some(name) } => match help::find(name) {
+                Some(topic) => {
+                    help::print_topic(topic);
+                    Ok(())
+                }
+                None => Err(anyhow!(
+                    "❌ Unknown help topic: {}\n\
+                     Available topics: {}",
+                    name,
+                    help::TOPICS.iter().map(|t| t.name).collect::<Vec<_>>().join(", ")
+                )),
+            },
+            Commands::Explain { code } => error_codes::explain(code.as_deref()),
+            Commands::Man => Ok(()), // Printed before the banner above
+            Commands::Extract { .. } => Ok(()), // Handled with the conversions
+            #[cfg(feature = "watch")]
+            Commands::Watch { .. } => Ok(()), // Handled with the conversions
+            Commands::CleanTemp => temp_workspace::clean_temp(cli.tmpdir.as_deref()),
+            Commands::Init { force } => init::run(cli.config.as_deref(), *force),
+            Commands::Canonical { image, output } => {
+                canonical::write_canonical(image, output.as_deref()).map(|_| ())
+            }
+            Commands::Info { images, stats } => info::run(images, *stats, cli.tmpdir.as_deref()),
+            Commands::Validate { path, decode, repair } => {
+                validate::run(path, *decode, *repair, cli.tmpdir.as_deref())
+            }
+            Commands::Stats { enable, disable } => stats::run(*enable, *disable),
+            #[cfg(feature = "self-update")]
+            Commands::SelfUpdate { check } => self_update::run(*check),
+        };
+    }
+
+    // Extension repair only renames files, so it needs no conversion tools
+    if let Some(dir) = &cli.fix_extensions {
+        return extension_fix::fix_extensions(dir, cli.normalize_names);
+    }
+
+    // The orphan report only inspects and moves files, so it needs no conversion tools either
+    if let Some(dir) = &cli.orphan_report {
+        return orphans::report_orphans(dir, cli.orphan_review_dir.as_deref());
+    }
+
+    // Advice only decodes the input; nothing is written
+    if cli.advise {
+        let input = cli
+            .input
+            .as_deref()
+            .ok_or_else(|| anyhow!("❌ --advise needs an image: heic_convert -i photo.heic --advise"))
+            .code(ErrorCode::InputMissing)?;
+        return advise::run(input, cli.tmpdir.as_deref());
+    }
+
+    // stdout can carry only one kind of data, and a stream has no directory next to it
+    if writes_to_stdout(&cli) {
+        let conflicting = [
+            (cli.json, "--json"),
+            (cli.delete_original || cli.trash_original, "--delete-original or --trash-original"),
+            (cli.split_output.is_some(), "--split-output"),
+            (cli.album_manifest, "--album-manifest"),
+        ];
+        if let Some((_, option)) = conflicting.iter().find(|(used, _)| *used) {
+            return Err(anyhow!("❌ -o - cannot be combined with {}", option));
+        }
+        #[cfg(feature = "upload")]
+        if cli.upload.is_some() || cli.import_to.is_some() {
+            return Err(anyhow!("❌ -o - cannot be combined with --upload or --import-to"));
+        }
+    }
+
+    // Read the configuration once; its rules are checked before any file is touched
+    let config = config::load(cli.config.as_deref()).code(ErrorCode::Config)?;
+    let rules = rules::compile(&config.rules).code(ErrorCode::Config)?;
+    let cli = with_defaults(format_from_output(cli)?, &config.defaults).code(ErrorCode::Config)?;
+
+    // The e-mail report needs SMTP settings; check them before spending time on the conversion
+    #[cfg(feature = "email")]
+    let smtp = match &cli.email_report {
+        Some(_) => Some(config.smtp.ok_or_else(|| {
+            anyhow!(
+                "❌ --email-report needs an [smtp] section in the configuration file.\n\
+                 Example:\n\
+                 [smtp]\n\
+                 host = \"smtp.example.com\"\n\
+                 username = \"me@example.com\"\n\
+                 password_env = \"SMTP_PASSWORD\"\n\
+                 from = \"heic_convert <me@example.com>\""
+            )
+        }).code(ErrorCode::Config)?),
+        None => None,
+    };
+
+    // Hold the conversion back until the allowed time window opens; watch starts watching
+    // at once and holds back the files that arrive instead
+    #[cfg(feature = "watch")]
+    let watching = matches!(cli.command, Some(Commands::Watch { .. }));
+    #[cfg(not(feature = "watch"))]
+    let watching = false;
+    if let Some(schedule) = cli.schedule.as_ref().filter(|_| !watching) {
+        schedule.wait_until_open();
+    }
+
+    // The time budget starts with the work, not with the wait for the schedule
+    let deadline = cli.time_budget.map(|budget| (Instant::now(), budget));
+
+    if cli.explain {
+        explain::enable();
+    }
+
+    // Restrict the conversion (and the tools it launches) to the requested core type;
+    // --eco moves to the efficiency cores unless the user picked cores explicitly
+    let core_selection = if cli.eco && eco::engage() && cli.cores == cores::CoreSelection::All {
+        cores::CoreSelection::Efficiency
+    } else {
+        cli.cores
+    };
+    cores::apply(core_selection);
+
+    // Check system requirements and available conversion tools, once for all the inputs
     check_system_requirements()?;
 
+    subscribe_reporting(&cli, #[cfg(feature = "email")] smtp);
+
+    // Images rescued from a container are converted one by one like normal inputs
+    if let Some(container) = &cli.extract_embedded {
+        let extracted = embedded::extract(container)?;
+        let converted = convert_each(&cli, &rules, &config.devices, &extracted, deadline)?;
+        say!("✅ Converted {} of {} images extracted from {}", converted, extracted.len(), container.display());
+        return Ok(());
+    }
+
+    // Photos pulled off a device go into date folders with a verification manifest
+    if let Some((from, to)) = &extract {
+        let started = Instant::now();
+        let mut tally = summary::Tally::default();
+        let result = extract::run(&cli, &rules, &config.devices, from, to, &mut tally);
+        publish_batch(&cli, tally, started);
+        return result;
+    }
+
+    // A watched folder has its new HEIC files converted until the run is interrupted
+    #[cfg(feature = "watch")]
+    if let Some(Commands::Watch { dir, to, recursive }) = &cli.command {
+        return watch::run(&cli, &rules, &config.devices, dir, to.as_deref(), *recursive);
+    }
+
+    // Photos are restored from the backup under their real names, then converted
+    #[cfg(feature = "ios-backup")]
+    if let Some(backup) = &cli.ios_backup {
+        let photos = ios_backup::photos(backup).code(ErrorCode::IosBackup)?;
+        let name = backup.file_name().unwrap_or_default().to_string_lossy();
+        let output_dir = PathBuf::from(format!("{}_photos", name));
+        say!("Found {} HEIC photos in the backup; restoring them to {}", photos.len(), output_dir.display());
+        let restored = photos
+            .iter()
+            .map(|photo| ios_backup::restore(photo, &output_dir))
+            .collect::<Result<Vec<_>>>()?;
+        let converted = convert_each(&cli, &rules, &config.devices, &restored, deadline)?;
+        say!("✅ Converted {} of {} photos restored into {}", converted, restored.len(), output_dir.display());
+        return Ok(());
+    }
+
+    // ZIP archives are read one photo at a time instead of being unpacked first
+    let arguments = cli.arguments();
+    if arguments.iter().any(|input| archive::is_archive(input)) {
+        if !arguments.iter().all(|input| archive::is_archive(input)) {
+            return Err(anyhow!("❌ Convert ZIP archives in a run of their own, without other inputs"));
+        }
+        if cli.output.is_some() {
+            return Err(anyhow!("❌ An archive holds many photos; name the folder for them with --output-dir instead of -o"));
+        }
+        let started = Instant::now();
+        let mut tally = summary::Tally::default();
+        let result = arguments.iter().try_for_each(|zip| archive::run(&cli, &rules, &config.devices, zip, &mut tally));
+        publish_batch(&cli, tally, started);
+        return result;
+    }
+
+    // Several inputs, a folder or a wildcard pattern: every HEIC file they stand for is
+    // converted next to its original
+    if let Some(photos) = inputs::collect(&arguments, cli.recursive)? {
+        let photos = inputs::exclude(photos, &cli.exclude, &arguments)?;
+        let source = match arguments.as_slice() {
+            [input] => format!("{} {}", if input.is_dir() { "in" } else { "matching" }, input.display()),
+            _ => format!("in the {} inputs given", arguments.len()),
+        };
+        say!("Found {} HEIC files {}", photos.len(), source);
+        // Date folders take the place of the input's own subfolders
+        let flat = cli.flatten || cli.organize_by_date;
+        let roots = if flat { Vec::new() } else { arguments.iter().filter_map(|input| inputs::root(input)).collect() };
+        // --archive-output: the outputs are gathered in a scratch folder and packed at the end
+        let pack = cli.archive_output.as_deref().map(|path| pack::Pack::create(path, cli.tmpdir.as_deref())).transpose()?;
+        let output_dir = pack.as_ref().map(pack::Pack::staging).or(cli.output_dir);
+        let cli = Cli { roots, output_dir, ..cli };
+        let converted = convert_each(&cli, &rules, &config.devices, &photos, deadline);
+        if let (Some(pack), Some(path)) = (pack, &cli.archive_output) {
+            let packed = pack.finish()?;
+            if packed > 0 {
+                say!("📦 Packed {} files into {}", packed, path.display());
+            }
+        }
+        let converted = converted?;
+        say!("✅ Converted {} of {} HEIC files {}", converted, photos.len(), source);
+        return Ok(());
+    }
+    if cli.archive_output.is_some() {
+        return Err(anyhow!("❌ --archive-output packs the outputs of a multi-file run; use -o for a single image"));
+    }
+
+    // Time the conversion so the run summary can report how long it took
+    if let Some(input) = &cli.input {
+        events::publish(events::Event::FileDiscovered(input));
+    }
+    let started = Instant::now();
+    let cli = output_for_many(&cli, 1)?;
+    let result = run_conversion(&cli, &rules, &config.devices).or_else(keep_existing);
+    let summary = RunSummary::from_result(&cli, &result, started.elapsed(), summary::take_warnings());
+    events::publish(events::Event::RunFinished(&summary));
+    if let Some(path) = &cli.report {
+        report::write(path, cli.input.as_slice())?;
+    }
+    result.map(|_| ())
+}
+
+// -o photo.jpg selects JPEG without -f; a contradicting -f, or an image type we cannot
+// write, is an error rather than JPEG bytes behind a .png name
+fn format_from_output(mut cli: Cli) -> Result<Cli> {
+    let Some(output) = cli.output.as_deref().filter(|_| !writes_to_stdout(&cli)) else {
+        return Ok(cli);
+    };
+    let extension = output.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    match (OutputFormat::from_extension(output), &cli.format) {
+        (Some(named), Some(given)) if named.extension() != given.extension() => Err(anyhow!(
+            "❌ -f {} does not match the output name {}\n\
+             Use -o {}, or drop -f to write {}.",
+            given.extension(),
+            output.display(),
+            output.with_extension(given.extension()).display(),
+            named.extension().to_uppercase()
+        )),
+        (Some(named), None) => {
+            cli.format = Some(named);
+            Ok(cli)
+        }
+        (None, _) if file_type::IMAGE_EXTENSIONS.contains(&extension.as_str()) => {
+            let mut supported: Vec<&str> = OutputFormat::value_variants().iter().map(OutputFormat::extension).collect();
+            supported.dedup(); // jpg and jpeg
+            Err(anyhow!(
+                "❌ Cannot write .{} files: {}\n\
+                 The supported output formats are {}.",
+                extension,
+                output.display(),
+                supported.join(", ")
+            ))
+        }
+        _ => Ok(cli),
+    }
+}
+
+// Fill in the options the command line leaves out from [defaults] in the configuration file
+fn with_defaults(mut cli: Cli, defaults: &config::Defaults) -> Result<Cli> {
+    if cli.format.is_none()
+        && let Some(format) = &defaults.format
+    {
+        let format = OutputFormat::from_str(format, true)
+            .map_err(|e| anyhow!("❌ Invalid format in [defaults] of the configuration file: {}", e))?;
+        cli.format = Some(format);
+    }
+    if let Some(quality) = defaults.quality.filter(|_| cli.quality.is_none()) {
+        if !(1..=100).contains(&quality) {
+            return Err(anyhow!("❌ Invalid quality in [defaults] of the configuration file: {} (expected 1-100)", quality));
+        }
+        cli.quality = Some(quality::Quality::Fixed(quality));
+    }
+    if cli.output.is_none() && cli.output_dir.is_none() {
+        cli.output_dir = defaults.output_dir.clone();
+    }
+    Ok(cli)
+}
+
+// The features that report on a run, in the order they hear about it
+fn subscribe_reporting(cli: &Cli, #[cfg(feature = "email")] smtp: Option<config::SmtpConfig>) {
+    // The run summary lists the warnings of the conversion
+    summary::collect_warnings();
+
+    // Multi-file runs number their files and show how much of the data is done
+    let (mut files, mut started, mut total_bytes, mut done_bytes) = (0, 0, 0u64, 0u64);
+    let size = |path: &Path| fs::metadata(path).map_or(0, |m| m.len());
+    events::subscribe(move |event: &events::Event| match event {
+        events::Event::FileDiscovered(input) => {
+            files += 1;
+            total_bytes += size(input);
+        }
+        events::Event::ConversionStarted(input) if files > 1 => {
+            started += 1;
+            let percent = (done_bytes * 100).checked_div(total_bytes).unwrap_or(0);
+            say!("[{}/{}, {}%] {}", started, files, percent, input.display());
+        }
+        events::Event::ConversionFinished { input, .. } | events::Event::ConversionSkipped { input, .. } => {
+            done_bytes += size(input)
+        }
+        _ => {}
+    });
+
+    // --report: every conversion goes into the manifest written at the end of the run
+    if cli.report.is_some() {
+        report::collect();
+    }
+
+    // Add each conversion to the local statistics (does nothing unless they were enabled)
+    events::subscribe(|event: &events::Event| {
+        if let events::Event::ConversionFinished { input, output, elapsed, .. } = event {
+            stats::record(Some(input), *output, *elapsed);
+        }
+    });
+
+    // --json: the summary is the only thing on stdout, also when the conversion failed.
+    // Headless runs end with the same line after their messages, so it is printed last.
+    if cli.json || (ui::headless() && !ui::stdout_is_data()) {
+        events::subscribe(|event: &events::Event| {
+            if let events::Event::RunFinished(summary) = event {
+                summary::keep_line(summary);
+            }
+        });
+    }
+
+    // Tell the webhook about the outcome, whether the run succeeded or failed
+    #[cfg(feature = "webhook")]
+    if let Some(url) = cli.webhook.clone() {
+        events::subscribe(move |event: &events::Event| {
+            if let events::Event::RunFinished(summary) = event
+                && let Err(e) = webhook::send(&url, summary)
+            {
+                events::warn(format!("Webhook notification failed: {}", e));
+            }
+        });
+    }
+
+    // Mail the report for scheduled server-side jobs
+    #[cfg(feature = "email")]
+    if let (Some(recipient), Some(smtp)) = (cli.email_report.clone(), smtp) {
+        events::subscribe(move |event: &events::Event| {
+            if let events::Event::RunFinished(summary) = event
+                && let Err(e) = email::send_report(&recipient, summary, &smtp)
+            {
+                events::warn(format!("E-mail report failed: {}", e));
+            }
+        });
+    }
+}
+
+// Tell the subscribers how a multi-file run ended
+fn publish_batch(cli: &Cli, tally: summary::Tally, started: Instant) {
+    let summary = RunSummary::from_tally(cli, tally, started.elapsed(), summary::take_warnings());
+    events::publish(events::Event::RunFinished(&summary));
+}
+
+// Where --time-budget lists the files it did not get to
+const REMAINING_LIST: &str = "heic_convert-remaining.txt";
+
+// -o as a folder (existing, or written with a trailing slash) takes the generated names
+// like --output-dir, for one input as for many; one file name would be overwritten by
+// every conversion of a run over several inputs
+fn output_for_many(cli: &Cli, inputs: usize) -> Result<Cli> {
+    let Some(output) = &cli.output else {
+        return Ok(cli.clone());
+    };
+    if output.is_dir() || paths::names_directory(output) {
+        return Ok(Cli { output: None, output_dir: Some(output.clone()), ..cli.clone() });
+    }
+    if inputs < 2 {
+        return Ok(cli.clone());
+    }
+    if writes_to_stdout(cli) {
+        return Err(anyhow!("❌ -o - carries one image, but this run converts {}; use a folder with -o DIR/", inputs));
+    }
+    Err(anyhow!(
+        "❌ -o {} names one file, but this run converts {} images\n\
+         Each conversion would overwrite the previous one. Use a folder instead\n\
+         (-o {}/ or --output-dir), or --name-expr to compute a name for each image.",
+        output.display(),
+        inputs,
+        output.with_extension("").display()
+    ))
+}
+
+// Convert several inputs with the same options, reporting failures as they happen.
+// With a time budget (start, length) no new file is started once it is used up.
+// Returns how many inputs were attempted.
+fn convert_each(
+    cli: &Cli,
+    rules: &[rules::Rule],
+    devices: &std::collections::BTreeMap<String, String>,
+    inputs: &[PathBuf],
+    budget: Option<(Instant, std::time::Duration)>,
+) -> Result<usize> {
+    let run_started = Instant::now();
+    let cli = &output_for_many(cli, inputs.len())?;
+    let mut inputs = inputs.to_vec();
+    // Files that cannot be read sort as empty; their conversion reports the problem
+    let size = |path: &PathBuf| fs::metadata(path).map_or(0, |m| m.len());
+    match cli.order {
+        Some(Order::SmallestFirst) => inputs.sort_by_cached_key(size),
+        Some(Order::LargestFirst) => inputs.sort_by_cached_key(|path| std::cmp::Reverse(size(path))),
+        None => {}
+    }
+
+    // --resume: the files the interrupted run finished are left out
+    let progress = resume::Progress::open(cli, &inputs)?;
+    if cli.resume {
+        let finished = progress.finished()?;
+        let total = inputs.len();
+        inputs.retain(|input| !finished.contains(&resume::key(input)));
+        say!("▶️  Resuming: {} of {} files were finished before, {} to go", total - inputs.len(), total, inputs.len());
+    }
+
+    // --skip-duplicates: copies wait until the input with the same content is done
+    let listed = inputs.clone();
+    let duplicates = if cli.skip_duplicates || cli.link_duplicates {
+        let (unique, duplicates) = duplicates::find(&inputs);
+        if !duplicates.is_empty() {
+            say!("Found {} copies of other inputs with the same content", duplicates.len());
+        }
+        inputs = unique;
+        duplicates
+    } else {
+        Vec::new()
+    };
+    let total = listed.len();
+
+    // Linked copies go through a conversion of their own, which finds their output name
+    let linked = duplicates.iter().map(|(copy, _)| copy).filter(|_| cli.link_duplicates);
+    for input in inputs.iter().chain(linked) {
+        events::publish(events::Event::FileDiscovered(input));
+    }
+
+    // Each worker takes the next file until none are left or the time budget is used up,
+    // so files still start in the chosen order
+    let jobs = match cli.jobs {
+        0 => std::thread::available_parallelism().map_or(1, usize::from),
+        jobs => jobs,
+    };
+    // --eco keeps the machine cool, which several conversions at once would not, and the
+    // numbered steps of --explain only make sense for one file at a time
+    let jobs = if eco::active() || explain::enabled() { 1 } else { jobs.clamp(1, inputs.len().max(1)) };
+    let next = AtomicUsize::new(0);
+    let worker = || {
+        let mut outcomes = Vec::new();
+        while budget.is_none_or(|(started, length)| started.elapsed() < length) {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(input) = inputs.get(index) else { break };
+            let output_dir = cli.output_dir.as_deref().map(|dir| paths::mirrored(dir, input, &cli.roots));
+            let single = Cli { input: Some(input.clone()), output_dir, position: index, ..cli.clone() };
+            let outcome = run_conversion(&single, rules, devices);
+            match &outcome {
+                Err(e) if !e.is::<Skipped>() => say_err!("{:?}", e),
+                _ => progress.record(input),
+            }
+            outcomes.push((index, outcome));
+        }
+        outcomes
+    };
+    let mut outcomes = std::thread::scope(|scope| {
+        let others: Vec<_> = (1..jobs).map(|_| scope.spawn(worker)).collect();
+        let mut outcomes = worker();
+        for other in others {
+            outcomes.extend(other.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)));
+        }
+        outcomes
+    });
+    outcomes.sort_by_key(|(index, _)| *index);
+    let mut outcomes: Vec<(&PathBuf, Result<PathBuf>)> =
+        outcomes.into_iter().map(|(index, outcome)| (&inputs[index], outcome)).collect();
+
+    // Files are handed out in order, so the ones never started are those after the last
+    let started = outcomes.len();
+
+    // Each copy follows the input it is a copy of: its output is linked with
+    // --link-duplicates, or it is skipped. Copies of inputs never started wait with them.
+    let mut waiting = Vec::new();
+    let mut copies = Vec::new();
+    for (position, (copy, original)) in duplicates.iter().enumerate() {
+        let Some((_, result)) = outcomes.iter().find(|(input, _)| *input == original) else {
+            waiting.push(copy);
+            continue;
+        };
+        let output = match result {
+            Ok(output) => Some(output),
+            Err(e) => e.downcast_ref::<Skipped>().map(|Skipped(output)| output),
+        };
+        let outcome = match output.filter(|_| cli.link_duplicates) {
+            Some(output) => {
+                let output_dir = cli.output_dir.as_deref().map(|dir| paths::mirrored(dir, copy, &cli.roots));
+                let single = Cli {
+                    input: Some(copy.clone()),
+                    output_dir,
+                    position: inputs.len() + position,
+                    duplicate_of: Some(output.clone()),
+                    ..cli.clone()
+                };
+                run_conversion(&single, rules, devices)
+            }
+            None => {
+                say!("⏭️  Skipped {}: same content as {}", copy.display(), original.display());
+                Err(Duplicate(original.clone()).into())
+            }
+        };
+        events::publish(events::Event::DuplicateFound { input: copy, original });
+        match &outcome {
+            Err(e) if !e.is::<Skipped>() && !e.is::<Duplicate>() => say_err!("{:?}", e),
+            _ => progress.record(copy),
+        }
+        copies.push((copy, outcome));
+    }
+    outcomes.extend(copies);
+
+    let attempted = outcomes.len();
+    if let Some((_, length)) = budget
+        && attempted < total
+    {
+        let remaining: Vec<&PathBuf> = inputs[started..].iter().chain(waiting).collect();
+        let list: String = remaining.iter().map(|path| format!("{}\n", path.display())).collect();
+        fs::write(REMAINING_LIST, list).with_context(|| format!("❌ Cannot write {}", REMAINING_LIST))?;
+        say!(
+            "⏱️  Time budget of {} used up after {} of {} images; {} left, listed in {}",
+            units::format_duration(length),
+            attempted,
+            total,
+            remaining.len(),
+            REMAINING_LIST
+        );
+    }
+
+    // The outcome of every file, after the conversion messages have scrolled by
+    if outcomes.len() > 1 {
+        say!();
+        say!("Results:");
+        for (input, outcome) in &outcomes {
+            match outcome {
+                Ok(output) => say!("  ✅ {} → {}", input.display(), output.display()),
+                Err(e) if e.is::<Skipped>() || e.is::<Duplicate>() => say!("  ⏭️  {}: skipped, {}", input.display(), e),
+                Err(e) => {
+                    let headline = headline(e);
+                    match error_codes::code_of(e) {
+                        Some(code) => say!("  ❌ {}: {} ({})", input.display(), headline, code),
+                        None => say!("  ❌ {}: {}", input.display(), headline),
+                    }
+                }
+            }
+        }
+    }
+    let count = |kind: fn(&anyhow::Error) -> bool| outcomes.iter().filter(|(_, outcome)| outcome.as_ref().is_err_and(kind)).count();
+    let skipped = count(|e| e.is::<Skipped>());
+    let copies = count(|e| e.is::<Duplicate>());
+    let failed = outcomes.iter().filter(|(_, outcome)| outcome.is_err()).count() - skipped - copies;
+    let mut tally = summary::Tally {
+        files: total,
+        converted: attempted - failed - skipped - copies,
+        skipped: skipped + copies,
+        failures: Vec::new(),
+    };
+    for (input, outcome) in &outcomes {
+        if let Err(e) = outcome
+            && !e.is::<Skipped>()
+            && !e.is::<Duplicate>()
+        {
+            tally.fail(input, e);
+        }
+    }
+    if let Some(path) = &cli.report {
+        report::write(path, &listed)?;
+    }
+    if failed == 0 && attempted == total {
+        progress.finish();
+    } else if attempted > failed {
+        say!("▶️  Run the same command with --resume to go on without redoing the finished files");
+    }
+    if skipped > 0 {
+        say!("⏭️  Skipped {} of {} images whose output already exists (--skip-existing)", skipped, attempted);
+    }
+    if copies > 0 {
+        say!("⏭️  Skipped {} of {} images with the same content as another one (--skip-duplicates)", copies, attempted);
+    }
+    publish_batch(cli, tally, run_started);
+    if failed > 0 {
+        return Err(anyhow!("❌ {} of {} images could not be converted", failed, attempted));
+    }
+    Ok(attempted - skipped - copies)
+}
+
+// First line of an error, without the ❌ it starts with
+fn headline(error: &anyhow::Error) -> String {
+    let message = format!("{:#}", error);
+    message.lines().next().unwrap_or_default().trim_start_matches("❌").trim().to_string()
+}
+
+// Why --skip-existing left an input alone: its output is already there
+#[derive(Debug)]
+struct Skipped(PathBuf);
+
+impl std::fmt::Display for Skipped {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} already exists", self.0.display())
+    }
+}
+
+impl std::error::Error for Skipped {}
+
+// Why --skip-duplicates left an input alone: this input of the run has the same content
+#[derive(Debug)]
+struct Duplicate(PathBuf);
+
+impl std::fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "same content as {}", self.0.display())
+    }
+}
+
+impl std::error::Error for Duplicate {}
+
+// Outputs --rename-on-conflict has handed out, so parallel conversions (--jobs) that
+// want the same name do not both pick the first free number
+static CLAIMED_OUTPUTS: std::sync::Mutex<std::collections::BTreeSet<PathBuf>> =
+    std::sync::Mutex::new(std::collections::BTreeSet::new());
+
+// Callers that only need the output take the one --skip-existing found in place
+fn keep_existing(error: anyhow::Error) -> Result<PathBuf> {
+    error.downcast::<Skipped>().map(|Skipped(output)| output)
+}
+
+// Convert the input of `cli`, announcing the start and the outcome to the subscribers.
+// Returns the path of the written image, or a `Skipped` error with --skip-existing.
+fn run_conversion(cli: &Cli, rules: &[rules::Rule], devices: &std::collections::BTreeMap<String, String>) -> Result<PathBuf> {
+    let started = Instant::now();
+    if let Some(input) = &cli.input {
+        events::publish(events::Event::ConversionStarted(input));
+    }
+    let result = convert_input(cli, rules, devices);
+    if let Some(input) = &cli.input {
+        if let Some(Skipped(output)) = result.as_ref().err().and_then(|e| e.downcast_ref::<Skipped>()) {
+            events::publish(events::Event::ConversionSkipped { input, output });
+        } else {
+            let (output, error) = (result.as_ref().ok().map(PathBuf::as_path), result.as_ref().err());
+            events::publish(events::Event::ConversionFinished { input, output, error, elapsed: started.elapsed() });
+        }
+    }
+    result
+}
+
+// Validate the command-line input and output, convert the image and hand the result
+// to any configured destinations. Returns the path of the written image.
+fn convert_input(cli: &Cli, rules: &[rules::Rule], devices: &std::collections::BTreeMap<String, String>) -> Result<PathBuf> {
     // Validate that input file was provided
-    let input_path = cli.input.ok_or_else(|| {
+    let input_path = cli.input.clone().ok_or_else(|| {
         anyhow!(
             "❌ Input file is required!\n\
              \n\
@@ -425,11 +2242,17 @@ fn main() -> Result<()> {
              \n\
              Use --bighelp for detailed examples and options."
         )
-    })?;
+    })
+    .code(ErrorCode::InputMissing)?;
+
+    // Files in synced folders may still be arriving
+    if let Some(limit) = cli.wait_for_sync {
+        sync_guard::wait_until_ready(&input_path, limit).code(ErrorCode::InputNotSettled)?;
+    }
 
     // Verify that the input file exists on the filesystem
     if !input_path.exists() {
-        return Err(anyhow!(
+        return Err(coded(ErrorCode::InputNotFound, anyhow!(
             "❌ Input file does not exist: {}\n\
              \n\
              Please check:\n\
@@ -437,48 +2260,141 @@ fn main() -> Result<()> {
              • File exists and is accessible\n\
              • You have read permissions for the file",
             input_path.display()
-        ));
+        )));
     }
 
     // Check if input file is readable
     match std::fs::metadata(&input_path) {
         Ok(metadata) => {
             if !metadata.is_file() {
-                return Err(anyhow!(
+                return Err(coded(ErrorCode::InputNotAFile, anyhow!(
                     "❌ Input path is not a file: {}\n\
                      Please provide a path to a HEIC file, not a directory.",
                     input_path.display()
-                ));
+                )));
             }
             if metadata.len() == 0 {
-                return Err(anyhow!(
+                return Err(coded(ErrorCode::InputEmpty, anyhow!(
                     "❌ Input file is empty: {}\n\
-                     The HEIC file appears to be corrupted or empty.",
+                     The HEIC file appears to be corrupted or empty.\n\
+                     If it is still being synced, wait for it with --wait-for-sync.",
                     input_path.display()
-                ));
+                )));
             }
         }
         Err(e) => {
-            return Err(anyhow!(
+            return Err(coded(ErrorCode::InputUnreadable, anyhow!(
                 "❌ Cannot access input file: {}\n\
                  Error: {}\n\
                  Please check file permissions and path.",
                 input_path.display(),
                 e
-            ));
+            )));
         }
     }
 
-    // Determine output path: use provided path or auto-generate based on input filename
-    let output_path = cli
-        .output
-        .unwrap_or_else(|| generate_output_path(&input_path, &cli.format));
+    // Configuration rules may change the format, backend and size for this file
+    explain::begin(&input_path);
+    let overrides = rules::evaluate(rules, &input_path);
+    // An -o name with an image extension fixes the format, also against a rule
+    let named = cli.output.as_deref().and_then(OutputFormat::from_extension);
+    let format = match (named, overrides.format) {
+        (Some(named), Some(rule)) if rule.extension() != named.extension() => {
+            explain::note(format!("Format: {} kept for the -o name; the rule asked for {}", named.extension(), rule.extension()));
+            named
+        }
+        (Some(named), _) => {
+            explain::note(format!("Format: {} (from the extension of the -o name)", named.extension()));
+            named
+        }
+        (None, rule) => rule.unwrap_or_else(|| cli.output_format()),
+    };
+
+    // A broken LUT should stop the run before anything is written
+    let lut = match &cli.lut {
+        Some(path) => Some(std::sync::Arc::new(lut::load(path).code(ErrorCode::Lut)?)),
+        None => None,
+    };
+
+    // Determine output path: use provided path, the --name-expr result, or auto-generate
+    // based on input filename
+    #[cfg(feature = "scripting")]
+    let named_path = match &cli.name_expr {
+        Some(expr) => Some(name_expr::output_path(expr, &input_path, format.extension()).code(ErrorCode::NameExpr)?),
+        None => None,
+    };
+    #[cfg(not(feature = "scripting"))]
+    let named_path: Option<PathBuf> = None;
+
+    // --name-template: the date is the capture date, or when the file was last changed
+    let template_name = match &cli.name_template {
+        Some(template) => {
+            let date = exif::read(&input_path).date.or_else(|| {
+                let modified = fs::metadata(&input_path).and_then(|metadata| metadata.modified()).ok()?;
+                Some(chrono::DateTime::<chrono::Local>::from(modified).naive_local())
+            });
+            let fields = name_template::Fields {
+                stem: &file_type::base_stem(&input_path),
+                date,
+                counter: cli.position + 1,
+                extension: format.extension(),
+                format: format.name(),
+            };
+            let name = template.render(&fields).ok_or_else(|| {
+                anyhow!(
+                    "❌ --name-template produced an unusable name for {}\n\
+                     The name must not be empty, absolute or contain \"..\".",
+                    input_path.display()
+                )
+            });
+            Some(name.code(ErrorCode::NameExpr)?)
+        }
+        None => None,
+    };
+
+    let device_folder = cli.by_device.then(|| device::folder(&input_path, devices));
+    let date_folder = cli.organize_by_date.then(|| paths::date_folder(exif::read(&input_path).date));
+    let output_options = paths::OutputOptions {
+        explicit: cli.output.as_deref(),
+        named: named_path.as_deref(),
+        extension: format.extension(),
+        directory: cli.output_dir.as_deref(),
+        template: template_name.as_deref(),
+        date_folder: date_folder.as_deref(),
+        device_folder: device_folder.as_deref(),
+        normalize_names: cli.normalize_names,
+    };
+    let output_path = paths::resolve_output(&input_path, &output_options);
+
+    match paths::origin(&output_options) {
+        paths::Origin::Explicit => explain::note(format!("Output: {} (given with -o)", output_path.display())),
+        paths::Origin::NameExpr => explain::note(format!("Output: {} (computed by --name-expr)", output_path.display())),
+        paths::Origin::Generated if template_name.is_some() => {
+            explain::note(format!("Output: {} (from --name-template)", output_path.display()))
+        }
+        paths::Origin::Generated if cli.output_dir.is_some() => explain::note(format!(
+            "Output: {} (input name with the .{} extension, in --output-dir or [defaults])",
+            output_path.display(),
+            format.extension()
+        )),
+        paths::Origin::Generated => explain::note(format!(
+            "Output: {} (input name with the .{} extension)",
+            output_path.display(),
+            format.extension()
+        )),
+    }
+    if let Some(folder) = date_folder.as_deref().filter(|_| paths::origin(&output_options) == paths::Origin::Generated) {
+        explain::note(format!("Date folder: {} (--organize-by-date, from the EXIF capture date)", folder.display()));
+    }
+    if let Some(folder) = &device_folder {
+        explain::note(format!("Device folder: {} (--by-device)", folder));
+    }
 
-    // Validate output path and check for potential issues
-    if let Some(parent) = output_path.parent() {
+    // Validate output path and check for potential issues (stdout needs no checks)
+    if let Some(parent) = output_path.parent().filter(|_| !writes_to_stdout(cli)) {
         // Check if parent directory exists, if not try to create it
         if !parent.exists() {
-            println!("Creating output directory: {}", parent.display());
+            say!("Creating output directory: {}", parent.display());
             fs::create_dir_all(parent)
                 .with_context(|| {
                     format!(
@@ -490,7 +2406,8 @@ fn main() -> Result<()> {
                          • Path too long", 
                         parent.display()
                     )
-                })?;
+                })
+                .code(ErrorCode::OutputDirCreate)?;
         }
         
         // Check if we can write to the output directory
@@ -498,64 +2415,245 @@ fn main() -> Result<()> {
             .map(|m| !m.permissions().readonly())
             .unwrap_or(false) 
         {
-            return Err(anyhow!(
+            return Err(coded(ErrorCode::OutputDirReadOnly, anyhow!(
                 "❌ No write permission to output directory: {}\n\
                  Please check directory permissions or choose a different output location.",
                 parent.display()
-            ));
+            )));
+        }
+    }
+
+    // An output that is already there means the input was done before (--skip-existing),
+    // must be kept (--no-clobber), makes room for a numbered name (--rename-on-conflict)
+    // or is replaced, with a warning unless --overwrite asked for it
+    let output_path = if writes_to_stdout(cli) {
+        output_path
+    } else if cli.rename_on_conflict {
+        let mut claimed = CLAIMED_OUTPUTS.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let free = paths::numbered(&output_path, |path| path.exists() || claimed.contains(path));
+        if free != output_path {
+            explain::note(format!("Output: {} instead, {} exists (--rename-on-conflict)", free.display(), output_path.display()));
+            say!("{} already exists; writing {}", output_path.display(), free.display());
+        }
+        claimed.insert(free.clone());
+        free
+    } else if output_path.exists() {
+        if cli.skip_existing {
+            explain::note("Skipped: the output already exists (--skip-existing)");
+            say!("⏭️  Skipped {}: {} already exists", input_path.display(), output_path.display());
+            return Err(Skipped(output_path).into());
+        }
+        if cli.no_clobber {
+            return Err(coded(ErrorCode::OutputExists, anyhow!(
+                "❌ Output file already exists: {}\n\
+                 --no-clobber keeps it; remove it, choose another output name or use --rename-on-conflict.",
+                output_path.display()
+            )));
+        }
+        if !cli.overwrite {
+            events::warn(format!("Output file already exists and will be overwritten: {}", output_path.display()));
         }
+        explain::note("Existing output will be replaced (overwriting is the default)");
+        output_path
+    } else {
+        output_path
+    };
+
+    // --link-duplicates: a copy shares the output of the same image converted before it,
+    // unless a rule gives it another format
+    if let Some(original) = cli.duplicate_of.as_deref().filter(|original| original.extension() == output_path.extension()) {
+        if output_path.exists() {
+            fs::remove_file(&output_path)
+                .with_context(|| format!("❌ Cannot replace {}", output_path.display()))
+                .code(ErrorCode::SaveFailed)?;
+        }
+        // Hard links cannot cross file systems (or exist on FAT drives); a copy can
+        fs::hard_link(original, &output_path)
+            .or_else(|_| fs::copy(original, &output_path).map(|_| ()))
+            .with_context(|| format!("❌ Cannot link {} to {}", output_path.display(), original.display()))
+            .code(ErrorCode::SaveFailed)?;
+        explain::note(format!("Linked to {}, the output of an input with the same content", original.display()));
+        say!("🔗 Linked {} to {} (same content)", output_path.display(), original.display());
+        return Ok(output_path);
     }
 
-    // Check if output file already exists and warn user
-    if output_path.exists() {
-        println!("⚠️  Output file already exists and will be overwritten: {}", output_path.display());
+    // Scratch space for intermediate files; removed automatically when it goes out of scope
+    let workspace = TempWorkspace::create(cli.tmpdir.as_deref()).code(ErrorCode::TempDir)?;
+
+    // On flaky storage, pull the input into scratch space first so read errors can be retried
+    let conversion_input = match cli.retry_io {
+        Some(retries) => {
+            let staged = workspace.staged_input_path(&input_path);
+            if let Some(dir) = staged.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            retry_io::copy_with_retry(&input_path, &staged, retries, cli.io_backoff)
+                .code(ErrorCode::ReadRetriesExhausted)?;
+            explain::note(format!("Input copied to the temporary directory first (--retry-io {})", retries));
+            staged
+        }
+        None => input_path.clone(),
+    };
+
+    // --quality only applies to JPEG and lossy WebP, which otherwise get the default of
+    // their format; --share-safe caps it, a lower --quality is kept
+    let fixed = match cli.quality {
+        Some(quality::Quality::Fixed(quality)) => Some(quality),
+        _ => None,
+    };
+    let jxl = matches!(format, OutputFormat::Jxl);
+    let lossless = cli.lossless && (jxl || matches!(format, OutputFormat::Webp));
+    if cli.lossless && !lossless {
+        events::warn("--lossless only applies to WebP and JPEG XL output; ignored");
+    }
+    if cli.jxl_distance.is_some() && !jxl {
+        events::warn("--jxl-distance only applies to JPEG XL output; ignored");
+    }
+    let jxl_distance = if lossless && jxl { Some(0.0) } else { cli.jxl_distance.filter(|_| jxl) };
+    let deep = matches!(format, OutputFormat::Png | OutputFormat::Tiff | OutputFormat::Jxl | OutputFormat::Ppm);
+    if cli.bit_depth.is_some() && !deep {
+        events::warn("--bit-depth only applies to PNG, TIFF, JPEG XL and PPM output; ignored");
+    }
+    let bit_depth = match cli.bit_depth.filter(|_| deep) {
+        Some(BitDepth::Sixteen) if cli.dither.is_some() => {
+            events::warn("--dither reduces to 8 bits per channel; --bit-depth 16 ignored");
+            None
+        }
+        depth => depth,
+    };
+    let quality = match (&format, cli.share_safe) {
+        (OutputFormat::Tiff | OutputFormat::Bmp | OutputFormat::Exr | OutputFormat::Ppm | OutputFormat::Ico, _) => None,
+        _ if lossless || jxl_distance.is_some() => None,
+        (OutputFormat::Png, true) => Some(SHARE_SAFE_QUALITY),
+        (OutputFormat::Png, false) => None,
+        (_, true) => Some(fixed.map_or(SHARE_SAFE_QUALITY, |q| q.min(SHARE_SAFE_QUALITY))),
+        (_, false) => fixed.or(quality::default_for(format.extension())),
+    };
+    let auto_quality = cli.quality == Some(quality::Quality::Auto) && quality.is_some() && !cli.share_safe;
+    if let Some(quality) = quality.filter(|_| fixed.is_none() && !auto_quality && !cli.share_safe) {
+        explain::note(format!("Quality: {} (default for {})", quality, format.extension()));
     }
 
     // Perform the actual HEIC to image conversion with comprehensive error handling
-    match convert_heic_to_image(&input_path, &output_path, &cli.format) {
-        Ok(()) => {
-            println!("✅ Conversion completed successfully!");
-            Ok(())
+    let mut options = ConvertOptions {
+        format,
+        backend: overrides.backend.unwrap_or(cli.backend),
+        deterministic: cli.deterministic,
+        strip_metadata: cli.share_safe,
+        quality,
+        lossless,
+        tiff_compression: cli.tiff_compression,
+        jxl_distance,
+        bit_depth,
+        auto_quality,
+        resize: overrides.resize.or(cli.resize).or(cli.share_safe.then_some(resize::Resize {
+            width: Some(SHARE_SAFE_EDGE),
+            height: Some(SHARE_SAFE_EDGE),
+        })),
+        adjust: adjust::Adjustments {
+            denoise: cli.denoise,
+            auto_levels: cli.auto_levels,
+            auto_white_balance: cli.auto_white_balance,
+            curves: cli.curve.clone(),
+            lut,
+            dither: cli.dither,
+        },
+        embed_thumbnail: cli.embed_thumbnail,
+        split_output: cli.split_output,
+        bwlimit: cli.bwlimit,
+        to_stdout: writes_to_stdout(cli),
+        requested: Vec::new(),
+        on_unsupported: cli.on_unsupported,
+    };
+    options.requested = negotiate::requested(&input_path, &options);
+    if cli.share_safe {
+        explain::note(format!(
+            "Share-safe: metadata and location removed, at most {}x{} pixels{}",
+            SHARE_SAFE_EDGE,
+            SHARE_SAFE_EDGE,
+            match options.format {
+                OutputFormat::Png => ", PNG has no quality setting",
+                OutputFormat::Jpg | OutputFormat::Jpeg => ", JPEG quality 80",
+                OutputFormat::Webp if options.lossless => ", lossless WebP",
+                OutputFormat::Webp => ", WebP quality 80",
+                OutputFormat::Avif => ", AVIF quality 80",
+                OutputFormat::Tiff => ", TIFF is lossless",
+                OutputFormat::Bmp => ", BMP is uncompressed",
+                OutputFormat::Jxl if options.lossless => ", lossless JPEG XL",
+                OutputFormat::Jxl if options.jxl_distance.is_some() => ", JPEG XL at the given --jxl-distance",
+                OutputFormat::Jxl => ", JPEG XL quality 80",
+                OutputFormat::Pdf => ", pages at JPEG quality 80",
+                OutputFormat::Exr => ", EXR has no quality setting",
+                OutputFormat::Ppm => ", PPM is uncompressed",
+                OutputFormat::Ico => ", ICO holds lossless PNG images",
+            }
+        ));
+    }
+    match convert_heic_to_image(&conversion_input, &output_path, &workspace, &options) {
+        Ok(final_path) => {
+            say!("✅ Conversion completed successfully!");
+
+            // Warn when a wide-colour source will look different as sRGB
+            if cli.gamut_check.is_some() {
+                report_gamut(&conversion_input, &workspace);
+            }
+
+            // Keep the gallery manifest of the output directory up to date
+            if cli.album_manifest {
+                explain::note("Listed in album.json next to the output (--album-manifest)");
+                if let Err(e) = album::update(&final_path, &input_path) {
+                    events::warn(format!("Could not update album.json: {}", e));
+                }
+            }
+
+            // Push the result to a remote destination when requested
+            #[cfg(feature = "upload")]
+            if let Some(target) = &cli.upload {
+                explain::note("Uploading the result (--upload)");
+                upload::upload_file(&final_path, target, cli.bwlimit).code(ErrorCode::UploadFailed)?;
+            }
+            #[cfg(feature = "upload")]
+            if let Some(server) = &cli.import_to {
+                let api_key = cli
+                    .api_key
+                    .clone()
+                    .or_else(|| std::env::var(photo_server::API_KEY_ENV).ok())
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "❌ --import-to needs an API key.\n\
+                             Pass it with --api-key or set {}.",
+                            photo_server::API_KEY_ENV
+                        )
+                    })
+                    .code(ErrorCode::ImportFailed)?;
+                let target = photo_server::ImportTarget {
+                    server: server.clone(),
+                    api_url: cli.api_url.clone().unwrap_or_default(),
+                    api_key,
+                };
+                explain::note(format!("Importing into {:?}, album from the input folder (--import-to)", server));
+                photo_server::import_file(&final_path, &input_path, &target)
+                    .code(ErrorCode::ImportFailed)?;
+            }
+
+            // The original goes last, once everything that needed it is done
+            if cli.delete_original {
+                originals::dispose(&input_path, &final_path, originals::Disposal::Delete);
+            } else if cli.trash_original {
+                originals::dispose(&input_path, &final_path, originals::Disposal::Trash);
+            }
+
+            Ok(final_path)
         }
         Err(e) => {
-            // Provide user-friendly error messages with solutions
-            if e.to_string().contains("HEIC format support is not available") {
-                eprintln!("❌ HEIC Conversion Failed - Missing Dependencies");
-                eprintln!();
-                eprintln!("🔧 Quick Fix Options:");
-                eprintln!("   1. Install ImageMagick: brew install imagemagick");
-                eprintln!("   2. Install FFmpeg: brew install ffmpeg");
-                eprintln!("   3. Use online converter: https://convertio.co/heic-png/");
-                eprintln!();
-                eprintln!("📱 macOS Users can also:");
-                eprintln!("   • Open HEIC in Preview → Export as PNG/JPEG");
-                eprintln!("   • Use Photos app → Export → JPEG");
-                eprintln!();
-                eprintln!("Original error: {}", e);
-            } else if e.to_string().contains("does not appear to be a HEIC file") {
-                eprintln!("❌ Invalid File Format");
-                eprintln!();
-                eprintln!("The input file doesn't appear to be a HEIC/HEIF file.");
-                eprintln!("Supported extensions: .heic, .heif");
-                eprintln!();
-                eprintln!("Current file: {}", input_path.display());
-                eprintln!("File extension: {:?}", input_path.extension());
-            } else if e.to_string().contains("Failed to save image") {
-                eprintln!("❌ Failed to Save Output File");
-                eprintln!();
-                eprintln!("Could not write to: {}", output_path.display());
-                eprintln!("Please check:");
-                eprintln!("   • Disk space availability");
-                eprintln!("   • Write permissions");
-                eprintln!("   • Output directory exists");
-            } else {
-                eprintln!("❌ Conversion Error: {}", e);
-                eprintln!();
-                eprintln!("💡 Try these solutions:");
-                eprintln!("   1. Check if input file is corrupted");
-                eprintln!("   2. Try a different output location");
-                eprintln!("   3. Install conversion tools: brew install imagemagick ffmpeg");
-                eprintln!("   4. Use --bighelp for more options");
+            // Quick fixes for the failure; `heic_convert explain <CODE>` has the details
+            if let Some(code) = error_codes::code_of(&e) {
+                let info = code.info();
+                say_err!("❌ {} ({})", info.title, info.id);
+                for fix in info.fixes {
+                    say_err!("   • {}", fix);
+                }
+                say_err!();
             }
             Err(e)
         }
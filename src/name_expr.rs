@@ -0,0 +1,97 @@
+// Output names computed by a Rhai expression (--name-expr, feature "scripting")
+//
+// The expression sees two object maps and returns the output name without extension:
+//   exif.date, exif.make, exif.model      (() when the tag is missing)
+//   file.stem, file.name, file.ext, file.size, file.modified
+// file.stem drops all image extensions, so IMG_0001.HEIC.heic has the stem IMG_0001.
+// Dates have .format("%Y-%m-%d") and .year / .month / .day / .hour / .minute / .second.
+// Example: exif.date.format("%Y-%m-%d") + "_" + file.stem
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, Timelike};
+use rhai::{Dynamic, Engine, EvalAltResult, Map, Scope};
+use std::fmt::Write as _;
+use std::path::{Component, Path, PathBuf};
+
+use crate::{exif, file_type};
+
+// Runaway expressions (endless loops) are stopped after this many operations
+const MAX_OPERATIONS: u64 = 100_000;
+
+#[derive(Clone)]
+struct Date(NaiveDateTime);
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine
+        .register_type_with_name::<Date>("Date")
+        .register_fn("format", |date: &mut Date, pattern: &str| -> Result<String, Box<EvalAltResult>> {
+            let mut text = String::new();
+            write!(text, "{}", date.0.format(pattern))
+                .map_err(|_| format!("invalid date format: {}", pattern))?;
+            Ok(text)
+        })
+        .register_fn("to_string", |date: &mut Date| date.0.format("%Y-%m-%d %H:%M:%S").to_string())
+        .register_get("year", |date: &mut Date| date.0.year() as i64)
+        .register_get("month", |date: &mut Date| date.0.month() as i64)
+        .register_get("day", |date: &mut Date| date.0.day() as i64)
+        .register_get("hour", |date: &mut Date| date.0.hour() as i64)
+        .register_get("minute", |date: &mut Date| date.0.minute() as i64)
+        .register_get("second", |date: &mut Date| date.0.second() as i64);
+    engine
+}
+
+// Missing values are () in the script
+fn optional(value: Option<Dynamic>) -> Dynamic {
+    value.unwrap_or(Dynamic::UNIT)
+}
+
+// Evaluate `expr` for `input` and return the output path (same directory as the input,
+// sub-directories allowed) with the given extension
+pub fn output_path(expr: &str, input: &Path, extension: &str) -> Result<PathBuf> {
+    let tags = exif::read(input);
+    let mut exif_map = Map::new();
+    exif_map.insert("date".into(), optional(tags.date.map(|date| Dynamic::from(Date(date)))));
+    exif_map.insert("make".into(), optional(tags.make.map(Dynamic::from)));
+    exif_map.insert("model".into(), optional(tags.model.map(Dynamic::from)));
+
+    let metadata = std::fs::metadata(input).ok();
+    let modified = metadata
+        .as_ref()
+        .and_then(|m| m.modified().ok())
+        .map(|time| Date(DateTime::<Local>::from(time).naive_local()));
+    let text = |part: Option<&std::ffi::OsStr>| part.unwrap_or_default().to_string_lossy().to_string();
+    let mut file_map = Map::new();
+    file_map.insert("stem".into(), file_type::base_stem(input).into());
+    file_map.insert("name".into(), text(input.file_name()).into());
+    file_map.insert("ext".into(), text(input.extension()).into());
+    file_map.insert("size".into(), (metadata.map_or(0, |m| m.len()) as i64).into());
+    file_map.insert("modified".into(), optional(modified.map(Dynamic::from)));
+
+    let mut scope = Scope::new();
+    scope.push_constant("exif", exif_map);
+    scope.push_constant("file", file_map);
+
+    let name = engine()
+        .eval_with_scope::<Dynamic>(&mut scope, expr)
+        .map_err(|e| anyhow!("❌ --name-expr failed for {}: {}", input.display(), e))?
+        .to_string();
+    let name = name.trim();
+
+    // The name may create sub-directories next to the input, but must stay below it
+    let relative = Path::new(name);
+    if name.is_empty()
+        || relative.is_absolute()
+        || relative.components().any(|c| !matches!(c, Component::Normal(_)))
+    {
+        bail!(
+            "❌ --name-expr produced an unusable name for {}: {:?}\n\
+             The result must be a relative name without \"..\", for example exif.date.format(\"%Y-%m-%d\") + \"_\" + file.stem",
+            input.display(),
+            name
+        );
+    }
+
+    let parent = input.parent().unwrap_or(Path::new("."));
+    Ok(parent.join(format!("{}.{}", name, extension)))
+}
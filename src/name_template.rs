@@ -0,0 +1,158 @@
+// Output names from a template (--name-template "{date}_{stem}.{ext}")
+//
+// A lighter alternative to --name-expr that needs no script engine. Placeholders:
+//   {stem}         input name without its image extensions (IMG_0001.HEIC.heic -> IMG_0001)
+//   {date}         capture date from EXIF, or the modification date, as 2024-07-14;
+//                  {date:%Y%m%d_%H%M%S} takes a strftime pattern
+//   {counter}      position of the input in the run, from 1; {counter:4} pads to 0001
+//   {ext}          extension of the output format (png, jpg, webp, avif, tiff, bmp, jxl, pdf, exr, ppm, ico)
+//   {format}       name of the output format (PNG, JPEG, WEBP, ...)
+// "{{" and "}}" stand for literal braces. The output extension is added when the name
+// does not end with it, and a "/" creates sub-folders.
+use chrono::NaiveDateTime;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template(Vec<Part>);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Stem,
+    Date(String), // strftime pattern
+    Counter(usize), // Minimum number of digits
+    Extension,
+    Format,
+}
+
+// What the placeholders of one input stand for
+pub struct Fields<'a> {
+    pub stem: &'a str,
+    pub date: Option<NaiveDateTime>,
+    pub counter: usize,
+    pub extension: &'a str,
+    pub format: &'a str,
+}
+
+const DATE_DEFAULT: &str = "%Y-%m-%d";
+
+pub fn parse_template(text: &str) -> Result<Template, String> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(['{', '}']) {
+        literal.push_str(&rest[..start]);
+        let brace = &rest[start..];
+        if brace.starts_with("{{") || brace.starts_with("}}") {
+            literal.push_str(&brace[..1]);
+            rest = &brace[2..];
+            continue;
+        }
+        let end = brace.find('}').filter(|_| brace.starts_with('{')).ok_or_else(|| format!("unmatched brace in '{}'", text))?;
+        let (name, argument) = match brace[1..end].split_once(':') {
+            Some((name, argument)) => (name, Some(argument)),
+            None => (&brace[1..end], None),
+        };
+        let part = match (name, argument) {
+            ("stem", None) => Part::Stem,
+            ("date", pattern) => Part::Date(check_date_pattern(pattern.unwrap_or(DATE_DEFAULT))?),
+            ("counter", None) => Part::Counter(1),
+            ("counter", Some(width)) => {
+                Part::Counter(width.parse().map_err(|_| format!("invalid counter width '{}'; use e.g. {{counter:4}}", width))?)
+            }
+            ("ext", None) => Part::Extension,
+            ("format", None) => Part::Format,
+            _ => return Err(format!("unknown placeholder {{{}}}; use {{stem}}, {{date}}, {{counter}}, {{ext}} or {{format}}", &brace[1..end])),
+        };
+        if !literal.is_empty() {
+            parts.push(Part::Text(std::mem::take(&mut literal)));
+        }
+        parts.push(part);
+        rest = &brace[end + 1..];
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        parts.push(Part::Text(literal));
+    }
+    if parts.is_empty() {
+        return Err(String::from("the template is empty"));
+    }
+    Ok(Template(parts))
+}
+
+// chrono reports a bad pattern only while formatting, so try it once here
+fn check_date_pattern(pattern: &str) -> Result<String, String> {
+    use std::fmt::Write;
+    let sample = NaiveDateTime::default();
+    let mut text = String::new();
+    write!(text, "{}", sample.format(pattern)).map_err(|_| format!("invalid date pattern '{}'", pattern))?;
+    Ok(pattern.to_string())
+}
+
+impl Template {
+    // The relative output name for one input, extension included. None when the result
+    // cannot be used as a name below the output folder (empty, absolute or with "..").
+    pub fn render(&self, fields: &Fields) -> Option<PathBuf> {
+        let mut name = String::new();
+        for part in &self.0 {
+            match part {
+                Part::Text(text) => name.push_str(text),
+                Part::Stem => name.push_str(fields.stem),
+                // Without a capture or modification date the placeholder is left empty
+                Part::Date(pattern) => {
+                    if let Some(date) = fields.date {
+                        name.push_str(&date.format(pattern).to_string());
+                    }
+                }
+                Part::Counter(width) => name.push_str(&format!("{:0width$}", fields.counter, width = *width)),
+                Part::Extension => name.push_str(fields.extension),
+                Part::Format => name.push_str(fields.format),
+            }
+        }
+        let suffix = format!(".{}", fields.extension);
+        if !name.to_lowercase().ends_with(&suffix.to_lowercase()) {
+            name.push_str(&suffix);
+        }
+
+        let relative = Path::new(&name);
+        let usable = name != suffix
+            && !relative.is_absolute()
+            && relative.components().all(|part| matches!(part, Component::Normal(_)));
+        usable.then(|| relative.to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn fields(date: Option<NaiveDateTime>) -> Fields<'static> {
+        Fields { stem: "IMG_0001", date, counter: 7, extension: "jpg", format: "JPEG" }
+    }
+
+    fn render(template: &str, date: Option<NaiveDateTime>) -> Option<PathBuf> {
+        parse_template(template).unwrap().render(&fields(date))
+    }
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        let date = NaiveDate::from_ymd_opt(2024, 7, 14).unwrap().and_hms_opt(9, 30, 5);
+        assert_eq!(render("{date}_{stem}.{ext}", date), Some(PathBuf::from("2024-07-14_IMG_0001.jpg")));
+        assert_eq!(render("{date:%Y/%m}/{counter:4}", date), Some(PathBuf::from("2024/07/0007.jpg")));
+        assert_eq!(render("{stem}-{format}", None), Some(PathBuf::from("IMG_0001-JPEG.jpg")));
+        assert_eq!(render("{{{stem}}}", None), Some(PathBuf::from("{IMG_0001}.jpg")));
+        assert_eq!(render("{date}_{stem}", None), Some(PathBuf::from("_IMG_0001.jpg")));
+    }
+
+    #[test]
+    fn unusable_templates_and_names_are_rejected() {
+        assert!(parse_template("{size}").is_err());
+        assert!(parse_template("{stem").is_err());
+        assert!(parse_template("stem}").is_err());
+        assert!(parse_template("{counter:x}").is_err());
+        assert_eq!(render("../{stem}", None), None);
+        assert_eq!(render("/tmp/{stem}", None), None);
+        assert_eq!(render("{date}", None), None);
+    }
+}
@@ -0,0 +1,128 @@
+// Which requested options the chosen backend can honour (--on-unsupported)
+//
+// Before a file is converted, the options that need support from the backend are
+// listed: JPEG quality, lossy WebP encoding, resizing, 16-bit processing, and keeping the EXIF metadata and
+// a wide-gamut colour profile of the source (only when it has them and they are not
+// being stripped). If the backend cannot honour all of them the conversion goes ahead
+// with a warning, moves to a backend that can, or stops, as --on-unsupported says.
+use anyhow::{Result, anyhow};
+use clap::ValueEnum;
+use std::path::Path;
+
+use crate::error_codes::{ErrorCode, coded};
+use crate::{Backend, ConvertOptions, OutputFormat, events, exif, explain, gamut};
+
+// What to do when the backend cannot honour a requested option
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OnUnsupported {
+    #[default]
+    Warn,   // Convert anyway and say what is lost
+    Switch, // Use an installed backend that can honour everything, if there is one
+    Fail,   // Stop with an error
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feature {
+    Quality,
+    LossyWebp,
+    Resize,
+    Metadata,
+    ColourProfile,
+    SixteenBit,
+}
+
+impl Feature {
+    fn describe(&self) -> &'static str {
+        match self {
+            Feature::Quality => "JPEG quality",
+            Feature::LossyWebp => "lossy WebP encoding",
+            Feature::Resize => "resizing",
+            Feature::Metadata => "EXIF metadata",
+            Feature::ColourProfile => "the wide-gamut colour profile",
+            Feature::SixteenBit => "16-bit processing",
+        }
+    }
+}
+
+// The options of a conversion of `input` that need backend support
+pub fn requested(input: &Path, options: &ConvertOptions) -> Vec<Feature> {
+    let jpeg = matches!(options.format, OutputFormat::Jpg | OutputFormat::Jpeg);
+    let keeps_metadata = !options.strip_metadata && !options.deterministic;
+    let wide_gamut = || matches!(gamut::source_space(input), Some(gamut::ColorSpace::DisplayP3 | gamut::ColorSpace::Other));
+    [
+        (Feature::Quality, options.quality.is_some() && jpeg),
+        (Feature::LossyWebp, matches!(options.format, OutputFormat::Webp) && !options.lossless),
+        (Feature::Resize, options.resize.is_some()),
+        (Feature::SixteenBit, options.adjust.dither.is_some()),
+        (Feature::Metadata, keeps_metadata && exif::present(input)),
+        // --share-safe strips metadata but keeps the colour profile
+        (Feature::ColourProfile, !options.deterministic && wide_gamut()),
+    ]
+    .into_iter()
+    .filter_map(|(feature, wanted)| wanted.then_some(feature))
+    .collect()
+}
+
+// What each backend can do; the built-in encoder writes pixels only, and WebP only
+// without loss. FFmpeg copies container metadata but neither EXIF nor the ICC profile
+fn supports(backend: Backend, feature: Feature) -> bool {
+    match backend {
+        Backend::Builtin => !matches!(feature, Feature::Metadata | Feature::ColourProfile | Feature::LossyWebp),
+        Backend::Ffmpeg => !matches!(feature, Feature::Metadata | Feature::ColourProfile),
+        Backend::Imagemagick | Backend::Auto => true,
+    }
+}
+
+fn installed(backend: Backend) -> bool {
+    match backend {
+        Backend::Imagemagick => crate::check_imagemagick_available(),
+        Backend::Ffmpeg => crate::check_ffmpeg_available(),
+        Backend::Builtin | Backend::Auto => false, // Only chosen when it can read the file
+    }
+}
+
+// The backend to convert with: `chosen` when it can honour everything, otherwise what
+// the policy says
+pub fn negotiate(chosen: Backend, requested: &[Feature], policy: OnUnsupported) -> Result<Backend> {
+    let missing: Vec<&str> = requested
+        .iter()
+        .filter(|feature| !supports(chosen, **feature))
+        .map(Feature::describe)
+        .collect();
+    if missing.is_empty() {
+        return Ok(chosen);
+    }
+    let missing = missing.join(", ");
+
+    match policy {
+        OnUnsupported::Warn => {
+            events::warn(format!("{:?} cannot keep {}; converting without (--on-unsupported warn)", chosen, missing));
+            Ok(chosen)
+        }
+        OnUnsupported::Switch => {
+            let better = [Backend::Imagemagick, Backend::Ffmpeg].into_iter().find(|&backend| {
+                backend != chosen && requested.iter().all(|feature| supports(backend, *feature)) && installed(backend)
+            });
+            match better {
+                Some(backend) => {
+                    say!("Switching from {:?} to {:?} to keep {}", chosen, backend, missing);
+                    explain::note(format!("Backend: {:?}, because {:?} cannot keep {} (--on-unsupported switch)", backend, chosen, missing));
+                    Ok(backend)
+                }
+                None => {
+                    events::warn(format!("{:?} cannot keep {} and no installed backend can; converting without", chosen, missing));
+                    Ok(chosen)
+                }
+            }
+        }
+        OnUnsupported::Fail => Err(coded(
+            ErrorCode::UnsupportedFeature,
+            anyhow!(
+                "❌ {:?} cannot keep {}\n\
+                 Install ImageMagick, or use --on-unsupported warn|switch.",
+                chosen,
+                missing
+            ),
+        )),
+    }
+}
@@ -0,0 +1,52 @@
+// Removing the source after a successful conversion (--delete-original, --trash-original)
+//
+// Meant for reclaiming space once a library has been migrated. The original is only
+// removed when the output is a different file that exists and reads back as an image;
+// anything else keeps it with a warning, and so does a failure to remove it, since the
+// conversion itself succeeded. Live Photo clips and .aae edits next to the original are
+// left alone (see --orphan-report).
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+use std::path::Path;
+
+use crate::{events, file_type};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Disposal {
+    Delete, // Gone for good
+    Trash,  // The system trash (recycle bin), where it can be restored from
+}
+
+pub fn dispose(input: &Path, output: &Path, disposal: Disposal) {
+    match verify(input, output).and_then(|_| remove(input, disposal)) {
+        Ok(()) => match disposal {
+            Disposal::Delete => say!("🗑️  Deleted the original {}", input.display()),
+            Disposal::Trash => say!("🗑️  Moved the original {} to the trash", input.display()),
+        },
+        Err(e) => events::warn(format!("Kept the original {}: {:#}", input.display(), e)),
+    }
+}
+
+// The output must be a separate, readable image before the only other copy goes
+fn verify(input: &Path, output: &Path) -> Result<()> {
+    let same = match (fs::canonicalize(input), fs::canonicalize(output)) {
+        (Ok(input), Ok(output)) => input == output,
+        _ => false,
+    };
+    if same {
+        return Err(anyhow!("the output replaced it"));
+    }
+    let size = fs::metadata(output).with_context(|| format!("{} is missing", output.display()))?.len();
+    if size == 0 {
+        return Err(anyhow!("{} is empty", output.display()));
+    }
+    file_type::dimensions(output).with_context(|| format!("{} does not read back as an image", output.display()))?;
+    Ok(())
+}
+
+fn remove(input: &Path, disposal: Disposal) -> Result<()> {
+    match disposal {
+        Disposal::Delete => fs::remove_file(input).context("cannot delete it"),
+        Disposal::Trash => trash::delete(input).map_err(|e| anyhow!("cannot move it to the trash: {}", e)),
+    }
+}
@@ -0,0 +1,132 @@
+// Find Live Photo (.mov) and edit sidecar (.aae) files whose HEIC original is gone
+// but whose converted output exists, i.e. leftovers from a migration that deleted the originals
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::file_type;
+
+// Companion file types that iPhones create next to a HEIC
+const COMPANION_EXTENSIONS: &[&str] = &["mov", "aae"];
+
+// Original photo extensions; a companion with one of these partners is not orphaned
+const ORIGINAL_EXTENSIONS: &[&str] = &["heic", "heif"];
+
+// Extensions of converted outputs that show the original was migrated
+const CONVERTED_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "avif", "tif", "tiff", "bmp", "jxl", "pdf", "exr", "ppm", "pnm", "ico"];
+
+// Report orphaned companions in `dir`, optionally moving them into `review_dir`
+pub fn report_orphans(dir: &Path, review_dir: Option<&Path>) -> Result<()> {
+    if !dir.is_dir() {
+        return Err(anyhow!(
+            "❌ Not a directory: {}\n\
+             --orphan-report expects the directory that holds the converted photos.",
+            dir.display()
+        ));
+    }
+
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("❌ Cannot read directory: {}", dir.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+
+    // Stems (lowercased, since iOS mixes IMG_0001.HEIC and IMG_0001.mov) by file role
+    let mut originals = HashSet::new();
+    let mut converted = HashSet::new();
+    for path in &files {
+        let (Some(stem), Some(extension)) = (lower_stem(path), lower_extension(path)) else {
+            continue;
+        };
+        if ORIGINAL_EXTENSIONS.contains(&extension.as_str()) {
+            originals.insert(stem);
+        } else if CONVERTED_EXTENSIONS.contains(&extension.as_str()) {
+            converted.insert(stem);
+        }
+    }
+
+    let orphans: Vec<&PathBuf> = files
+        .iter()
+        .filter(|path| {
+            let (Some(stem), Some(extension)) = (lower_stem(path), lower_extension(path)) else {
+                return false;
+            };
+            COMPANION_EXTENSIONS.contains(&extension.as_str())
+                && !originals.contains(&stem)
+                && converted.contains(&stem)
+        })
+        .collect();
+
+    if orphans.is_empty() {
+        say!("✅ No orphaned .mov/.aae files found in {}", dir.display());
+        return Ok(());
+    }
+
+    say!("Orphaned companion files in {}:", dir.display());
+    for orphan in &orphans {
+        say!("  • {}", orphan.display());
+    }
+    say!();
+
+    let Some(review_dir) = review_dir else {
+        say!(
+            "⚠️  {} orphaned file(s) found. Re-run with --orphan-review-dir <DIR> to move them for review.",
+            orphans.len()
+        );
+        return Ok(());
+    };
+
+    fs::create_dir_all(review_dir).with_context(|| {
+        format!(
+            "❌ Failed to create review directory: {}",
+            review_dir.display()
+        )
+    })?;
+
+    let mut moved = 0;
+    for orphan in &orphans {
+        let Some(file_name) = orphan.file_name() else {
+            continue;
+        };
+        let target = review_dir.join(file_name);
+        if target.exists() {
+            say!("⚠️  Not moving {}: {} already exists", orphan.display(), target.display());
+            continue;
+        }
+        move_file(orphan, &target)?;
+        moved += 1;
+    }
+
+    say!(
+        "✅ Moved {} of {} orphaned file(s) to {}",
+        moved,
+        orphans.len(),
+        review_dir.display()
+    );
+    Ok(())
+}
+
+// Rename when possible, copy + remove when the review folder is on another filesystem
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)
+        .with_context(|| format!("❌ Failed to move {} to {}", from.display(), to.display()))?;
+    fs::remove_file(from)
+        .with_context(|| format!("❌ Copied but could not remove {}", from.display()))?;
+    Ok(())
+}
+
+// IMG_0001.HEIC.heic pairs with IMG_0001.MOV as well
+fn lower_stem(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?;
+    Some(file_type::base_stem(Path::new(stem)).to_lowercase())
+}
+
+fn lower_extension(path: &Path) -> Option<String> {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase())
+}
@@ -0,0 +1,140 @@
+// One archive instead of loose output files (--archive-output photos.zip)
+//
+// A multi-file run with --archive-output writes its outputs into a scratch folder, with
+// the same subfolders --output-dir would get, and packs them into one ZIP or gzipped
+// tar file when the run ends, also when some inputs failed. The archive is written next
+// to its final name and renamed into place, so an interrupted run never leaves half an
+// archive behind; the scratch folder goes with the run. The images are stored as they
+// are in a ZIP file, since PNG, JPEG and the other formats are compressed already.
+use anyhow::{Context, Result, anyhow};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::temp_workspace::TempWorkspace;
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Zip,
+    TarGz,
+}
+
+pub struct Pack {
+    path: PathBuf,
+    kind: Kind,
+    workspace: TempWorkspace,
+}
+
+impl Pack {
+    // An archive at `path`, its type chosen by the name: .zip, .tar.gz or .tgz
+    pub fn create(path: &Path, tmpdir: Option<&Path>) -> Result<Pack> {
+        let name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let kind = if name.ends_with(".zip") {
+            Kind::Zip
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Kind::TarGz
+        } else {
+            return Err(anyhow!(
+                "❌ Cannot tell the archive type of {}\n\
+                 --archive-output writes .zip, .tar.gz and .tgz files.",
+                path.display()
+            ));
+        };
+        if path.is_dir() {
+            return Err(anyhow!("❌ --archive-output names a file, but {} is a folder", path.display()));
+        }
+        Ok(Pack { path: path.to_path_buf(), kind, workspace: TempWorkspace::create(tmpdir)? })
+    }
+
+    // The folder the conversions write into
+    pub fn staging(&self) -> PathBuf {
+        self.workspace.path().join("outputs")
+    }
+
+    // Pack every converted file and return how many there were; without any, no
+    // archive is written
+    pub fn finish(self) -> Result<usize> {
+        let staging = self.staging();
+        let mut files = Vec::new();
+        if staging.is_dir() {
+            gather(&staging, &mut files).with_context(|| format!("❌ Cannot read {}", staging.display()))?;
+        }
+        files.sort();
+        if files.is_empty() {
+            return Ok(0);
+        }
+        if self.path.exists() {
+            crate::events::warn(format!("Replacing the existing archive {}", self.path.display()));
+        }
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| format!("❌ Cannot create {}", parent.display()))?;
+        }
+
+        let mut partial_name = self.path.file_name().unwrap_or_default().to_os_string();
+        partial_name.push(".partial");
+        let partial = self.path.with_file_name(partial_name);
+        let written = File::create(&partial).and_then(|file| {
+            let file = BufWriter::new(file);
+            match self.kind {
+                Kind::Zip => write_zip(file, &staging, &files),
+                Kind::TarGz => write_tar_gz(file, &staging, &files),
+            }
+        });
+        if let Err(e) = written.and_then(|_| fs::rename(&partial, &self.path)) {
+            let _ = fs::remove_file(&partial);
+            return Err(e).with_context(|| {
+                format!(
+                    "❌ Cannot write the archive {}\n\
+                     Possible causes:\n\
+                     • Disk full\n\
+                     • No write permission to the folder",
+                    self.path.display()
+                )
+            });
+        }
+        Ok(files.len())
+    }
+}
+
+// Every file below `dir`
+fn gather(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            gather(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// The name of `file` in the archive: its path below the staging folder, with forward
+// slashes on every system
+fn entry_name(staging: &Path, file: &Path) -> String {
+    let relative = file.strip_prefix(staging).unwrap_or(file);
+    relative.components().map(|part| part.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+fn write_zip(file: BufWriter<File>, staging: &Path, files: &[PathBuf]) -> io::Result<()> {
+    let mut zip = zip::ZipWriter::new(file);
+    for path in files {
+        let size = fs::metadata(path)?.len();
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored)
+            .large_file(size >= u32::MAX as u64);
+        zip.start_file(entry_name(staging, path), options).map_err(io::Error::other)?;
+        io::copy(&mut File::open(path)?, &mut zip)?;
+    }
+    zip.finish().map_err(io::Error::other)?.flush()
+}
+
+fn write_tar_gz(file: BufWriter<File>, staging: &Path, files: &[PathBuf]) -> io::Result<()> {
+    let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::fast()));
+    for path in files {
+        tar.append_path_with_name(path, entry_name(staging, path))?;
+    }
+    tar.into_inner()?.finish()?.flush()
+}
@@ -0,0 +1,363 @@
+// Where a conversion writes its output
+//
+// All decisions about the output path are made here, by a pure function: the inputs
+// that need the file system or a script (the EXIF device folder, the --name-expr
+// result) are worked out by the caller and passed in. The steps, in order:
+//   1. the base path: -o as given, the --name-expr result, or the input name with the
+//      new extension next to the input (or in --output-dir), in the year and month
+//      folder of --organize-by-date,
+//   2. the --by-device folder, inserted just before the file name,
+//   3. a lowercase extension for --normalize-names,
+//   4. platform quirks for generated names (names Windows cannot store).
+// `-o -` (stdout) is never changed.
+use chrono::NaiveDateTime;
+use std::path::{Path, PathBuf};
+
+use crate::file_type;
+
+// Names Windows reserves for devices, with any extension (CON.png is as invalid as CON)
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// Everything that decides the output path besides the input itself
+#[derive(Clone, Debug, Default)]
+pub struct OutputOptions<'a> {
+    pub explicit: Option<&'a Path>,     // -o
+    pub named: Option<&'a Path>,        // Result of --name-expr
+    pub extension: &'a str,             // Extension of the output format, without the dot
+    pub directory: Option<&'a Path>,    // --output-dir, for generated names
+    pub template: Option<&'a Path>,     // Result of --name-template, instead of stem.extension
+    pub date_folder: Option<&'a Path>,  // --organize-by-date, for generated names
+    pub device_folder: Option<&'a str>, // --by-device
+    pub normalize_names: bool,          // --normalize-names
+}
+
+// How the base path was chosen, for --explain
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Origin {
+    Explicit,
+    NameExpr,
+    Generated,
+}
+
+pub fn origin(opts: &OutputOptions) -> Origin {
+    match (opts.explicit, opts.named) {
+        (Some(_), _) => Origin::Explicit,
+        (None, Some(_)) => Origin::NameExpr,
+        (None, None) => Origin::Generated,
+    }
+}
+
+pub fn resolve_output(input: &Path, opts: &OutputOptions) -> PathBuf {
+    let path = match (opts.explicit, opts.named) {
+        (Some(explicit), _) => explicit.to_path_buf(),
+        (None, Some(named)) => named.to_path_buf(),
+        (None, None) => generated(input, opts),
+    };
+    if is_stdout(&path) {
+        return path;
+    }
+
+    let path = match opts.device_folder {
+        Some(folder) => in_folder(&path, folder),
+        None => path,
+    };
+    let path = if opts.normalize_names { file_type::lowercase_extension(&path) } else { path };
+    if origin(opts) == Origin::Generated { portable(&path) } else { path }
+}
+
+// `-o -` writes to stdout
+pub fn is_stdout(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+// `-o converted/` names a folder even before it exists
+pub fn names_directory(path: &Path) -> bool {
+    path.as_os_str().to_string_lossy().ends_with(['/', std::path::MAIN_SEPARATOR])
+}
+
+// The input name without its image extensions and with the new extension (or the name
+// from a template), in --output-dir or next to the input, and in the date folder
+fn generated(input: &Path, opts: &OutputOptions) -> PathBuf {
+    let parent = opts.directory.or(input.parent()).unwrap_or(Path::new("."));
+    let parent = match opts.date_folder {
+        Some(folder) => parent.join(folder),
+        None => parent.to_path_buf(),
+    };
+    match opts.template {
+        Some(name) => parent.join(name),
+        None => parent.join(format!("{}.{}", file_type::base_stem(input), opts.extension)),
+    }
+}
+
+// 2024/07 for --organize-by-date, or undated for photos without a capture date
+pub fn date_folder(date: Option<NaiveDateTime>) -> PathBuf {
+    match date {
+        Some(date) => Path::new(&date.format("%Y").to_string()).join(date.format("%m").to_string()),
+        None => PathBuf::from("undated"),
+    }
+}
+
+// dir/name.png -> dir/folder/name.png
+fn in_folder(path: &Path, folder: &str) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent.join(folder).join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+// The folder in `directory` for a batch input: the input's own folder relative to the
+// root (a folder or pattern given with -i) it was found below, so the tree is mirrored
+pub fn mirrored(directory: &Path, input: &Path, roots: &[PathBuf]) -> PathBuf {
+    let parent = input.parent().unwrap_or(Path::new(""));
+    roots
+        .iter()
+        .filter_map(|root| parent.strip_prefix(root).ok())
+        .min_by_key(|relative| relative.components().count())
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .map_or_else(|| directory.to_path_buf(), |relative| directory.join(relative))
+}
+
+// `path`, or the first of name_1.ext, name_2.ext, ... that is not `taken`
+pub fn numbered(path: &Path, taken: impl Fn(&Path) -> bool) -> PathBuf {
+    if !taken(path) {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|number| path.with_file_name(format!("{}_{}{}", stem, number, extension)))
+        .find(|candidate| !taken(candidate))
+        .expect("some number is free")
+}
+
+// Generated names must survive a copy to a Windows disk or share, so reserved device
+// names get an underscore and trailing dots and spaces (dropped by Windows) are removed
+fn portable(path: &Path) -> PathBuf {
+    let Some(name) = path.file_name().map(|name| name.to_string_lossy().to_string()) else {
+        return path.to_path_buf();
+    };
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem.to_string(), Some(extension)),
+        _ => (name.clone(), None),
+    };
+    let mut stem = stem.trim_end_matches(['.', ' ']).to_string();
+    if stem.is_empty() {
+        stem.push('_');
+    }
+    let device = stem.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(device)) {
+        stem.insert(device.len(), '_');
+    }
+    let portable = match extension {
+        Some(extension) => format!("{}.{}", stem, extension),
+        None => stem,
+    };
+    if portable == name { path.to_path_buf() } else { path.with_file_name(portable) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(extension: &str) -> OutputOptions<'_> {
+        OutputOptions { extension, ..Default::default() }
+    }
+
+    fn resolve(input: &str, opts: &OutputOptions) -> PathBuf {
+        resolve_output(Path::new(input), opts)
+    }
+
+    #[test]
+    fn generated_name_sits_next_to_the_input() {
+        assert_eq!(resolve("photos/IMG_0001.heic", &options("png")), Path::new("photos/IMG_0001.png"));
+        assert_eq!(resolve("/abs/dir/IMG_0001.HEIC", &options("jpg")), Path::new("/abs/dir/IMG_0001.jpg"));
+    }
+
+    #[test]
+    fn output_directory_replaces_the_input_folder() {
+        let opts = OutputOptions { directory: Some(Path::new("/converted")), ..options("jpg") };
+        assert_eq!(resolve("photos/IMG_0001.heic", &opts), Path::new("/converted/IMG_0001.jpg"));
+
+        // Only generated names move; -o and --name-expr are used as given
+        let opts = OutputOptions {
+            directory: Some(Path::new("/converted")),
+            explicit: Some(Path::new("out/x.jpg")),
+            ..options("jpg")
+        };
+        assert_eq!(resolve("a.heic", &opts), Path::new("out/x.jpg"));
+    }
+
+    #[test]
+    fn bare_file_name_stays_in_the_current_directory() {
+        assert_eq!(resolve("IMG_0001.heic", &options("png")), Path::new("IMG_0001.png"));
+    }
+
+    #[test]
+    fn stacked_and_mixed_case_extensions_are_replaced() {
+        assert_eq!(resolve("IMG_0001.HEIC.heic", &options("png")), Path::new("IMG_0001.png"));
+        assert_eq!(resolve("IMG_0001.Heif", &options("png")), Path::new("IMG_0001.png"));
+    }
+
+    #[test]
+    fn other_dots_are_kept() {
+        assert_eq!(resolve("trip.v2.heic", &options("png")), Path::new("trip.v2.png"));
+        assert_eq!(resolve("archive.tar", &options("png")), Path::new("archive.tar.png"));
+    }
+
+    #[test]
+    fn input_without_extension_gets_one() {
+        assert_eq!(resolve("dir/IMG_0001", &options("webp")), Path::new("dir/IMG_0001.webp"));
+    }
+
+    #[test]
+    fn hidden_file_keeps_its_leading_dot() {
+        assert_eq!(resolve(".photo.heic", &options("png")), Path::new(".photo.png"));
+    }
+
+    #[test]
+    fn explicit_output_wins() {
+        let opts = OutputOptions {
+            explicit: Some(Path::new("out/x.PNG")),
+            named: Some(Path::new("named.png")),
+            ..options("png")
+        };
+        assert_eq!(resolve("a.heic", &opts), Path::new("out/x.PNG"));
+        assert_eq!(origin(&opts), Origin::Explicit);
+    }
+
+    #[test]
+    fn name_expression_is_used_without_explicit_output() {
+        let opts = OutputOptions { named: Some(Path::new("2026/a.png")), ..options("png") };
+        assert_eq!(resolve("a.heic", &opts), Path::new("2026/a.png"));
+        assert_eq!(origin(&opts), Origin::NameExpr);
+    }
+
+    #[test]
+    fn generated_origin_is_reported() {
+        assert_eq!(origin(&options("png")), Origin::Generated);
+    }
+
+    #[test]
+    fn trailing_separator_names_a_directory() {
+        assert!(names_directory(Path::new("converted/")));
+        assert!(names_directory(Path::new("/abs/converted/")));
+        assert!(!names_directory(Path::new("converted")));
+        assert!(!names_directory(Path::new("photo.png")));
+        assert!(!names_directory(Path::new("-")));
+    }
+
+    #[test]
+    fn stdout_is_never_changed() {
+        let opts = OutputOptions {
+            explicit: Some(Path::new("-")),
+            device_folder: Some("iPhone 15"),
+            normalize_names: true,
+            ..options("png")
+        };
+        assert_eq!(resolve("a.heic", &opts), Path::new("-"));
+        assert!(is_stdout(Path::new("-")));
+        assert!(!is_stdout(Path::new("./-")));
+    }
+
+    #[test]
+    fn device_folder_goes_before_the_file_name() {
+        let opts = OutputOptions { device_folder: Some("Anna"), ..options("png") };
+        assert_eq!(resolve("photos/a.heic", &opts), Path::new("photos/Anna/a.png"));
+        assert_eq!(resolve("a.heic", &opts), Path::new("Anna/a.png"));
+
+        let opts = OutputOptions {
+            explicit: Some(Path::new("out/x.png")),
+            device_folder: Some("Anna"),
+            ..options("png")
+        };
+        assert_eq!(resolve("a.heic", &opts), Path::new("out/Anna/x.png"));
+    }
+
+    #[test]
+    fn date_folder_holds_generated_names_only() {
+        let july = chrono::NaiveDate::from_ymd_opt(2024, 7, 14).unwrap().and_hms_opt(9, 30, 0);
+        let folder = date_folder(july);
+        let opts = OutputOptions { directory: Some(Path::new("library")), date_folder: Some(&folder), ..options("jpg") };
+        assert_eq!(resolve("import/a.heic", &opts), Path::new("library/2024/07/a.jpg"));
+        assert_eq!(date_folder(None), Path::new("undated"));
+
+        let opts = OutputOptions { explicit: Some(Path::new("x.jpg")), date_folder: Some(&folder), ..options("jpg") };
+        assert_eq!(resolve("a.heic", &opts), Path::new("x.jpg"));
+    }
+
+    #[test]
+    fn normalize_names_lowercases_only_the_extension() {
+        let opts = OutputOptions { explicit: Some(Path::new("Out/IMG.PNG")), normalize_names: true, ..options("png") };
+        assert_eq!(resolve("a.heic", &opts), Path::new("Out/IMG.png"));
+
+        let opts = OutputOptions { named: Some(Path::new("IMG.JPG")), normalize_names: true, ..options("jpg") };
+        assert_eq!(resolve("a.heic", &opts), Path::new("IMG.jpg"));
+    }
+
+    #[test]
+    fn extension_case_is_kept_without_normalize_names() {
+        let opts = OutputOptions { explicit: Some(Path::new("IMG.PNG")), ..options("png") };
+        assert_eq!(resolve("a.heic", &opts), Path::new("IMG.PNG"));
+    }
+
+    #[test]
+    fn reserved_windows_names_are_escaped() {
+        assert_eq!(resolve("dir/CON.heic", &options("png")), Path::new("dir/CON_.png"));
+        assert_eq!(resolve("nul.heic", &options("png")), Path::new("nul_.png"));
+        assert_eq!(resolve("com1.v2.heic", &options("png")), Path::new("com1_.v2.png"));
+    }
+
+    #[test]
+    fn names_only_starting_like_reserved_ones_are_kept() {
+        assert_eq!(resolve("CONCERT.heic", &options("png")), Path::new("CONCERT.png"));
+        assert_eq!(resolve("COM10.heic", &options("png")), Path::new("COM10.png"));
+    }
+
+    #[test]
+    fn trailing_dots_and_spaces_are_removed() {
+        assert_eq!(resolve("holiday .heic", &options("png")), Path::new("holiday.png"));
+        assert_eq!(resolve("wait... .heic", &options("png")), Path::new("wait.png"));
+    }
+
+    #[test]
+    fn explicit_and_named_paths_are_not_made_portable() {
+        let opts = OutputOptions { explicit: Some(Path::new("CON.png")), ..options("png") };
+        assert_eq!(resolve("a.heic", &opts), Path::new("CON.png"));
+        let opts = OutputOptions { named: Some(Path::new("NUL.png")), ..options("png") };
+        assert_eq!(resolve("a.heic", &opts), Path::new("NUL.png"));
+    }
+
+    #[test]
+    fn all_steps_combine() {
+        let opts = OutputOptions { device_folder: Some("Ben"), normalize_names: true, ..options("JPG") };
+        assert_eq!(resolve("dcim/aux.HEIC", &opts), Path::new("dcim/Ben/aux_.jpg"));
+    }
+
+    #[test]
+    fn numbered_names_skip_taken_ones() {
+        let taken = |path: &Path| ["out/photo.png", "out/photo_1.png"].contains(&path.to_str().unwrap());
+        assert_eq!(numbered(Path::new("out/photo.png"), taken), PathBuf::from("out/photo_2.png"));
+        assert_eq!(numbered(Path::new("out/other.png"), taken), PathBuf::from("out/other.png"));
+        assert_eq!(numbered(Path::new("README"), |path| path == Path::new("README")), PathBuf::from("README_1"));
+    }
+
+    #[test]
+    fn batch_inputs_keep_their_folder_below_the_root() {
+        let roots = [PathBuf::from("Export"), PathBuf::from("Export/2023")];
+        let out = Path::new("converted");
+        assert_eq!(mirrored(out, Path::new("Export/2023/07/IMG_1.HEIC"), &roots), Path::new("converted/07"));
+        assert_eq!(mirrored(out, Path::new("Export/IMG_2.HEIC"), &roots), Path::new("converted"));
+        assert_eq!(mirrored(out, Path::new("elsewhere/IMG_3.HEIC"), &roots), Path::new("converted"));
+    }
+
+    #[test]
+    fn template_names_replace_the_stem_in_the_usual_folder() {
+        let opts = OutputOptions { template: Some(Path::new("2024/07/IMG_1.png")), ..options("png") };
+        assert_eq!(resolve("dcim/IMG_1.HEIC", &opts), Path::new("dcim/2024/07/IMG_1.png"));
+        let opts = OutputOptions { directory: Some(Path::new("out")), ..opts };
+        assert_eq!(resolve("dcim/IMG_1.HEIC", &opts), Path::new("out/2024/07/IMG_1.png"));
+    }
+}
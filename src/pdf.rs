@@ -0,0 +1,121 @@
+// PDF output, one page per image (-f pdf)
+//
+// Receipts and documents captured as HEIC are easiest to file as PDF. Every image
+// becomes a page of its own size at PAGE_DPI, drawn from a JPEG stream (DCTDecode) at
+// the --quality of JPEG output, so a page costs about as much as the JPEG would. HEIC
+// files that hold several images give one page each when ImageMagick decodes them.
+// The file is written by hand: a catalog, the page tree and three objects per page
+// (page, image, content stream), followed by the cross-reference table. It carries no
+// dates or producer string, so the same pages always give the same bytes.
+use image::codecs::jpeg::JpegEncoder;
+use image::{ColorType, DynamicImage};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+// Resolution the page size is worked out with: a 4032x3024 photo becomes a page of
+// about 34x26 cm, which prints sharp at any smaller size
+const PAGE_DPI: f64 = 300.0;
+
+pub fn save(pages: &[DynamicImage], path: &Path, quality: u8) -> image::ImageResult<()> {
+    fs::write(path, document(pages, quality)?)?;
+    Ok(())
+}
+
+fn document(pages: &[DynamicImage], quality: u8) -> image::ImageResult<Vec<u8>> {
+    let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::new();
+    let mut object = |pdf: &mut Vec<u8>, body: &[u8]| {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n", offsets.len()).as_bytes());
+        pdf.extend(body);
+        pdf.extend(b"\nendobj\n");
+    };
+
+    // Objects 1 and 2, then page n is object 3n + 3, its image 3n + 4 and its contents 3n + 5
+    let kids: Vec<String> = (0..pages.len()).map(|n| format!("{} 0 R", 3 * n + 3)).collect();
+    object(&mut pdf, b"<< /Type /Catalog /Pages 2 0 R >>");
+    object(&mut pdf, format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).as_bytes());
+    for (n, page) in pages.iter().enumerate() {
+        // JPEG holds grey or RGB at 8 bits per channel; alpha is dropped
+        let (page, space) = match page.color() {
+            ColorType::L8 => (page.clone(), "DeviceGray"),
+            ColorType::La8 | ColorType::L16 | ColorType::La16 => (DynamicImage::ImageLuma8(page.to_luma8()), "DeviceGray"),
+            _ => (DynamicImage::ImageRgb8(page.to_rgb8()), "DeviceRGB"),
+        };
+        let mut jpeg = Vec::new();
+        page.write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, quality))?;
+
+        let (width, height) = (page.width(), page.height());
+        let (points_wide, points_high) = (width as f64 * 72.0 / PAGE_DPI, height as f64 * 72.0 / PAGE_DPI);
+        object(
+            &mut pdf,
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>",
+                points_wide,
+                points_high,
+                3 * n + 4,
+                3 * n + 5
+            )
+            .as_bytes(),
+        );
+        let mut image = format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{} /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+            width,
+            height,
+            space,
+            jpeg.len()
+        )
+        .into_bytes();
+        image.extend(&jpeg);
+        image.extend(b"\nendstream");
+        object(&mut pdf, &image);
+        // Scale the unit square the image is drawn into to the page
+        let contents = format!("q {:.2} 0 0 {:.2} 0 0 cm /Im0 Do Q", points_wide, points_high);
+        object(&mut pdf, format!("<< /Length {} >>\nstream\n{}\nendstream", contents.len(), contents).as_bytes());
+    }
+
+    let xref = pdf.len();
+    write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1)?;
+    for offset in &offsets {
+        writeln!(pdf, "{:010} 00000 n ", offset)?;
+    }
+    write!(pdf, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", offsets.len() + 1, xref)?;
+    Ok(pdf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, RgbImage};
+
+    fn count(haystack: &[u8], needle: &[u8]) -> usize {
+        haystack.windows(needle.len()).filter(|window| *window == needle).count()
+    }
+
+    #[test]
+    fn every_image_is_a_page_of_its_own_size() {
+        let pages = [
+            DynamicImage::ImageRgb8(RgbImage::new(600, 300)),
+            DynamicImage::ImageLuma8(GrayImage::new(300, 600)),
+        ];
+        let pdf = document(&pages, 85).unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.4") && pdf.ends_with(b"%%EOF\n"));
+        assert_eq!(count(&pdf, b"/Type /Page "), 2);
+        assert_eq!(count(&pdf, b"/Count 2"), 1);
+        assert_eq!(count(&pdf, b"/MediaBox [0 0 144.00 72.00]"), 1);
+        assert_eq!(count(&pdf, b"/MediaBox [0 0 72.00 144.00]"), 1);
+        assert_eq!(count(&pdf, b"/DeviceGray"), 1);
+    }
+
+    #[test]
+    fn the_cross_reference_table_points_at_every_object() {
+        let pdf = document(&[DynamicImage::ImageRgb8(RgbImage::new(8, 8))], 85).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        let table = &text[text.rfind("xref\n").unwrap()..];
+        for (number, line) in table.lines().skip(3).take_while(|line| line.ends_with(" n ")).enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", number + 1).as_bytes()), "object {}", number + 1);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+// Import converted images into a self-hosted photo server (--import-to, feature "upload").
+// The album name is taken from the folder the original HEIC lives in, so an export
+// organised as Holidays/IMG_0001.heic ends up in a "Holidays" album on the server.
+use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
+use serde_json::{Value, json};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Environment variable consulted when --api-key is not given on the command line
+pub const API_KEY_ENV: &str = "HEIC_CONVERT_API_KEY";
+
+// Photo servers we know how to talk to
+#[derive(Clone, Debug, ValueEnum)]
+pub enum PhotoServer {
+    Immich,     // https://immich.app
+    Photoprism, // https://photoprism.app
+}
+
+// Connection details for the photo server
+pub struct ImportTarget {
+    pub server: PhotoServer,
+    pub api_url: String,
+    pub api_key: String,
+}
+
+// Upload `file` to the server and add it to the album derived from `source`'s folder name
+pub fn import_file(file: &Path, source: &Path, target: &ImportTarget) -> Result<()> {
+    let album = album_name(source);
+    let base_url = target.api_url.trim_end_matches('/');
+
+    match target.server {
+        PhotoServer::Immich => import_immich(file, album.as_deref(), base_url, &target.api_key),
+        PhotoServer::Photoprism => {
+            import_photoprism(file, album.as_deref(), base_url, &target.api_key)
+        }
+    }
+}
+
+// Album name from the source folder, e.g. ~/Export/Holidays/IMG_1.heic -> "Holidays"
+fn album_name(source: &Path) -> Option<String> {
+    source
+        .parent()
+        .and_then(|parent| parent.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .filter(|name| !name.is_empty() && name != ".")
+}
+
+// Immich: POST /api/assets, then add the asset to the (possibly new) album
+fn import_immich(file: &Path, album: Option<&str>, base_url: &str, api_key: &str) -> Result<()> {
+    let file_name = file_name(file);
+    let data = fs::read(file).with_context(|| format!("❌ Cannot read {}", file.display()))?;
+    let modified = fs::metadata(file).and_then(|m| m.modified()).unwrap_or_else(|_| SystemTime::now());
+    let modified = chrono::DateTime::<chrono::Utc>::from(modified).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
+    say!("Importing {} into Immich at {}", file_name, base_url);
+
+    let device_asset_id = format!("{}-{}", file_name, data.len());
+    let fields = [
+        ("deviceAssetId", device_asset_id.as_str()),
+        ("deviceId", "heic_convert"),
+        ("fileCreatedAt", modified.as_str()),
+        ("fileModifiedAt", modified.as_str()),
+    ];
+    let (content_type, body) = multipart_body(&fields, "assetData", &file_name, &data);
+
+    let response: Value = ureq::post(&format!("{}/api/assets", base_url))
+        .set("x-api-key", api_key)
+        .set("Accept", "application/json")
+        .set("Content-Type", &content_type)
+        .send_bytes(&body)
+        .map_err(|e| server_error("Immich", base_url, e))?
+        .into_json()?;
+
+    let asset_id = response["id"]
+        .as_str()
+        .ok_or_else(|| anyhow!("❌ Unexpected response from Immich: {}", response))?
+        .to_string();
+    if response["status"] == "duplicate" {
+        say!("Immich already has this image (asset {})", asset_id);
+    }
+
+    let Some(album) = album else {
+        say!("✅ Imported {} into Immich", file_name);
+        return Ok(());
+    };
+
+    // Reuse an existing album with the same name, otherwise create it with this asset
+    let albums: Value = ureq::get(&format!("{}/api/albums", base_url))
+        .set("x-api-key", api_key)
+        .set("Accept", "application/json")
+        .call()
+        .map_err(|e| server_error("Immich", base_url, e))?
+        .into_json()?;
+    let existing = albums
+        .as_array()
+        .and_then(|list| list.iter().find(|a| a["albumName"] == album))
+        .and_then(|a| a["id"].as_str())
+        .map(str::to_string);
+
+    match existing {
+        Some(album_id) => {
+            ureq::put(&format!("{}/api/albums/{}/assets", base_url, album_id))
+                .set("x-api-key", api_key)
+                .send_json(json!({ "ids": [asset_id] }))
+                .map_err(|e| server_error("Immich", base_url, e))?;
+        }
+        None => {
+            ureq::post(&format!("{}/api/albums", base_url))
+                .set("x-api-key", api_key)
+                .send_json(json!({ "albumName": album, "assetIds": [asset_id] }))
+                .map_err(|e| server_error("Immich", base_url, e))?;
+        }
+    }
+
+    say!("✅ Imported {} into Immich album '{}'", file_name, album);
+    Ok(())
+}
+
+// PhotoPrism: upload into a temporary upload folder, then trigger the import with albums
+fn import_photoprism(file: &Path, album: Option<&str>, base_url: &str, api_key: &str) -> Result<()> {
+    let file_name = file_name(file);
+    let data = fs::read(file).with_context(|| format!("❌ Cannot read {}", file.display()))?;
+    let authorization = format!("Bearer {}", api_key);
+
+    say!("Importing {} into PhotoPrism at {}", file_name, base_url);
+
+    // The upload endpoints are scoped to the user that owns the app password
+    let session: Value = ureq::get(&format!("{}/api/v1/session", base_url))
+        .set("Authorization", &authorization)
+        .call()
+        .map_err(|e| server_error("PhotoPrism", base_url, e))?
+        .into_json()?;
+    let user_uid = session["user"]["UID"]
+        .as_str()
+        .ok_or_else(|| anyhow!("❌ PhotoPrism did not report a user for this API key"))?
+        .to_string();
+
+    // Any unique token works; it names the upload folder on the server
+    let token = format!(
+        "heicconvert{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0)
+    );
+    let upload_url = format!("{}/api/v1/users/{}/upload/{}", base_url, user_uid, token);
+
+    let (content_type, body) = multipart_body(&[], "files", &file_name, &data);
+    ureq::post(&upload_url)
+        .set("Authorization", &authorization)
+        .set("Content-Type", &content_type)
+        .send_bytes(&body)
+        .map_err(|e| server_error("PhotoPrism", base_url, e))?;
+
+    let albums: Vec<&str> = album.into_iter().collect();
+    ureq::put(&upload_url)
+        .set("Authorization", &authorization)
+        .send_json(json!({ "albums": albums }))
+        .map_err(|e| server_error("PhotoPrism", base_url, e))?;
+
+    match album {
+        Some(album) => say!("✅ Imported {} into PhotoPrism album '{}'", file_name, album),
+        None => say!("✅ Imported {} into PhotoPrism", file_name),
+    }
+    Ok(())
+}
+
+fn file_name(file: &Path) -> String {
+    file.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("image"))
+}
+
+// Build a multipart/form-data body with text fields and one file part
+fn multipart_body(
+    fields: &[(&str, &str)],
+    file_field: &str,
+    file_name: &str,
+    data: &[u8],
+) -> (String, Vec<u8>) {
+    let boundary = format!(
+        "heic-convert-{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0)
+    );
+
+    let mut body = Vec::new();
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary, name, value
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n",
+            boundary,
+            file_field,
+            file_name.replace('"', "_")
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    (format!("multipart/form-data; boundary={}", boundary), body)
+}
+
+// Translate HTTP failures into the tool's troubleshooting style
+fn server_error(server: &str, base_url: &str, error: ureq::Error) -> anyhow::Error {
+    match error {
+        ureq::Error::Status(401, _) | ureq::Error::Status(403, _) => anyhow!(
+            "❌ {} at {} rejected the API key.\n\
+             Create an API key (Immich) or app password (PhotoPrism) and pass it with\n\
+             --api-key or the {} environment variable.",
+            server,
+            base_url,
+            API_KEY_ENV
+        ),
+        other => anyhow!(
+            "❌ Import into {} at {} failed: {}\n\
+             Please check the server address and that the server is running.",
+            server,
+            base_url,
+            other
+        ),
+    }
+}
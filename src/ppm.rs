@@ -0,0 +1,52 @@
+// PPM output for netpbm tool chains and scripts (-f ppm)
+//
+// Binary PPM (P6) is about the simplest raster file there is: a text header with the
+// size and the largest sample value, followed by the RGB samples row by row, so any
+// script can read it and every netpbm tool accepts it. Grey images are written as RGB
+// too, so readers that expect PPM never get a PGM file, and transparency is dropped.
+// Images with more than 8 bits per channel keep them, as big-endian 16-bit samples
+// (maxval 65535). The image crate writes 8-bit PPM only, hence this writer.
+use image::{ColorType, DynamicImage};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub fn save(img: &DynamicImage, path: &Path) -> image::ImageResult<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(&encode(img))?;
+    file.flush()?;
+    Ok(())
+}
+
+fn encode(img: &DynamicImage) -> Vec<u8> {
+    let deep = matches!(
+        img.color(),
+        ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 | ColorType::Rgb32F | ColorType::Rgba32F
+    );
+    let maxval = if deep { u16::MAX } else { u8::MAX as u16 };
+    let mut ppm = format!("P6\n{} {}\n{}\n", img.width(), img.height(), maxval).into_bytes();
+    if deep {
+        ppm.extend(img.to_rgb16().as_raw().iter().flat_map(|sample| sample.to_be_bytes()));
+    } else {
+        ppm.extend(img.to_rgb8().as_raw());
+    }
+    ppm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayAlphaImage, ImageBuffer, LumaA, Rgb};
+
+    #[test]
+    fn grey_with_alpha_becomes_rgb() {
+        let img = DynamicImage::ImageLumaA8(GrayAlphaImage::from_pixel(2, 1, LumaA([200, 10])));
+        assert_eq!(encode(&img), b"P6\n2 1\n255\n\xC8\xC8\xC8\xC8\xC8\xC8");
+    }
+
+    #[test]
+    fn sixteen_bit_samples_are_big_endian() {
+        let img = DynamicImage::ImageRgb16(ImageBuffer::from_pixel(1, 1, Rgb([0x0102, 0x0304, 0xFFFF])));
+        assert_eq!(encode(&img), b"P6\n1 1\n65535\n\x01\x02\x03\x04\xFF\xFF");
+    }
+}
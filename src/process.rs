@@ -0,0 +1,85 @@
+// Running external conversion tools (ImageMagick, FFmpeg)
+//
+// The backends describe what to run as an Invocation and hand it to a ProcessRunner.
+// SystemRunner starts the real process; tests use a runner that records the invocation
+// and answers with canned output, so argument lists and error messages can be checked
+// without the tools installed. SystemRunner is the one place external conversion
+// processes are started, so limits on them (time, sandboxing) belong there.
+// Without the "external-tools" feature it starts nothing and every tool counts as
+// missing, so only the built-in decoder is used.
+use std::ffi::{OsStr, OsString};
+use std::io;
+
+#[cfg(feature = "external-tools")]
+use crate::cores;
+
+// A program with its arguments and extra environment variables
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Invocation {
+    pub program: String,
+    pub args: Vec<OsString>,
+    pub env: Vec<(String, String)>,
+}
+
+impl Invocation {
+    pub fn new(program: &str) -> Self {
+        Invocation { program: program.to_string(), ..Default::default() }
+    }
+
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    pub fn env(&mut self, key: &str, value: &str) -> &mut Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+}
+
+// What a finished process left behind
+#[derive(Clone, Debug, Default)]
+pub struct Finished {
+    pub success: bool,
+    pub stderr: Vec<u8>,
+}
+
+impl Finished {
+    pub fn stderr_text(&self) -> String {
+        String::from_utf8_lossy(&self.stderr).into_owned()
+    }
+}
+
+pub trait ProcessRunner {
+    // Run to completion; Err means the program could not be started at all
+    fn run(&self, invocation: &Invocation) -> io::Result<Finished>;
+}
+
+// Starts real processes, honouring --cores
+pub struct SystemRunner;
+
+impl ProcessRunner for SystemRunner {
+    #[cfg(feature = "external-tools")]
+    fn run(&self, invocation: &Invocation) -> io::Result<Finished> {
+        let output = cores::command(&invocation.program)
+            .args(&invocation.args)
+            .envs(invocation.env.iter().map(|(key, value)| (key, value)))
+            .output()?;
+        Ok(Finished { success: output.status.success(), stderr: output.stderr })
+    }
+
+    #[cfg(not(feature = "external-tools"))]
+    fn run(&self, invocation: &Invocation) -> io::Result<Finished> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} cannot be started: this build has no external tool support", invocation.program),
+        ))
+    }
+}
+
+// Whether `program -version` starts and succeeds
+pub fn available(runner: &dyn ProcessRunner, program: &str) -> bool {
+    runner
+        .run(Invocation::new(program).arg("-version"))
+        .is_ok_and(|finished| finished.success)
+}
@@ -0,0 +1,119 @@
+// Output quality: the default of each format and --quality auto
+//
+// Without --quality every format that has a quality setting gets the value that suits
+// photos best for its size, from the table below. --quality auto looks at the image
+// instead: fine detail and sensor noise hide compression artefacts, so busy images get
+// a lower quality, while smooth skies and gradients, where blocks and banding show
+// first, get a higher one. How busy an image is comes from the average difference
+// between neighbouring pixels of a small copy.
+use image::DynamicImage;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quality {
+    Auto,
+    Fixed(u8), // 1-100
+}
+
+// Visually lossless for photos at about a third of the size of quality 100
+pub const JPEG_DEFAULT: u8 = 85;
+
+// WebP reaches the look of JPEG 85 at a lower setting
+pub const WEBP_DEFAULT: u8 = 80;
+
+// The default of cavif, the reference AVIF encoder
+pub const AVIF_DEFAULT: u8 = 80;
+
+// The default of cjxl, the same as its distance 1: visually lossless
+pub const JXL_DEFAULT: u8 = 90;
+
+// Default quality by output extension; formats without a quality setting are missing
+const DEFAULTS: &[(&str, u8)] =
+    &[("jpg", JPEG_DEFAULT), ("webp", WEBP_DEFAULT), ("avif", AVIF_DEFAULT), ("jxl", JXL_DEFAULT), ("pdf", JPEG_DEFAULT)];
+
+// Range --quality auto chooses from, for smooth and for busy images
+const SMOOTHEST: u8 = 92;
+const BUSIEST: u8 = 75;
+
+// Average neighbour difference (0-255) up to which an image counts as smooth, and from
+// which as busy
+const SMOOTH: f64 = 2.0;
+const BUSY: f64 = 24.0;
+
+// Longest edge of the copy that is measured
+const SAMPLE_EDGE: u32 = 256;
+
+pub fn parse_quality(text: &str) -> Result<Quality, String> {
+    match text.trim().to_lowercase().as_str() {
+        "auto" => Ok(Quality::Auto),
+        value => value
+            .parse::<u8>()
+            .ok()
+            .filter(|quality| (1..=100).contains(quality))
+            .map(Quality::Fixed)
+            .ok_or_else(|| format!("invalid quality '{}'; use 1-100 or auto", text)),
+    }
+}
+
+pub fn default_for(extension: &str) -> Option<u8> {
+    DEFAULTS.iter().find(|(format, _)| *format == extension).map(|(_, quality)| *quality)
+}
+
+// Average difference between horizontally and vertically neighbouring luminance values
+pub fn busyness(image: &DynamicImage) -> f64 {
+    let sample = if image.width().max(image.height()) > SAMPLE_EDGE {
+        image.thumbnail(SAMPLE_EDGE, SAMPLE_EDGE).to_luma8()
+    } else {
+        image.to_luma8()
+    };
+    let (width, height) = sample.dimensions();
+    let (mut sum, mut count) = (0u64, 0u64);
+    for y in 0..height {
+        for x in 0..width {
+            let here = sample.get_pixel(x, y)[0];
+            if x + 1 < width {
+                sum += here.abs_diff(sample.get_pixel(x + 1, y)[0]) as u64;
+                count += 1;
+            }
+            if y + 1 < height {
+                sum += here.abs_diff(sample.get_pixel(x, y + 1)[0]) as u64;
+                count += 1;
+            }
+        }
+    }
+    sum as f64 / count.max(1) as f64
+}
+
+// The quality --quality auto picks for an image of the given busyness
+pub fn auto(busyness: f64) -> u8 {
+    let share = ((busyness - SMOOTH) / (BUSY - SMOOTH)).clamp(0.0, 1.0);
+    (SMOOTHEST as f64 - share * (SMOOTHEST - BUSIEST) as f64).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn quality_is_a_number_or_auto() {
+        assert_eq!(parse_quality("85"), Ok(Quality::Fixed(85)));
+        assert_eq!(parse_quality("Auto"), Ok(Quality::Auto));
+        assert!(parse_quality("0").is_err());
+        assert!(parse_quality("101").is_err());
+        assert!(parse_quality("high").is_err());
+    }
+
+    #[test]
+    fn only_formats_with_a_quality_setting_have_a_default() {
+        assert_eq!(default_for("jpg"), Some(85));
+        assert_eq!(default_for("png"), None);
+    }
+
+    #[test]
+    fn smooth_images_get_a_higher_quality_than_busy_ones() {
+        let gradient = GrayImage::from_fn(200, 100, |x, _| Luma([(x * 255 / 199) as u8]));
+        let checkers = GrayImage::from_fn(200, 100, |x, y| Luma([if (x + y) % 2 == 0 { 0 } else { 255 }]));
+        assert_eq!(auto(busyness(&DynamicImage::ImageLuma8(gradient))), SMOOTHEST);
+        assert_eq!(auto(busyness(&DynamicImage::ImageLuma8(checkers))), BUSIEST);
+    }
+}
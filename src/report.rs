@@ -0,0 +1,200 @@
+// Manifest of every conversion in a run (--report run.json, --report run.csv)
+//
+// For auditing large migrations: one entry per input with the output it became, the
+// backend that converted it, how long that took, the size of the output in pixels and
+// whether it was converted, skipped (--skip-existing), a copy of another input
+// (--skip-duplicates, --link-duplicates) or failed, and why. The entries
+// are collected from the conversion events and written when the run ends, in the order
+// of the inputs, as CSV when the file name ends in .csv and as JSON otherwise.
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use crate::{error_codes, events, file_type};
+
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub finished: String, // RFC 3339
+    pub files: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    pub input: String,
+    pub output: Option<String>, // The existing output for skipped inputs; None when the conversion failed
+    pub backend: Option<String>, // builtin, imagemagick or ffmpeg; None when none was reached
+    pub duration_ms: u128,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub status: &'static str, // "converted", "skipped", "duplicate" or "failed"
+    pub duplicate_of: Option<String>, // The input with the same content
+    pub error: Option<String>,
+    pub code: Option<String>, // E0xx, see `heic_convert explain`
+}
+
+const CSV_HEADER: &str = "input,output,backend,duration_ms,width,height,status,duplicate_of,error,code";
+
+// The entries of the conversions so far, with their input paths, and where the latest
+// entry of each input is, so an event finds its entry without a search of the list
+struct Entries {
+    list: Vec<(PathBuf, Entry)>,
+    latest: BTreeMap<PathBuf, usize>,
+}
+
+impl Entries {
+    fn push(&mut self, input: &Path, entry: Entry) -> &mut Entry {
+        self.latest.insert(input.to_path_buf(), self.list.len());
+        self.list.push((input.to_path_buf(), entry));
+        &mut self.list.last_mut().expect("just added").1
+    }
+
+    // The entry of the conversion of `input`; only inputs whose conversion has started have one
+    fn get(&mut self, input: &Path) -> Option<&mut Entry> {
+        let index = *self.latest.get(input)?;
+        Some(&mut self.list[index].1)
+    }
+}
+
+static ENTRIES: Mutex<Entries> = Mutex::new(Entries { list: Vec::new(), latest: BTreeMap::new() });
+
+// Start recording the conversions of this run
+pub fn collect() {
+    events::subscribe(|event: &events::Event| {
+        let mut entries = ENTRIES.lock().unwrap_or_else(PoisonError::into_inner);
+        match event {
+            events::Event::ConversionStarted(input) => {
+                entries.push(input, Entry::new(input));
+            }
+            // A later backend only decoded the input again, for --gamut-check
+            events::Event::BackendChosen { input, backend } => {
+                if let Some(entry) = entries.get(input) {
+                    entry.backend.get_or_insert_with(|| backend.to_string());
+                }
+            }
+            events::Event::ConversionSkipped { input, output } => {
+                if let Some(entry) = entries.get(input) {
+                    entry.status = "skipped";
+                    entry.set_output(output);
+                }
+            }
+            // A linked copy has an entry of its own conversion already
+            events::Event::DuplicateFound { input, original } => {
+                if entries.get(input).is_none() {
+                    let mut copy = Entry::new(input);
+                    copy.status = "duplicate";
+                    entries.push(input, copy);
+                }
+                let entry = entries.get(input).expect("just added");
+                entry.duplicate_of = Some(original.display().to_string());
+            }
+            events::Event::ConversionFinished { input, output, error, elapsed } => {
+                let Some(entry) = entries.get(input) else { return };
+                entry.duration_ms = elapsed.as_millis();
+                if let Some(output) = output {
+                    entry.status = "converted";
+                    entry.set_output(output);
+                }
+                if let Some(error) = error {
+                    entry.error = Some(crate::headline(error));
+                    entry.code = error_codes::code_of(error).map(|code| code.to_string());
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+impl Entry {
+    fn new(input: &Path) -> Entry {
+        Entry {
+            input: input.display().to_string(),
+            output: None,
+            backend: None,
+            duration_ms: 0,
+            width: None,
+            height: None,
+            status: "failed",
+            duplicate_of: None,
+            error: None,
+            code: None,
+        }
+    }
+
+    fn set_output(&mut self, output: &Path) {
+        self.output = Some(output.display().to_string());
+        // Nothing to measure behind -o -
+        if let Some((width, height)) = file_type::dimensions(output) {
+            (self.width, self.height) = (Some(width), Some(height));
+        }
+    }
+}
+
+// Write the manifest of the conversions of `inputs` to `path`
+pub fn write(path: &Path, inputs: &[PathBuf]) -> Result<()> {
+    let mut recorded = {
+        let mut entries = ENTRIES.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.latest.clear();
+        std::mem::take(&mut entries.list)
+    };
+    // Parallel jobs (--jobs) finish out of order
+    let order: HashMap<&Path, usize> = inputs.iter().enumerate().map(|(index, input)| (input.as_path(), index)).collect();
+    recorded.sort_by_key(|(input, _)| order.get(input.as_path()).copied());
+    let files: Vec<Entry> = recorded.into_iter().map(|(_, entry)| entry).collect();
+
+    let csv = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+    let text = if csv {
+        to_csv(&files)
+    } else {
+        let manifest = Manifest { finished: chrono::Local::now().to_rfc3339(), files };
+        serde_json::to_string_pretty(&manifest)? + "\n"
+    };
+    fs::write(path, text).with_context(|| format!("❌ Cannot write the report {}", path.display()))?;
+    say!("📋 Report written to {}", path.display());
+    Ok(())
+}
+
+fn to_csv(files: &[Entry]) -> String {
+    let mut text = format!("{}\n", CSV_HEADER);
+    for entry in files {
+        let optional = |value: Option<u32>| value.map(|value| value.to_string()).unwrap_or_default();
+        let fields = [
+            field(&entry.input),
+            field(entry.output.as_deref().unwrap_or_default()),
+            field(entry.backend.as_deref().unwrap_or_default()),
+            entry.duration_ms.to_string(),
+            optional(entry.width),
+            optional(entry.height),
+            entry.status.to_string(),
+            field(entry.duplicate_of.as_deref().unwrap_or_default()),
+            field(entry.error.as_deref().unwrap_or_default()),
+            entry.code.clone().unwrap_or_default(),
+        ];
+        text.push_str(&fields.join(","));
+        text.push('\n');
+    }
+    text
+}
+
+// A CSV field, quoted when it holds a separator, a quote or a line break (RFC 4180)
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(field("IMG_0001.HEIC"), "IMG_0001.HEIC");
+        assert_eq!(field("Trip, day 1/IMG_0001.HEIC"), "\"Trip, day 1/IMG_0001.HEIC\"");
+        assert_eq!(field("the \"best\" one"), "\"the \"\"best\"\" one\"");
+    }
+}
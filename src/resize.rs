@@ -0,0 +1,129 @@
+// Downscaling with --resize (also settable per file by config rules)
+//
+// Geometry follows ImageMagick's short form: "4000x" (width), "x3000" (height) or
+// "4000x3000" (fit inside the box). The aspect ratio is kept and images are only ever
+// made smaller, never enlarged.
+use image::DynamicImage;
+use image::imageops::FilterType;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resize {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+// Parse "4000x", "x3000" or "4000x3000"
+pub fn parse_resize(text: &str) -> Result<Resize, String> {
+    let usage = || format!("invalid size '{}'; use WIDTHx, xHEIGHT or WIDTHxHEIGHT, e.g. 4000x", text);
+    let lower = text.trim().to_lowercase();
+    let (width, height) = lower.split_once('x').ok_or_else(usage)?;
+    let dimension = |part: &str| -> Result<Option<u32>, String> {
+        match part.trim() {
+            "" => Ok(None),
+            value => value.parse::<u32>().ok().filter(|&v| v > 0).map(Some).ok_or_else(usage),
+        }
+    };
+    let resize = Resize {
+        width: dimension(width)?,
+        height: dimension(height)?,
+    };
+    if resize.width.is_none() && resize.height.is_none() {
+        return Err(usage());
+    }
+    Ok(resize)
+}
+
+impl fmt::Display for Resize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let part = |value: Option<u32>| value.map(|v| v.to_string()).unwrap_or_default();
+        write!(f, "{}x{}", part(self.width), part(self.height))
+    }
+}
+
+impl Resize {
+    // Target size for an image of the given size, or None when it already fits
+    pub fn target(&self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let scale_w = self.width.map_or(1.0, |w| w as f64 / width as f64);
+        let scale_h = self.height.map_or(1.0, |h| h as f64 / height as f64);
+        let scale = scale_w.min(scale_h);
+        if scale >= 1.0 {
+            return None;
+        }
+        let scaled = |value: u32| ((value as f64 * scale).round() as u32).max(1);
+        Some((scaled(width), scaled(height)))
+    }
+
+    // Shrink a decoded image (built-in backend)
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        match self.target(image.width(), image.height()) {
+            Some((width, height)) => image.resize_exact(width, height, FilterType::Lanczos3),
+            None => image,
+        }
+    }
+
+    // ImageMagick geometry; ">" only shrinks larger images
+    pub fn imagemagick_geometry(&self) -> String {
+        format!("{}>", self)
+    }
+
+    // FFmpeg scale filter that fits the box without enlarging; -2 keeps the other side even
+    pub fn ffmpeg_filter(&self) -> String {
+        match (self.width, self.height) {
+            (Some(w), None) => format!("scale='min({},iw)':-2", w),
+            (None, Some(h)) => format!("scale=-2:'min({},ih)'", h),
+            (Some(w), Some(h)) => format!(
+                "scale='min({},iw)':'min({},ih)':force_original_aspect_ratio=decrease",
+                w, h
+            ),
+            (None, None) => String::from("null"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geometry_is_parsed() {
+        let cases = [
+            ("4000x", Some((Some(4000), None))),
+            ("x3000", Some((None, Some(3000)))),
+            ("4000X3000", Some((Some(4000), Some(3000)))),
+            (" 800 x 600 ", Some((Some(800), Some(600)))),
+            ("x", None),
+            ("4000", None),
+            ("0x100", None),
+            ("-5x", None),
+            ("widex", None),
+        ];
+        for (text, expected) in cases {
+            let parsed = parse_resize(text).ok().map(|resize| (resize.width, resize.height));
+            assert_eq!(parsed, expected, "{:?}", text);
+        }
+        assert_eq!(parse_resize("4000x").unwrap().to_string(), "4000x");
+    }
+
+    #[test]
+    fn target_shrinks_keeping_the_aspect_ratio() {
+        let cases = [
+            // (geometry, image size, target)
+            ("4000x", (8000, 6000), Some((4000, 3000))),
+            ("x3000", (8000, 6000), Some((4000, 3000))),
+            ("4000x4000", (8000, 6000), Some((4000, 3000))),
+            ("4000x1000", (8000, 6000), Some((1333, 1000))),
+            ("1000x", (3000, 2000), Some((1000, 667))),
+            // Never enlarged: images that fit are left alone
+            ("4000x", (4000, 3000), None),
+            ("4000x3000", (1000, 800), None),
+            ("x3000", (9000, 3000), None),
+            // Never below one pixel
+            ("10x", (10000, 5), Some((10, 1))),
+            ("x1", (300, 100), Some((3, 1))),
+        ];
+        for (geometry, (width, height), expected) in cases {
+            assert_eq!(parse_resize(geometry).unwrap().target(width, height), expected, "{} of {}x{}", geometry, width, height);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+// Progress of a multi-file run, so that an interrupted one can go on (--resume)
+//
+// Every input that a multi-file run converts, or skips with --skip-existing, is added to
+// a progress file as soon as it is done, one absolute path per line, flushed to the
+// disk so that the list survives Ctrl-C, a crash or a power loss. The file belongs to
+// the run: its name is a checksum of the inputs and where they are converted to, so
+// running the same command again with --resume finds it and leaves the listed inputs
+// out; failed ones are tried again. A run that ends with every file done removes it,
+// and a run without --resume starts over.
+//   $XDG_DATA_HOME/heic_convert/progress/<checksum>.txt  (usually ~/.local/share/...)
+//   %LOCALAPPDATA%\heic_convert\progress\<checksum>.txt  (Windows)
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use crate::{Cli, events, stats};
+
+pub struct Progress {
+    path: Option<PathBuf>,     // None without a home directory; nothing is noted then
+    file: Mutex<Option<File>>, // Opened when the first input is done
+}
+
+impl Progress {
+    // The progress file of converting `inputs` with the output options of `cli`. Unless
+    // the run is resumed, an earlier one of the same run is removed.
+    pub fn open(cli: &Cli, inputs: &[PathBuf]) -> Result<Progress> {
+        let path = stats::data_dir().map(|dir| dir.join("progress").join(format!("{}.txt", run_id(cli, inputs))));
+        if let Some(path) = path.as_deref().filter(|_| !cli.resume) {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    return Err(e).with_context(|| format!("❌ Cannot remove the old progress file {}", path.display()));
+                }
+                _ => {}
+            }
+        }
+        Ok(Progress { path, file: Mutex::new(None) })
+    }
+
+    // The inputs the interrupted run finished; none when it left no progress file
+    pub fn finished(&self) -> Result<HashSet<PathBuf>> {
+        let Some(path) = &self.path else { return Ok(HashSet::new()) };
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(text.lines().filter(|line| !line.is_empty()).map(PathBuf::from).collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(HashSet::new()),
+            Err(e) => Err(e).with_context(|| format!("❌ Cannot read the progress file {}", path.display())),
+        }
+    }
+
+    // Note `input` as done; a progress file that cannot be written only costs the resume
+    pub fn record(&self, input: &Path) {
+        let Some(path) = &self.path else { return };
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        let line = format!("{}\n", key(input).display());
+        let written = match &mut *file {
+            Some(file) => file.write_all(line.as_bytes()).and_then(|_| file.sync_data()),
+            None => fs::create_dir_all(path.parent().unwrap_or(Path::new(".")))
+                .and_then(|_| OpenOptions::new().create(true).append(true).open(path))
+                .and_then(|opened| {
+                    let opened = file.insert(opened);
+                    opened.write_all(line.as_bytes()).and_then(|_| opened.sync_data())
+                }),
+        };
+        if let Err(e) = written {
+            events::warn(format!("Cannot note the progress in {}: {}", path.display(), e));
+        }
+    }
+
+    // Every file of the run is done: nothing is left to resume
+    pub fn finish(self) {
+        drop(self.file);
+        if let Some(path) = &self.path {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+// Inputs are noted by their absolute path, so "photos/IMG_0001.HEIC" and
+// "./photos/IMG_0001.HEIC" are the same input
+pub fn key(input: &Path) -> PathBuf {
+    fs::canonicalize(input).unwrap_or_else(|_| input.to_path_buf())
+}
+
+// Checksum of what the run converts and into what, whatever the order of the inputs
+fn run_id(cli: &Cli, inputs: &[PathBuf]) -> String {
+    let mut keys: Vec<PathBuf> = inputs.iter().map(|input| key(input)).collect();
+    keys.sort();
+    let mut hasher = Sha256::new();
+    for key in &keys {
+        hasher.update(key.to_string_lossy().as_bytes());
+        hasher.update([0]);
+    }
+    // Not canonicalized: the output folder may only be created by the run
+    let target = cli.output_dir.as_ref().or(cli.output.as_ref()).map(|target| {
+        let absolute = std::path::absolute(target).unwrap_or_else(|_| target.clone());
+        absolute.components().collect::<PathBuf>()
+    });
+    hasher.update(format!("{}\0{:?}", cli.output_format().extension(), target).as_bytes());
+    hasher.finalize().iter().take(8).map(|byte| format!("{:02x}", byte)).collect()
+}
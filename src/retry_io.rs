@@ -0,0 +1,122 @@
+// Resumable reads from unreliable storage (--retry-io N, --io-backoff)
+//
+// The input is copied into the scratch workspace before conversion. A read error does
+// not restart the copy: the source is reopened and reading resumes at the last byte
+// that arrived, so a dying SD card or a flaky network share only has to deliver each
+// byte once.
+use anyhow::{Context, Result, anyhow};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crate::units;
+
+const CHUNK_SIZE: usize = 256 * 1024;
+
+// Delay before the first retry; doubled for every further attempt with --io-backoff
+const FIRST_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+// Copy `source` to `destination`, retrying failed reads up to `retries` times in a row.
+// The retry budget is refilled whenever a retry makes progress.
+pub fn copy_with_retry(source: &Path, destination: &Path, retries: u32, backoff: bool) -> Result<u64> {
+    let mut output = File::create(destination).with_context(|| {
+        format!("❌ Failed to create temporary copy: {}", destination.display())
+    })?;
+
+    let mut copied: u64 = 0;
+    let mut failures: u32 = 0;
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+
+    loop {
+        let (total, error) = match read_from(source, copied, &mut buffer, &mut output) {
+            Ok(Progress::Finished(total)) => {
+                output.flush()?;
+                return Ok(total);
+            }
+            Ok(Progress::Interrupted(total, error)) => (total, error),
+            // Write errors are local (scratch disk) and not worth retrying
+            Err(e) => {
+                return Err(anyhow!(
+                    "❌ Failed to write temporary copy: {}\nError: {}",
+                    destination.display(),
+                    e
+                ));
+            }
+        };
+
+        if total > copied {
+            failures = 0;
+        }
+        copied = total;
+        failures += 1;
+        if failures > retries {
+            return Err(anyhow!(
+                "❌ Could not read input file: {}\n\
+                 Error: {}\n\
+                 Gave up after {} retries at byte {}.\n\
+                 Please check:\n\
+                 • The storage device is still connected\n\
+                 • The network share is reachable\n\
+                 • Copying the file with the system file manager works",
+                source.display(),
+                error,
+                retries,
+                copied
+            ));
+        }
+
+        let delay = retry_delay(failures, backoff);
+        say_err!(
+            "⚠️  Read error at byte {} of {} ({}); retry {}/{} in {}",
+            copied,
+            source.display(),
+            error,
+            failures,
+            retries,
+            units::format_duration(delay)
+        );
+        thread::sleep(delay);
+    }
+}
+
+enum Progress {
+    Finished(u64),               // Whole file copied; total size
+    Interrupted(u64, io::Error), // Reading failed after reaching this offset
+}
+
+// Read `source` from `offset` to the end, appending to `output`.
+// Read-side failures (including open and seek) are reported as Progress::Interrupted.
+fn read_from(source: &Path, offset: u64, buffer: &mut [u8], output: &mut File) -> io::Result<Progress> {
+    let mut input = match File::open(source) {
+        Ok(file) => file,
+        Err(e) => return Ok(Progress::Interrupted(offset, e)),
+    };
+    if let Err(e) = input.seek(SeekFrom::Start(offset)) {
+        return Ok(Progress::Interrupted(offset, e));
+    }
+
+    let mut position = offset;
+    loop {
+        match input.read(buffer) {
+            Ok(0) => return Ok(Progress::Finished(position)),
+            Ok(n) => {
+                output.write_all(&buffer[..n])?;
+                position += n as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Ok(Progress::Interrupted(position, e)),
+        }
+    }
+}
+
+fn retry_delay(attempt: u32, backoff: bool) -> Duration {
+    if !backoff {
+        return FIRST_DELAY;
+    }
+    FIRST_DELAY
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_DELAY)
+}
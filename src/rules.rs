@@ -0,0 +1,355 @@
+// Per-file option overrides from [[rule]] sections of the configuration file
+//
+//   [[rule]]
+//   when = "width > 8000"
+//   set = { resize = "4000x" }
+//
+// Conditions compare file facts with values and can be joined with `and` / `or`
+// (`and` binds tighter):
+//   width, height, megapixels, aspect (width / height), size (bytes, units allowed: 5MB)
+//   ext, name, make, model (strings in double quotes, compared with == and !=,
+//   case-insensitive; make and model are the camera's EXIF tags)
+// Every matching rule is applied in file order, so later rules win.
+use anyhow::{Result, anyhow, bail};
+use clap::ValueEnum;
+use std::path::Path;
+
+use crate::config::RuleConfig;
+use crate::resize::{self, Resize};
+use crate::{Backend, OutputFormat, exif, explain, file_type, units};
+
+// Options a rule may change
+#[derive(Debug, Default, Clone)]
+pub struct Overrides {
+    pub format: Option<OutputFormat>,
+    pub backend: Option<Backend>,
+    pub resize: Option<Resize>,
+}
+
+pub struct Rule {
+    when: String,
+    condition: Condition,
+    overrides: Overrides,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+#[derive(Debug)]
+enum Value {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug)]
+enum Condition {
+    Compare(String, Op, Value),
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+}
+
+// What the conditions can look at
+struct Facts {
+    dimensions: Option<(u32, u32)>,
+    size: u64,
+    ext: String,
+    name: String,
+    make: String,  // Empty without EXIF
+    model: String,
+}
+
+const NUMERIC: &[&str] = &["width", "height", "megapixels", "aspect", "size"];
+const TEXT: &[&str] = &["ext", "name", "make", "model"];
+
+// Check every rule when the configuration is loaded, so mistakes show up before any
+// file is converted
+pub fn compile(configs: &[RuleConfig]) -> Result<Vec<Rule>> {
+    configs
+        .iter()
+        .enumerate()
+        .map(|(index, config)| {
+            let context = |e: anyhow::Error| {
+                anyhow!("❌ Invalid [[rule]] #{} (when = \"{}\"): {}", index + 1, config.when, e)
+            };
+            let condition = parse_condition(&config.when).map_err(context)?;
+            let set = &config.set;
+            let overrides = Overrides {
+                format: set.format.as_deref().map(|f| OutputFormat::from_str(f, true)).transpose().map_err(|e| context(anyhow!("format: {}", e)))?,
+                backend: set.backend.as_deref().map(|b| Backend::from_str(b, true)).transpose().map_err(|e| context(anyhow!("backend: {}", e)))?,
+                resize: set.resize.as_deref().map(resize::parse_resize).transpose().map_err(|e| context(anyhow!("resize: {}", e)))?,
+            };
+            Ok(Rule {
+                when: config.when.clone(),
+                condition,
+                overrides,
+            })
+        })
+        .collect()
+}
+
+// The combined overrides of all rules that match `input`
+pub fn evaluate(rules: &[Rule], input: &Path) -> Overrides {
+    let mut result = Overrides::default();
+    if rules.is_empty() {
+        return result;
+    }
+
+    let text = |part: Option<&std::ffi::OsStr>| part.unwrap_or_default().to_string_lossy().to_lowercase();
+    let tags = exif::read(input);
+    let facts = Facts {
+        dimensions: file_type::dimensions(input),
+        size: std::fs::metadata(input).map(|m| m.len()).unwrap_or(0),
+        ext: text(input.extension()),
+        name: text(input.file_name()),
+        make: tags.make.unwrap_or_default(),
+        model: tags.model.unwrap_or_default(),
+    };
+
+    for (index, rule) in rules.iter().enumerate() {
+        if !rule.condition.matches(&facts) {
+            continue;
+        }
+        let mut changes = Vec::new();
+        if let Some(format) = &rule.overrides.format {
+            changes.push(format!("format = {}", format.extension()));
+            result.format = Some(format.clone());
+        }
+        if let Some(backend) = rule.overrides.backend {
+            changes.push(format!("backend = {:?}", backend).to_lowercase());
+            result.backend = Some(backend);
+        }
+        if let Some(resize) = rule.overrides.resize {
+            changes.push(format!("resize = {}", resize));
+            result.resize = Some(resize);
+        }
+        explain::note(format!("Rule #{} matched ({}): {}", index + 1, rule.when, changes.join(", ")));
+    }
+    result
+}
+
+impl Condition {
+    fn matches(&self, facts: &Facts) -> bool {
+        match self {
+            Condition::And(parts) => parts.iter().all(|part| part.matches(facts)),
+            Condition::Or(parts) => parts.iter().any(|part| part.matches(facts)),
+            Condition::Compare(name, op, Value::Text(expected)) => {
+                let actual = match name.as_str() {
+                    "ext" => &facts.ext,
+                    "make" => &facts.make,
+                    "model" => &facts.model,
+                    _ => &facts.name,
+                };
+                let equal = actual.eq_ignore_ascii_case(expected.trim_start_matches('.'));
+                if *op == Op::Eq { equal } else { !equal }
+            }
+            Condition::Compare(name, op, Value::Number(expected)) => {
+                let dimension = |pick: fn(u32, u32) -> f64| facts.dimensions.map(|(w, h)| pick(w, h));
+                let actual = match name.as_str() {
+                    "width" => dimension(|w, _| w as f64),
+                    "height" => dimension(|_, h| h as f64),
+                    "megapixels" => dimension(|w, h| w as f64 * h as f64 / 1_000_000.0),
+                    "aspect" => dimension(|w, h| w as f64 / h.max(1) as f64),
+                    _ => Some(facts.size as f64),
+                };
+                // Unknown dimensions never match
+                let Some(actual) = actual else {
+                    return false;
+                };
+                match op {
+                    Op::Lt => actual < *expected,
+                    Op::Le => actual <= *expected,
+                    Op::Gt => actual > *expected,
+                    Op::Ge => actual >= *expected,
+                    Op::Eq => actual == *expected,
+                    Op::Ne => actual != *expected,
+                }
+            }
+        }
+    }
+}
+
+// `a and b or c` -> Or[And[a, b], c]
+fn parse_condition(text: &str) -> Result<Condition> {
+    let tokens = tokenize(text)?;
+    let mut alternatives = Vec::new();
+    for alternative in tokens.split(|token| token == "or") {
+        let mut terms = Vec::new();
+        for term in alternative.split(|token| token == "and") {
+            terms.push(parse_comparison(term)?);
+        }
+        alternatives.push(Condition::And(terms));
+    }
+    Ok(Condition::Or(alternatives))
+}
+
+fn parse_comparison(tokens: &[String]) -> Result<Condition> {
+    let [name, op, value] = tokens else {
+        bail!("expected a comparison such as width > 8000, found \"{}\"", tokens.join(" "));
+    };
+    let op = match op.as_str() {
+        "<" => Op::Lt,
+        "<=" => Op::Le,
+        ">" => Op::Gt,
+        ">=" => Op::Ge,
+        "==" | "=" => Op::Eq,
+        "!=" => Op::Ne,
+        other => bail!("unknown operator \"{}\"", other),
+    };
+
+    if TEXT.contains(&name.as_str()) {
+        let Some(text) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+            bail!("{} is compared with a quoted string, e.g. {} == \"png\"", name, name);
+        };
+        if !matches!(op, Op::Eq | Op::Ne) {
+            bail!("{} can only be compared with == or !=", name);
+        }
+        return Ok(Condition::Compare(name.clone(), op, Value::Text(text.to_string())));
+    }
+    if !NUMERIC.contains(&name.as_str()) {
+        bail!(
+            "unknown value \"{}\"; use one of {}, {}",
+            name,
+            NUMERIC.join(", "),
+            TEXT.join(", ")
+        );
+    }
+
+    let number = if name == "size" {
+        units::parse_size(value).map_err(|e| anyhow!("{}", e))? as f64
+    } else {
+        value.parse::<f64>().map_err(|_| anyhow!("\"{}\" is not a number", value))?
+    };
+    Ok(Condition::Compare(name.clone(), op, Value::Number(number)))
+}
+
+// Split into words, numbers (with units), quoted strings and operators
+fn tokenize(text: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            let mut token = String::from(chars.next().unwrap_or(c));
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => token.push(c),
+                    None => bail!("missing closing quote"),
+                }
+            }
+            token.push('"');
+            tokens.push(token);
+        } else if "<>=!".contains(c) {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek().filter(|c| "<>=!".contains(**c)) {
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        } else if c.is_alphanumeric() || c == '.' || c == '_' {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '.' || **c == '_') {
+                token.push(c);
+                chars.next();
+            }
+            // Words are case-insensitive; numbers keep their unit spelling for parse_size
+            if token.starts_with(|c: char| c.is_alphabetic()) {
+                token = token.to_lowercase();
+            }
+            tokens.push(token);
+        } else {
+            bail!("unexpected character '{}'", c);
+        }
+    }
+    if tokens.is_empty() {
+        bail!("the condition is empty");
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 6000x4000 photo of 5 MB from an iPhone
+    fn photo() -> Facts {
+        Facts {
+            dimensions: Some((6000, 4000)),
+            size: 5_000_000,
+            ext: String::from("heic"),
+            name: String::from("img_0001.heic"),
+            make: String::from("Apple"),
+            model: String::from("iPhone 15 Pro"),
+        }
+    }
+
+    #[test]
+    fn conditions_match_the_facts() {
+        let cases = [
+            ("width > 5000", true),
+            ("width >= 6000 and height <= 4000", true),
+            ("megapixels = 24", true),
+            ("aspect > 1.4 and aspect < 1.6", true),
+            ("size > 4.5MB", true),
+            ("size >= 5MiB", false),
+            ("ext == \".HEIC\"", true),
+            ("EXT != \"heic\"", false),
+            ("model == \"iphone 15 pro\"", true),
+            ("name == \"img_0001.heic\" and make != \"Apple\"", false),
+            // `and` binds tighter than `or`
+            ("width < 100 and height < 100 or size > 1MB", true),
+            ("width < 100 or height > 100 and size < 1MB", false),
+            ("width > 100 or height < 100 and size < 1MB", true),
+        ];
+        for (when, expected) in cases {
+            let condition = parse_condition(when).unwrap_or_else(|e| panic!("{}: {}", when, e));
+            assert_eq!(condition.matches(&photo()), expected, "{}", when);
+        }
+    }
+
+    #[test]
+    fn unknown_dimensions_never_match() {
+        let unreadable = Facts { dimensions: None, ..photo() };
+        assert!(!parse_condition("width > 0").unwrap().matches(&unreadable));
+        assert!(!parse_condition("width != 0").unwrap().matches(&unreadable));
+        assert!(parse_condition("size > 1KB").unwrap().matches(&unreadable));
+    }
+
+    #[test]
+    fn mistakes_are_explained() {
+        let cases = [
+            ("", "the condition is empty"),
+            ("width >> 5000", "unknown operator \">>\""),
+            ("width =< 5000", "unknown operator \"=<\""),
+            ("colour == 5", "unknown value \"colour\""),
+            ("width > wide", "\"wide\" is not a number"),
+            ("size > 5XB", "invalid size unit"),
+            ("ext == heic", "compared with a quoted string"),
+            ("name < \"b\"", "can only be compared with == or !="),
+            ("name == \"IMG", "missing closing quote"),
+            ("width > 5000 and", "expected a comparison"),
+            ("width > 5000 height > 3000", "expected a comparison"),
+            ("width > 5000 # large", "unexpected character '#'"),
+        ];
+        for (when, message) in cases {
+            let error = parse_condition(when).err().unwrap_or_else(|| panic!("{} was accepted", when));
+            assert!(error.to_string().contains(message), "{}: {}", when, error);
+        }
+    }
+
+    #[test]
+    fn quoted_strings_keep_spaces_case_and_keywords() {
+        let tokens = tokenize("name == \"Trip AND Day 1.heic\" or ext != \"PNG\"").unwrap();
+        assert_eq!(tokens, ["name", "==", "\"Trip AND Day 1.heic\"", "or", "ext", "!=", "\"PNG\""]);
+        let holiday = Facts { name: String::from("trip and day 1.heic"), ..photo() };
+        assert!(parse_condition("name == \"Trip AND Day 1.heic\"").unwrap().matches(&holiday));
+    }
+}
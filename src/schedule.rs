@@ -0,0 +1,165 @@
+// Time windows during which conversions are allowed to run (--schedule "02:00-06:00")
+use chrono::{Local, NaiveTime, Timelike};
+use std::thread;
+use std::time::Duration;
+
+// One daily window; `end` before `start` means the window runs past midnight
+#[derive(Clone, Copy, Debug)]
+struct Window {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl Window {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+// A set of daily windows, e.g. "02:00-06:00" or "12:00-13:00,22:00-07:00"
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    windows: Vec<Window>,
+}
+
+// Parse a --schedule value (used as a clap value parser)
+pub fn parse_schedule(text: &str) -> Result<Schedule, String> {
+    let mut windows = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = part
+            .split_once('-')
+            .ok_or_else(|| format!("invalid window '{}': expected HH:MM-HH:MM", part))?;
+        let parse = |value: &str| {
+            NaiveTime::parse_from_str(value.trim(), "%H:%M")
+                .map_err(|_| format!("invalid time '{}': expected HH:MM (24-hour clock)", value.trim()))
+        };
+        let window = Window {
+            start: parse(start)?,
+            end: parse(end)?,
+        };
+        if window.start == window.end {
+            return Err(format!("window '{}' is empty", part));
+        }
+        windows.push(window);
+    }
+
+    if windows.is_empty() {
+        return Err(String::from("schedule must contain at least one HH:MM-HH:MM window"));
+    }
+    Ok(Schedule { windows })
+}
+
+impl Schedule {
+    // Whether conversions may run at the given local time
+    pub fn is_open_at(&self, time: NaiveTime) -> bool {
+        self.windows.iter().any(|window| window.contains(time))
+    }
+
+    // Whether conversions may run now
+    pub fn is_open(&self) -> bool {
+        self.is_open_at(Local::now().time())
+    }
+
+    // Start of the next window from now
+    pub fn opens_at(&self) -> NaiveTime {
+        self.next_opening(Local::now().time())
+    }
+
+    // Block until the current local time falls inside one of the windows
+    pub fn wait_until_open(&self) {
+        if self.is_open() {
+            return;
+        }
+
+        let opens_at = self.opens_at();
+        say!(
+            "⏳ Outside the scheduled window; waiting until {} to start converting...",
+            opens_at.format("%H:%M")
+        );
+
+        // Re-check every 30 seconds so clock changes (DST, suspend/resume) are picked up
+        while !self.is_open() {
+            thread::sleep(Duration::from_secs(30));
+        }
+        say!("▶️  Scheduled window open, starting.");
+    }
+
+    // Start of the next window after `now`
+    fn next_opening(&self, now: NaiveTime) -> NaiveTime {
+        let seconds_until = |start: NaiveTime| {
+            let now_s = now.num_seconds_from_midnight() as i64;
+            let start_s = start.num_seconds_from_midnight() as i64;
+            (start_s - now_s).rem_euclid(86_400)
+        };
+        self.windows
+            .iter()
+            .map(|window| window.start)
+            .min_by_key(|start| seconds_until(*start))
+            .unwrap_or(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(text, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn windows_are_parsed() {
+        let schedule = parse_schedule(" 12:00-13:00, 22:00-07:00 ,").unwrap();
+        assert_eq!(schedule.windows.len(), 2);
+        assert_eq!((schedule.windows[0].start, schedule.windows[0].end), (at("12:00"), at("13:00")));
+        assert_eq!((schedule.windows[1].start, schedule.windows[1].end), (at("22:00"), at("07:00")));
+    }
+
+    #[test]
+    fn day_window_includes_its_start_but_not_its_end() {
+        let schedule = parse_schedule("02:00-06:00").unwrap();
+        assert!(schedule.is_open_at(at("02:00")));
+        assert!(schedule.is_open_at(at("05:59")));
+        assert!(!schedule.is_open_at(at("06:00")));
+        assert!(!schedule.is_open_at(at("01:59")));
+    }
+
+    #[test]
+    fn night_window_runs_past_midnight() {
+        let schedule = parse_schedule("22:00-07:00").unwrap();
+        for time in ["22:00", "23:59", "00:00", "03:30", "06:59"] {
+            assert!(schedule.is_open_at(at(time)), "{} is inside", time);
+        }
+        for time in ["07:00", "12:00", "21:59"] {
+            assert!(!schedule.is_open_at(at(time)), "{} is outside", time);
+        }
+    }
+
+    #[test]
+    fn empty_windows_are_refused() {
+        assert!(parse_schedule("08:00-08:00").unwrap_err().contains("is empty"));
+        assert!(parse_schedule("").unwrap_err().contains("at least one"));
+        assert!(parse_schedule(" , ").unwrap_err().contains("at least one"));
+    }
+
+    #[test]
+    fn bad_input_is_refused() {
+        assert!(parse_schedule("22:00").unwrap_err().contains("expected HH:MM-HH:MM"));
+        assert!(parse_schedule("25:00-07:00").unwrap_err().contains("invalid time '25:00'"));
+        assert!(parse_schedule("10pm-7am").unwrap_err().contains("invalid time"));
+        assert!(parse_schedule("02:00-06:00,nightly").is_err());
+    }
+
+    #[test]
+    fn next_opening_wraps_past_midnight() {
+        let schedule = parse_schedule("02:00-06:00,12:00-13:00").unwrap();
+        assert_eq!(schedule.next_opening(at("23:30")), at("02:00"));
+        assert_eq!(schedule.next_opening(at("07:00")), at("12:00"));
+        assert_eq!(schedule.next_opening(at("13:30")), at("02:00"));
+        assert_eq!(parse_schedule("22:00-07:00").unwrap().next_opening(at("08:00")), at("22:00"));
+    }
+}
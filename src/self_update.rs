@@ -0,0 +1,181 @@
+// `heic_convert self-update`: fetch the latest release, verify its signature and replace
+// the running binary (feature "self-update")
+//
+// Every release carries one binary per platform, named heic_convert-<arch>-<os>[.exe],
+// next to an Ed25519 signature of that binary in <name>.sig (hex). The public key is
+// compiled in from HEIC_CONVERT_RELEASE_KEY (hex) when the release binaries are built;
+// builds without a key refuse to install anything.
+use anyhow::{Context, Result, anyhow, bail};
+use ring::signature::{ED25519, UnparsedPublicKey};
+use serde_json::Value;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const RELEASE_FEED: &str = "https://api.github.com/repos/developmentAC/heic_convert/releases/latest";
+const RELEASE_KEY: Option<&str> = option_env!("HEIC_CONVERT_RELEASE_KEY");
+
+// Largest binary we are willing to download
+const MAX_DOWNLOAD: u64 = 200 * 1024 * 1024;
+
+pub fn run(check_only: bool) -> Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    say!("Checking for a newer release ...");
+
+    let release: Value = ureq::get(RELEASE_FEED)
+        .set("User-Agent", concat!("heic_convert/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(|e| anyhow!("❌ Cannot reach the release feed: {}", e))?
+        .into_json()
+        .context("❌ The release feed sent an unexpected answer")?;
+
+    let tag = release["tag_name"]
+        .as_str()
+        .ok_or_else(|| anyhow!("❌ The release feed does not name a version"))?;
+    let latest = tag.trim_start_matches('v');
+
+    if !is_newer(latest, current) {
+        say!("✅ heic_convert {} is up to date.", current);
+        return Ok(());
+    }
+    say!("A new version is available: {} (installed: {})", latest, current);
+    if check_only {
+        say!("Install it with: heic_convert self-update");
+        return Ok(());
+    }
+
+    let key = RELEASE_KEY.ok_or_else(|| {
+        anyhow!(
+            "❌ This build has no release signing key, so updates cannot be verified.\n\
+             Update with cargo install --path . or download the release manually."
+        )
+    })?;
+    let key = decode_hex(key).context("❌ The compiled-in release key is not valid hex")?;
+
+    let asset = asset_name();
+    let binary_url = asset_url(&release, &asset)?;
+    let signature_url = asset_url(&release, &format!("{}.sig", asset))?;
+
+    say!("Downloading {} ...", asset);
+    let binary = download(&binary_url)?;
+    let signature = download(&signature_url)?;
+    let signature = decode_hex(String::from_utf8_lossy(&signature).trim())
+        .context("❌ The release signature is not valid hex")?;
+
+    UnparsedPublicKey::new(&ED25519, &key)
+        .verify(&binary, &signature)
+        .map_err(|_| {
+            anyhow!(
+                "❌ Signature check failed for {}; nothing was installed.\n\
+                 The download may be corrupted or tampered with. Try again later.",
+                asset
+            )
+        })?;
+    say!("Signature verified.");
+
+    let exe = std::env::current_exe().context("❌ Cannot locate the running binary")?;
+    replace_binary(&exe, &binary)?;
+    say!("✅ Updated {} to {}", exe.display(), latest);
+    Ok(())
+}
+
+// heic_convert-x86_64-linux, heic_convert-aarch64-macos, heic_convert-x86_64-windows.exe
+fn asset_name() -> String {
+    format!(
+        "heic_convert-{}-{}{}",
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        std::env::consts::EXE_SUFFIX
+    )
+}
+
+fn asset_url(release: &Value, name: &str) -> Result<String> {
+    release["assets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|asset| asset["name"].as_str() == Some(name))
+        .and_then(|asset| asset["browser_download_url"].as_str())
+        .map(String::from)
+        .ok_or_else(|| {
+            anyhow!(
+                "❌ The latest release has no download for this platform ({}).\n\
+                 Build from source instead: cargo install --path .",
+                name
+            )
+        })
+}
+
+fn download(url: &str) -> Result<Vec<u8>> {
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| anyhow!("❌ Download failed: {}: {}", url, e))?;
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_DOWNLOAD)
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("❌ Download interrupted: {}", url))?;
+    Ok(bytes)
+}
+
+// Write the new binary next to the old one and swap it in with a rename, so an
+// interrupted update leaves the installed binary untouched
+fn replace_binary(exe: &Path, binary: &[u8]) -> Result<()> {
+    let staging = sibling(exe, "new");
+    fs::write(&staging, binary).with_context(|| {
+        format!(
+            "❌ Cannot write to {}\n\
+             Run the update with the permissions used to install heic_convert.",
+            exe.parent().unwrap_or(Path::new(".")).display()
+        )
+    })?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staging, fs::Permissions::from_mode(0o755))?;
+    }
+
+    // Windows cannot overwrite a running executable, but it can rename it
+    #[cfg(windows)]
+    {
+        let old = sibling(exe, "old");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old).context("❌ Cannot move the running binary aside")?;
+    }
+
+    fs::rename(&staging, exe).map_err(|e| {
+        let _ = fs::remove_file(&staging);
+        #[cfg(windows)]
+        let _ = fs::rename(sibling(exe, "old"), exe);
+        anyhow!("❌ Cannot replace {}: {}", exe.display(), e)
+    })
+}
+
+fn sibling(exe: &Path, suffix: &str) -> PathBuf {
+    let mut name = exe.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", suffix));
+    exe.with_file_name(name)
+}
+
+// Compare dotted version numbers numerically (0.10.0 is newer than 0.9.3)
+fn is_newer(latest: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .split(['.', '-', '+'])
+            .map_while(|part| part.parse().ok())
+            .collect()
+    };
+    parse(latest) > parse(current)
+}
+
+fn decode_hex(text: &str) -> Result<Vec<u8>> {
+    if !text.is_ascii() || !text.len().is_multiple_of(2) {
+        bail!("expected an even number of hex digits");
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|e| anyhow!("{}", e)))
+        .collect()
+}
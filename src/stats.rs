@@ -0,0 +1,138 @@
+// Opt-in lifetime usage statistics, kept only in a local file (no network involved).
+//
+// Statistics are recorded only while the stats file exists; `heic_convert stats --enable`
+// creates it and `--disable` removes it again.
+//   $XDG_DATA_HOME/heic_convert/stats.json  (usually ~/.local/share/heic_convert/stats.json)
+//   %LOCALAPPDATA%\heic_convert\stats.json  (Windows)
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::units;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub files_converted: u64,
+    pub files_failed: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub bytes_saved: u64, // Sum over files whose output is smaller than the input
+    pub time_spent_ms: u64,
+    pub since: String, // RFC 3339 time of --enable
+}
+
+pub fn stats_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("stats.json"))
+}
+
+// Where heic_convert keeps its own files (statistics, the progress of runs for --resume)
+pub fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_DATA_HOME").map(PathBuf::from).or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })
+    }?;
+    Some(base.join("heic_convert"))
+}
+
+fn load(path: &Path) -> Result<Stats> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("❌ Cannot read statistics file: {}", path.display()))?;
+    if content.trim().is_empty() {
+        return Ok(Stats::default());
+    }
+    serde_json::from_str(&content)
+        .with_context(|| format!("❌ Statistics file is damaged: {}", path.display()))
+}
+
+fn save(path: &Path, stats: &Stats) -> Result<()> {
+    // Write next to the file and rename, so an interrupted run cannot leave half a file
+    let staging = path.with_extension("json.tmp");
+    fs::write(&staging, serde_json::to_string_pretty(stats)?)?;
+    fs::rename(&staging, path)?;
+    Ok(())
+}
+
+// Add one run to the statistics, if they are enabled. Never fails the run.
+pub fn record(input: Option<&Path>, output: Option<&Path>, elapsed: Duration) {
+    let Some(path) = stats_path().filter(|path| path.exists()) else {
+        return;
+    };
+    let Ok(mut stats) = load(&path) else {
+        return;
+    };
+
+    let size = |file: Option<&Path>| file.and_then(|f| fs::metadata(f).ok()).map(|m| m.len());
+    match (size(input), size(output)) {
+        (Some(bytes_in), Some(bytes_out)) => {
+            stats.files_converted += 1;
+            stats.bytes_in += bytes_in;
+            stats.bytes_out += bytes_out;
+            stats.bytes_saved += bytes_in.saturating_sub(bytes_out);
+        }
+        _ => stats.files_failed += 1,
+    }
+    stats.time_spent_ms += elapsed.as_millis() as u64;
+
+    if let Err(e) = save(&path, &stats) {
+        say_err!("⚠️  Could not update usage statistics: {}", e);
+    }
+}
+
+// `heic_convert stats [--enable | --disable]`
+pub fn run(enable: bool, disable: bool) -> Result<()> {
+    let path = stats_path().ok_or_else(|| {
+        anyhow!("❌ Cannot find a home directory for the statistics file (set HOME or XDG_DATA_HOME)")
+    })?;
+
+    if enable {
+        if path.exists() {
+            say!("Usage statistics are already enabled: {}", path.display());
+            return Ok(());
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("❌ Cannot create {}", dir.display()))?;
+        }
+        let stats = Stats {
+            since: chrono::Local::now().to_rfc3339(),
+            ..Stats::default()
+        };
+        save(&path, &stats)?;
+        say!("✅ Usage statistics enabled. They are stored only in {}", path.display());
+        return Ok(());
+    }
+
+    if disable {
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("❌ Cannot remove {}", path.display()))?;
+        }
+        say!("✅ Usage statistics disabled and deleted.");
+        return Ok(());
+    }
+
+    if !path.exists() {
+        say!("Usage statistics are off. Turn them on with: heic_convert stats --enable");
+        say!("(They are kept only in {} and never sent anywhere.)", path.display());
+        return Ok(());
+    }
+
+    let stats = load(&path)?;
+    let since = chrono::DateTime::parse_from_rfc3339(&stats.since)
+        .map(|time| time.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|_| String::from("the beginning"));
+    say!("Usage statistics since {}:", since);
+    say!("  Files converted:  {}", stats.files_converted);
+    say!("  Failed runs:      {}", stats.files_failed);
+    say!("  Read:             {}", units::format_size(stats.bytes_in));
+    say!("  Written:          {}", units::format_size(stats.bytes_out));
+    say!("  Space saved:      {}", units::format_size(stats.bytes_saved));
+    say!("  Time spent:       {}", units::format_duration(Duration::from_millis(stats.time_spent_ms)));
+    Ok(())
+}
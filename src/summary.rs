@@ -0,0 +1,207 @@
+// Summary of a conversion run, shared by the notification channels
+use anyhow::Result;
+use chrono::Local;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "external-tools")]
+use std::process::Command;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::{Cli, events};
+#[cfg(any(feature = "webhook", feature = "email"))]
+use crate::units;
+
+// Outcome of one run in a form that serialises cleanly to JSON.
+// Sizes, durations and times are raw values here; text for people is formatted on output.
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    pub tool: &'static str,
+    pub version: &'static str,
+    pub host: Option<String>,
+    pub status: &'static str, // "success" or "failed"
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub format: String,
+    pub input_bytes: Option<u64>,
+    pub output_bytes: Option<u64>,
+    pub duration_ms: u128,
+    pub finished_at: String, // RFC 3339, local time with offset
+    pub error: Option<String>,
+    pub warnings: Vec<String>, // Problems that did not stop the conversion
+    pub files: usize, // 1 for a single conversion, the inputs of a multi-file run otherwise
+    pub converted: usize,
+    pub skipped: usize, // --skip-existing and --skip-duplicates
+    pub failures: Vec<Failure>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Failure {
+    pub input: String,
+    pub error: String, // First line of the error
+}
+
+// How the files of a multi-file run ended, counted as they finish
+#[derive(Debug, Default)]
+pub struct Tally {
+    pub files: usize,
+    pub converted: usize,
+    pub skipped: usize,
+    pub failures: Vec<Failure>,
+}
+
+impl Tally {
+    pub fn fail(&mut self, input: &Path, error: &anyhow::Error) {
+        self.failures.push(Failure { input: input.display().to_string(), error: crate::headline(error) });
+    }
+}
+
+// Warnings raised during the run, for the summary at its end
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub fn collect_warnings() {
+    events::subscribe(|event: &events::Event| {
+        if let events::Event::Warning(message) = event {
+            WARNINGS.lock().unwrap_or_else(PoisonError::into_inner).push(message.to_string());
+        }
+    });
+}
+
+pub fn take_warnings() -> Vec<String> {
+    std::mem::take(&mut *WARNINGS.lock().unwrap_or_else(PoisonError::into_inner))
+}
+
+// The JSON line for --json and headless runs, printed when the program ends
+static LINE: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn keep_line(summary: &RunSummary) {
+    if let Ok(line) = serde_json::to_string(summary) {
+        *LINE.lock().unwrap_or_else(PoisonError::into_inner) = Some(line);
+    }
+}
+
+pub fn print_line() {
+    if let Some(line) = LINE.lock().unwrap_or_else(PoisonError::into_inner).take() {
+        println!("{}", line);
+    }
+}
+
+impl RunSummary {
+    // Build the summary from the parsed options, the result of the conversion and the
+    // warnings raised on the way
+    pub fn from_result(cli: &Cli, result: &Result<PathBuf>, elapsed: Duration, warnings: Vec<String>) -> RunSummary {
+        let size = |path: &PathBuf| std::fs::metadata(path).map(|m| m.len()).ok();
+        RunSummary {
+            tool: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            host: host_name(),
+            status: if result.is_ok() { "success" } else { "failed" },
+            input: cli.input.as_ref().map(|p| p.display().to_string()),
+            output: result.as_ref().ok().map(|p| p.display().to_string()),
+            format: cli.output_format().extension().to_string(),
+            input_bytes: cli.input.as_ref().and_then(size),
+            output_bytes: result.as_ref().ok().and_then(size),
+            duration_ms: elapsed.as_millis(),
+            finished_at: Local::now().to_rfc3339(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            warnings,
+            files: 1,
+            converted: usize::from(result.is_ok()),
+            skipped: 0,
+            failures: match (&cli.input, result) {
+                (Some(input), Err(e)) => {
+                    let mut tally = Tally::default();
+                    tally.fail(input, e);
+                    tally.failures
+                }
+                _ => Vec::new(),
+            },
+        }
+    }
+
+    // Summary of a multi-file run; input and output are left empty
+    pub fn from_tally(cli: &Cli, tally: Tally, elapsed: Duration, warnings: Vec<String>) -> RunSummary {
+        let failed = tally.failures.len();
+        RunSummary {
+            tool: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            host: host_name(),
+            status: if failed == 0 { "success" } else { "failed" },
+            input: None,
+            output: None,
+            format: cli.output_format().extension().to_string(),
+            input_bytes: None,
+            output_bytes: None,
+            duration_ms: elapsed.as_millis(),
+            finished_at: Local::now().to_rfc3339(),
+            error: (failed > 0).then(|| format!("{} of {} images could not be converted", failed, tally.files)),
+            warnings,
+            files: tally.files,
+            converted: tally.converted,
+            skipped: tally.skipped,
+            failures: tally.failures,
+        }
+    }
+
+    // One-line human readable description, used as the message text in chat notifications
+    #[cfg(any(feature = "webhook", feature = "email"))]
+    pub fn headline(&self) -> String {
+        let host = self.host.as_deref().unwrap_or("unknown host");
+        if self.input.is_none() && self.files > 0 {
+            return match self.failures.len() {
+                0 => format!(
+                    "✅ heic_convert on {}: converted {} of {} images in {}",
+                    host,
+                    self.converted,
+                    self.files,
+                    units::format_duration(self.duration())
+                ),
+                failed => format!("❌ heic_convert on {}: {} of {} images could not be converted", host, failed, self.files),
+            };
+        }
+        let input = self.input.as_deref().unwrap_or("(no input)");
+        match self.error.as_deref() {
+            None => format!(
+                "✅ heic_convert on {}: converted {} -> {} ({}) in {}",
+                host,
+                input,
+                self.output.as_deref().unwrap_or("?"),
+                self.output_bytes.map(units::format_size).unwrap_or_else(|| String::from("?")),
+                units::format_duration(self.duration())
+            ),
+            Some(error) => format!(
+                "❌ heic_convert on {}: failed to convert {}: {}",
+                host,
+                input,
+                error.lines().next().unwrap_or(error).trim_start_matches("❌ ")
+            ),
+        }
+    }
+
+    #[cfg(any(feature = "webhook", feature = "email"))]
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms as u64)
+    }
+}
+
+// Name of this machine, so notifications from several servers can be told apart
+fn host_name() -> Option<String> {
+    if let Ok(name) = std::env::var("HOSTNAME")
+        && !name.is_empty()
+    {
+        return Some(name);
+    }
+    hostname_command()
+}
+
+#[cfg(feature = "external-tools")]
+fn hostname_command() -> Option<String> {
+    let output = Command::new("hostname").output().ok()?;
+    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(not(feature = "external-tools"))]
+fn hostname_command() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok().filter(|name| !name.is_empty())
+}
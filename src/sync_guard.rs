@@ -0,0 +1,135 @@
+// Inputs that are still arriving (--wait-for-sync DURATION)
+//
+// In folders synced by iCloud Drive, Dropbox, OneDrive or Syncthing, and while another
+// program copies a file in, the file is visible before its content is complete: it is
+// empty, still growing, only a cloud placeholder, or has a temporary name that is
+// renamed once the download is done. Instead of failing such a file as corrupt, the
+// conversion waits with increasing pauses until the file has stopped changing.
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::units;
+
+// Pause before the second check, doubled after every further one
+const FIRST_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(8);
+
+// A file modified more recently than this may still be written to
+const QUIET_PERIOD: Duration = Duration::from_secs(2);
+
+// Names under which sync clients and browsers keep files they are still downloading.
+// The finished file appears under its real name.
+const TEMP_SUFFIXES: &[(&str, &str)] = &[
+    (".icloud", "an iCloud Drive placeholder"),
+    (".part", "a partial download"),
+    (".partial", "a partial download"),
+    (".crdownload", "an unfinished Chrome download"),
+    (".download", "an unfinished Safari download"),
+    (".!sync", "an unfinished Resilio Sync transfer"),
+    (".tmp", "a temporary file"),
+];
+const TEMP_PREFIXES: &[(&str, &str)] = &[
+    (".syncthing.", "an unfinished Syncthing transfer"),
+    ("~$", "a temporary OneDrive / Office file"),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum State {
+    Ready,
+    TempName(&'static str),  // What kind of temporary file the name belongs to
+    Placeholder(PathBuf),    // Only the iCloud placeholder of the file exists
+    Empty,
+    Changing,
+}
+
+// The kind of temporary file `path` is, judging by its name
+pub fn temp_name(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?.to_lowercase();
+    TEMP_SUFFIXES
+        .iter()
+        .find(|(suffix, _)| name.ends_with(suffix))
+        .or_else(|| TEMP_PREFIXES.iter().find(|(prefix, _)| name.starts_with(prefix)))
+        .map(|(_, kind)| *kind)
+}
+
+// iCloud Drive replaces files that were evicted to the cloud with ".NAME.icloud"
+fn icloud_placeholder(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    let placeholder = path.with_file_name(format!(".{}.icloud", name));
+    placeholder.exists().then_some(placeholder)
+}
+
+// One look at the file. `previous` holds the size and modification time seen by the
+// last look and is updated.
+pub fn check(path: &Path, previous: &mut Option<(u64, SystemTime)>) -> State {
+    if let Some(kind) = temp_name(path) {
+        return State::TempName(kind);
+    }
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        // Missing files and directories are reported by the regular input checks
+        _ => return icloud_placeholder(path).map_or(State::Ready, State::Placeholder),
+    };
+
+    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    let sample = (metadata.len(), modified);
+    let changed = previous.replace(sample).is_some_and(|before| before != sample);
+    let recent = modified.elapsed().is_ok_and(|age| age < QUIET_PERIOD);
+    if sample.0 == 0 {
+        State::Empty
+    } else if changed || recent {
+        State::Changing
+    } else {
+        State::Ready
+    }
+}
+
+// Wait until `path` is complete, for at most `limit`
+pub fn wait_until_ready(path: &Path, limit: Duration) -> Result<()> {
+    let started = Instant::now();
+    let mut delay = FIRST_DELAY;
+    let mut previous = None;
+
+    loop {
+        let reason = match check(path, &mut previous) {
+            State::Ready => return Ok(()),
+            State::TempName(kind) => {
+                return Err(anyhow!(
+                    "❌ Input file is {}: {}\n\
+                     The finished file appears under its real name once the transfer completes;\n\
+                     convert that one instead.",
+                    kind,
+                    path.display()
+                ));
+            }
+            State::Placeholder(placeholder) => {
+                format!("is still in iCloud only ({})", placeholder.display())
+            }
+            State::Empty => "is still empty".to_string(),
+            State::Changing => "is still being written".to_string(),
+        };
+
+        let waited = started.elapsed();
+        if waited >= limit {
+            return Err(anyhow!(
+                "❌ Input file {} after waiting {}: {}\n\
+                 \n\
+                 Please check:\n\
+                 • The sync client (iCloud Drive, Dropbox, OneDrive) is running and online\n\
+                 • No program is still copying the file\n\
+                 • Allow more time with a longer --wait-for-sync",
+                reason,
+                units::format_duration(waited),
+                path.display()
+            ));
+        }
+
+        let pause = delay.min(limit - waited);
+        say!("⏳ {} {}; checking again in {}", path.display(), reason, units::format_duration(pause));
+        thread::sleep(pause);
+        delay = (delay * 2).min(MAX_DELAY);
+    }
+}
@@ -0,0 +1,261 @@
+// Scratch directory handling for intermediate files (partial outputs, external-tool scratch)
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "external-tools")]
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, Once};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::throttle::ThrottledWriter;
+use crate::{units, volumes};
+
+// Every scratch directory created by this tool starts with this prefix so that
+// `heic_convert clean-temp` can recognise leftovers from crashed runs
+const WORKSPACE_PREFIX: &str = "heic_convert-";
+
+// Directories that are still alive, so the Ctrl-C handler can remove them before exiting
+static ACTIVE_WORKSPACES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+// Workspaces created so far; parallel conversions (--jobs) can start in the same nanosecond
+static CREATED: AtomicUsize = AtomicUsize::new(0);
+static INTERRUPT_HANDLER: Once = Once::new();
+
+// A per-run scratch directory that is removed again when dropped (normal exit, error or panic)
+pub struct TempWorkspace {
+    path: PathBuf,
+}
+
+impl TempWorkspace {
+    // Create a fresh scratch directory inside `base` (or the system temp directory)
+    pub fn create(base: Option<&Path>) -> Result<TempWorkspace> {
+        let base = temp_base(base);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let count = CREATED.fetch_add(1, Ordering::Relaxed);
+        let path = base.join(format!("{}{}-{}-{}", WORKSPACE_PREFIX, std::process::id(), nanos, count));
+
+        fs::create_dir_all(&path).with_context(|| {
+            format!(
+                "❌ Failed to create temporary directory: {}\n\
+                 Possible causes:\n\
+                 • The --tmpdir location does not exist or is not writable\n\
+                 • Disk full",
+                path.display()
+            )
+        })?;
+
+        install_interrupt_handler();
+        ACTIVE_WORKSPACES.lock().unwrap().push(path.clone());
+
+        Ok(TempWorkspace { path })
+    }
+
+    // Scratch location used while `output` is being written, before it is moved into place
+    pub fn partial_path(&self, output: &Path) -> PathBuf {
+        let file_name = output
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("output"));
+        self.path.join(format!("partial-{}", file_name))
+    }
+
+    // The scratch directory itself
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Local copy of `input`, keeping the file name so tools can still sniff the extension
+    pub fn staged_input_path(&self, input: &Path) -> PathBuf {
+        let file_name = input
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("input"));
+        self.path.join("input").join(file_name)
+    }
+}
+
+impl Drop for TempWorkspace {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+        if let Ok(mut active) = ACTIVE_WORKSPACES.lock() {
+            active.retain(|p| p != &self.path);
+        }
+    }
+}
+
+// Resolve the directory that holds scratch workspaces
+pub fn temp_base(base: Option<&Path>) -> PathBuf {
+    base.map(Path::to_path_buf).unwrap_or_else(std::env::temp_dir)
+}
+
+// Move a finished partial file to its final location.
+// A plain rename is tried first; across filesystems we fall back to copy + remove.
+// With a bandwidth limit the data is always copied at the requested rate.
+pub fn persist(partial: &Path, destination: &Path, bwlimit: Option<u64>) -> Result<()> {
+    if let Some(bytes_per_second) = bwlimit {
+        copy_throttled(partial, destination, bytes_per_second)?;
+        let _ = fs::remove_file(partial);
+        return Ok(());
+    }
+
+    if fs::rename(partial, destination).is_ok() {
+        return Ok(());
+    }
+
+    fs::copy(partial, destination).with_context(|| {
+        format!(
+            "Failed to save image to: {}\n\
+             Possible causes:\n\
+             - Insufficient disk space\n\
+             - No write permission to directory\n\
+             - Invalid output path",
+            destination.display()
+        )
+    })?;
+    let _ = fs::remove_file(partial);
+    Ok(())
+}
+
+// Copy `source` to `destination` without exceeding `bytes_per_second`.
+// The data goes to a `.partial` sibling first so the destination never holds a truncated image.
+fn copy_throttled(source: &Path, destination: &Path, bytes_per_second: u64) -> Result<()> {
+    let mut staging_name = destination.file_name().unwrap_or_default().to_os_string();
+    staging_name.push(".partial");
+    let staging = destination.with_file_name(staging_name);
+
+    let result = (|| -> Result<()> {
+        let mut reader = File::open(source)?;
+        let file = File::create(&staging)?;
+        let mut writer = ThrottledWriter::new(BufWriter::new(file), bytes_per_second);
+        io::copy(&mut reader, &mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&staging, destination)?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&staging);
+    }
+    result.with_context(|| {
+        format!(
+            "Failed to save image to: {}\n\
+             Possible causes:\n\
+             - Network destination unavailable\n\
+             - No write permission to directory\n\
+             - Insufficient disk space",
+            destination.display()
+        )
+    })
+}
+
+// Remove the remaining workspaces and exit when the user presses Ctrl-C,
+// since `process::exit` would otherwise skip the Drop based cleanup
+fn install_interrupt_handler() {
+    INTERRUPT_HANDLER.call_once(|| {
+        let _ = ctrlc::set_handler(|| {
+            if let Ok(active) = ACTIVE_WORKSPACES.lock() {
+                for path in active.iter() {
+                    let _ = fs::remove_dir_all(path);
+                }
+            }
+            say_err!();
+            say_err!("⚠️  Interrupted - temporary files removed.");
+            std::process::exit(130);
+        });
+    });
+}
+
+// Purge workspaces left behind by runs that crashed or were killed.
+// Workspaces belonging to a process that is still running are left alone.
+pub fn clean_temp(base: Option<&Path>) -> Result<()> {
+    let base = temp_base(base);
+    let entries = fs::read_dir(&base).with_context(|| {
+        format!(
+            "❌ Cannot read temporary directory: {}\n\
+             Please check that the path exists and is readable.",
+            base.display()
+        )
+    })?;
+
+    let mut removed = 0;
+    let mut skipped = 0;
+    let mut freed: u64 = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(rest) = name.strip_prefix(WORKSPACE_PREFIX) else {
+            continue;
+        };
+        if !entry.path().is_dir() {
+            continue;
+        }
+
+        // Directory names look like heic_convert-<pid>-<nanos>-<count>
+        let pid = rest.split('-').next().and_then(|p| p.parse::<u32>().ok());
+        if let Some(pid) = pid
+            && pid != std::process::id()
+            && process_is_running(pid)
+        {
+            say!("Skipping {} (process {} is still running)", entry.path().display(), pid);
+            skipped += 1;
+            continue;
+        }
+
+        let size = volumes::directory_size(&entry.path());
+        match fs::remove_dir_all(entry.path()) {
+            Ok(()) => {
+                say!("Removed {} ({})", entry.path().display(), units::format_size(size));
+                removed += 1;
+                freed += size;
+            }
+            Err(e) => say_err!("⚠️  Could not remove {}: {}", entry.path().display(), e),
+        }
+    }
+
+    say!(
+        "✅ Temporary directory cleanup finished: {} removed ({} freed), {} in use ({})",
+        removed,
+        units::format_size(freed),
+        skipped,
+        base.display()
+    );
+    Ok(())
+}
+
+// Check whether a process with the given id is alive
+#[cfg(feature = "external-tools")]
+fn process_is_running(pid: u32) -> bool {
+    if cfg!(windows) {
+        match Command::new("tasklist")
+            .arg("/FI")
+            .arg(format!("PID eq {}", pid))
+            .output()
+        {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()),
+            Err(_) => true, // Be conservative when we cannot tell
+        }
+    } else {
+        match Command::new("ps").arg("-p").arg(pid.to_string()).output() {
+            Ok(output) => output.status.success(),
+            Err(_) => true, // Be conservative when we cannot tell
+        }
+    }
+}
+
+// Without external tools: signal 0 checks for the process without touching it
+#[cfg(all(not(feature = "external-tools"), unix))]
+fn process_is_running(pid: u32) -> bool {
+    // SAFETY: plain FFI call; signal 0 performs only the existence and permission check
+    let alive = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+    alive || io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+}
+
+#[cfg(all(not(feature = "external-tools"), not(unix)))]
+fn process_is_running(_pid: u32) -> bool {
+    true // Be conservative when we cannot tell
+}
@@ -0,0 +1,97 @@
+// Bandwidth limiting for writes to slow destinations (--bwlimit)
+use std::io::{self, Write};
+#[cfg(feature = "upload")]
+use std::io::Read;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Parse a rate such as "10MB/s", "500KiB/s" or "2MB" into bytes per second
+pub fn parse_rate(text: &str) -> Result<u64, String> {
+    let trimmed = text.trim();
+    let without_unit = trimmed
+        .strip_suffix("/s")
+        .or_else(|| trimmed.strip_suffix("/S"))
+        .or_else(|| trimmed.strip_suffix("ps"))
+        .unwrap_or(trimmed);
+    crate::units::parse_size(without_unit)
+}
+
+// Writer wrapper that sleeps as needed to keep the average throughput below `bytes_per_second`
+pub struct ThrottledWriter<W: Write> {
+    inner: W,
+    bytes_per_second: u64,
+    started: Instant,
+    written: u64,
+}
+
+impl<W: Write> ThrottledWriter<W> {
+    pub fn new(inner: W, bytes_per_second: u64) -> ThrottledWriter<W> {
+        ThrottledWriter {
+            inner,
+            bytes_per_second: bytes_per_second.max(1),
+            started: Instant::now(),
+            written: 0,
+        }
+    }
+
+    // Sleep until the bytes written so far are within the allowed rate
+    fn pace(&self) {
+        let allowed_after = Duration::from_secs_f64(self.written as f64 / self.bytes_per_second as f64);
+        let elapsed = self.started.elapsed();
+        if allowed_after > elapsed {
+            thread::sleep(allowed_after - elapsed);
+        }
+    }
+}
+
+impl<W: Write> Write for ThrottledWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Write in small slices so the pacing stays smooth even for large buffers
+        let chunk = buf.len().min(64 * 1024).min(self.bytes_per_second as usize).max(1);
+        let n = self.inner.write(&buf[..chunk])?;
+        self.written += n as u64;
+        self.pace();
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Reader wrapper with the same pacing, used for uploads that stream from a file
+#[cfg(feature = "upload")]
+pub struct ThrottledReader<R: Read> {
+    inner: R,
+    bytes_per_second: u64,
+    started: Instant,
+    read: u64,
+}
+
+#[cfg(feature = "upload")]
+impl<R: Read> ThrottledReader<R> {
+    pub fn new(inner: R, bytes_per_second: u64) -> ThrottledReader<R> {
+        ThrottledReader {
+            inner,
+            bytes_per_second: bytes_per_second.max(1),
+            started: Instant::now(),
+            read: 0,
+        }
+    }
+}
+
+#[cfg(feature = "upload")]
+impl<R: Read> Read for ThrottledReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let chunk = buf.len().min(64 * 1024).min(self.bytes_per_second as usize).max(1);
+        let n = self.inner.read(&mut buf[..chunk])?;
+        self.read += n as u64;
+
+        let allowed_after = Duration::from_secs_f64(self.read as f64 / self.bytes_per_second as f64);
+        let elapsed = self.started.elapsed();
+        if allowed_after > elapsed {
+            thread::sleep(allowed_after - elapsed);
+        }
+        Ok(n)
+    }
+}
@@ -0,0 +1,327 @@
+// EXIF thumbnails in converted JPEGs (--embed-thumbnail)
+//
+// Photo browsers show the small JPEG stored in IFD1 of the EXIF block in grid views
+// instead of decoding the full image. The thumbnail is added to an existing EXIF
+// block (ImageMagick keeps the original one) or a new block is inserted.
+use anyhow::{Result, anyhow, bail};
+use image::codecs::jpeg::JpegEncoder;
+use std::fs;
+use std::path::Path;
+
+// Largest edge of the thumbnail; 160x120 is the size the EXIF standard recommends
+const THUMBNAIL_EDGE: u32 = 160;
+
+// An APP1 segment holds at most 64 KiB including its header
+const MAX_SEGMENT: usize = 65_533;
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+// Embed a thumbnail into the JPEG at `path`. Returns false when the file already had one.
+pub fn embed(path: &Path) -> Result<bool> {
+    let jpeg = fs::read(path)?;
+    if !jpeg.starts_with(&[0xFF, 0xD8]) {
+        bail!("{} is not a JPEG file", path.display());
+    }
+
+    let image = image::load_from_memory(&jpeg)?;
+    let small = image.thumbnail(THUMBNAIL_EDGE, THUMBNAIL_EDGE).to_rgb8();
+
+    let segments = segments(&jpeg)?;
+    let existing = segments.iter().find(|s| s.marker == 0xE1 && jpeg[s.data.clone()].starts_with(EXIF_HEADER));
+
+    // Lower the quality until the thumbnail fits into the segment with the rest of the EXIF data
+    let room = MAX_SEGMENT.saturating_sub(existing.map_or(64, |s| s.data.len()) + 64);
+    let mut thumbnail = Vec::new();
+    for quality in [80, 65, 50, 35] {
+        thumbnail.clear();
+        JpegEncoder::new_with_quality(&mut thumbnail, quality).encode_image(&small)?;
+        if thumbnail.len() <= room {
+            break;
+        }
+    }
+    if thumbnail.len() > room {
+        bail!("the EXIF block leaves no room for a thumbnail");
+    }
+
+    let (tiff, insert_at, replace_len) = match existing {
+        Some(segment) => {
+            let mut tiff = jpeg[segment.data.start + EXIF_HEADER.len()..segment.data.end].to_vec();
+            if !append_ifd1(&mut tiff, &thumbnail)? {
+                return Ok(false);
+            }
+            // Replace the whole segment, marker and length included
+            (tiff, segment.data.start - 4, segment.data.len() + 4)
+        }
+        None => {
+            let mut tiff = new_tiff();
+            append_ifd1(&mut tiff, &thumbnail)?;
+            // After SOI, or after a JFIF APP0 segment when there is one
+            let after = segments
+                .iter()
+                .find(|s| s.marker == 0xE0)
+                .map_or(2, |s| s.data.end);
+            (tiff, after, 0)
+        }
+    };
+
+    let mut segment = vec![0xFF, 0xE1];
+    segment.extend_from_slice(&((tiff.len() + EXIF_HEADER.len() + 2) as u16).to_be_bytes());
+    segment.extend_from_slice(EXIF_HEADER);
+    segment.extend_from_slice(&tiff);
+
+    let mut output = Vec::with_capacity(jpeg.len() + segment.len());
+    output.extend_from_slice(&jpeg[..insert_at]);
+    output.extend_from_slice(&segment);
+    output.extend_from_slice(&jpeg[insert_at + replace_len..]);
+    fs::write(path, output)?;
+    Ok(true)
+}
+
+// A marker segment before the image data; `data` excludes the marker and length bytes
+struct Segment {
+    marker: u8,
+    data: std::ops::Range<usize>,
+}
+
+fn segments(jpeg: &[u8]) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut at = 2;
+    while at + 4 <= jpeg.len() {
+        if jpeg[at] != 0xFF {
+            bail!("damaged JPEG structure at byte {}", at);
+        }
+        let marker = jpeg[at + 1];
+        // Start of scan: the compressed image follows
+        if marker == 0xDA {
+            break;
+        }
+        let length = u16::from_be_bytes([jpeg[at + 2], jpeg[at + 3]]) as usize;
+        if length < 2 || at + 2 + length > jpeg.len() {
+            bail!("damaged JPEG segment at byte {}", at);
+        }
+        segments.push(Segment {
+            marker,
+            data: at + 4..at + 2 + length,
+        });
+        at += 2 + length;
+    }
+    Ok(segments)
+}
+
+// Little-endian TIFF header with an IFD0 that only names the software
+fn new_tiff() -> Vec<u8> {
+    let software = b"heic_convert\0";
+    let mut tiff = b"II*\0".to_vec();
+    tiff.extend_from_slice(&8u32.to_le_bytes());
+    tiff.extend_from_slice(&1u16.to_le_bytes());
+    // Software (0x0131), ASCII, pointing behind the IFD
+    let value_offset = 8 + 2 + 12 + 4;
+    tiff.extend_from_slice(&0x0131u16.to_le_bytes());
+    tiff.extend_from_slice(&2u16.to_le_bytes());
+    tiff.extend_from_slice(&(software.len() as u32).to_le_bytes());
+    tiff.extend_from_slice(&(value_offset as u32).to_le_bytes());
+    tiff.extend_from_slice(&0u32.to_le_bytes()); // No next IFD yet
+    tiff.extend_from_slice(software);
+    tiff
+}
+
+// Append IFD1 with the thumbnail to a TIFF block and link it from IFD0. Returns false
+// when IFD0 already links to an IFD1 (the file has a thumbnail).
+fn append_ifd1(tiff: &mut Vec<u8>, thumbnail: &[u8]) -> Result<bool> {
+    let damaged = || anyhow!("damaged EXIF block");
+    let little = match tiff.get(0..2) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        _ => return Err(damaged()),
+    };
+    let read16 = |bytes: &[u8], at: usize| -> Option<u32> {
+        let b: [u8; 2] = bytes.get(at..at + 2)?.try_into().ok()?;
+        Some(if little { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) } as u32)
+    };
+    let read32 = |bytes: &[u8], at: usize| -> Option<u32> {
+        let b: [u8; 4] = bytes.get(at..at + 4)?.try_into().ok()?;
+        Some(if little { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+    };
+    let put16 = |bytes: &mut Vec<u8>, value: u16| {
+        bytes.extend_from_slice(&if little { value.to_le_bytes() } else { value.to_be_bytes() })
+    };
+    let put32 = |bytes: &mut Vec<u8>, value: u32| {
+        bytes.extend_from_slice(&if little { value.to_le_bytes() } else { value.to_be_bytes() })
+    };
+
+    let ifd0 = read32(tiff, 4).ok_or_else(damaged)? as usize;
+    let entries = read16(tiff, ifd0).ok_or_else(damaged)? as usize;
+    let next_pointer = ifd0 + 2 + entries * 12;
+    if read32(tiff, next_pointer).ok_or_else(damaged)? != 0 {
+        return Ok(false);
+    }
+
+    // IFD offsets must be even
+    if tiff.len() % 2 == 1 {
+        tiff.push(0);
+    }
+    let ifd1 = tiff.len() as u32;
+    // The two resolution rationals follow the IFD, then the thumbnail
+    let resolution_offset = ifd1 + 2 + 6 * 12 + 4;
+    let thumbnail_offset = resolution_offset + 2 * 8;
+
+    // Entries must be sorted by tag
+    put16(tiff, 6);
+    // Compression (0x0103) = 6, JPEG
+    put16(tiff, 0x0103);
+    put16(tiff, 3);
+    put32(tiff, 1);
+    put16(tiff, 6);
+    put16(tiff, 0);
+    // XResolution (0x011A) and YResolution (0x011B), RATIONAL: 72 dpi
+    for (tag, offset) in [(0x011A, resolution_offset), (0x011B, resolution_offset + 8)] {
+        put16(tiff, tag);
+        put16(tiff, 5);
+        put32(tiff, 1);
+        put32(tiff, offset);
+    }
+    // ResolutionUnit (0x0128) = 2, inches
+    put16(tiff, 0x0128);
+    put16(tiff, 3);
+    put32(tiff, 1);
+    put16(tiff, 2);
+    put16(tiff, 0);
+    // JPEGInterchangeFormat (0x0201): where the thumbnail starts
+    put16(tiff, 0x0201);
+    put16(tiff, 4);
+    put32(tiff, 1);
+    put32(tiff, thumbnail_offset);
+    // JPEGInterchangeFormatLength (0x0202)
+    put16(tiff, 0x0202);
+    put16(tiff, 4);
+    put32(tiff, 1);
+    put32(tiff, thumbnail.len() as u32);
+    put32(tiff, 0); // Last IFD
+    for _ in 0..2 {
+        put32(tiff, 72);
+        put32(tiff, 1);
+    }
+    tiff.extend_from_slice(thumbnail);
+
+    let link = if little { ifd1.to_le_bytes() } else { ifd1.to_be_bytes() };
+    tiff[next_pointer..next_pointer + 4].copy_from_slice(&link);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    fn jpeg() -> Vec<u8> {
+        let mut jpeg = Vec::new();
+        let img = RgbImage::from_fn(640, 480, |x, y| image::Rgb([(x % 256) as u8, (y % 256) as u8, 128]));
+        JpegEncoder::new_with_quality(&mut jpeg, 90).encode_image(&img).unwrap();
+        jpeg
+    }
+
+    // A TIFF block whose IFD0 holds only Orientation = 6
+    fn tiff(little: bool) -> Vec<u8> {
+        let u16s = |v: u16| if little { v.to_le_bytes() } else { v.to_be_bytes() };
+        let u32s = |v: u32| if little { v.to_le_bytes() } else { v.to_be_bytes() };
+        let mut tiff = if little { b"II".to_vec() } else { b"MM".to_vec() };
+        tiff.extend_from_slice(&u16s(42));
+        tiff.extend_from_slice(&u32s(8));
+        tiff.extend_from_slice(&u16s(1));
+        tiff.extend_from_slice(&u16s(0x0112));
+        tiff.extend_from_slice(&u16s(3));
+        tiff.extend_from_slice(&u32s(1));
+        tiff.extend_from_slice(&u16s(6));
+        tiff.extend_from_slice(&u16s(0));
+        tiff.extend_from_slice(&u32s(0));
+        tiff
+    }
+
+    // The JPEG with an APP1 EXIF segment holding `tiff` right after SOI
+    fn with_exif(tiff: &[u8]) -> Vec<u8> {
+        let jpeg = jpeg();
+        let mut output = jpeg[..2].to_vec();
+        output.extend_from_slice(&[0xFF, 0xE1]);
+        output.extend_from_slice(&((tiff.len() + EXIF_HEADER.len() + 2) as u16).to_be_bytes());
+        output.extend_from_slice(EXIF_HEADER);
+        output.extend_from_slice(tiff);
+        output.extend_from_slice(&jpeg[2..]);
+        output
+    }
+
+    // Embed a thumbnail through a file and return the resulting TIFF block
+    fn embedded(jpeg: &[u8], name: &str) -> Vec<u8> {
+        let dir = std::env::temp_dir().join(format!("heic_convert-thumbnail-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, jpeg).unwrap();
+        assert!(embed(&path).unwrap());
+        assert!(!embed(&path).unwrap(), "a second thumbnail was added");
+        let output = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        image::load_from_memory(&output).expect("the converted image is still readable");
+        let exif: Vec<_> = segments(&output)
+            .unwrap()
+            .into_iter()
+            .filter(|s| s.marker == 0xE1 && output[s.data.clone()].starts_with(EXIF_HEADER))
+            .collect();
+        assert_eq!(exif.len(), 1);
+        output[exif[0].data.start + EXIF_HEADER.len()..exif[0].data.end].to_vec()
+    }
+
+    // Check IFD1 and its thumbnail; returns the tags of IFD0
+    fn check(tiff: &[u8]) -> Vec<u16> {
+        let little = &tiff[..2] == b"II";
+        let read16 = |at: usize| {
+            let b = [tiff[at], tiff[at + 1]];
+            if little { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) }
+        };
+        let read32 = |at: usize| {
+            let b = [tiff[at], tiff[at + 1], tiff[at + 2], tiff[at + 3]];
+            if little { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) }
+        };
+        let entries = |ifd: usize| (0..read16(ifd) as usize).map(move |n| ifd + 2 + n * 12);
+
+        let ifd0 = read32(4) as usize;
+        let ifd0_tags = entries(ifd0).map(read16).collect();
+        let ifd1 = read32(ifd0 + 2 + read16(ifd0) as usize * 12) as usize;
+        assert!(ifd1 > ifd0 && ifd1.is_multiple_of(2), "IFD1 at {}", ifd1);
+        let tags: Vec<_> = entries(ifd1).map(read16).collect();
+        assert_eq!(tags, [0x0103, 0x011A, 0x011B, 0x0128, 0x0201, 0x0202]);
+        assert_eq!(read32(ifd1 + 2 + tags.len() * 12), 0, "IFD1 is not the last IFD");
+
+        let value = |tag: u16| entries(ifd1).find(|&e| read16(e) == tag).unwrap() + 8;
+        assert_eq!(read16(value(0x0103)), 6);
+        assert_eq!(read16(value(0x0128)), 2);
+        for tag in [0x011A, 0x011B] {
+            let rational = read32(value(tag)) as usize;
+            assert_eq!((read32(rational), read32(rational + 4)), (72, 1));
+        }
+        let start = read32(value(0x0201)) as usize;
+        let length = read32(value(0x0202)) as usize;
+        assert_eq!(start + length, tiff.len());
+        let thumbnail = image::load_from_memory(&tiff[start..start + length]).expect("the thumbnail decodes");
+        assert_eq!((thumbnail.width(), thumbnail.height()), (160, 120));
+        ifd0_tags
+    }
+
+    #[test]
+    fn thumbnail_goes_into_a_new_exif_block() {
+        let tiff = embedded(&jpeg(), "plain.jpg");
+        assert_eq!(check(&tiff), [0x0131]);
+    }
+
+    #[test]
+    fn thumbnail_is_added_to_an_intel_exif_block() {
+        let tiff = embedded(&with_exif(&tiff(true)), "intel.jpg");
+        assert_eq!(&tiff[..2], b"II");
+        assert_eq!(check(&tiff), [0x0112]);
+    }
+
+    #[test]
+    fn thumbnail_is_added_to_a_motorola_exif_block() {
+        let tiff = embedded(&with_exif(&tiff(false)), "motorola.jpg");
+        assert_eq!(&tiff[..2], b"MM");
+        assert_eq!(check(&tiff), [0x0112]);
+    }
+}
@@ -0,0 +1,79 @@
+// TIFF output with a choice of compression (-f tiff --tiff-compression lzw)
+//
+// Print and scanning pipelines that take TIFF differ in the compression they accept:
+// uncompressed files work everywhere, LZW is understood by nearly every program that
+// reads TIFF, and Deflate (Adobe's "ZIP" compression) gives the smallest files. All
+// three are lossless. The image crate writes uncompressed TIFF only, so the image is
+// handed to the tiff encoder here. 16-bit images keep their depth; grey images with
+// transparency are written as RGBA, which TIFF readers support more widely.
+use clap::ValueEnum;
+use image::{DynamicImage, ImageFormat};
+use std::fs::File;
+use std::io::{BufWriter, Seek, Write};
+use std::path::Path;
+use tiff::TiffResult;
+use tiff::encoder::TiffEncoder;
+use tiff::encoder::colortype::{Gray8, Gray16, RGB8, RGB16, RGBA8, RGBA16};
+use tiff::encoder::compression::{Compression, Deflate, Lzw, Uncompressed};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum TiffCompression {
+    #[default]
+    None,    // Uncompressed, read by everything
+    Lzw,     // Lossless, the common choice of print workflows
+    Deflate, // Lossless, smallest ("ZIP" in Photoshop)
+}
+
+impl TiffCompression {
+    // The -compress value of ImageMagick
+    pub fn imagemagick(&self) -> &'static str {
+        match self {
+            TiffCompression::None => "None",
+            TiffCompression::Lzw => "LZW",
+            TiffCompression::Deflate => "Zip",
+        }
+    }
+
+    // The -compression_algo value of FFmpeg's TIFF encoder
+    pub fn ffmpeg(&self) -> &'static str {
+        match self {
+            TiffCompression::None => "raw",
+            TiffCompression::Lzw => "lzw",
+            TiffCompression::Deflate => "deflate",
+        }
+    }
+}
+
+pub fn save(img: &DynamicImage, path: &Path, compression: TiffCompression) -> image::ImageResult<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let written = match compression {
+        TiffCompression::None => encode(file, img, Uncompressed),
+        TiffCompression::Lzw => encode(file, img, Lzw),
+        TiffCompression::Deflate => encode(file, img, Deflate::default()),
+    };
+    written.map_err(|e| image::ImageError::Encoding(image::error::EncodingError::new(ImageFormat::Tiff.into(), e)))
+}
+
+fn encode<W: Write + Seek, D: Compression>(writer: W, img: &DynamicImage, compression: D) -> TiffResult<()> {
+    let mut encoder = TiffEncoder::new(writer)?;
+    let (width, height) = (img.width(), img.height());
+    match img {
+        DynamicImage::ImageLuma8(pixels) => encoder.write_image_with_compression::<Gray8, D>(width, height, compression, pixels),
+        DynamicImage::ImageRgb8(pixels) => encoder.write_image_with_compression::<RGB8, D>(width, height, compression, pixels),
+        DynamicImage::ImageRgba8(pixels) => encoder.write_image_with_compression::<RGBA8, D>(width, height, compression, pixels),
+        DynamicImage::ImageLuma16(pixels) => {
+            encoder.write_image_with_compression::<Gray16, D>(width, height, compression, pixels)
+        }
+        DynamicImage::ImageRgb16(pixels) => encoder.write_image_with_compression::<RGB16, D>(width, height, compression, pixels),
+        DynamicImage::ImageRgba16(pixels) => {
+            encoder.write_image_with_compression::<RGBA16, D>(width, height, compression, pixels)
+        }
+        DynamicImage::ImageLumaA8(_) => {
+            encoder.write_image_with_compression::<RGBA8, D>(width, height, compression, &img.to_rgba8())
+        }
+        DynamicImage::ImageRgb32F(_) => {
+            encoder.write_image_with_compression::<RGB16, D>(width, height, compression, &img.to_rgb16())
+        }
+        _ => encoder.write_image_with_compression::<RGBA16, D>(width, height, compression, &img.to_rgba16()),
+    }
+}
@@ -6,6 +6,21 @@ use colored::Colorize;
 use std::io;
 use std::io::Write; // For flushing output
 
+// Like print!, but on stderr when stdout carries data (-o -, --json)
+macro_rules! out {
+    ($($arg:tt)*) => {
+        if crate::ui::stdout_is_data() { eprint!($($arg)*) } else { print!($($arg)*) }
+    };
+}
+
+fn flush() {
+    if crate::ui::stdout_is_data() {
+        io::stderr().flush().unwrap();
+    } else {
+        io::stdout().flush().unwrap();
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct Package {
     name: String,
@@ -20,52 +35,57 @@ struct CargoToml {
 
 // Print colored text to the console
 pub fn colour_print(text: &str, colour: &str) {
+    // No colours or cursor control codes in --ascii-only mode
+    if crate::ui::ascii_only() {
+        say!("{}", text);
+        return;
+    }
     match colour {
         "flush_green" => {
-            print!("\x1b[2K\r"); // Clear the line and move to the beginning
-            io::stdout().flush().unwrap();
-            print!(" {}", text.bright_green().bold());
-            io::stdout().flush().unwrap();
+            out!("\x1b[2K\r"); // Clear the line and move to the beginning
+            flush();
+            out!(" {}", text.bright_green().bold());
+            flush();
         }
         "green" => {
-            print!("\x1b[2K\r");
-            println!("{}", text.bright_green().bold());
+            out!("\x1b[2K\r");
+            say!("{}", text.bright_green().bold());
         }
         "green_noLineFeed" => {
-            print!("\x1b[2K\r");
-            print!("{}", text.bright_green().bold());
+            out!("\x1b[2K\r");
+            out!("{}", text.bright_green().bold());
         }
         "red" => {
-            print!("\x1b[2K\r");
-            println!("{}", text.bright_red().bold());
+            out!("\x1b[2K\r");
+            say!("{}", text.bright_red().bold());
         }
         "cyan" => {
-            print!("\x1b[2K\r");
-            println!("{}", text.bright_cyan().bold());
+            out!("\x1b[2K\r");
+            say!("{}", text.bright_cyan().bold());
         }
         "purple" => {
-            print!("\x1b[2K\r");
-            println!("{}", text.bright_purple().bold());
+            out!("\x1b[2K\r");
+            say!("{}", text.bright_purple().bold());
         }
         "purple_noLineFeed" => {
-            print!("\x1b[2K\r");
-            print!("{}", text.bright_purple().bold());
+            out!("\x1b[2K\r");
+            out!("{}", text.bright_purple().bold());
         }
         "blue" => {
-            print!("\x1b[2K\r");
-            println!("{}", text.bright_blue().bold());
+            out!("\x1b[2K\r");
+            say!("{}", text.bright_blue().bold());
         }
         "yellow" => {
-            print!("\x1b[2K\r");
-            println!("{}", text.bright_yellow().bold());
+            out!("\x1b[2K\r");
+            say!("{}", text.bright_yellow().bold());
         }
         "yellow_noLineFeed" => {
-            print!("\x1b[2K\r");
-            print!("{}", text.bright_yellow().bold());
+            out!("\x1b[2K\r");
+            out!("{}", text.bright_yellow().bold());
         }
         _ => {
-            print!("\x1b[2K\r");
-            println!("{}", text.bright_yellow().bold());
+            out!("\x1b[2K\r");
+            say!("{}", text.bright_yellow().bold());
         }
     }
 }
@@ -73,7 +93,7 @@ pub fn colour_print(text: &str, colour: &str) {
 fn parse_cargo_toml(file_path: &str) {
     // Check if the file exists
     if !std::path::Path::new(file_path).exists() {
-        eprintln!("\t Cargo.toml file not found;\n\t Cannot display version information.\n");
+        say_err!("\t Cargo.toml file not found;\n\t Cannot display version information.\n");
         return;
     }
 
@@ -0,0 +1,108 @@
+// Output style: decorative (emoji, colours, banner) or plain ASCII (--ascii-only).
+//
+// All console messages go through the `say!` / `say_err!` macros, which pass the text
+// through `plain` so that --ascii-only output is readable with screen readers and on
+// terminals without Unicode fonts.
+//
+// With `-o -` (image bytes) or `--json` (run summary) stdout carries data, so every
+// message, the banner included, goes to stderr instead and pipes stay clean.
+//
+// Run headless (stdout is not a terminal, or a CI system is detected) the output is
+// plain as with --ascii-only and a conversion ends with its JSON summary as the last
+// line. HEIC_CONVERT_HEADLESS=1 or =0 overrides the detection.
+use std::borrow::Cow;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ASCII_ONLY: AtomicBool = AtomicBool::new(false);
+static STDOUT_IS_DATA: AtomicBool = AtomicBool::new(false);
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+
+// Set by common CI systems (GitHub Actions, GitLab, Jenkins, Azure Pipelines, ...)
+const CI_VARIABLES: &[&str] = &[
+    "CI",
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "BUILDKITE",
+    "CIRCLECI",
+    "JENKINS_URL",
+    "TF_BUILD",
+    "TEAMCITY_VERSION",
+];
+
+// Symbols used in messages and the words a screen reader should hear instead
+const REPLACEMENTS: &[(&str, &str)] = &[
+    ("⚠️  Warning: ", "Warning: "),
+    ("❌ ", "Error: "),
+    ("⚠️  ", "Warning: "),
+    ("⚠️ ", "Warning: "),
+    ("✅ ", "Done: "),
+    ("ℹ️  ", "Note: "),
+    ("⏳ ", "Waiting: "),
+    ("⏱️  ", ""),
+    ("▶️  ", ""),
+    ("🔎 ", ""),
+    ("🍃 ", ""),
+    ("🔌 ", ""),
+    ("👀 ", ""),
+    ("📥 ", ""),
+    ("⏭️  ", ""),
+    ("🗑️  ", ""),
+    ("📋 ", ""),
+    ("🔗 ", ""),
+    ("📦 ", ""),
+    ("• ", "- "),
+    ("→", "->"),
+    ("—", "-"),
+];
+
+// --ascii-only is needed before the command line is parsed (the banner is printed first),
+// so look for it in the raw arguments; the same goes for the data modes
+pub fn init_from_args() {
+    let args: Vec<_> = std::env::args_os().collect();
+    let headless = detect_headless();
+    HEADLESS.store(headless, Ordering::Relaxed);
+    if headless || args.iter().any(|arg| arg == "--ascii-only") {
+        ASCII_ONLY.store(true, Ordering::Relaxed);
+        colored::control::set_override(false);
+    }
+    let data_mode = args.iter().any(|arg| arg == "--json" || arg == "--output=-" || arg == "-o-")
+        || args.windows(2).any(|pair| (pair[0] == "-o" || pair[0] == "--output") && pair[1] == "-");
+    STDOUT_IS_DATA.store(data_mode, Ordering::Relaxed);
+}
+
+fn detect_headless() -> bool {
+    match std::env::var("HEIC_CONVERT_HEADLESS").as_deref() {
+        Ok("1" | "true" | "yes") => return true,
+        Ok("0" | "false" | "no") => return false,
+        _ => {}
+    }
+    let set = |name: &&str| std::env::var(name).is_ok_and(|value| !value.is_empty() && value != "false");
+    !std::io::stdout().is_terminal() || CI_VARIABLES.iter().any(set)
+}
+
+// Whether nobody is watching: no terminal on stdout, or running under CI
+pub fn headless() -> bool {
+    HEADLESS.load(Ordering::Relaxed)
+}
+
+pub fn ascii_only() -> bool {
+    ASCII_ONLY.load(Ordering::Relaxed)
+}
+
+// Whether stdout is reserved for data, so messages must go to stderr
+pub fn stdout_is_data() -> bool {
+    STDOUT_IS_DATA.load(Ordering::Relaxed)
+}
+
+// The message as it should be shown in the current output style
+pub fn plain(text: &str) -> Cow<'_, str> {
+    if !ascii_only() || text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let mut text = text.to_string();
+    for (symbol, words) in REPLACEMENTS {
+        text = text.replace(symbol, words);
+    }
+    Cow::Owned(text)
+}
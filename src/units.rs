@@ -0,0 +1,185 @@
+// Parsing and formatting of human-readable quantities (sizes, rates, durations)
+use clap::ValueEnum;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::locale;
+
+// How byte sizes are shown to the user (--units)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum UnitSystem {
+    #[default]
+    Si,     // kB, MB, GB: powers of 1000
+    Binary, // KiB, MiB, GiB: powers of 1024
+}
+
+static UNIT_SYSTEM: OnceLock<UnitSystem> = OnceLock::new();
+
+pub fn set_unit_system(units: UnitSystem) {
+    let _ = UNIT_SYSTEM.set(units);
+}
+
+// Parse a size such as "4.7GB", "700MB", "1GiB" or "123456" into bytes.
+// Decimal suffixes (KB, MB, GB, TB) use powers of 1000, binary ones (KiB, MiB, ...) powers of 1024.
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let trimmed = text.trim();
+    let split_at = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, suffix) = trimmed.split_at(split_at);
+
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}': expected a number like 4.7GB or 700MB", text))?;
+
+    let multiplier: f64 = match suffix.trim().to_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "k" | "kb" => 1e3,
+        "m" | "mb" => 1e6,
+        "g" | "gb" => 1e9,
+        "t" | "tb" => 1e12,
+        "kib" => 1024.0,
+        "mib" => 1024.0 * 1024.0,
+        "gib" => 1024.0 * 1024.0 * 1024.0,
+        "tib" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        other => {
+            return Err(format!(
+                "invalid size unit '{}': use B, KB, MB, GB, TB or KiB, MiB, GiB, TiB",
+                other
+            ));
+        }
+    };
+
+    let bytes = value * multiplier;
+    if bytes < 1.0 {
+        return Err(format!("size '{}' must be at least one byte", text));
+    }
+    if bytes >= u64::MAX as f64 {
+        return Err(format!("size '{}' is too large", text));
+    }
+    Ok(bytes as u64)
+}
+
+// Parse a duration such as "90s", "5m", "1h30m", "500ms" or "30" (seconds)
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let usage = || format!("invalid duration '{}': expected a number like 90s, 5m or 1h30m", text);
+    let mut rest = text.trim();
+    if rest.is_empty() {
+        return Err(usage());
+    }
+
+    let mut seconds = 0.0;
+    while !rest.is_empty() {
+        let split_at = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(rest.len());
+        let (number, after) = rest.split_at(split_at);
+        let value: f64 = number.parse().map_err(|_| usage())?;
+        let unit_len = after.find(|c: char| c.is_ascii_digit()).unwrap_or(after.len());
+        let (unit, after) = after.split_at(unit_len);
+        seconds += match unit.trim().to_lowercase().as_str() {
+            "ms" => value / 1000.0,
+            "" | "s" | "sec" => value,
+            "m" | "min" => value * 60.0,
+            "h" => value * 3600.0,
+            other => return Err(format!("invalid duration unit '{}': use ms, s, m or h", other)),
+        };
+        rest = after.trim_start();
+    }
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("duration '{}' is too long", text))
+}
+
+// Format a byte count for people, e.g. "4,7 GB" (de_DE) or "4.4 GiB" (--units binary)
+pub fn format_size(bytes: u64) -> String {
+    let (base, units): (f64, [&str; 5]) = match UNIT_SYSTEM.get().copied().unwrap_or_default() {
+        UnitSystem::Si => (1000.0, ["B", "kB", "MB", "GB", "TB"]),
+        UnitSystem::Binary => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB"]),
+    };
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{} {}", locale::format_number(value, 1), units[unit])
+    }
+}
+
+// Format a throughput such as a --bwlimit value, e.g. "10.0 MB/s"
+pub fn format_rate(bytes_per_second: u64) -> String {
+    format!("{}/s", format_size(bytes_per_second))
+}
+
+// Format an elapsed time, e.g. "850 ms", "12,4 s" or "3 min 05 s"
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+    if seconds < 1.0 {
+        format!("{} ms", duration.as_millis())
+    } else if seconds < 60.0 {
+        format!("{} s", locale::format_number(seconds, 1))
+    } else if seconds < 3600.0 {
+        let whole = duration.as_secs();
+        format!("{} min {:02} s", whole / 60, whole % 60)
+    } else {
+        let whole = duration.as_secs();
+        format!("{} h {:02} min", whole / 3600, (whole % 3600) / 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_in_decimal_and_binary_units() {
+        assert_eq!(parse_size("123456"), Ok(123_456));
+        assert_eq!(parse_size("700MB"), Ok(700_000_000));
+        assert_eq!(parse_size("4.7 GB"), Ok(4_700_000_000));
+        assert_eq!(parse_size("1GiB"), Ok(1 << 30));
+        assert_eq!(parse_size("2kib"), Ok(2048));
+        assert_eq!(parse_size("1TB"), Ok(1_000_000_000_000));
+    }
+
+    #[test]
+    fn bad_sizes_are_refused() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("GB").is_err());
+        assert!(parse_size("1.2.3MB").is_err());
+        assert!(parse_size("5 parsecs").unwrap_err().contains("invalid size unit"));
+        assert!(parse_size("0.5").unwrap_err().contains("at least one byte"));
+        assert!(parse_size("99999999999999999999TiB").unwrap_err().contains("too large"));
+    }
+
+    #[test]
+    fn durations_in_every_unit() {
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("2 min"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1.5h"), Ok(Duration::from_secs(5400)));
+    }
+
+    #[test]
+    fn combined_durations_add_up() {
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("1h 2m 3s"), Ok(Duration::from_secs(3723)));
+        assert_eq!(parse_duration("1s500ms"), Ok(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn bad_durations_are_refused() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("3 days").unwrap_err().contains("invalid duration unit"));
+    }
+
+    #[test]
+    fn overlong_durations_are_refused_without_a_panic() {
+        let huge = format!("{}h", "9".repeat(400));
+        assert!(parse_duration(&huge).unwrap_err().contains("too long"));
+        assert!(parse_duration("99999999999999999999999h").unwrap_err().contains("too long"));
+    }
+}
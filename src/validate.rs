@@ -0,0 +1,104 @@
+// `heic_convert validate <PATH>`: find corrupt or truncated HEIC files without converting
+//
+// Every HEIC file (by content, or by extension when the header itself is damaged) has its
+// box structure checked: truncated boxes, missing metadata and image data that points
+// past the end of the file. With --decode the files that pass are also decoded in full
+// with the conversion backends. Nothing is written next to the files, except with
+// --repair: a corrected copy (IMG_0001.repaired.HEIC) of each file whose damage is only
+// in the container, while its main image is complete. The original is never changed.
+use anyhow::{Context, Result, anyhow};
+use std::path::{Path, PathBuf};
+
+use crate::file_type::{self, DetectedType};
+use crate::temp_workspace::TempWorkspace;
+use crate::{heif, units};
+
+pub fn run(path: &Path, decode: bool, repair: bool, tmpdir: Option<&Path>) -> Result<()> {
+    let files = if path.is_dir() {
+        file_type::files_below(path).with_context(|| format!("❌ Cannot scan {}", path.display()))?
+    } else if path.is_file() {
+        vec![path.to_path_buf()]
+    } else {
+        return Err(anyhow!("❌ No such file or directory: {}", path.display()));
+    };
+    let candidates: Vec<PathBuf> = files.into_iter().filter(|file| looks_like_heic(file)).collect();
+    if candidates.is_empty() {
+        say!("No HEIC files in {}", path.display());
+        return Ok(());
+    }
+    say!("🔎 Checking {} HEIC files{}", candidates.len(), if decode { " (full decode)" } else { "" });
+
+    let (mut damaged, mut repaired) = (0, 0);
+    for file in &candidates {
+        let problems = check_file(file, decode, tmpdir)?;
+        if problems.is_empty() {
+            continue;
+        }
+        damaged += 1;
+        let size = std::fs::metadata(file).map_or(0, |m| m.len());
+        say!("❌ {} ({})", file.display(), units::format_size(size));
+        for problem in problems {
+            say!("   • {}", problem);
+        }
+        if repair {
+            match write_repaired(file)? {
+                Some(copy) => {
+                    repaired += 1;
+                    say!("   🔧 Repaired copy: {}", copy.display());
+                }
+                None => say!("   Cannot be repaired: the main image itself is damaged or incomplete"),
+            }
+        }
+    }
+
+    if damaged > repaired {
+        let written = if repair { format!(", {} repaired", repaired) } else { String::new() };
+        return Err(anyhow!("❌ {} of {} HEIC files are damaged{}", damaged, candidates.len(), written));
+    }
+    if damaged > 0 {
+        say!("✅ Wrote repaired copies of all {} damaged HEIC files", damaged);
+        return Ok(());
+    }
+    say!("✅ All {} HEIC files are intact", candidates.len());
+    Ok(())
+}
+
+// What is wrong with one file; empty when it is intact
+fn check_file(file: &Path, decode: bool, tmpdir: Option<&Path>) -> Result<Vec<String>> {
+    let bytes = match std::fs::read(file) {
+        Ok(bytes) => bytes,
+        Err(e) => return Ok(vec![format!("cannot be read: {}", e)]),
+    };
+    let problems: Vec<String> = heif::check(&heif::parse(&bytes)).iter().map(ToString::to_string).collect();
+    if !problems.is_empty() || !decode {
+        return Ok(problems);
+    }
+    let workspace = TempWorkspace::create(tmpdir)?;
+    Ok(match crate::decode_image(file, &workspace) {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![format!("does not decode: {}", format!("{:#}", e).lines().next().unwrap_or_default())],
+    })
+}
+
+// Write IMG_0001.repaired.HEIC next to a damaged file; None when it cannot be repaired
+fn write_repaired(file: &Path) -> Result<Option<PathBuf>> {
+    let bytes = std::fs::read(file).with_context(|| format!("❌ Cannot read {}", file.display()))?;
+    let Some(fixed) = heif::repair(&bytes, &heif::parse(&bytes)) else {
+        return Ok(None);
+    };
+    let extension = file.extension().map_or(String::from("heic"), |e| e.to_string_lossy().into_owned());
+    let copy = file.with_extension(format!("repaired.{}", extension));
+    std::fs::write(&copy, fixed).with_context(|| format!("❌ Cannot write {}", copy.display()))?;
+    Ok(Some(copy))
+}
+
+// HEIC by content, or by its extension when the start of the file is unrecognisable
+fn looks_like_heic(file: &Path) -> bool {
+    match file_type::detect(file) {
+        Some(kind) => kind == DetectedType::Heic,
+        None => file
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| DetectedType::Heic.matches_extension(extension)),
+    }
+}
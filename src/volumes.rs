@@ -0,0 +1,90 @@
+// Spread converted outputs over numbered directories of a maximum size (--split-output)
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::units;
+
+// Volume directories are named part_001, part_002, ... inside the output directory
+const VOLUME_PREFIX: &str = "part_";
+
+static PLACING: Mutex<()> = Mutex::new(());
+
+// Held from choosing a volume until the file is in it, so parallel conversions (--jobs)
+// count each other's files when they measure the newest volume
+pub fn placing() -> MutexGuard<'static, ()> {
+    PLACING.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// Pick the volume directory that should receive a file of `file_size` bytes.
+// Volumes are filled in order: the newest volume is used while the file still fits,
+// otherwise the next numbered volume is started.
+pub fn select_volume(base: &Path, file_size: u64, limit: u64) -> Result<PathBuf> {
+    if file_size > limit {
+        return Err(anyhow!(
+            "❌ Output file is larger than the --split-output volume size\n\
+             File size: {}, volume size: {}\n\
+             Please choose a larger volume size.",
+            units::format_size(file_size),
+            units::format_size(limit)
+        ));
+    }
+
+    let last = last_volume_number(base);
+    if last > 0 {
+        let current = volume_path(base, last);
+        if directory_size(&current) + file_size <= limit {
+            return Ok(current);
+        }
+    }
+
+    let next = volume_path(base, last + 1);
+    say!(
+        "Starting new output volume: {} (up to {})",
+        next.display(),
+        units::format_size(limit)
+    );
+    Ok(next)
+}
+
+fn volume_path(base: &Path, number: u32) -> PathBuf {
+    base.join(format!("{}{:03}", VOLUME_PREFIX, number))
+}
+
+// Highest existing volume number in `base`, or 0 when no volume exists yet
+fn last_volume_number(base: &Path) -> u32 {
+    let Ok(entries) = fs::read_dir(base) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix(VOLUME_PREFIX))
+                .and_then(|number| number.parse::<u32>().ok())
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+// Total size of all files below `dir`
+pub fn directory_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                directory_size(&path)
+            } else {
+                entry.metadata().map(|m| m.len()).unwrap_or(0)
+            }
+        })
+        .sum()
+}
@@ -0,0 +1,133 @@
+// `watch` subcommand: convert HEIC files as they arrive in a folder
+//
+// The folder (a Downloads or AirDrop folder, a camera upload share) is watched with the
+// file system notifications of the operating system. Every HEIC file that appears or
+// changes in it, or with --recursive in its subfolders, is converted into --to (or the
+// output_dir of the configuration file, or next to it) once it is complete: downloads
+// and AirDrop transfers show up before their content has arrived, so a file waits until
+// it has stopped changing, as with --wait-for-sync. Outside the windows of --schedule,
+// complete files wait in a queue and are converted when the next window opens. The
+// subfolders of a --recursive watch are recreated in the output folder. Files
+// that are already there when watching starts are left alone. Runs until interrupted
+// with Ctrl-C.
+use anyhow::{Context, Result, anyhow};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
+
+use crate::error_codes::{ErrorCode, coded};
+use crate::sync_guard::{self, State};
+use crate::{Cli, events, inputs, paths, rules};
+
+// How often files that are still arriving are looked at again
+const POLL: Duration = Duration::from_secs(1);
+
+pub fn run(
+    cli: &Cli,
+    rules: &[rules::Rule],
+    devices: &BTreeMap<String, String>,
+    dir: &Path,
+    to: Option<&Path>,
+    recursive: bool,
+) -> Result<()> {
+    if !dir.is_dir() {
+        return Err(coded(ErrorCode::InputNotFound, anyhow!("❌ Not a directory: {}", dir.display())));
+    }
+    if cli.output.is_some() {
+        return Err(anyhow!("❌ watch converts many files; name the folder for them with --to instead of -o"));
+    }
+    if cli.report.is_some() {
+        return Err(anyhow!("❌ watch runs until it is interrupted, so a --report would never be written"));
+    }
+    if cli.archive_output.is_some() {
+        return Err(anyhow!("❌ watch runs until it is interrupted, so --archive-output would never be packed"));
+    }
+    let cli = match to {
+        Some(to) => Cli { output_dir: Some(to.to_path_buf()), ..cli.clone() },
+        None => cli.clone(),
+    };
+    // With --recursive the subfolders are mirrored in the output folder, as in a batch,
+    // so photos with the same name in two subfolders do not overwrite each other. The
+    // notifications may name the files by the resolved path of the folder.
+    let roots: Vec<PathBuf> = if recursive && !(cli.flatten || cli.organize_by_date) {
+        [Some(dir.to_path_buf()), fs::canonicalize(dir).ok()].into_iter().flatten().collect()
+    } else {
+        Vec::new()
+    };
+
+    let (sender, changes) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).context("❌ Cannot watch for new files")?;
+    let mode = if recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+    watcher.watch(dir, mode).with_context(|| format!("❌ Cannot watch {}", dir.display()))?;
+    match &cli.output_dir {
+        Some(output_dir) => say!("👀 Watching {} for new HEIC files, converting them into {}", dir.display(), output_dir.display()),
+        None => say!("👀 Watching {} for new HEIC files, converting them next to the originals", dir.display()),
+    }
+    say!("   Press Ctrl-C to stop.");
+
+    // Files seen but not complete yet, with their last size and modification time, the
+    // complete ones waiting for their turn (or for the --schedule window), and the files
+    // converted so far as they were when converted
+    let mut arriving: BTreeMap<PathBuf, Option<(u64, SystemTime)>> = BTreeMap::new();
+    let mut ready: BTreeSet<PathBuf> = BTreeSet::new();
+    let mut converted: HashMap<PathBuf, (u64, SystemTime)> = HashMap::new();
+    let mut held_back = false;
+    loop {
+        match changes.recv_timeout(POLL) {
+            Ok(Ok(event)) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                for path in event.paths.into_iter().filter(|path| inputs::is_heic_name(path)) {
+                    arriving.entry(path).or_default();
+                }
+            }
+            Ok(Ok(_)) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Ok(Err(e)) => events::warn(format!("Watching for new files: {}", e)),
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Err(anyhow!("❌ Stopped receiving changes of {}", dir.display())),
+        }
+
+        arriving.retain(|path, previous| match sync_guard::check(path, previous) {
+            State::Changing | State::Empty | State::Placeholder(_) => true,
+            State::TempName(_) => false, // The finished file arrives under its real name
+            State::Ready => {
+                ready.insert(path.clone());
+                false
+            }
+        });
+        if let Some(schedule) = &cli.schedule {
+            if !schedule.is_open() {
+                if !ready.is_empty() && !held_back {
+                    say!("⏳ Outside the scheduled window; new files wait until {}", schedule.opens_at().format("%H:%M"));
+                    held_back = true;
+                }
+                continue;
+            }
+            if held_back {
+                say!("▶️  Scheduled window open, converting the {} waiting file(s).", ready.len());
+                held_back = false;
+            }
+        }
+        for photo in std::mem::take(&mut ready) {
+            let Some(stamp) = stamp(&photo) else { continue }; // Gone again
+            if converted.get(&photo) == Some(&stamp) {
+                continue;
+            }
+            say!("📥 New file: {}", photo.display());
+            let output_dir = cli.output_dir.as_deref().map(|dir| paths::mirrored(dir, &photo, &roots));
+            let single = Cli { input: Some(photo.clone()), output_dir, ..cli.clone() };
+            match crate::run_conversion(&single, rules, devices) {
+                Ok(output) => say!("✅ {} → {}", photo.display(), output.display()),
+                Err(e) if e.is::<crate::Skipped>() => {} // Reported as skipped already
+                Err(e) => say_err!("{:?}", e),
+            }
+            converted.insert(photo, stamp);
+        }
+    }
+}
+
+// Size and modification time, to tell a changed file from one that was only touched
+fn stamp(path: &Path) -> Option<(u64, SystemTime)> {
+    let metadata = fs::metadata(path).ok().filter(|metadata| metadata.is_file())?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
@@ -0,0 +1,32 @@
+// Completion notifications via HTTP webhooks (--webhook)
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+use crate::summary::RunSummary;
+use crate::ui;
+
+// POST the run summary as JSON.
+// Besides the structured fields the payload carries the headline as `content` (Discord)
+// and `text` (Slack, Mattermost, Telegram's sendMessage), so chat webhooks work unchanged.
+pub fn send(url: &str, summary: &RunSummary) -> Result<()> {
+    let mut payload = serde_json::to_value(summary)?;
+    if let Value::Object(map) = &mut payload {
+        // --ascii-only covers the chat message, too
+        let headline = ui::plain(&summary.headline()).into_owned();
+        map.insert(String::from("content"), Value::String(headline.clone()));
+        map.insert(String::from("text"), Value::String(headline));
+    }
+
+    match ureq::post(url).send_json(payload) {
+        Ok(_) => {
+            say!("Webhook notified: {}", url);
+            Ok(())
+        }
+        Err(ureq::Error::Status(code, response)) => Err(anyhow!(
+            "server answered HTTP {}: {}",
+            code,
+            response.into_string().unwrap_or_default().trim()
+        )),
+        Err(e) => Err(anyhow!("{}", e)),
+    }
+}
@@ -0,0 +1,638 @@
+// Batch runs: -i with a folder converts the HEIC files in it one by one (with
+// --recursive also those in subfolders), leaves other files and hidden AppleDouble
+// companions alone, and lists the outcome of every file at the end; several -i or
+// trailing inputs, or a --files-from list, make a batch as well, which --jobs converts
+// in parallel (sharing one album.json) and --skip-existing or --resume can run again
+// without redoing finished files. --skip-duplicates converts copies with the same
+// content once. --output-dir recreates the subfolders of a folder input unless
+// --flatten or --organize-by-date is given; --exclude leaves out the files whose
+// name or folder matches a pattern. --report lists every input of a run,
+// --delete-original removes verified sources, ZIP archives are read as input and
+// --archive-output packs the outputs, and `watch` converts files as they arrive (into
+// the same subfolders with --recursive)
+mod common;
+
+use common::{UNDECODABLE_HEIC, gradient, run, scratch};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+// A folder with two HEICs that no backend can decode (only their 'ftyp' box), a PNG and
+// an AppleDouble file
+fn photos(dir: &Path) {
+    std::fs::write(dir.join("IMG_0001.HEIC"), UNDECODABLE_HEIC).unwrap();
+    std::fs::write(dir.join("IMG_0002.heif"), UNDECODABLE_HEIC).unwrap();
+    std::fs::write(dir.join("._IMG_0001.HEIC"), b"\0\x05\x16\x07").unwrap();
+    std::fs::copy(gradient(), dir.join("screenshot.png")).unwrap();
+}
+
+#[test]
+fn folder_input_converts_each_heic() {
+    let dir = scratch("folder");
+    photos(&dir);
+
+    let output = run(&["--ascii-only", "-i", dir.to_str().unwrap(), "--backend", "builtin"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stdout.contains("Found 2 HEIC files"), "{}", stdout);
+    assert!(stderr.contains("2 of 2 images could not be converted"), "{}", stderr);
+    // The conversion tools are looked for once, not for every file
+    let checks = stdout.matches("Conversion tools available").count() + stdout.matches("conversion tools detected").count();
+    assert_eq!(checks, 1, "{}", stdout);
+    assert!(!dir.join("screenshot.png.png").exists() && !dir.join("._IMG_0001.png").exists());
+}
+
+#[test]
+fn folder_without_heic_files_is_an_error() {
+    let dir = scratch("empty");
+    let output = run(&["-i", dir.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No .heic or .heif files"));
+}
+
+#[test]
+fn recursive_run_reports_every_file() {
+    let dir = scratch("recursive");
+    photos(&dir);
+    let month = dir.join("2023").join("07");
+    std::fs::create_dir_all(&month).unwrap();
+    photos(&month);
+
+    let flat = run(&["--ascii-only", "-i", dir.to_str().unwrap(), "--backend", "builtin"]);
+    assert!(String::from_utf8_lossy(&flat.stdout).contains("Found 2 HEIC files"));
+
+    let output = run(&["--ascii-only", "-i", dir.to_str().unwrap(), "--recursive", "--backend", "builtin"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("Found 4 HEIC files"), "{}", stdout);
+    let results = stdout.split("Results:").nth(1).expect("no results list");
+    assert_eq!(results.matches("Error:").count(), 4, "{}", results);
+    assert!(results.contains(&month.join("IMG_0002.heif").display().to_string()), "{}", results);
+}
+
+// A link back up the tree must not make the scan go round in circles
+#[cfg(unix)]
+#[test]
+fn recursive_run_does_not_follow_folder_links() {
+    let dir = scratch("loop");
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    photos(&dir.join("sub"));
+    std::os::unix::fs::symlink("..", dir.join("sub").join("up")).unwrap();
+
+    let output = run(&["--ascii-only", "-i", dir.to_str().unwrap(), "-r", "--backend", "builtin"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Found 2 HEIC files"), "{}", stdout);
+}
+
+#[test]
+fn pattern_is_expanded_and_other_matches_are_skipped() {
+    let dir = scratch("pattern");
+    photos(&dir);
+    std::fs::write(dir.join("IMG_0003.MOV"), b"\0\0\0\x14ftypqt  \0\0\0\0qt  ").unwrap();
+
+    let pattern = dir.join("img_*");
+    let output = run(&["--ascii-only", "-i", pattern.to_str().unwrap(), "--backend", "builtin"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let all = format!("{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("Found 2 HEIC files matching"), "{}", stdout);
+    assert!(all.contains("Skipped") && all.contains("IMG_0003.MOV"), "{}", all);
+    assert!(!all.contains("._IMG_0001"), "{}", all);
+
+    let output = run(&["-i", dir.join("nothing_*.heic").to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No HEIC files match"));
+}
+
+#[test]
+fn several_inputs_make_one_batch() {
+    let dir = scratch("several");
+    photos(&dir);
+    let out = dir.join("out");
+    let screenshot = dir.join("screenshot.png");
+    let first = dir.join("IMG_0001.HEIC");
+
+    let output = run(&[
+        "--ascii-only",
+        "-i",
+        screenshot.to_str().unwrap(),
+        "-i",
+        first.to_str().unwrap(),
+        first.to_str().unwrap(),
+        dir.join("IMG_0002.heif").to_str().unwrap(),
+        "-o",
+        &format!("{}/", out.display()),
+        "--backend",
+        "builtin",
+    ]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("Found 3 HEIC files in the 4 inputs given"), "{}", stdout);
+    let results = stdout.split("Results:").nth(1).expect("no results list");
+    assert_eq!(results.matches("Error:").count(), 2, "{}", results);
+    assert!(out.join("screenshot.png").exists(), "{}", stdout);
+}
+
+#[test]
+fn file_list_is_read_from_stdin() {
+    let dir = scratch("files-from");
+    photos(&dir);
+    let list = format!("{}\0{}\0", dir.join("IMG_0001.HEIC").display(), dir.join("IMG_0002.heif").display());
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_heic_convert"))
+        .args(["--ascii-only", "--files-from", "-", "--backend", "builtin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("cannot run heic_convert");
+    child.stdin.take().unwrap().write_all(list.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Found 2 HEIC files"), "{}", stdout);
+
+    let listed = dir.join("list.txt");
+    std::fs::write(&listed, "\r\n").unwrap();
+    let output = run(&["--files-from", listed.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No files listed"));
+}
+
+#[test]
+fn parallel_run_reports_every_file_in_order() {
+    let dir = scratch("jobs");
+    for month in ["01", "02", "03"] {
+        std::fs::create_dir_all(dir.join(month)).unwrap();
+        photos(&dir.join(month));
+    }
+
+    let output = run(&["--ascii-only", "-i", dir.to_str().unwrap(), "-r", "--jobs", "4", "--backend", "builtin"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("6 of 6 images could not be converted"), "{}", stderr);
+    let results = stdout.split("Results:").nth(1).expect("no results list");
+    let listed: Vec<&str> = results.lines().filter(|line| line.contains("Error:")).collect();
+    assert_eq!(listed.len(), 6, "{}", results);
+    assert!(listed[0].contains(&dir.join("01").join("IMG_0001.HEIC").display().to_string()), "{}", results);
+    assert!(listed[5].contains(&dir.join("03").join("IMG_0002.heif").display().to_string()), "{}", results);
+}
+
+#[test]
+fn parallel_runs_share_one_album_manifest() {
+    let dir = scratch("album");
+    let gradient = gradient();
+    let inputs: Vec<String> = (1..=8)
+        .map(|n| {
+            let input = dir.join(format!("photo{}.png", n));
+            std::fs::copy(&gradient, &input).unwrap();
+            input.display().to_string()
+        })
+        .collect();
+    let out = dir.join("out");
+    let mut args = vec!["--jobs", "4", "--album-manifest", "-f", "jpg", "--output-dir", out.to_str().unwrap()];
+    args.extend(inputs.iter().map(String::as_str));
+    let output = run(&args);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let album: serde_json::Value = serde_json::from_slice(&std::fs::read(out.join("album.json")).unwrap()).unwrap();
+    assert_eq!(album["images"].as_array().unwrap().len(), 8, "{}", album);
+}
+
+#[test]
+fn second_run_skips_existing_outputs() {
+    let dir = scratch("skip");
+    photos(&dir);
+    let screenshot = dir.join("screenshot.png");
+    std::fs::copy(&screenshot, dir.join("diagram.png")).unwrap();
+    let out = format!("{}/", dir.join("out").display());
+    let inputs = [screenshot.to_str().unwrap().to_string(), dir.join("diagram.png").to_str().unwrap().to_string()];
+    let args = |extra: &[&str]| {
+        let mut args = vec!["--ascii-only", "-f", "jpg", "-o", &out, &inputs[0], &inputs[1]];
+        args.extend_from_slice(extra);
+        run(&args)
+    };
+
+    let first = args(&[]);
+    assert!(first.status.success(), "{}", String::from_utf8_lossy(&first.stderr));
+    let written = std::fs::metadata(dir.join("out").join("screenshot.jpg")).unwrap().modified().unwrap();
+
+    std::fs::remove_file(dir.join("out").join("diagram.jpg")).unwrap();
+    let second = args(&["--skip-existing"]);
+    let stdout = String::from_utf8_lossy(&second.stdout);
+    assert!(second.status.success(), "{}", String::from_utf8_lossy(&second.stderr));
+    assert!(stdout.contains("Skipped 1 of 2 images"), "{}", stdout);
+    assert!(stdout.contains("Converted 1 of 2"), "{}", stdout);
+    assert!(dir.join("out").join("diagram.jpg").exists());
+    assert_eq!(std::fs::metadata(dir.join("out").join("screenshot.jpg")).unwrap().modified().unwrap(), written);
+}
+
+#[test]
+fn output_dir_mirrors_the_input_tree() {
+    let dir = scratch("mirror");
+    let month = dir.join("photos").join("2023").join("07");
+    std::fs::create_dir_all(&month).unwrap();
+    photos(&month);
+    let out = dir.join("converted");
+    let output = |extra: &[&str]| {
+        let mut args = vec!["--ascii-only", "--explain", "--backend", "builtin", "--output-dir", out.to_str().unwrap()];
+        args.extend_from_slice(extra);
+        String::from_utf8_lossy(&run(&args).stdout).to_string()
+    };
+
+    let photos = dir.join("photos");
+    let mirrored = output(&["-i", photos.to_str().unwrap(), "--recursive"]);
+    let expected = out.join("2023").join("07").join("IMG_0001.png");
+    assert!(mirrored.contains(&format!("Output: {} ", expected.display())), "{}", mirrored);
+
+    let pattern = photos.join("*").join("07").join("*.heic");
+    let matched = output(&["-i", pattern.to_str().unwrap()]);
+    let expected = out.join("2023").join("07").join("IMG_0001.png");
+    assert!(matched.contains(&format!("Output: {} ", expected.display())), "{}", matched);
+
+    let flat = output(&["-i", photos.to_str().unwrap(), "--recursive", "--flatten"]);
+    assert!(flat.contains(&format!("Output: {} ", out.join("IMG_0001.png").display())), "{}", flat);
+
+    // Without a capture date (these have no EXIF) the outputs go into undated/
+    let dated = output(&["-i", photos.to_str().unwrap(), "--recursive", "--organize-by-date"]);
+    let expected = out.join("undated").join("IMG_0001.png");
+    assert!(dated.contains(&format!("Output: {} ", expected.display())), "{}", dated);
+}
+
+#[test]
+fn interrupted_run_is_resumed() {
+    let dir = scratch("resume");
+    let gradient = gradient();
+    std::fs::copy(&gradient, dir.join("first.png")).unwrap();
+    std::fs::copy(&gradient, dir.join("second.png")).unwrap();
+    std::fs::write(dir.join("broken.png"), b"not an image").unwrap();
+    let out = format!("{}/", dir.join("out").display());
+    let inputs = ["first.png", "broken.png", "second.png"].map(|name| dir.join(name).display().to_string());
+    let args = |extra: &[&str]| {
+        let mut args = vec!["--ascii-only", "-f", "jpg", "-o", &out, &inputs[0], &inputs[1], &inputs[2]];
+        args.extend_from_slice(extra);
+        Command::new(env!("CARGO_BIN_EXE_heic_convert"))
+            .args(args)
+            .env("XDG_DATA_HOME", dir.join("data"))
+            .env("LOCALAPPDATA", dir.join("data"))
+            .output()
+            .expect("cannot run heic_convert")
+    };
+    let progress_files = || std::fs::read_dir(dir.join("data").join("heic_convert").join("progress")).map_or(0, |files| files.count());
+
+    let first = args(&[]);
+    assert!(!first.status.success());
+    assert!(String::from_utf8_lossy(&first.stdout).contains("--resume"));
+    assert_eq!(progress_files(), 1);
+    let written = std::fs::metadata(dir.join("out").join("first.jpg")).unwrap().modified().unwrap();
+
+    std::fs::copy(&gradient, dir.join("broken.png")).unwrap();
+    let resumed = args(&["--resume"]);
+    let stdout = String::from_utf8_lossy(&resumed.stdout);
+    assert!(resumed.status.success(), "{}", String::from_utf8_lossy(&resumed.stderr));
+    assert!(stdout.contains("2 of 3 files were finished before, 1 to go"), "{}", stdout);
+    assert!(dir.join("out").join("broken.jpg").exists());
+    assert_eq!(std::fs::metadata(dir.join("out").join("first.jpg")).unwrap().modified().unwrap(), written);
+    assert_eq!(progress_files(), 0, "the finished run left its progress behind");
+}
+
+#[test]
+fn copies_are_converted_once() {
+    let dir = scratch("duplicates");
+    let gradient = gradient();
+    std::fs::copy(&gradient, dir.join("photo.png")).unwrap();
+    std::fs::copy(&gradient, dir.join("photo copy.png")).unwrap();
+    // The same pixels, but other bytes: not a copy
+    let mut other = std::fs::read(&gradient).unwrap();
+    other.extend_from_slice(b"trailing");
+    std::fs::write(dir.join("other.png"), other).unwrap();
+    let inputs = ["photo.png", "photo copy.png", "other.png"].map(|name| dir.join(name).display().to_string());
+    let convert = |out: &str, option: &str| {
+        let out = format!("{}/", dir.join(out).display());
+        run(&["--ascii-only", "-f", "jpg", "-o", &out, option, &inputs[0], &inputs[1], &inputs[2]])
+    };
+
+    let skipped = convert("skipped", "--skip-duplicates");
+    let stdout = String::from_utf8_lossy(&skipped.stdout);
+    assert!(skipped.status.success(), "{}", String::from_utf8_lossy(&skipped.stderr));
+    assert!(stdout.contains("Skipped 1 of 3 images with the same content"), "{}", stdout);
+    assert!(dir.join("skipped").join("other.jpg").exists());
+    assert!(!dir.join("skipped").join("photo copy.jpg").exists());
+
+    let linked = convert("linked", "--link-duplicates");
+    assert!(linked.status.success(), "{}", String::from_utf8_lossy(&linked.stderr));
+    let copy = std::fs::read(dir.join("linked").join("photo copy.jpg")).expect("the copy has no output");
+    assert_eq!(copy, std::fs::read(dir.join("linked").join("photo.jpg")).unwrap());
+}
+
+#[test]
+fn excluded_files_and_folders_are_left_out() {
+    let dir = scratch("exclude");
+    photos(&dir);
+    let thumbnails = dir.join("2023").join("Thumbnails");
+    std::fs::create_dir_all(&thumbnails).unwrap();
+    photos(&thumbnails);
+    photos(&dir.join("2023"));
+
+    let root = dir.to_str().unwrap();
+    let output = run(&["--ascii-only", "-i", root, "-r", "--backend", "builtin", "--exclude", "*/Thumbnails/*", "--exclude", "img_0002*"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Left out 4 of 6 HEIC files matching --exclude"), "{}", stdout);
+    assert!(stdout.contains("Found 2 HEIC files"), "{}", stdout);
+    let results = stdout.split("Results:").nth(1).expect("no results list");
+    assert!(!results.contains("Thumbnails") && !results.contains("IMG_0002"), "{}", results);
+
+    let output = run(&["-i", root, "-r", "--exclude", "2023", "--exclude", "IMG_0001.HEIC", "--exclude", "*.heif"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("All 6 HEIC files found match an --exclude pattern"));
+}
+
+// --report: a PNG that converts and a HEIC that no backend can decode
+fn mixed(dir: &Path) -> [String; 2] {
+    std::fs::copy(gradient(), dir.join("gradient.png")).unwrap();
+    std::fs::write(dir.join("IMG_0001.HEIC"), UNDECODABLE_HEIC).unwrap();
+    [dir.join("gradient.png").display().to_string(), dir.join("IMG_0001.HEIC").display().to_string()]
+}
+
+#[test]
+fn json_report_lists_every_input() {
+    let dir = scratch("report-json");
+    let [png, heic] = mixed(&dir);
+    let report = dir.join("run.json");
+    let out = format!("{}/", dir.join("out").display());
+
+    let report_arg = report.to_str().unwrap();
+    let result = run(&["--ascii-only", "-f", "jpg", "-o", &out, "--jobs", "2", "--report", report_arg, &png, &heic]);
+    assert!(!result.status.success());
+    let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    let files = manifest["files"].as_array().unwrap();
+    assert_eq!(files.len(), 2, "{}", manifest);
+
+    assert_eq!(files[0]["input"], png.as_str());
+    assert_eq!(files[0]["status"], "converted");
+    assert_eq!(files[0]["backend"], "builtin");
+    assert_eq!(files[0]["output"], dir.join("out").join("gradient.jpg").display().to_string());
+    assert!(files[0]["width"].as_u64().is_some_and(|width| width > 0), "{}", manifest);
+
+    assert_eq!(files[1]["input"], heic.as_str());
+    assert_eq!(files[1]["status"], "failed");
+    assert!(files[1]["output"].is_null() && files[1]["error"].is_string(), "{}", manifest);
+}
+
+#[test]
+fn csv_report_for_a_csv_name() {
+    let dir = scratch("report-csv");
+    let [png, heic] = mixed(&dir);
+    let report = dir.join("run.CSV");
+
+    run(&["--ascii-only", "--backend", "builtin", "--report", report.to_str().unwrap(), &png, &heic]);
+    let text = std::fs::read_to_string(&report).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "input,output,backend,duration_ms,width,height,status,duplicate_of,error,code");
+    assert_eq!(lines.len(), 3, "{}", text);
+    assert!(lines[1].starts_with(&format!("{},", png)) && lines[1].contains(",builtin,"), "{}", text);
+    assert!(lines[1].contains(",converted,"), "{}", text);
+    assert!(lines[2].starts_with(&format!("{},,builtin,", heic)) && lines[2].contains(",failed,"), "{}", text);
+}
+
+// --delete-original removes the input only after its output reads back as an image,
+// and never when the output took the input's place
+#[test]
+fn original_is_deleted_after_a_verified_conversion() {
+    let dir = scratch("delete-original");
+    let gradient = gradient();
+    let input = dir.join("photo.png");
+    std::fs::copy(&gradient, &input).unwrap();
+
+    let result = run(&["--ascii-only", "-i", input.to_str().unwrap(), "-f", "jpg", "--delete-original"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(dir.join("photo.jpg").exists());
+    assert!(!input.exists(), "the original is still there");
+
+    // Converting a file onto itself leaves nothing to delete
+    std::fs::copy(&gradient, &input).unwrap();
+    let result = run(&["--ascii-only", "-i", input.to_str().unwrap(), "-o", input.to_str().unwrap(), "--delete-original"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(String::from_utf8_lossy(&result.stderr).contains("Kept the original"));
+    assert!(input.exists());
+}
+
+// ZIP archives as input: an iCloud-like export with one HEIC that no backend can
+// decode, a resource fork and a name that leads out of the target folder
+fn export(path: &Path) {
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    let entries: [(&str, &[u8]); 4] = [
+        ("iCloud Photos/IMG_0001.HEIC", UNDECODABLE_HEIC),
+        ("__MACOSX/iCloud Photos/._IMG_0001.HEIC", b"\0\x05\x16\x07"),
+        ("../IMG_0002.HEIC", UNDECODABLE_HEIC),
+        ("iCloud Photos/notes.txt", b"not a photo"),
+    ];
+    for (name, content) in entries {
+        zip.start_file(name, options).unwrap();
+        zip.write_all(content).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+#[test]
+fn heic_files_in_an_archive_are_converted_into_its_folder() {
+    let dir = scratch("archive-input");
+    let archive = dir.join("Photos.zip");
+    export(&archive);
+
+    let output = run(&["--ascii-only", "--explain", archive.to_str().unwrap()]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stdout.contains("Found 1 HEIC files in"), "{}", stdout);
+    let expected = dir.join("Photos").join("iCloud Photos").join("IMG_0001.png");
+    assert!(stdout.contains(&format!("Output: {} ", expected.display())), "{}", stdout);
+    assert!(stderr.contains("Skipped ../IMG_0002.HEIC"), "{}", stderr);
+    assert!(stderr.contains("1 of 1 HEIC files"), "{}", stderr);
+    assert!(!dir.join("IMG_0002.png").exists());
+
+    let output = run(&[archive.to_str().unwrap(), "--report", dir.join("run.json").to_str().unwrap()]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--report cannot be used"));
+}
+
+// --archive-output: the outputs of a multi-file run end up in one ZIP or tar.gz file
+// and nowhere else
+#[test]
+fn outputs_are_packed_into_one_archive() {
+    let dir = scratch("archive-output");
+    let gradient = gradient();
+    let inputs: Vec<String> = ["first.png", "second.png"]
+        .iter()
+        .map(|name| {
+            std::fs::copy(&gradient, dir.join(name)).unwrap();
+            dir.join(name).display().to_string()
+        })
+        .collect();
+
+    let packed = dir.join("client.zip");
+    let output = run(&["--ascii-only", "-f", "jpg", "--archive-output", packed.to_str().unwrap(), &inputs[0], &inputs[1]]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Packed 2 files into"));
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(&packed).unwrap()).unwrap();
+    let mut names: Vec<&str> = zip.file_names().collect();
+    names.sort();
+    assert_eq!(names, ["first.jpg", "second.jpg"]);
+    assert!(zip.by_name("first.jpg").unwrap().size() > 0);
+    assert!(!dir.join("first.jpg").exists() && !dir.join("client.zip.partial").exists());
+
+    let packed = dir.join("client.tgz");
+    let output = run(&["--archive-output", packed.to_str().unwrap(), &inputs[0], &inputs[1]]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(std::fs::File::open(&packed).unwrap()));
+    let mut names: Vec<String> =
+        tar.entries().unwrap().map(|entry| entry.unwrap().path().unwrap().display().to_string()).collect();
+    names.sort();
+    assert_eq!(names, ["first.png", "second.png"]);
+
+    let output = run(&["--archive-output", dir.join("client.rar").to_str().unwrap(), &inputs[0], &inputs[1]]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Cannot tell the archive type"));
+
+    // The originals would be gone before the archive is written
+    let output = run(&["--archive-output", dir.join("moved.zip").to_str().unwrap(), "--delete-original", &inputs[0], &inputs[1]]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+    assert!(!dir.join("moved.zip").exists() && dir.join("first.png").exists());
+}
+
+// `heic_convert watch`: HEIC files that arrive in the folder are picked up once complete
+// and other files are ignored
+#[test]
+fn new_heic_files_are_picked_up() {
+    let dir = scratch("watch");
+    let out = dir.join("out");
+    std::fs::write(dir.join("IMG_0000.HEIC"), UNDECODABLE_HEIC).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_heic_convert"))
+        .args(["--ascii-only", "watch", dir.to_str().unwrap(), "--to", out.to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("cannot run heic_convert");
+    std::thread::sleep(Duration::from_secs(1));
+    std::fs::write(dir.join("IMG_0001.HEIC"), UNDECODABLE_HEIC).unwrap();
+    std::fs::write(dir.join("notes.txt"), b"not a photo").unwrap();
+
+    // The file is taken once it has been quiet for a moment
+    let started = Instant::now();
+    while !out.exists() && started.elapsed() < Duration::from_secs(20) {
+        std::thread::sleep(Duration::from_millis(200));
+    }
+    child.kill().unwrap();
+    let mut stdout = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut stdout).unwrap();
+    child.wait().unwrap();
+
+    assert!(stdout.contains("Watching"), "{}", stdout);
+    assert!(stdout.contains("New file:") && stdout.contains("IMG_0001.HEIC"), "{}", stdout);
+    assert!(!stdout.contains("IMG_0000.HEIC") && !stdout.contains("notes.txt"), "{}", stdout);
+}
+
+// watch --recursive recreates the subfolder of a new file in --to
+#[test]
+fn recursive_watch_mirrors_subfolders() {
+    let dir = scratch("watch-recursive");
+    let out = dir.join("out");
+    std::fs::create_dir_all(dir.join("trip")).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_heic_convert"))
+        .args(["--ascii-only", "watch", "--recursive", dir.to_str().unwrap(), "--to", out.to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("cannot run heic_convert");
+    std::thread::sleep(Duration::from_secs(1));
+    std::fs::write(dir.join("trip/IMG_0001.HEIC"), UNDECODABLE_HEIC).unwrap();
+
+    // The output folder is created before the (undecodable) image is read
+    let started = Instant::now();
+    while !out.join("trip").exists() && started.elapsed() < Duration::from_secs(20) {
+        std::thread::sleep(Duration::from_millis(200));
+    }
+    child.kill().unwrap();
+    let mut stdout = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut stdout).unwrap();
+    child.wait().unwrap();
+
+    assert!(stdout.contains("New file:"), "{}", stdout);
+    assert!(out.join("trip").is_dir(), "{}", stdout);
+}
+
+// Outside the --schedule window a complete file waits instead of being converted
+#[test]
+fn files_wait_for_the_schedule_window() {
+    let dir = scratch("watch-schedule");
+    let out = dir.join("out");
+    let now = chrono::Local::now().time();
+    let later = |hours| (now + chrono::Duration::hours(hours)).format("%H:%M").to_string();
+    let window = format!("{}-{}", later(2), later(3));
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_heic_convert"))
+        .args(["--ascii-only", "--schedule", &window, "watch", dir.to_str().unwrap(), "--to", out.to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("cannot run heic_convert");
+    std::thread::sleep(Duration::from_secs(1));
+    std::fs::write(dir.join("IMG_0001.HEIC"), UNDECODABLE_HEIC).unwrap();
+    std::thread::sleep(Duration::from_secs(6));
+    child.kill().unwrap();
+    let mut stdout = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut stdout).unwrap();
+    child.wait().unwrap();
+
+    assert!(stdout.contains("Watching"), "{}", stdout);
+    assert!(stdout.contains("new files wait until"), "{}", stdout);
+    assert!(!stdout.contains("New file:") && !out.exists(), "{}", stdout);
+}
+
+// --webhook: a batch POSTs one summary with its counts and failures when it ends
+#[test]
+fn batch_sends_one_webhook() {
+    let dir = scratch("webhook");
+    photos(&dir);
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(20))).unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 4096];
+        // Read the headers, then as many bytes as Content-Length announces
+        let body = loop {
+            let read = stream.read(&mut buffer).unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|n| n.trim().parse::<usize>().unwrap()))
+                    .unwrap_or(0);
+                if body.len() >= length || read == 0 {
+                    break body.to_string();
+                }
+            }
+        };
+        stream.write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n").unwrap();
+        body
+    });
+
+    let heic = dir.join("IMG_0001.HEIC");
+    let screenshot = dir.join("screenshot.png");
+    let out = format!("{}/", dir.join("out").display());
+    let args = ["--ascii-only", "--backend", "builtin", "-o", &out, "--webhook", &url];
+    let output = run(&[&args[..], &[screenshot.to_str().unwrap(), heic.to_str().unwrap()]].concat());
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Webhook notified"), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let summary: serde_json::Value = serde_json::from_str(&server.join().unwrap()).unwrap();
+    assert_eq!(summary["status"], "failed");
+    assert_eq!((summary["files"].as_u64(), summary["converted"].as_u64()), (Some(2), Some(1)), "{}", summary);
+    assert_eq!(summary["failures"][0]["input"], heic.display().to_string());
+    let text = summary["text"].as_str().unwrap();
+    assert!(text.starts_with("Error: heic_convert on ") && text.contains("1 of 2 images"), "{}", text);
+    assert!(text.is_ascii(), "{}", text);
+}
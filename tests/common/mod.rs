@@ -0,0 +1,24 @@
+// Helpers shared by the integration tests; each test file uses only some of them
+#![allow(dead_code)]
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+// A HEIC that no backend can decode: only its 'ftyp' box, as when a download stopped
+pub const UNDECODABLE_HEIC: &[u8] = b"\0\0\0\x18ftypheic\0\0\0\0mif1heic";
+
+// The 16x12 PNG of the golden tests, which every backend converts
+pub fn gradient() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/inputs/gradient.png")
+}
+
+pub fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_heic_convert")).args(args).output().expect("cannot run heic_convert")
+}
+
+// An empty folder for one test, named after the test file and the test
+pub fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("heic_convert-{}-{}-{}", env!("CARGO_CRATE_NAME"), std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,61 @@
+// `heic_convert extract`: the device folder is only read, non-HEIC files are left alone
+// and every HEIC is accounted for in the verification manifest, converted or not
+mod common;
+
+use common::{UNDECODABLE_HEIC, gradient, run, scratch};
+use std::path::{Path, PathBuf};
+
+// A device with one HEIC that no backend can decode (only its 'ftyp' box) and a PNG screenshot
+fn device(dir: &Path) -> PathBuf {
+    let dcim = dir.join("DCIM").join("100APPLE");
+    std::fs::create_dir_all(&dcim).unwrap();
+    std::fs::write(dcim.join("IMG_0001.HEIC"), UNDECODABLE_HEIC).unwrap();
+    std::fs::copy(gradient(), dcim.join("IMG_0002.PNG")).unwrap();
+    dir.join("DCIM")
+}
+
+fn listing(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(listing(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn failed_conversions_are_listed_and_the_device_is_untouched() {
+    let dir = scratch("manifest");
+    let from = device(&dir);
+    let to = dir.join("Converted");
+    let before = listing(&from);
+
+    let output = run(&["extract", "--from", from.to_str().unwrap(), "--to", to.to_str().unwrap()]);
+    assert!(!output.status.success(), "an unconverted photo must fail the run");
+    assert_eq!(listing(&from), before);
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(to.join("extract-manifest.json")).unwrap()).unwrap();
+    let files = manifest["files"].as_array().unwrap();
+    assert_eq!(files.len(), 1, "only the HEIC is extracted: {}", manifest);
+    assert_eq!(files[0]["source"], Path::new("100APPLE").join("IMG_0001.HEIC").display().to_string());
+    assert_eq!(files[0]["source_sha256"].as_str().unwrap().len(), 64);
+    assert_eq!(files[0]["verified"], false);
+    assert!(files[0]["error"].is_string());
+}
+
+#[test]
+fn target_inside_the_source_is_refused() {
+    let dir = scratch("inside");
+    let from = device(&dir);
+    let to = from.join("Converted");
+
+    let output = run(&["extract", "--from", from.to_str().unwrap(), "--to", to.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(!to.exists(), "nothing may be created on the device");
+}
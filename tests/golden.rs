@@ -0,0 +1,136 @@
+// Golden-image tests: every backend must reproduce the reference images in
+// tests/golden/expected within the tolerances given in tests/golden/cases.toml.
+//
+// Regenerate the references with the built-in backend after an intended change:
+//   UPDATE_GOLDEN=1 cargo test --test golden
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Deserialize)]
+struct Cases {
+    case: Vec<Case>,
+}
+
+#[derive(Deserialize)]
+struct Case {
+    input: String,
+    expected: Option<String>,
+    backends: Vec<String>,
+    max_diff: f64,
+    mean_diff: f64,
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+// External tools are optional; their cases are skipped when the tool is missing
+fn backend_available(backend: &str) -> bool {
+    let program = match backend {
+        "builtin" => return true,
+        "imagemagick" => "convert",
+        other => other,
+    };
+    Command::new(program)
+        .arg("-version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+fn convert(input: &Path, output: &Path, backend: &str) -> Result<(), String> {
+    let result = Command::new(env!("CARGO_BIN_EXE_heic_convert"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg("-i")
+        .arg(input)
+        .arg("-o")
+        .arg(output)
+        .arg("--backend")
+        .arg(backend)
+        .arg("--deterministic")
+        .output()
+        .map_err(|e| format!("cannot run heic_convert: {}", e))?;
+    if result.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&result.stderr).into_owned())
+    }
+}
+
+// Largest and average per-channel difference in 8-bit steps, or an error on size mismatch
+fn compare(actual: &Path, expected: &Path) -> Result<(f64, f64), String> {
+    let open = |path: &Path| {
+        image::open(path)
+            .map(|image| image.to_rgba16())
+            .map_err(|e| format!("cannot decode {}: {}", path.display(), e))
+    };
+    let (actual, expected) = (open(actual)?, open(expected)?);
+    if actual.dimensions() != expected.dimensions() {
+        return Err(format!(
+            "size {:?} differs from reference {:?}",
+            actual.dimensions(),
+            expected.dimensions()
+        ));
+    }
+
+    let mut max: u16 = 0;
+    let mut total: u64 = 0;
+    for (a, e) in actual.as_raw().iter().zip(expected.as_raw()) {
+        let diff = a.abs_diff(*e);
+        max = max.max(diff);
+        total += diff as u64;
+    }
+    let scale = 257.0; // u16 steps per 8-bit step
+    let mean = total as f64 / actual.as_raw().len().max(1) as f64;
+    Ok((max as f64 / scale, mean / scale))
+}
+
+#[test]
+fn golden_images() {
+    let dir = golden_dir();
+    let cases: Cases = toml::from_str(&fs::read_to_string(dir.join("cases.toml")).unwrap())
+        .expect("tests/golden/cases.toml is invalid");
+    let scratch = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    fs::create_dir_all(&scratch).unwrap();
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+
+    let mut failures = Vec::new();
+    for case in &cases.case {
+        let input = dir.join("inputs").join(&case.input);
+        let expected_name = case.expected.clone().unwrap_or_else(|| {
+            let stem = Path::new(&case.input).file_stem().unwrap().to_string_lossy();
+            format!("{}.png", stem)
+        });
+        let expected = dir.join("expected").join(expected_name);
+
+        if update {
+            if let Err(e) = convert(&input, &expected, "builtin") {
+                failures.push(format!("{}: updating reference failed: {}", case.input, e));
+            }
+            continue;
+        }
+
+        for backend in &case.backends {
+            if !backend_available(backend) {
+                eprintln!("skipping {} with {}: backend not installed", case.input, backend);
+                continue;
+            }
+
+            let stem = Path::new(&case.input).file_stem().unwrap().to_string_lossy();
+            let actual = scratch.join(format!("{}-{}.png", stem, backend));
+            let outcome = convert(&input, &actual, backend).and_then(|_| compare(&actual, &expected));
+            match outcome {
+                Ok((max, mean)) if max <= case.max_diff && mean <= case.mean_diff => {}
+                Ok((max, mean)) => failures.push(format!(
+                    "{} [{}]: max diff {:.2} (allowed {}), mean diff {:.3} (allowed {})",
+                    case.input, backend, max, case.max_diff, mean, case.mean_diff
+                )),
+                Err(e) => failures.push(format!("{} [{}]: {}", case.input, backend, e.trim())),
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "golden image mismatches:\n{}", failures.join("\n"));
+}
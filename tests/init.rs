@@ -0,0 +1,56 @@
+// `heic_convert init`: answers piped into the wizard end up in [defaults], and the next
+// conversion uses them
+mod common;
+
+use common::{gradient, scratch};
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], answers: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_heic_convert"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("cannot run heic_convert");
+    child.stdin.take().unwrap().write_all(answers.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn answers_become_the_defaults() {
+    let dir = scratch("answers");
+    let config = dir.join("config.toml");
+    let converted = dir.join("converted");
+    // An invalid quality is asked again
+    let answers = format!("jpeg\n101\n75\n{}\n", converted.display());
+    let output = run(&["init", "--config", config.to_str().unwrap()], &answers);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let written = std::fs::read_to_string(&config).unwrap();
+    assert!(written.contains("format = \"jpg\""), "{}", written);
+    assert!(written.contains("quality = 75"), "{}", written);
+
+    let input = gradient();
+    let output = run(&["-i", input.to_str().unwrap(), "--config", config.to_str().unwrap()], "");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(converted.join("gradient.jpg").exists(), "the output is not in the configured folder");
+}
+
+#[test]
+fn existing_configuration_is_kept_without_force() {
+    let dir = scratch("existing");
+    let config = dir.join("config.toml");
+    std::fs::write(&config, "[devices]\n").unwrap();
+
+    let output = run(&["init", "--config", config.to_str().unwrap()], "\n\n");
+    assert!(!output.status.success());
+    assert_eq!(std::fs::read_to_string(&config).unwrap(), "[devices]\n");
+
+    // Empty answers take the suggestions; the old file is kept as a backup
+    let output = run(&["init", "--force", "--config", config.to_str().unwrap()], "");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(std::fs::read_to_string(&config).unwrap().contains("format = \"png\""));
+    assert_eq!(std::fs::read_to_string(dir.join("config.toml.bak")).unwrap(), "[devices]\n");
+}
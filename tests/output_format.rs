@@ -0,0 +1,256 @@
+// The format follows the -o name: photo.jpg gets JPEG bytes without -f, and a -f that
+// contradicts the name is refused before anything is written. WebP from the built-in
+// encoder is lossless, AVIF comes from the in-process AV1 encoder,
+// TIFF is compressed as asked without losing a pixel, BMP is plain 24-bit, and
+// --bit-depth 16 gives a 16-bit PNG. A PDF has a page for the image, EXR holds the
+// pixels as linear float light, PPM can be piped from stdout, and ICO is square.
+// An output that is already there is replaced with a warning by default, silently
+// with --overwrite, kept with --no-clobber (the input fails) or next to a numbered new
+// one with --rename-on-conflict; --name-template names the outputs of a batch, and a
+// folder given to -o takes the output under the input's name.
+mod common;
+
+use common::{gradient, run, scratch};
+
+#[test]
+fn extension_selects_the_format() {
+    let dir = scratch("extension");
+    let output = dir.join("photo.JPEG");
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(std::fs::read(&output).unwrap().starts_with(&[0xFF, 0xD8, 0xFF]), "not a JPEG");
+}
+
+#[test]
+fn contradicting_format_is_refused() {
+    let dir = scratch("contradicting");
+    let output = dir.join("photo.png");
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap(), "-f", "jpg"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("does not match the output name"));
+    assert!(!output.exists());
+
+    let output = dir.join("photo.gif");
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap()]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("are png, jpg, webp, avif, tiff"));
+    assert!(!output.exists());
+}
+
+#[test]
+fn webp_is_written_lossless_by_the_builtin_encoder() {
+    let dir = scratch("webp");
+    let output = dir.join("photo.webp");
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap(), "--backend", "builtin"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let bytes = std::fs::read(&output).unwrap();
+    assert!(bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP", "not a WebP file");
+    assert!(String::from_utf8_lossy(&result.stderr).contains("cannot keep lossy WebP encoding"));
+
+    let output = dir.join("lossless.webp");
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap(), "--backend", "builtin", "--lossless"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(!String::from_utf8_lossy(&result.stderr).contains("lossy WebP"));
+    assert_eq!(image::open(&output).unwrap().to_rgb8(), image::open(gradient()).unwrap().to_rgb8());
+}
+
+#[test]
+fn avif_is_encoded_in_process() {
+    let dir = scratch("avif");
+    let output = dir.join("photo.avif");
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap(), "--backend", "builtin", "--quality", "60"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let bytes = std::fs::read(&output).unwrap();
+    assert!(&bytes[4..8] == b"ftyp" && &bytes[8..12] == b"avif", "not an AVIF file");
+    assert!(String::from_utf8_lossy(&result.stderr).is_empty(), "{}", String::from_utf8_lossy(&result.stderr));
+}
+
+#[test]
+fn tiff_compression_is_lossless() {
+    let dir = scratch("tiff");
+    let mut sizes = Vec::new();
+    for compression in ["none", "lzw", "deflate"] {
+        let output = dir.join(format!("scan-{}.tif", compression));
+        let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap(), "--tiff-compression", compression]);
+        assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+        assert_eq!(image::open(&output).unwrap().to_rgba8(), image::open(gradient()).unwrap().to_rgba8(), "{}", compression);
+        sizes.push(std::fs::metadata(&output).unwrap().len());
+    }
+    assert!(sizes[1] < sizes[0] && sizes[2] < sizes[0], "{:?}", sizes);
+}
+
+#[test]
+fn bmp_is_plain_24_bit() {
+    let dir = scratch("bmp");
+    let output = dir.join("legacy.bmp");
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let bytes = std::fs::read(&output).unwrap();
+    assert!(bytes.starts_with(b"BM"), "not a BMP file");
+    assert_eq!(u16::from_le_bytes([bytes[28], bytes[29]]), 24, "bits per pixel");
+    assert_eq!(image::open(&output).unwrap().to_rgb8(), image::open(gradient()).unwrap().to_rgb8());
+}
+
+#[test]
+fn bit_depth_16_writes_a_16_bit_png() {
+    let dir = scratch("bit-depth");
+    let output = dir.join("deep.png");
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap(), "--bit-depth", "16"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let written = image::open(&output).unwrap();
+    assert_eq!(written.color().bytes_per_pixel() / written.color().channel_count(), 2, "{:?}", written.color());
+    assert_eq!(written.to_rgba8(), image::open(gradient()).unwrap().to_rgba8());
+
+    let output = dir.join("shallow.jpg");
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap(), "--bit-depth", "16"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(String::from_utf8_lossy(&result.stderr).contains("--bit-depth only applies to PNG, TIFF, JPEG XL and PPM"));
+}
+
+#[test]
+fn pdf_has_a_page_for_the_image() {
+    let dir = scratch("pdf");
+    let output = dir.join("receipt.pdf");
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap(), "--quality", "70"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let bytes = std::fs::read(&output).unwrap();
+    assert!(bytes.starts_with(b"%PDF-") && bytes.ends_with(b"%%EOF\n"), "not a PDF file");
+    let pages = bytes.windows(12).filter(|window| window == b"/Type /Page ").count();
+    assert_eq!(pages, 1);
+    assert!(bytes.windows(18).any(|window| window == b"/Filter /DCTDecode"), "pages are JPEG images");
+}
+
+#[test]
+fn exr_holds_linear_light() {
+    let dir = scratch("exr");
+    let output = dir.join("linear.exr");
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let written = image::open(&output).unwrap().to_rgb32f();
+    let original = image::open(gradient()).unwrap().to_rgb32f();
+    assert_eq!(written.dimensions(), original.dimensions());
+    // Mid-tones sit well below their sRGB code values; black and white stay put
+    for (linear, encoded) in written.pixels().zip(original.pixels()) {
+        for (linear, encoded) in linear.0.iter().zip(encoded.0) {
+            assert!(*linear <= encoded + 1e-4, "{} is brighter than {}", linear, encoded);
+            if (0.3..0.7).contains(&encoded) {
+                assert!(*linear < encoded * 0.75, "{} is not linear light for {}", linear, encoded);
+            }
+        }
+    }
+}
+
+#[test]
+fn ppm_can_be_piped_from_stdout() {
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", "-", "-f", "ppm"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let original = image::open(gradient()).unwrap();
+    let header = format!("P6\n{} {}\n255\n", original.width(), original.height());
+    assert!(result.stdout.starts_with(header.as_bytes()), "not a binary PPM");
+    assert_eq!(result.stdout.len(), header.len() + original.width() as usize * original.height() as usize * 3);
+    let piped = image::load_from_memory_with_format(&result.stdout, image::ImageFormat::Pnm).unwrap();
+    assert_eq!(piped.to_rgb8(), original.to_rgb8());
+}
+
+#[test]
+fn ico_is_a_square_icon_without_upscaling() {
+    let dir = scratch("ico");
+    let output = dir.join("logo.ico");
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let bytes = std::fs::read(&output).unwrap();
+    // The 16x12 input gives the 16 pixel icon only
+    assert_eq!(&bytes[..6], [0, 0, 1, 0, 1, 0], "not an icon with one image");
+    assert_eq!(&bytes[6..8], [16, 16]);
+    let icon = image::load_from_memory_with_format(&bytes, image::ImageFormat::Ico).unwrap().to_rgba8();
+    assert_eq!(icon.dimensions(), (16, 16));
+    assert_eq!(icon.get_pixel(8, 0).0[3], 0, "no transparent margin above the wide image");
+    assert_eq!(icon.get_pixel(8, 8).0[3], 255);
+}
+
+#[test]
+fn overwrite_policies() {
+    let dir = scratch("overwrite");
+    let output = dir.join("photo.jpg");
+    std::fs::write(&output, b"older").unwrap();
+
+    let result = run(&["--ascii-only", "-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap(), "--no-clobber"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("E026"));
+    assert_eq!(std::fs::read(&output).unwrap(), b"older");
+
+    let result = run(&["--ascii-only", "-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap(), "--overwrite"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(!String::from_utf8_lossy(&result.stderr).contains("will be overwritten"));
+    assert!(std::fs::read(&output).unwrap().starts_with(&[0xFF, 0xD8, 0xFF]));
+
+    let result = run(&["-i", gradient().to_str().unwrap(), "-o", output.to_str().unwrap(), "--overwrite", "--no-clobber"]);
+    assert!(!result.status.success(), "conflicting policies were accepted");
+}
+
+#[test]
+fn conflicts_get_numbered_names() {
+    let dir = scratch("rename");
+    for folder in ["a", "b"] {
+        std::fs::create_dir_all(dir.join(folder)).unwrap();
+        std::fs::copy(gradient(), dir.join(folder).join("photo.png")).unwrap();
+    }
+    let out = dir.join("out");
+    std::fs::create_dir_all(&out).unwrap();
+    std::fs::write(out.join("photo.jpg"), b"older").unwrap();
+
+    let result = run(&[
+        "--ascii-only",
+        "-f",
+        "jpg",
+        "-o",
+        out.to_str().unwrap(),
+        "--rename-on-conflict",
+        "--jobs",
+        "2",
+        dir.join("a").join("photo.png").to_str().unwrap(),
+        dir.join("b").join("photo.png").to_str().unwrap(),
+    ]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert_eq!(std::fs::read(out.join("photo.jpg")).unwrap(), b"older");
+    assert!(out.join("photo_1.jpg").exists() && out.join("photo_2.jpg").exists());
+}
+
+#[test]
+fn outputs_are_named_by_the_template() {
+    let dir = scratch("template");
+    for name in ["b.png", "a.png"] {
+        std::fs::copy(gradient(), dir.join(name)).unwrap();
+    }
+
+    let (a, b) = (dir.join("a.png"), dir.join("b.png"));
+    let template = "{counter:2}-{stem}.{format}.{ext}";
+    let result = run(&["-f", "jpg", "--name-template", template, a.to_str().unwrap(), b.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(dir.join("01-a.JPEG.jpg").exists() && dir.join("02-b.JPEG.jpg").exists());
+
+    let result = run(&["-f", "webp", "--name-template", "{stem}-{format}", a.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(dir.join("a-WEBP.webp").exists());
+
+    let result = run(&["-i", a.to_str().unwrap(), "--name-template", "{stem}_{size}"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("unknown placeholder {size}"));
+}
+
+#[test]
+fn output_folder_keeps_the_input_name() {
+    let dir = scratch("folder");
+    let input = dir.join("photo.png");
+    std::fs::copy(gradient(), &input).unwrap();
+
+    // A folder that is already there, and a new one written with a trailing slash
+    let existing = dir.join("existing");
+    std::fs::create_dir(&existing).unwrap();
+    let new = format!("{}/", dir.join("new").display());
+    for (output, expected) in [(existing.to_str().unwrap(), existing.join("photo.jpg")), (&new, dir.join("new/photo.jpg"))] {
+        let result = run(&["-i", input.to_str().unwrap(), "-f", "jpg", "-o", output]);
+        assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+        assert!(expected.is_file(), "{} was not written", expected.display());
+    }
+}
@@ -0,0 +1,110 @@
+// The stdout contract: with `-o -` stdout carries exactly the image bytes, with `--json`
+// exactly one JSON summary line, for a batch too. Banner, version information, progress
+// and --explain output all go to stderr, so both modes can be piped safely.
+mod common;
+
+use common::{gradient, scratch};
+use std::process::{Command, Output};
+
+// Run from the manifest directory, so the version banner from Cargo.toml is printed too
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_heic_convert"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(args)
+        .output()
+        .expect("cannot run heic_convert")
+}
+
+#[test]
+fn png_to_stdout_is_only_the_image() {
+    let output = run(&["-i", gradient().to_str().unwrap(), "-o", "-", "--explain"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let png = &output.stdout;
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"), "stdout does not start with the PNG signature");
+    // IEND chunk (length, type, CRC) closes the file; nothing may follow it
+    assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND", "bytes after the end of the PNG");
+    let decoded = image::load_from_memory(png).expect("stdout is not a valid PNG");
+    assert_eq!(decoded.width(), image::image_dimensions(gradient()).unwrap().0);
+
+    // The messages are still shown, on stderr
+    let messages = String::from_utf8_lossy(&output.stderr);
+    assert!(messages.contains("Explain"), "--explain output is missing from stderr");
+}
+
+#[test]
+fn jpeg_to_stdout_is_only_the_image() {
+    let output = run(&["-i", gradient().to_str().unwrap(), "-o", "-", "-f", "jpg"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stdout.starts_with(&[0xFF, 0xD8]), "stdout does not start with a JPEG marker");
+    assert!(output.stdout.ends_with(&[0xFF, 0xD9]), "bytes after the end of the JPEG");
+    image::load_from_memory(&output.stdout).expect("stdout is not a valid JPEG");
+}
+
+#[test]
+fn json_success_is_only_the_summary() {
+    let target = scratch("json").join("success.png");
+    let output = run(&["-i", gradient().to_str().unwrap(), "-o", target.to_str().unwrap(), "--json", "--explain"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is not UTF-8");
+    assert_eq!(stdout.lines().count(), 1, "expected exactly one line on stdout:\n{}", stdout);
+    let summary: serde_json::Value = serde_json::from_str(&stdout).expect("stdout is not JSON");
+    assert_eq!(summary["status"], "success");
+    assert_eq!(summary["output"], target.display().to_string());
+    assert!(target.exists());
+    let _ = std::fs::remove_file(&target);
+}
+
+#[test]
+fn json_failure_is_still_only_the_summary() {
+    let output = run(&["-i", "does-not-exist.heic", "--json"]);
+    assert!(!output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is not UTF-8");
+    assert_eq!(stdout.lines().count(), 1, "expected exactly one line on stdout:\n{}", stdout);
+    let summary: serde_json::Value = serde_json::from_str(&stdout).expect("stdout is not JSON");
+    assert_eq!(summary["status"], "failed");
+    assert!(summary["error"].as_str().unwrap_or_default().contains("does not exist"));
+}
+
+#[test]
+fn json_batch_is_only_its_summary() {
+    let dir = scratch("batch");
+    let broken = dir.join("broken.png");
+    std::fs::write(&broken, b"not an image").unwrap();
+    let out = format!("{}/", dir.join("out").display());
+    let output = run(&["-f", "jpg", "-o", &out, "--json", gradient().to_str().unwrap(), broken.to_str().unwrap()]);
+    assert!(!output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is not UTF-8");
+    assert_eq!(stdout.lines().count(), 1, "expected exactly one line on stdout:\n{}", stdout);
+    let summary: serde_json::Value = serde_json::from_str(&stdout).expect("stdout is not JSON");
+    assert_eq!(summary["status"], "failed");
+    assert_eq!((summary["files"].as_u64(), summary["converted"].as_u64()), (Some(2), Some(1)), "{}", summary);
+    assert_eq!(summary["failures"][0]["input"], broken.display().to_string());
+}
+
+#[test]
+fn usage_errors_keep_stdout_empty() {
+    // Both modes want stdout
+    let output = run(&["-i", gradient().to_str().unwrap(), "-o", "-", "--json"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty(), "stdout: {}", String::from_utf8_lossy(&output.stdout));
+
+    // Rejected by the argument parser, after the banner has been printed
+    let output = run(&["-o", "-", "--no-such-option"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty(), "stdout: {}", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn version_json_is_only_the_capabilities() {
+    let output = run(&["--version", "--json"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let capabilities: serde_json::Value = serde_json::from_slice(&output.stdout).expect("stdout is not JSON");
+    assert_eq!(capabilities["version"], env!("CARGO_PKG_VERSION"));
+    assert!(capabilities["codecs"]["encode"].as_array().is_some_and(|formats| formats.iter().any(|f| f == "png")));
+    assert!(capabilities["backends"].as_array().is_some_and(|backends| backends.iter().any(|b| b["name"] == "builtin")));
+}
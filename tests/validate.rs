@@ -0,0 +1,41 @@
+// `heic_convert validate`: damaged HEIC files are named and fail the run, other images are
+// not checked and nothing is written next to the files
+mod common;
+
+use common::{UNDECODABLE_HEIC, gradient, scratch};
+use std::process::Command;
+
+#[test]
+fn damaged_files_are_reported() {
+    let dir = scratch("damaged");
+    std::fs::create_dir_all(dir.join("2024")).unwrap();
+    // Only the 'ftyp' box: the download stopped right after it
+    std::fs::write(dir.join("2024").join("IMG_0001.HEIC"), UNDECODABLE_HEIC).unwrap();
+    std::fs::copy(gradient(), dir.join("IMG_0002.PNG")).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_heic_convert"))
+        .args(["--ascii-only", "validate", dir.to_str().unwrap()])
+        .output()
+        .expect("cannot run heic_convert");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{}", stdout);
+    assert!(stdout.contains("Checking 1 HEIC files"), "{}", stdout);
+    assert!(stdout.contains("IMG_0001.HEIC") && stdout.contains("no 'meta' box"), "{}", stdout);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2, "validate wrote a file");
+}
+
+#[test]
+fn unrepairable_files_get_no_copy() {
+    let dir = scratch("repair");
+    let photo = dir.join("IMG_0003.HEIC");
+    std::fs::write(&photo, UNDECODABLE_HEIC).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_heic_convert"))
+        .args(["--ascii-only", "validate", "--repair", photo.to_str().unwrap()])
+        .output()
+        .expect("cannot run heic_convert");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{}", stdout);
+    assert!(stdout.contains("Cannot be repaired"), "{}", stdout);
+    assert!(!dir.join("IMG_0003.repaired.HEIC").exists());
+}